@@ -0,0 +1,8 @@
+-- persistent store for tide sessions
+CREATE TABLE IF NOT EXISTS sessions (
+    id VARCHAR PRIMARY KEY NOT NULL,
+    session VARCHAR NOT NULL,
+    expires_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS sessions_expires_at_idx ON sessions (expires_at);
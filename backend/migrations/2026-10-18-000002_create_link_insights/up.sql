@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS link_insights (
+    id SERIAL PRIMARY KEY NOT NULL,
+    link_id INT NOT NULL,
+    click_count INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL,
+    created_bucket TIMESTAMP GENERATED ALWAYS AS (DATE_TRUNC('hour', created_at)) STORED NOT NULL,
+    UNIQUE(link_id, created_bucket),
+    FOREIGN KEY (link_id) REFERENCES links(id) ON DELETE CASCADE
+);
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS profile_visitors;
+
+ALTER TABLE user_insights DROP COLUMN IF EXISTS unique_view_count;
@@ -0,0 +1,11 @@
+ALTER TABLE user_insights ADD COLUMN IF NOT EXISTS unique_view_count INT NOT NULL DEFAULT 0;
+
+-- visitors of the profile by hourly bucket, counted once per bucket towards unique_view_count
+CREATE TABLE IF NOT EXISTS profile_visitors (
+    user_id INT NOT NULL,
+    visitor_key VARCHAR(64) NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    created_bucket TIMESTAMP GENERATED ALWAYS AS (DATE_TRUNC('hour', created_at)) STORED NOT NULL,
+    PRIMARY KEY (user_id, created_bucket, visitor_key),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,13 @@
+-- profile views broken down by where they came from, one row per user, bucket and source
+CREATE TABLE IF NOT EXISTS user_insight_sources (
+    id SERIAL PRIMARY KEY NOT NULL,
+    user_id INT NOT NULL,
+    -- either referrer or country
+    source_type VARCHAR(16) NOT NULL,
+    source VARCHAR(255) NOT NULL,
+    view_count INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL,
+    created_bucket TIMESTAMP GENERATED ALWAYS AS (DATE_TRUNC('hour', created_at)) STORED NOT NULL,
+    UNIQUE(user_id, created_bucket, source_type, source),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
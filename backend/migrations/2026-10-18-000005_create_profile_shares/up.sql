@@ -0,0 +1,11 @@
+-- shares already counted towards share_count in the current hourly bucket,
+-- each viewer can add at most one share per channel and bucket
+CREATE TABLE IF NOT EXISTS profile_shares (
+    user_id INT NOT NULL,
+    visitor_key VARCHAR(64) NOT NULL,
+    channel VARCHAR(16) NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    created_bucket TIMESTAMP GENERATED ALWAYS AS (DATE_TRUNC('hour', created_at)) STORED NOT NULL,
+    PRIMARY KEY (user_id, created_bucket, visitor_key, channel),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,3 @@
+ALTER TABLE links DROP CONSTRAINT IF EXISTS links_visibility_window_check;
+ALTER TABLE links DROP COLUMN IF EXISTS visible_until;
+ALTER TABLE links DROP COLUMN IF EXISTS visible_from;
@@ -0,0 +1,5 @@
+-- links outside of their window are hidden from everyone but the owner
+ALTER TABLE links ADD COLUMN IF NOT EXISTS visible_from TIMESTAMP;
+ALTER TABLE links ADD COLUMN IF NOT EXISTS visible_until TIMESTAMP;
+ALTER TABLE links ADD CONSTRAINT links_visibility_window_check
+    CHECK (visible_from IS NULL OR visible_until IS NULL OR visible_from < visible_until);
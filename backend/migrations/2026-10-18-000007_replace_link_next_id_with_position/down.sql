@@ -0,0 +1,67 @@
+DROP FUNCTION IF EXISTS reorder_link(node_id INT, new_position_id INT);
+DROP FUNCTION IF EXISTS rebalance_link_positions(owner_id INT);
+DROP TRIGGER IF EXISTS set_link_position_trigger ON links;
+DROP FUNCTION IF EXISTS set_link_position_before_insert();
+
+-- rebuild the chains from the positions, the last link of every user has no next link
+ALTER TABLE links ADD COLUMN IF NOT EXISTS next_id INT UNIQUE;
+UPDATE links SET next_id = ordered.next_id
+FROM (
+    SELECT id, LEAD(id) OVER (PARTITION BY user_id ORDER BY position, id) AS next_id
+    FROM links
+) ordered
+WHERE links.id = ordered.id;
+
+ALTER TABLE links DROP CONSTRAINT IF EXISTS links_user_position_key;
+ALTER TABLE links DROP COLUMN IF EXISTS position;
+DROP FUNCTION IF EXISTS link_position_gap();
+
+CREATE OR REPLACE FUNCTION reorder_link(node_id INT, new_position_id INT) RETURNS VOID AS $$
+DECLARE
+    current_next INT;
+BEGIN
+    IF node_id = new_position_id THEN
+        RETURN;
+    END IF;
+    SELECT next_id into current_next FROM links WHERE id = node_id;
+    UPDATE links SET next_id = NULL WHERE id = node_id;
+
+    UPDATE links SET next_id = current_next WHERE next_id = node_id;
+
+    IF new_position_id IS NULL THEN
+        UPDATE links SET next_id = node_id WHERE next_id IS NULL AND id != node_id;
+    ELSE
+        UPDATE links SET next_id = node_id WHERE next_id = new_position_id;
+        UPDATE links SET next_id = new_position_id WHERE id = node_id;
+    END IF;
+
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION reorder_link_after_create() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.next_id IS NULL THEN
+        UPDATE links SET next_id = NEW.id WHERE links.next_id IS NULL AND links.id != NEW.id;
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION reorder_link_after_delete() RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE links SET next_id = OLD.next_id WHERE links.next_id = OLD.id;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER reorder_links_trigger
+AFTER DELETE on links
+FOR EACH ROW
+EXECUTE FUNCTION reorder_link_after_delete();
+
+CREATE TRIGGER reorder_links_on_create_trigger
+AFTER INSERT on links
+FOR EACH ROW
+EXECUTE FUNCTION reorder_link_after_create();
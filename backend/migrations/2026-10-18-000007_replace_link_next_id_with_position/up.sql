@@ -0,0 +1,131 @@
+-- links are ordered by a sparse position key instead of a linked list through next_id,
+-- keys start link_position_gap() apart so that a link can be moved between two others with a single update
+CREATE OR REPLACE FUNCTION link_position_gap() RETURNS BIGINT AS $$
+    SELECT 1024::BIGINT;
+$$ LANGUAGE sql IMMUTABLE;
+
+ALTER TABLE links ADD COLUMN IF NOT EXISTS position BIGINT;
+
+-- backfill by walking every chain from its head, next_id may point to links of other users
+-- so only edges between links of the same user are followed. links that are not reachable
+-- from a head (cycles) are placed after the chains by id
+WITH RECURSIVE chain AS (
+    SELECT head.id, head.user_id, head.next_id, head.id AS head_id, 1 AS depth, ARRAY[head.id] AS path
+    FROM links head
+    WHERE NOT EXISTS (
+        SELECT 1 FROM links prev WHERE prev.next_id = head.id AND prev.user_id = head.user_id
+    )
+    UNION ALL
+    SELECT next.id, next.user_id, next.next_id, chain.head_id, chain.depth + 1, chain.path || next.id
+    FROM chain
+    JOIN links next ON next.id = chain.next_id AND next.user_id = chain.user_id
+    WHERE NOT next.id = ANY(chain.path)
+),
+first_visit AS (
+    SELECT DISTINCT ON (id) id, head_id, depth
+    FROM chain
+    ORDER BY id, head_id, depth
+),
+ranked AS (
+    SELECT links.id, ROW_NUMBER() OVER (
+        PARTITION BY links.user_id
+        ORDER BY first_visit.head_id NULLS LAST, first_visit.depth, links.id
+    ) AS rank
+    FROM links
+    LEFT JOIN first_visit ON first_visit.id = links.id
+)
+UPDATE links SET position = ranked.rank * link_position_gap()
+FROM ranked
+WHERE links.id = ranked.id;
+
+DROP TRIGGER IF EXISTS reorder_links_trigger ON links;
+DROP TRIGGER IF EXISTS reorder_links_on_create_trigger ON links;
+DROP FUNCTION IF EXISTS reorder_link_after_delete();
+DROP FUNCTION IF EXISTS reorder_link_after_create();
+DROP FUNCTION IF EXISTS reorder_link(node_id INT, new_position_id INT);
+ALTER TABLE links DROP COLUMN IF EXISTS next_id;
+
+-- deferrable so that renumbering all links of a user in one statement is only checked at the end
+ALTER TABLE links ALTER COLUMN position SET NOT NULL;
+ALTER TABLE links ADD CONSTRAINT links_user_position_key UNIQUE (user_id, position) DEFERRABLE;
+
+-- new links are appended after the last link of the user
+CREATE OR REPLACE FUNCTION set_link_position_before_insert() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.position IS NULL THEN
+        SELECT COALESCE(MAX(position), 0) + link_position_gap() INTO NEW.position
+        FROM links WHERE user_id = NEW.user_id;
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER set_link_position_trigger
+BEFORE INSERT ON links
+FOR EACH ROW
+EXECUTE FUNCTION set_link_position_before_insert();
+
+-- spreads the positions of the user's links link_position_gap() apart again, keeping their order
+CREATE OR REPLACE FUNCTION rebalance_link_positions(owner_id INT) RETURNS VOID AS $$
+BEGIN
+    UPDATE links SET position = ranked.rank * link_position_gap()
+    FROM (
+        SELECT id, ROW_NUMBER() OVER (ORDER BY position, id) AS rank
+        FROM links WHERE user_id = owner_id
+    ) ranked
+    WHERE links.id = ranked.id;
+END;
+$$ LANGUAGE plpgsql;
+
+-- moves node_id in front of new_position_id, or to the end if new_position_id is NULL.
+-- only node_id is updated unless there is no key left between the two neighbours
+CREATE OR REPLACE FUNCTION reorder_link(node_id INT, new_position_id INT) RETURNS VOID AS $$
+DECLARE
+    owner_id INT;
+    next_position BIGINT;
+    prev_position BIGINT;
+BEGIN
+    IF node_id = new_position_id THEN
+        RETURN;
+    END IF;
+
+    SELECT user_id INTO owner_id FROM links WHERE id = node_id;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'link % does not exist', node_id;
+    END IF;
+
+    -- concurrent reorders of the same user have to see the same neighbours
+    PERFORM 1 FROM links WHERE user_id = owner_id FOR UPDATE;
+
+    IF new_position_id IS NULL THEN
+        UPDATE links SET position = (
+            SELECT COALESCE(MAX(position), 0) + link_position_gap()
+            FROM links WHERE user_id = owner_id AND id != node_id
+        )
+        WHERE id = node_id;
+        RETURN;
+    END IF;
+
+    SELECT position INTO next_position FROM links WHERE id = new_position_id AND user_id = owner_id;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'link % does not belong to the owner of link %', new_position_id, node_id;
+    END IF;
+
+    SELECT MAX(position) INTO prev_position
+    FROM links WHERE user_id = owner_id AND position < next_position AND id != node_id;
+
+    IF prev_position IS NULL THEN
+        prev_position := next_position - 2 * link_position_gap();
+    ELSIF next_position - prev_position < 2 THEN
+        PERFORM rebalance_link_positions(owner_id);
+        SELECT position INTO next_position FROM links WHERE id = new_position_id;
+        SELECT MAX(position) INTO prev_position
+        FROM links WHERE user_id = owner_id AND position < next_position AND id != node_id;
+        prev_position := COALESCE(prev_position, next_position - 2 * link_position_gap());
+    END IF;
+
+    UPDATE links SET position = prev_position + (next_position - prev_position) / 2
+    WHERE id = node_id;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,6 @@
+DROP FUNCTION IF EXISTS reorder_link_section(node_id INT, new_position_id INT);
+DROP TRIGGER IF EXISTS set_link_section_position_trigger ON link_sections;
+DROP FUNCTION IF EXISTS set_link_section_position_before_insert();
+ALTER TABLE links DROP CONSTRAINT IF EXISTS links_section_fkey;
+ALTER TABLE links DROP COLUMN IF EXISTS section_id;
+DROP TABLE IF EXISTS link_sections;
@@ -0,0 +1,69 @@
+-- named groups of links shown under a header, ordered like links
+CREATE TABLE IF NOT EXISTS link_sections (
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL,
+    title VARCHAR(100) NOT NULL,
+    is_collapsed BOOLEAN NOT NULL DEFAULT FALSE,
+    position BIGINT NOT NULL,
+    UNIQUE (id, user_id),
+    CONSTRAINT link_sections_user_position_key UNIQUE (user_id, position) DEFERRABLE,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- links can only be in sections of their own user, deleting a section keeps its links
+ALTER TABLE links ADD COLUMN IF NOT EXISTS section_id INT;
+ALTER TABLE links ADD CONSTRAINT links_section_fkey
+    FOREIGN KEY (section_id, user_id) REFERENCES link_sections(id, user_id) ON DELETE SET NULL (section_id);
+
+CREATE OR REPLACE FUNCTION set_link_section_position_before_insert() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.position IS NULL THEN
+        SELECT COALESCE(MAX(position), 0) + link_position_gap() INTO NEW.position
+        FROM link_sections WHERE user_id = NEW.user_id;
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER set_link_section_position_trigger
+BEFORE INSERT ON link_sections
+FOR EACH ROW
+EXECUTE FUNCTION set_link_section_position_before_insert();
+
+-- moves node_id in front of new_position_id, or to the end if new_position_id is NULL.
+-- a user only has a few sections so they are simply renumbered
+CREATE OR REPLACE FUNCTION reorder_link_section(node_id INT, new_position_id INT) RETURNS VOID AS $$
+DECLARE
+    owner_id INT;
+    ordered INT[];
+    next_index INT;
+BEGIN
+    IF node_id = new_position_id THEN
+        RETURN;
+    END IF;
+
+    SELECT user_id INTO owner_id FROM link_sections WHERE id = node_id;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'section % does not exist', node_id;
+    END IF;
+
+    PERFORM 1 FROM link_sections WHERE user_id = owner_id FOR UPDATE;
+
+    SELECT COALESCE(ARRAY_AGG(id ORDER BY position, id), '{}') INTO ordered
+    FROM link_sections WHERE user_id = owner_id AND id != node_id;
+
+    IF new_position_id IS NULL THEN
+        ordered := ordered || node_id;
+    ELSE
+        next_index := ARRAY_POSITION(ordered, new_position_id);
+        IF next_index IS NULL THEN
+            RAISE EXCEPTION 'section % does not belong to the owner of section %', new_position_id, node_id;
+        END IF;
+        ordered := ordered[1:next_index - 1] || node_id || ordered[next_index:];
+    END IF;
+
+    UPDATE link_sections SET position = ARRAY_POSITION(ordered, id) * link_position_gap()
+    WHERE user_id = owner_id;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,14 @@
+-- result of the latest health check of each link
+CREATE TABLE IF NOT EXISTS link_health (
+    link_id INT PRIMARY KEY,
+    -- null when the request failed without a response
+    status_code INT,
+    latency_ms INT NOT NULL,
+    checked_at TIMESTAMP NOT NULL,
+    consecutive_failures INT NOT NULL DEFAULT 0,
+    -- whether the owner was notified about the current run of failures
+    is_notified BOOLEAN NOT NULL DEFAULT FALSE,
+    FOREIGN KEY (link_id) REFERENCES links(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS link_health_checked_at_idx ON link_health (checked_at);
@@ -0,0 +1,3 @@
+-- resized copies of an image, img_src and filename point at the default rendition.
+-- images uploaded before renditions keep an empty list and only have their original file
+ALTER TABLE images ADD COLUMN IF NOT EXISTS renditions JSONB NOT NULL DEFAULT '[]';
@@ -0,0 +1,16 @@
+-- uploads that were presigned straight to the object store and are not completed yet
+CREATE TABLE IF NOT EXISTS uploads (
+    id SERIAL PRIMARY KEY,
+    -- sha256 of the token handed to the client
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    user_id INT NOT NULL,
+    -- the link the image is for, null for the profile image
+    link_id INT,
+    object_key VARCHAR(255) NOT NULL,
+    content_type VARCHAR(32) NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (link_id) REFERENCES links(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS uploads_expires_at_idx ON uploads (expires_at);
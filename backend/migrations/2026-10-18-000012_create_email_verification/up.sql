@@ -0,0 +1,16 @@
+-- password reset emails are only sent to addresses that were confirmed
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified BOOLEAN NOT NULL DEFAULT FALSE;
+-- users from before verification keep password reset, their addresses are trusted as they were
+UPDATE users SET email_verified = TRUE;
+
+-- an address waiting to be confirmed, the address of a new user or the new address of an email change.
+-- a user has at most one, asking again replaces it
+CREATE TABLE IF NOT EXISTS email_verification_request (
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL UNIQUE,
+    email VARCHAR NOT NULL,
+    -- sha256 of the token sent in the verification link
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
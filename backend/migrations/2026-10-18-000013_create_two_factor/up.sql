@@ -0,0 +1,22 @@
+-- totp secret of a user, enabled once the first code from the authenticator app is confirmed
+CREATE TABLE IF NOT EXISTS two_factor (
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL UNIQUE,
+    -- base32 secret shared with the authenticator app
+    secret VARCHAR(64) NOT NULL,
+    is_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    -- time step of the last accepted code, so that a code cannot be used twice
+    last_used_step BIGINT,
+    created_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- one time codes for logging in without the authenticator app
+CREATE TABLE IF NOT EXISTS recovery_codes (
+    id SERIAL PRIMARY KEY,
+    user_id INT NOT NULL,
+    -- sha256 of the code
+    code_hash VARCHAR(64) NOT NULL,
+    UNIQUE (user_id, code_hash),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,10 @@
+-- failed attempts of rate limited routes like login, shared by every instance of the backend.
+-- keys are hashes of the ip or the account that was tried
+CREATE TABLE IF NOT EXISTS rate_limits (
+    key VARCHAR(128) PRIMARY KEY,
+    failures INT NOT NULL,
+    last_failure_at TIMESTAMP NOT NULL
+);
+
+-- stale keys are swept by their last failure
+CREATE INDEX IF NOT EXISTS rate_limits_last_failure_at_idx ON rate_limits (last_failure_at);
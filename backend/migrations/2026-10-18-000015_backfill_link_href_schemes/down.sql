@@ -0,0 +1,2 @@
+-- the schemes added to legacy hrefs are valid hrefs on their own and are kept
+SELECT 1;
@@ -0,0 +1,12 @@
+-- hrefs saved before they were validated on the server can be missing a scheme, the old frontend
+-- rendered those as "//" + href. give them the same https default as normalize_href.
+-- hrefs that would no longer fit the column are left to href_with_scheme when they are followed
+UPDATE links SET href = 'https:' || btrim(href)
+WHERE btrim(href) LIKE '//%'
+    AND char_length(btrim(href)) <= 249;
+
+UPDATE links SET href = 'https://' || btrim(href)
+WHERE btrim(href) NOT LIKE '%://%'
+    AND btrim(href) !~ '^[A-Za-z][A-Za-z0-9+-]*:([^0-9]|$)'
+    AND btrim(href) != ''
+    AND char_length(btrim(href)) <= 247;
@@ -0,0 +1,2 @@
+DROP TRIGGER IF EXISTS reset_link_health_trigger ON links;
+DROP FUNCTION IF EXISTS reset_link_health_after_href_update();
@@ -0,0 +1,13 @@
+-- the health of a link belongs to its href, a new href starts without checks or failures
+CREATE OR REPLACE FUNCTION reset_link_health_after_href_update() RETURNS TRIGGER AS $$
+BEGIN
+    DELETE FROM link_health WHERE link_id = NEW.id;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER reset_link_health_trigger
+AFTER UPDATE OF href ON links
+FOR EACH ROW
+WHEN (OLD.href IS DISTINCT FROM NEW.href)
+EXECUTE FUNCTION reset_link_health_after_href_update();
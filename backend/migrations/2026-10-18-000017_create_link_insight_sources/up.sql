@@ -0,0 +1,11 @@
+-- link clicks broken down by the share channel they came through, one row per link, bucket and source
+CREATE TABLE IF NOT EXISTS link_insight_sources (
+    id SERIAL PRIMARY KEY NOT NULL,
+    link_id INT NOT NULL,
+    source VARCHAR(255) NOT NULL,
+    click_count INT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL,
+    created_bucket TIMESTAMP GENERATED ALWAYS AS (DATE_TRUNC('hour', created_at)) STORED NOT NULL,
+    UNIQUE(link_id, created_bucket, source),
+    FOREIGN KEY (link_id) REFERENCES links(id) ON DELETE CASCADE
+);
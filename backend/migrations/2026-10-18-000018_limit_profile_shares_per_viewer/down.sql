@@ -0,0 +1,2 @@
+ALTER TABLE profile_shares DROP CONSTRAINT IF EXISTS profile_shares_pkey;
+ALTER TABLE profile_shares ADD PRIMARY KEY (user_id, created_bucket, visitor_key, channel);
@@ -0,0 +1,11 @@
+-- each viewer can add at most one share per bucket whatever the channel,
+-- the channel of the first share in the bucket is kept
+DELETE FROM profile_shares later
+USING profile_shares earlier
+WHERE later.user_id = earlier.user_id
+    AND later.created_bucket = earlier.created_bucket
+    AND later.visitor_key = earlier.visitor_key
+    AND (later.created_at, later.channel) > (earlier.created_at, earlier.channel);
+
+ALTER TABLE profile_shares DROP CONSTRAINT IF EXISTS profile_shares_pkey;
+ALTER TABLE profile_shares ADD PRIMARY KEY (user_id, created_bucket, visitor_key);
@@ -0,0 +1,24 @@
+#!/bin/sh
+# downloads the dbip country lite csv to the default GEOIP_DATABASE_PATH,
+# run it from the backend folder and again every month to keep the ranges current.
+# the database is by db-ip.com under CC BY 4.0
+set -eu
+
+DEST=${1:-geoip/dbip-country-lite.csv}
+URL="https://download.db-ip.com/free/dbip-country-lite"
+
+mkdir -p "$(dirname "$DEST")"
+TMP="$DEST.tmp.gz"
+trap 'rm -f "$TMP"' EXIT
+
+# the file of the current month is published during the month, fall back to the last one
+for MONTH in "$(date -u +%Y-%m)" "$(date -u -d "$(date -u +%Y-%m-01) -1 day" +%Y-%m)"; do
+    if curl -fsSL -o "$TMP" "$URL-$MONTH.csv.gz"; then
+        gunzip -c "$TMP" > "$DEST"
+        echo "Saved the $MONTH GeoIP database to $DEST"
+        exit 0
+    fi
+done
+
+echo "Failed to download the GeoIP database" >&2
+exit 1
@@ -0,0 +1,89 @@
+use diesel::PgConnection;
+
+use crate::{
+    connectors::db::{
+        link::{
+            clear_link_sections, get_link_owner_ids, get_user_link_order, get_user_links_by_id,
+        },
+        section::get_user_sections,
+    },
+    helpers::links::{find_link_order_issues, LinkOrderIssue},
+};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct LinkOrderReport {
+    pub users_checked: usize,
+    pub users_with_issues: usize,
+    pub users_repaired: usize,
+}
+
+// checks that the profiles of the given users show every link once, by position and in one of the
+// user's sections. with repair links in sections of other users are taken out of them, which
+// keeps their position so that running it twice gives the same order. a profile that shows
+// other links than the user has cannot be repaired in the data and is only reported
+pub async fn scan_link_order(
+    conn: &mut PgConnection,
+    user_ids: &[i32],
+    repair: bool,
+) -> Result<LinkOrderReport, diesel::result::Error> {
+    let mut report = LinkOrderReport::default();
+
+    for &user_id in user_ids {
+        report.users_checked += 1;
+
+        let links = get_user_link_order(conn, user_id).await?;
+        let shown_link_ids = get_user_links_by_id(conn, user_id)
+            .await?
+            .into_iter()
+            .map(|(link, _)| link.id)
+            .collect::<Vec<i32>>();
+        let section_ids = get_user_sections(conn, user_id)
+            .await?
+            .into_iter()
+            .map(|section| section.id)
+            .collect::<Vec<i32>>();
+        let issues = find_link_order_issues(&links, &shown_link_ids, &section_ids);
+        if issues.is_empty() {
+            continue;
+        }
+        report.users_with_issues += 1;
+
+        let mut foreign_section_link_ids = Vec::<i32>::new();
+        for issue in issues.iter() {
+            match issue {
+                LinkOrderIssue::ProfileOrderMismatch { expected, shown } => println!(
+                    "user {}: profile shows links {:?} instead of {:?}",
+                    user_id, shown, expected
+                ),
+                LinkOrderIssue::ForeignSection {
+                    link_id,
+                    section_id,
+                } => {
+                    println!(
+                        "user {}: link {} is in section {} of another user",
+                        user_id, link_id, section_id
+                    );
+                    foreign_section_link_ids.push(*link_id);
+                }
+            }
+        }
+
+        if repair && !foreign_section_link_ids.is_empty() {
+            clear_link_sections(conn, user_id, &foreign_section_link_ids).await?;
+            report.users_repaired += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+// checks the links of every user that has links
+pub async fn check_links(conn: &mut PgConnection, repair: bool) -> tide::Result<()> {
+    let user_ids = get_link_owner_ids(conn).await?;
+    let report = scan_link_order(conn, &user_ids, repair).await?;
+    println!(
+        "checked {} users, {} with issues, {} repaired",
+        report.users_checked, report.users_with_issues, report.users_repaired
+    );
+    Ok(())
+}
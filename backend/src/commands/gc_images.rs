@@ -0,0 +1,45 @@
+use chrono::Utc;
+use diesel::PgConnection;
+
+use crate::{
+    connectors::buckets::store::ObjectStore,
+    jobs::image_gc::{image_gc_grace_period, run_image_gc},
+};
+
+// deletes objects that no image refers to, with dry run nothing is deleted and they are only listed
+pub async fn gc_images(
+    conn: &mut PgConnection,
+    object_store: &dyn ObjectStore,
+    dry_run: bool,
+) -> tide::Result<()> {
+    let report = run_image_gc(
+        conn,
+        object_store,
+        image_gc_grace_period(),
+        dry_run,
+        Utc::now().naive_utc(),
+    )
+    .await?;
+
+    for (bucket, key) in report.orphaned_objects.iter() {
+        println!("orphaned object {}/{}", bucket.name(), key);
+    }
+    for missing in report.missing_objects.iter() {
+        println!(
+            "image {}: missing object {}/{}",
+            missing.image_id,
+            missing.bucket.name(),
+            missing.key
+        );
+    }
+    println!(
+        "checked {} objects, {} orphaned, {} deleted, {} missing, {} detached images, {} expired uploads",
+        report.objects_checked,
+        report.orphaned_objects.len(),
+        report.objects_deleted,
+        report.missing_objects.len(),
+        report.detached_images,
+        report.expired_uploads
+    );
+    Ok(())
+}
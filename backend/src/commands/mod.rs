@@ -0,0 +1,32 @@
+pub mod check_links;
+pub mod gc_images;
+
+use diesel::PgConnection;
+
+use crate::{
+    commands::{check_links::check_links, gc_images::gc_images},
+    connectors::buckets::store::object_store_from_env,
+};
+
+const USAGE: &str = "usage: saladify [check-links [--repair] | gc-images [--dry-run]]";
+
+// runs the subcommand given on the command line instead of the server
+pub async fn run_command(conn: &mut PgConnection, args: &[String]) -> tide::Result<()> {
+    match args {
+        [command] if command == "check-links" => check_links(conn, false).await,
+        [command, flag] if command == "check-links" && flag == "--repair" => {
+            check_links(conn, true).await
+        }
+        [command] if command == "gc-images" => {
+            gc_images(conn, object_store_from_env().await.as_ref(), false).await
+        }
+        [command, flag] if command == "gc-images" && flag == "--dry-run" => {
+            gc_images(conn, object_store_from_env().await.as_ref(), true).await
+        }
+        // a usage error is not a request error, exit like other command line tools do
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2)
+        }
+    }
+}
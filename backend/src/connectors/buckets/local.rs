@@ -0,0 +1,408 @@
+use std::{
+    env, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use sha2::Sha256;
+use tokio::{fs, io::AsyncReadExt};
+
+use crate::{
+    connectors::buckets::store::{
+        check_object_key, Bucket, ObjectStore, PresignedUpload, StoredObject,
+    },
+    helpers::random::make_random_string,
+    types::error::{Error, StorageErrors},
+};
+
+// object store on the local disk, served by the backend under /storage.
+// meant for development and tests without aws
+
+const DEFAULT_STORAGE_PATH: &str = "storage";
+const DEFAULT_STORAGE_URL: &str = "http://localhost:8080/storage";
+
+// signs presigned uploads, random per process unless it is configured
+static LOCAL_STORAGE_SECRET: Lazy<String> = Lazy::new(|| {
+    env::var("LOCAL_STORAGE_SECRET")
+        .ok()
+        .filter(|secret| !secret.is_empty())
+        .unwrap_or_else(|| make_random_string(32))
+});
+
+pub struct LocalObjectStore {
+    root: PathBuf,
+    base_url: String,
+    secret: String,
+}
+
+fn io_error(e: io::Error) -> Error {
+    match e.kind() {
+        io::ErrorKind::NotFound => Error::StorageError(StorageErrors::NotFound),
+        _ => Error::StorageError(StorageErrors::Backend(e.to_string())),
+    }
+}
+
+impl LocalObjectStore {
+    pub fn new(root: &Path, base_url: &str, secret: &str) -> LocalObjectStore {
+        LocalObjectStore {
+            root: root.to_path_buf(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            secret: secret.to_string(),
+        }
+    }
+
+    // LOCAL_STORAGE_PATH is where objects are kept, LOCAL_STORAGE_URL where they are served from
+    pub fn from_env() -> LocalObjectStore {
+        let var = |name: &str, default: &str| {
+            env::var(name)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .unwrap_or(default.to_string())
+        };
+        LocalObjectStore::new(
+            Path::new(&var("LOCAL_STORAGE_PATH", DEFAULT_STORAGE_PATH)),
+            &var("LOCAL_STORAGE_URL", DEFAULT_STORAGE_URL),
+            &LOCAL_STORAGE_SECRET,
+        )
+    }
+
+    fn object_path(&self, bucket: Bucket, key: &str) -> Result<PathBuf, Error> {
+        check_object_key(key)?;
+        Ok(self.root.join(bucket.name()).join(key))
+    }
+
+    // hmac of everything a presigned put is limited to, the fields are separated by newlines
+    // because a header value cannot contain one
+    fn signature_mac(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        content_type: &str,
+        expires: i64,
+    ) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes())
+            .expect("hmac takes keys of any length");
+        mac.update(format!("{}\n{}\n{}\n{}", bucket.name(), key, content_type, expires).as_bytes());
+        mac
+    }
+
+    fn signature(&self, bucket: Bucket, key: &str, content_type: &str, expires: i64) -> String {
+        hex::encode(
+            self.signature_mac(bucket, key, content_type, expires)
+                .finalize()
+                .into_bytes(),
+        )
+    }
+}
+
+#[async_trait]
+impl ObjectStore for LocalObjectStore {
+    async fn setup(&self) -> Result<(), Error> {
+        for bucket in Bucket::ALL {
+            fs::create_dir_all(self.root.join(bucket.name()))
+                .await
+                .map_err(io_error)?;
+        }
+        Ok(())
+    }
+
+    // written to a temporary file first so that readers never see half an object
+    async fn put(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        body: Vec<u8>,
+        _content_type: &str,
+    ) -> Result<(), Error> {
+        let path = self.object_path(bucket, key)?;
+        let bucket_path = self.root.join(bucket.name());
+        fs::create_dir_all(&bucket_path).await.map_err(io_error)?;
+        let temp_path = bucket_path.join(format!(".{}.{}", key, make_random_string(8)));
+        fs::write(&temp_path, body).await.map_err(io_error)?;
+        fs::rename(&temp_path, &path).await.map_err(io_error)
+    }
+
+    async fn get(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, Error> {
+        fs::read(self.object_path(bucket, key)?)
+            .await
+            .map_err(io_error)
+    }
+
+    async fn get_prefix(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        max_len: usize,
+    ) -> Result<Vec<u8>, Error> {
+        let file = fs::File::open(self.object_path(bucket, key)?)
+            .await
+            .map_err(io_error)?;
+        let mut bytes = Vec::new();
+        file.take(max_len as u64)
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(io_error)?;
+        Ok(bytes)
+    }
+
+    async fn delete(&self, bucket: Bucket, key: &str) -> Result<(), Error> {
+        match fs::remove_file(self.object_path(bucket, key)?).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(io_error(e)),
+            _ => Ok(()),
+        }
+    }
+
+    // temporary files of puts that are in progress are left out
+    async fn list(&self, bucket: Bucket) -> Result<Vec<StoredObject>, Error> {
+        let mut entries = match fs::read_dir(self.root.join(bucket.name())).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(io_error(e)),
+        };
+        let mut objects = Vec::new();
+        while let Some(entry) = entries.next_entry().await.map_err(io_error)? {
+            let key = entry.file_name().to_string_lossy().to_string();
+            if key.starts_with('.') {
+                continue;
+            }
+            let metadata = entry.metadata().await.map_err(io_error)?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let modified = metadata.modified().map_err(io_error)?;
+            objects.push(StoredObject {
+                key,
+                last_modified: DateTime::<Utc>::from(modified).naive_utc(),
+            });
+        }
+        Ok(objects)
+    }
+
+    async fn presign_put(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        content_type: &str,
+        expires_in: Duration,
+    ) -> Result<PresignedUpload, Error> {
+        check_object_key(key)?;
+        let expires = Utc::now().timestamp() + expires_in.as_secs() as i64;
+        let signature = self.signature(bucket, key, content_type, expires);
+        Ok(PresignedUpload {
+            url: format!(
+                "{}/{}/{}?expires={}&signature={}",
+                self.base_url,
+                bucket.name(),
+                key,
+                expires,
+                signature
+            ),
+            method: String::from("PUT"),
+            headers: [(String::from("content-type"), content_type.to_string())].into(),
+        })
+    }
+
+    fn base_url(&self, bucket: Bucket) -> String {
+        format!("{}/{}", self.base_url, bucket.name())
+    }
+
+    fn verify_presigned_put(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        content_type: &str,
+        expires: i64,
+        signature: &str,
+    ) -> Result<(), Error> {
+        check_object_key(key)?;
+        let invalid = || Error::StorageError(StorageErrors::InvalidSignature);
+        if expires < Utc::now().timestamp() {
+            return Err(invalid());
+        }
+        // compared in constant time so that the signature cannot be guessed byte by byte
+        let signature = hex::decode(signature).map_err(|_| invalid())?;
+        self.signature_mac(bucket, key, content_type, expires)
+            .verify_slice(&signature)
+            .map_err(|_| invalid())
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::time::Duration;
+
+    use chrono::Utc;
+    use url::Url;
+
+    use crate::{
+        connectors::buckets::store::{Bucket, ObjectStore},
+        types::error::{Error, StorageErrors},
+    };
+
+    use super::LocalObjectStore;
+
+    #[tokio::test]
+    async fn it_puts_gets_and_deletes_objects() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalObjectStore::new(dir.path(), "http://localhost:8080/storage/", "secret");
+        store.setup().await.unwrap();
+
+        store
+            .put(Bucket::LinkImages, "a.png", b"image".to_vec(), "image/png")
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get(Bucket::LinkImages, "a.png").await.unwrap(),
+            b"image".to_vec()
+        );
+        assert_eq!(
+            store
+                .get_prefix(Bucket::LinkImages, "a.png", 3)
+                .await
+                .unwrap(),
+            b"ima".to_vec()
+        );
+        assert_eq!(
+            store
+                .get_prefix(Bucket::LinkImages, "a.png", 64)
+                .await
+                .unwrap(),
+            b"image".to_vec()
+        );
+        // buckets are separate
+        assert!(matches!(
+            store.get(Bucket::ProfileImages, "a.png").await,
+            Err(Error::StorageError(StorageErrors::NotFound))
+        ));
+        assert_eq!(
+            store.base_url(Bucket::LinkImages),
+            "http://localhost:8080/storage/link-images-salad"
+        );
+
+        let objects = store.list(Bucket::LinkImages).await.unwrap();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].key, "a.png");
+        assert!(store.list(Bucket::ProfileImages).await.unwrap().is_empty());
+
+        store.delete(Bucket::LinkImages, "a.png").await.unwrap();
+        assert!(store.get(Bucket::LinkImages, "a.png").await.is_err());
+        assert!(store.list(Bucket::LinkImages).await.unwrap().is_empty());
+        // deleting twice is fine
+        assert!(store.delete(Bucket::LinkImages, "a.png").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn it_rejects_keys_outside_of_the_bucket() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalObjectStore::new(dir.path(), "http://localhost", "secret");
+        for key in ["../a.png", "a/../../b.png", ""] {
+            assert!(store
+                .put(Bucket::LinkImages, key, b"image".to_vec(), "image/png")
+                .await
+                .is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn it_verifies_presigned_uploads() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalObjectStore::new(dir.path(), "http://localhost/storage", "secret");
+        let upload = store
+            .presign_put(
+                Bucket::ProfileImages,
+                "a.png",
+                "image/png",
+                Duration::from_secs(60),
+            )
+            .await
+            .unwrap();
+        assert_eq!(upload.method, "PUT");
+
+        let url = Url::parse(&upload.url).unwrap();
+        assert_eq!(url.path(), "/storage/profile-images-salad/a.png");
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.to_string())
+                .unwrap()
+        };
+        let expires = param("expires").parse::<i64>().unwrap();
+        let signature = param("signature");
+
+        assert!(store
+            .verify_presigned_put(
+                Bucket::ProfileImages,
+                "a.png",
+                "image/png",
+                expires,
+                &signature
+            )
+            .is_ok());
+        // the signature covers the bucket, key, content type and expiry
+        assert!(store
+            .verify_presigned_put(
+                Bucket::LinkImages,
+                "a.png",
+                "image/png",
+                expires,
+                &signature
+            )
+            .is_err());
+        assert!(store
+            .verify_presigned_put(
+                Bucket::ProfileImages,
+                "b.png",
+                "image/png",
+                expires,
+                &signature
+            )
+            .is_err());
+        assert!(store
+            .verify_presigned_put(
+                Bucket::ProfileImages,
+                "a.png",
+                "text/html",
+                expires,
+                &signature
+            )
+            .is_err());
+        assert!(store
+            .verify_presigned_put(
+                Bucket::ProfileImages,
+                "a.png",
+                "image/png",
+                expires + 1,
+                &signature
+            )
+            .is_err());
+        // only the whole signature is accepted
+        for tampered in [&signature[..32], "not hex", ""] {
+            assert!(store
+                .verify_presigned_put(
+                    Bucket::ProfileImages,
+                    "a.png",
+                    "image/png",
+                    expires,
+                    tampered
+                )
+                .is_err());
+        }
+
+        // expired uploads are rejected even with a valid signature
+        let expired = Utc::now().timestamp() - 1;
+        let signature = store.signature(Bucket::ProfileImages, "a.png", "image/png", expired);
+        assert!(store
+            .verify_presigned_put(
+                Bucket::ProfileImages,
+                "a.png",
+                "image/png",
+                expired,
+                &signature
+            )
+            .is_err());
+    }
+}
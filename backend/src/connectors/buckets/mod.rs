@@ -1 +1,4 @@
 pub mod file;
+pub mod local;
+pub mod s3;
+pub mod store;
@@ -0,0 +1,401 @@
+use std::{env, time::Duration};
+
+use async_trait::async_trait;
+use aws_config::{meta::region::RegionProviderChain, BehaviorVersion};
+use aws_sdk_s3::{
+    self as s3,
+    error::SdkError,
+    operation::{
+        create_bucket::{CreateBucketError, CreateBucketOutput},
+        get_object::GetObjectError,
+    },
+    presigning::PresigningConfig,
+    primitives::ByteStream,
+    types::{BucketLocationConstraint, CreateBucketConfiguration},
+};
+use chrono::{DateTime, Utc};
+use tide::log::error;
+
+use crate::{
+    connectors::buckets::store::{Bucket, ObjectStore, PresignedUpload, StoredObject},
+    types::error::{Error, StorageErrors},
+};
+
+// object store on aws s3, served through a cdn per bucket
+pub struct S3ObjectStore {
+    client: s3::Client,
+    region: String,
+    profile_image_cdn: String,
+    link_image_cdn: String,
+}
+
+fn backend_error<E: std::fmt::Debug>(e: E) -> Error {
+    Error::StorageError(StorageErrors::Backend(format!("{:?}", e)))
+}
+
+impl S3ObjectStore {
+    pub fn new(
+        client: s3::Client,
+        region: String,
+        profile_image_cdn: String,
+        link_image_cdn: String,
+    ) -> S3ObjectStore {
+        S3ObjectStore {
+            client,
+            region,
+            profile_image_cdn,
+            link_image_cdn,
+        }
+    }
+
+    pub async fn from_env() -> S3ObjectStore {
+        let region_provider = RegionProviderChain::default_provider().or_else("ap-southeast-2");
+        let config = aws_config::defaults(BehaviorVersion::latest())
+            .region(region_provider)
+            .load()
+            .await;
+        S3ObjectStore::new(
+            s3::Client::new(&config),
+            env::var("AWS_REGION").expect("No aws region found"),
+            env::var("PROFILE_IMAGE_CDN").expect("unable to process cdn"),
+            env::var("LINK_IMAGE_CDN").expect("unable to process cdn"),
+        )
+    }
+
+    async fn create_bucket(
+        &self,
+        bucket_name: &str,
+    ) -> Result<CreateBucketOutput, SdkError<CreateBucketError>> {
+        let constraint = BucketLocationConstraint::from(self.region.as_str());
+        let cfg = CreateBucketConfiguration::builder()
+            .location_constraint(constraint)
+            .build();
+        self.client
+            .create_bucket()
+            .create_bucket_configuration(cfg)
+            .bucket(bucket_name)
+            .send()
+            .await
+    }
+}
+
+#[async_trait]
+impl ObjectStore for S3ObjectStore {
+    // buckets that already exist fail to be created, they only have to be reachable
+    async fn setup(&self) -> Result<(), Error> {
+        for bucket in Bucket::ALL {
+            if let Err(e) = self.create_bucket(bucket.name()).await {
+                self.client
+                    .head_bucket()
+                    .bucket(bucket.name())
+                    .send()
+                    .await
+                    .map_err(|head_error| {
+                        error!("creating bucket {} failed with: {:?}", bucket.name(), e);
+                        backend_error(head_error)
+                    })?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn put(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+    ) -> Result<(), Error> {
+        self.client
+            .put_object()
+            .bucket(bucket.name())
+            .key(key)
+            .content_type(content_type)
+            .body(ByteStream::from(body))
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(backend_error)
+    }
+
+    async fn get(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, Error> {
+        let object = self
+            .client
+            .get_object()
+            .bucket(bucket.name())
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| match e.as_service_error() {
+                Some(GetObjectError::NoSuchKey(_)) => Error::StorageError(StorageErrors::NotFound),
+                _ => backend_error(e),
+            })?;
+        object
+            .body
+            .collect()
+            .await
+            .map(|data| data.into_bytes().to_vec())
+            .map_err(backend_error)
+    }
+
+    // a ranged get, objects that are empty cannot satisfy a range at all
+    async fn get_prefix(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        max_len: usize,
+    ) -> Result<Vec<u8>, Error> {
+        if max_len == 0 {
+            return Ok(Vec::new());
+        }
+        let result = self
+            .client
+            .get_object()
+            .bucket(bucket.name())
+            .key(key)
+            .range(format!("bytes=0-{}", max_len - 1))
+            .send()
+            .await;
+        let object = match result {
+            Ok(object) => object,
+            Err(e)
+                if e.raw_response()
+                    .is_some_and(|res| res.status().as_u16() == 416) =>
+            {
+                return Ok(Vec::new())
+            }
+            Err(e) => {
+                return Err(match e.as_service_error() {
+                    Some(GetObjectError::NoSuchKey(_)) => {
+                        Error::StorageError(StorageErrors::NotFound)
+                    }
+                    _ => backend_error(e),
+                })
+            }
+        };
+        let mut bytes = object
+            .body
+            .collect()
+            .await
+            .map(|data| data.into_bytes().to_vec())
+            .map_err(backend_error)?;
+        bytes.truncate(max_len);
+        Ok(bytes)
+    }
+
+    async fn delete(&self, bucket: Bucket, key: &str) -> Result<(), Error> {
+        self.client
+            .delete_object()
+            .bucket(bucket.name())
+            .key(key)
+            .send()
+            .await
+            .map(|_| ())
+            .map_err(backend_error)
+    }
+
+    // listed a page at a time, objects without a modification time count as just modified
+    async fn list(&self, bucket: Bucket) -> Result<Vec<StoredObject>, Error> {
+        let mut objects = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let page = self
+                .client
+                .list_objects_v2()
+                .bucket(bucket.name())
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(backend_error)?;
+            for object in page.contents() {
+                let Some(key) = object.key() else {
+                    continue;
+                };
+                let last_modified = object
+                    .last_modified()
+                    .and_then(|time| DateTime::from_timestamp(time.secs(), time.subsec_nanos()))
+                    .unwrap_or_else(Utc::now);
+                objects.push(StoredObject {
+                    key: key.to_string(),
+                    last_modified: last_modified.naive_utc(),
+                });
+            }
+            continuation_token = page
+                .next_continuation_token()
+                .map(|token| token.to_string());
+            if continuation_token.is_none() {
+                return Ok(objects);
+            }
+        }
+    }
+
+    async fn presign_put(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        content_type: &str,
+        expires_in: Duration,
+    ) -> Result<PresignedUpload, Error> {
+        let config = PresigningConfig::expires_in(expires_in).map_err(backend_error)?;
+        let request = self
+            .client
+            .put_object()
+            .bucket(bucket.name())
+            .key(key)
+            .content_type(content_type)
+            .presigned(config)
+            .await
+            .map_err(backend_error)?;
+        Ok(PresignedUpload {
+            url: request.uri().to_string(),
+            method: request.method().to_string(),
+            headers: request
+                .headers()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        })
+    }
+
+    fn base_url(&self, bucket: Bucket) -> String {
+        let cdn = match bucket {
+            Bucket::ProfileImages => &self.profile_image_cdn,
+            Bucket::LinkImages => &self.link_image_cdn,
+        };
+        cdn.trim_end_matches('/').to_string()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::time::Duration;
+
+    use aws_config::{meta::region::RegionProviderChain, BehaviorVersion};
+    use aws_sdk_s3::{
+        self as s3,
+        config::{retry::RetryConfig, Credentials, Region},
+    };
+    use url::Url;
+
+    use crate::connectors::buckets::store::{Bucket, ObjectStore};
+
+    use super::S3ObjectStore;
+
+    // signs with fixed credentials and sends to a port nothing listens on, so no aws is needed
+    fn create_offline_s3_store() -> S3ObjectStore {
+        let config = s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("ap-southeast-2"))
+            .credentials_provider(Credentials::new("AKIDTEST", "secret", None, None, "test"))
+            .endpoint_url("http://127.0.0.1:9")
+            .force_path_style(true)
+            .retry_config(RetryConfig::disabled())
+            .build();
+        S3ObjectStore::new(
+            s3::Client::from_conf(config),
+            String::from("ap-southeast-2"),
+            String::from("https://profile.cdn.example.com/"),
+            String::from("https://link.cdn.example.com"),
+        )
+    }
+
+    async fn create_s3_store() -> S3ObjectStore {
+        let region_provider = RegionProviderChain::default_provider().or_else("ap-southeast-2");
+        let config = aws_config::defaults(BehaviorVersion::latest())
+            .region(region_provider)
+            .load()
+            .await;
+        S3ObjectStore::new(
+            s3::Client::new(&config),
+            String::from("ap-southeast-2"),
+            String::new(),
+            String::new(),
+        )
+    }
+
+    #[tokio::test]
+    async fn it_presigns_puts_of_the_content_type() {
+        let store = create_offline_s3_store();
+        let upload = store
+            .presign_put(
+                Bucket::ProfileImages,
+                "upload-1.png",
+                "image/png",
+                Duration::from_secs(600),
+            )
+            .await
+            .unwrap();
+        assert_eq!(upload.method, "PUT");
+        assert_eq!(upload.headers["content-type"], "image/png");
+
+        let url = Url::parse(&upload.url).unwrap();
+        assert_eq!(
+            url.path(),
+            format!("/{}/upload-1.png", Bucket::ProfileImages.name())
+        );
+        let query = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        assert_eq!(query("X-Amz-Expires").as_deref(), Some("600"));
+        assert!(query("X-Amz-Signature").is_some());
+        assert!(query("X-Amz-SignedHeaders")
+            .unwrap()
+            .split(';')
+            .any(|header| header == "content-type"));
+    }
+
+    #[tokio::test]
+    async fn it_serves_buckets_from_their_cdn() {
+        let store = create_offline_s3_store();
+        assert_eq!(
+            store.base_url(Bucket::ProfileImages),
+            "https://profile.cdn.example.com"
+        );
+        assert_eq!(
+            store.base_url(Bucket::LinkImages),
+            "https://link.cdn.example.com"
+        );
+    }
+
+    #[tokio::test]
+    async fn it_fails_when_s3_is_unreachable() {
+        let store = create_offline_s3_store();
+        assert!(store.get(Bucket::ProfileImages, "-123").await.is_err());
+        assert!(store.setup().await.is_err());
+    }
+
+    // run with `cargo test -- --ignored` against the buckets of the configured aws account
+    #[tokio::test]
+    #[ignore = "needs aws credentials and the buckets"]
+    async fn it_puts_gets_and_deletes_objects_on_s3() {
+        let store = create_s3_store().await;
+        let key = format!("test-object-{}.txt", uuid::Uuid::new_v4());
+        store
+            .put(
+                Bucket::ProfileImages,
+                &key,
+                b"old content".to_vec(),
+                "text/plain",
+            )
+            .await
+            .unwrap();
+        store
+            .put(
+                Bucket::ProfileImages,
+                &key,
+                b"updated content".to_vec(),
+                "text/plain",
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get(Bucket::ProfileImages, &key).await.unwrap(),
+            b"updated content"
+        );
+
+        store.delete(Bucket::ProfileImages, &key).await.unwrap();
+        assert!(store.get(Bucket::ProfileImages, &key).await.is_err());
+        assert!(store.get(Bucket::ProfileImages, "-123").await.is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use std::{collections::HashMap, env, time::Duration};
+
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use crate::{
+    connectors::buckets::{local::LocalObjectStore, s3::S3ObjectStore},
+    types::error::{Error, StorageErrors},
+};
+
+// defines the object store trait which the buckets are accessed through
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Bucket {
+    ProfileImages,
+    LinkImages,
+}
+
+impl Bucket {
+    pub const ALL: [Bucket; 2] = [Bucket::ProfileImages, Bucket::LinkImages];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Bucket::ProfileImages => "profile-images-salad",
+            Bucket::LinkImages => "link-images-salad",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Bucket> {
+        Bucket::ALL.into_iter().find(|bucket| bucket.name() == name)
+    }
+}
+
+// a request that uploads an object straight to the store, without going through the backend
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PresignedUpload {
+    pub url: String,
+    pub method: String,
+    // headers that have to be sent as they are signed
+    pub headers: HashMap<String, String>,
+}
+
+// an object as it is listed in its bucket
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredObject {
+    pub key: String,
+    pub last_modified: NaiveDateTime,
+}
+
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    // creates the buckets if they do not exist yet
+    async fn setup(&self) -> Result<(), Error>;
+    async fn put(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        body: Vec<u8>,
+        content_type: &str,
+    ) -> Result<(), Error>;
+    async fn get(&self, bucket: Bucket, key: &str) -> Result<Vec<u8>, Error>;
+    // at most the first max_len bytes of the object, the rest is never read
+    async fn get_prefix(&self, bucket: Bucket, key: &str, max_len: usize)
+        -> Result<Vec<u8>, Error>;
+    // deleting an object that does not exist is not an error
+    async fn delete(&self, bucket: Bucket, key: &str) -> Result<(), Error>;
+    // every object in the bucket
+    async fn list(&self, bucket: Bucket) -> Result<Vec<StoredObject>, Error>;
+    async fn presign_put(
+        &self,
+        bucket: Bucket,
+        key: &str,
+        content_type: &str,
+        expires_in: Duration,
+    ) -> Result<PresignedUpload, Error>;
+    // url that the objects of a bucket are publicly served under, without a trailing slash
+    fn base_url(&self, bucket: Bucket) -> String;
+    // checks an upload that was presigned to the backend itself, other stores never sign those
+    fn verify_presigned_put(
+        &self,
+        _bucket: Bucket,
+        _key: &str,
+        _content_type: &str,
+        _expires: i64,
+        _signature: &str,
+    ) -> Result<(), Error> {
+        Err(Error::StorageError(StorageErrors::InvalidSignature))
+    }
+}
+
+// keys are single path segments so that they cannot escape their bucket
+pub fn check_object_key(key: &str) -> Result<(), Error> {
+    let is_valid = !key.is_empty()
+        && key.len() <= 255
+        && key != "."
+        && key != ".."
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if !is_valid {
+        return Err(Error::StorageError(StorageErrors::InvalidKey));
+    }
+    Ok(())
+}
+
+// OBJECT_STORE picks the store, s3 (default) or local
+pub fn is_local_object_store() -> bool {
+    env::var("OBJECT_STORE").is_ok_and(|store| store == "local")
+}
+
+pub async fn object_store_from_env() -> Box<dyn ObjectStore> {
+    if is_local_object_store() {
+        Box::new(LocalObjectStore::from_env())
+    } else {
+        Box::new(S3ObjectStore::from_env().await)
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::{check_object_key, Bucket};
+
+    #[test]
+    fn it_checks_object_keys() {
+        for key in ["a.png", "0b6c-64.webp", "test_user"] {
+            assert!(check_object_key(key).is_ok(), "{}", key);
+        }
+        for key in ["", ".", "..", "../secret", "a/b.png", "a\\b", "%2e%2e"] {
+            assert!(check_object_key(key).is_err(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn it_finds_buckets_by_name() {
+        for bucket in Bucket::ALL {
+            assert_eq!(Bucket::from_name(bucket.name()), Some(bucket));
+        }
+        assert_eq!(Bucket::from_name("other-bucket"), None);
+    }
+}
@@ -0,0 +1,100 @@
+use chrono::NaiveDateTime;
+use diesel::{
+    BoolExpressionMethods, ExpressionMethods, JoinOnDsl, NullableExpressionMethods, PgConnection,
+    PgSortExpressionMethods, PgTextExpressionMethods, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+
+use crate::models::{
+    health::{GetLinkHealth, LinkHealthCheck},
+    links::GetLink,
+};
+
+// web links that were never checked or not since checked_before, least recently checked first.
+// mailto and tel links have nothing to probe
+pub async fn get_links_due_for_check(
+    conn: &mut PgConnection,
+    checked_before: NaiveDateTime,
+    limit: i64,
+) -> Result<Vec<GetLink>, diesel::result::Error> {
+    use crate::schema::{link_health, links};
+    links::table
+        .left_join(link_health::table.on(link_health::link_id.eq(links::id)))
+        .filter(
+            link_health::checked_at
+                .nullable()
+                .is_null()
+                .or(link_health::checked_at.lt(checked_before)),
+        )
+        .filter(
+            links::href
+                .ilike("http://%")
+                .or(links::href.ilike("https://%")),
+        )
+        .order_by((
+            link_health::checked_at.nullable().asc().nulls_first(),
+            links::id,
+        ))
+        .limit(limit)
+        .select(GetLink::as_select())
+        .load::<GetLink>(conn)
+}
+
+// stores the check, failures are counted until the link is healthy again
+pub async fn record_link_check(
+    conn: &mut PgConnection,
+    check: &LinkHealthCheck,
+) -> Result<GetLinkHealth, diesel::result::Error> {
+    use crate::schema::link_health::dsl::*;
+    let insert = diesel::insert_into(link_health).values((
+        link_id.eq(check.link_id),
+        status_code.eq(check.status_code),
+        latency_ms.eq(check.latency_ms),
+        checked_at.eq(check.checked_at),
+        consecutive_failures.eq(if check.is_healthy { 0 } else { 1 }),
+        is_notified.eq(false),
+    ));
+    let result = (
+        status_code.eq(check.status_code),
+        latency_ms.eq(check.latency_ms),
+        checked_at.eq(check.checked_at),
+    );
+    if check.is_healthy {
+        insert
+            .on_conflict(link_id)
+            .do_update()
+            .set((result, consecutive_failures.eq(0), is_notified.eq(false)))
+            .returning(GetLinkHealth::as_returning())
+            .get_result(conn)
+    } else {
+        insert
+            .on_conflict(link_id)
+            .do_update()
+            .set((result, consecutive_failures.eq(consecutive_failures + 1)))
+            .returning(GetLinkHealth::as_returning())
+            .get_result(conn)
+    }
+}
+
+pub async fn mark_link_health_notified(
+    conn: &mut PgConnection,
+    health_link_id: i32,
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::link_health::dsl::*;
+    diesel::update(link_health.filter(link_id.eq(health_link_id)))
+        .set(is_notified.eq(true))
+        .execute(conn)
+        .map(|_| ())
+}
+
+// the latest check of every link of the user that was checked
+pub async fn get_user_link_health(
+    conn: &mut PgConnection,
+    userid: i32,
+) -> Result<Vec<GetLinkHealth>, diesel::result::Error> {
+    use crate::schema::{link_health, links};
+    link_health::table
+        .inner_join(links::table)
+        .filter(links::user_id.eq(userid))
+        .select(GetLinkHealth::as_select())
+        .load::<GetLinkHealth>(conn)
+}
@@ -94,3 +94,19 @@ pub async fn delete_link_image(
         .execute(conn)
         .map(|_| ())
 }
+
+// every image row, including rows whose owner was deleted
+pub async fn get_all_images(
+    conn: &mut PgConnection,
+) -> Result<Vec<GetImage>, diesel::result::Error> {
+    use crate::schema::images::dsl::*;
+    images.select(GetImage::as_select()).load::<GetImage>(conn)
+}
+
+// rows left behind when their user was deleted, as user_id is set to null
+pub async fn delete_detached_images(
+    conn: &mut PgConnection,
+) -> Result<usize, diesel::result::Error> {
+    use crate::schema::images::dsl::*;
+    diesel::delete(images.filter(user_id.is_null()).filter(link_id.is_null())).execute(conn)
+}
@@ -1,7 +1,16 @@
-use diesel::{ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper};
+use chrono::{DurationRound, NaiveDateTime, TimeDelta};
+use diesel::{
+    sql_types::{Int4, Int8, Text, Timestamp},
+    AggregateExpressionMethods, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    SelectableHelper,
+};
 
 use crate::{
-    models::insights::{GetUserInsight, UpdateUserInsight},
+    models::insights::{
+        GetInsightSource, GetLinkInsight, GetLinkInsightSource, GetUserInsight, InsertProfileShare,
+        InsertProfileVisitor, InsightSourceType, InsightWindow, UpdateLinkInsight,
+        UpdateLinkInsightSource, UpdateUserInsight, UpdateUserInsightSource,
+    },
     types::error::Error,
 };
 
@@ -16,6 +25,8 @@ pub async fn update_user_insights(
         .do_update()
         .set((
             view_count.eq(view_count + update_user_insight.view_count.unwrap_or(0)),
+            unique_view_count
+                .eq(unique_view_count + update_user_insight.unique_view_count.unwrap_or(0)),
             follow_count.eq(follow_count + update_user_insight.follow_count.unwrap_or(0)),
             unfollow_count.eq(unfollow_count + update_user_insight.unfollow_count.unwrap_or(0)),
             follow_request_count
@@ -38,3 +49,294 @@ pub async fn get_user_insights(
         .load::<GetUserInsight>(conn)
         .map_err(|e| Error::DieselError(e))
 }
+
+// gets the next page of user insight buckets after the given bucket in ascending order,
+// used to read the whole history without loading it all at once
+pub async fn get_user_insights_page(
+    conn: &mut PgConnection,
+    user_id: i32,
+    after_bucket: Option<NaiveDateTime>,
+    page_size: i64,
+) -> Result<Vec<GetUserInsight>, Error> {
+    use crate::schema::user_insights;
+    let mut query = user_insights::table
+        .filter(user_insights::user_id.eq(user_id))
+        .into_boxed();
+    if let Some(after_bucket) = after_bucket {
+        query = query.filter(user_insights::created_bucket.gt(after_bucket));
+    }
+    query
+        .order(user_insights::created_bucket.asc())
+        .limit(page_size)
+        .select(GetUserInsight::as_select())
+        .load::<GetUserInsight>(conn)
+        .map_err(Error::DieselError)
+}
+
+pub async fn update_link_insights(
+    conn: &mut PgConnection,
+    update_link_insight: UpdateLinkInsight,
+) -> Result<(), Error> {
+    use crate::schema::link_insights::dsl::*;
+    diesel::insert_into(link_insights)
+        .values(&update_link_insight)
+        .on_conflict((created_bucket, link_id))
+        .do_update()
+        .set(click_count.eq(click_count + update_link_insight.click_count.unwrap_or(0)))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Error::DieselError)
+}
+
+// gets the click buckets of every link owned by the user
+pub async fn get_link_insights(
+    conn: &mut PgConnection,
+    user_id: i32,
+) -> Result<Vec<GetLinkInsight>, Error> {
+    use crate::schema::link_insights;
+    use crate::schema::links;
+    link_insights::table
+        .inner_join(links::table)
+        .filter(links::user_id.eq(user_id))
+        .select(GetLinkInsight::as_select())
+        .load::<GetLinkInsight>(conn)
+        .map_err(Error::DieselError)
+}
+
+// gets the user insights of the window regrouped into buckets of the window granularity,
+// buckets without any insights are zero filled. the first and last buckets are cut off at
+// the window, so the first bucket starts at the hour of from. unique views are the distinct visitors
+// of each bucket rather than the sum of the hourly unique views
+pub async fn get_user_insights_in_window(
+    conn: &mut PgConnection,
+    user_id: i32,
+    window: InsightWindow,
+) -> Result<Vec<GetUserInsight>, Error> {
+    diesel::sql_query(
+        "WITH buckets AS ( \
+            SELECT GREATEST(series.bucket, DATE_TRUNC('hour', $3)) AS bucket_start, \
+                LEAST(series.bucket + ('1 ' || $2)::INTERVAL, $4) AS bucket_end \
+            FROM generate_series( \
+                DATE_TRUNC($2, $3), $4 - INTERVAL '1 microsecond', ('1 ' || $2)::INTERVAL \
+            ) AS series(bucket) \
+        ) \
+        SELECT $1 AS user_id, \
+            buckets.bucket_start AS created_bucket, \
+            COALESCE(SUM(user_insights.view_count), 0)::INT4 AS view_count, \
+            ( \
+                SELECT COUNT(DISTINCT profile_visitors.visitor_key) \
+                FROM profile_visitors \
+                WHERE profile_visitors.user_id = $1 \
+                    AND profile_visitors.created_bucket >= buckets.bucket_start \
+                    AND profile_visitors.created_bucket < buckets.bucket_end \
+            )::INT4 AS unique_view_count, \
+            COALESCE(SUM(user_insights.follow_count), 0)::INT4 AS follow_count, \
+            COALESCE(SUM(user_insights.unfollow_count), 0)::INT4 AS unfollow_count, \
+            COALESCE(SUM(user_insights.follow_request_count), 0)::INT4 AS follow_request_count, \
+            COALESCE(SUM(user_insights.share_count), 0)::INT4 AS share_count \
+        FROM buckets \
+        LEFT JOIN user_insights \
+            ON user_insights.user_id = $1 \
+            AND user_insights.created_bucket >= buckets.bucket_start \
+            AND user_insights.created_bucket < buckets.bucket_end \
+        GROUP BY buckets.bucket_start, buckets.bucket_end \
+        ORDER BY buckets.bucket_start",
+    )
+    .bind::<Int4, _>(user_id)
+    .bind::<Text, _>(window.granularity.as_str())
+    .bind::<Timestamp, _>(window.from)
+    .bind::<Timestamp, _>(window.to)
+    .load::<GetUserInsight>(conn)
+    .map_err(Error::DieselError)
+}
+
+// gets the click buckets of every link owned by the user in the window,
+// every link gets a zero filled series even if it was never clicked
+pub async fn get_link_insights_in_window(
+    conn: &mut PgConnection,
+    user_id: i32,
+    window: InsightWindow,
+) -> Result<Vec<GetLinkInsight>, Error> {
+    diesel::sql_query(
+        "WITH buckets AS ( \
+            SELECT GREATEST(series.bucket, DATE_TRUNC('hour', $3)) AS bucket_start, \
+                LEAST(series.bucket + ('1 ' || $2)::INTERVAL, $4) AS bucket_end \
+            FROM generate_series( \
+                DATE_TRUNC($2, $3), $4 - INTERVAL '1 microsecond', ('1 ' || $2)::INTERVAL \
+            ) AS series(bucket) \
+        ) \
+        SELECT links.id AS link_id, \
+            buckets.bucket_start AS created_bucket, \
+            COALESCE(SUM(link_insights.click_count), 0)::INT4 AS click_count \
+        FROM links \
+        CROSS JOIN buckets \
+        LEFT JOIN link_insights \
+            ON link_insights.link_id = links.id \
+            AND link_insights.created_bucket >= buckets.bucket_start \
+            AND link_insights.created_bucket < buckets.bucket_end \
+        WHERE links.user_id = $1 \
+        GROUP BY links.id, buckets.bucket_start \
+        ORDER BY links.id, buckets.bucket_start",
+    )
+    .bind::<Int4, _>(user_id)
+    .bind::<Text, _>(window.granularity.as_str())
+    .bind::<Timestamp, _>(window.from)
+    .bind::<Timestamp, _>(window.to)
+    .load::<GetLinkInsight>(conn)
+    .map_err(Error::DieselError)
+}
+
+// counts the distinct visitors of the profile in the window. anonymous visitor keys
+// rotate daily, so an anonymous visitor is counted once per day of the window
+pub async fn count_profile_visitors_in_window(
+    conn: &mut PgConnection,
+    user_id: i32,
+    window: InsightWindow,
+) -> Result<i32, Error> {
+    use crate::schema::profile_visitors;
+    // the hour from falls in is kept, its visitors are only stored by the hour
+    let from_bucket = window
+        .from
+        .duration_trunc(TimeDelta::hours(1))
+        .unwrap_or(window.from);
+    profile_visitors::table
+        .filter(profile_visitors::user_id.eq(user_id))
+        .filter(profile_visitors::created_bucket.ge(from_bucket))
+        .filter(profile_visitors::created_bucket.lt(window.to))
+        .select(diesel::dsl::count(profile_visitors::visitor_key).aggregate_distinct())
+        .get_result::<i64>(conn)
+        .map(|count| count as i32)
+        .map_err(Error::DieselError)
+}
+
+// records the visitor in the current hourly bucket of the profile,
+// returns true if the visitor had not been seen in that bucket yet.
+// visitors are kept so unique views can be counted over any window
+pub async fn record_profile_visitor(
+    conn: &mut PgConnection,
+    profile_visitor: InsertProfileVisitor,
+) -> Result<bool, Error> {
+    use crate::schema::profile_visitors::dsl::*;
+    diesel::insert_into(profile_visitors)
+        .values(&profile_visitor)
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .map(|inserted| inserted > 0)
+        .map_err(Error::DieselError)
+}
+
+// records the share in the current hourly bucket of the profile,
+// returns false if the viewer already shared the profile in that bucket through any channel
+pub async fn record_profile_share(
+    conn: &mut PgConnection,
+    profile_share: InsertProfileShare,
+) -> Result<bool, Error> {
+    use crate::schema::profile_shares::dsl::*;
+
+    // shares of earlier buckets are never needed again
+    let current_bucket = profile_share
+        .created_at
+        .duration_trunc(TimeDelta::hours(1))
+        .unwrap_or(profile_share.created_at);
+    diesel::delete(
+        profile_shares
+            .filter(user_id.eq(profile_share.user_id))
+            .filter(created_bucket.lt(current_bucket)),
+    )
+    .execute(conn)
+    .map_err(Error::DieselError)?;
+
+    diesel::insert_into(profile_shares)
+        .values(&profile_share)
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .map(|inserted| inserted > 0)
+        .map_err(Error::DieselError)
+}
+
+pub async fn update_user_insight_sources(
+    conn: &mut PgConnection,
+    update_sources: Vec<UpdateUserInsightSource>,
+) -> Result<(), Error> {
+    use crate::schema::user_insight_sources::dsl::*;
+    diesel::insert_into(user_insight_sources)
+        .values(&update_sources)
+        .on_conflict((user_id, created_bucket, source_type, source))
+        .do_update()
+        .set(view_count.eq(view_count + diesel::upsert::excluded(view_count)))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Error::DieselError)
+}
+
+// gets the sources with the most views in the window, most viewed first
+pub async fn get_top_user_insight_sources(
+    conn: &mut PgConnection,
+    user_id: i32,
+    source_type: InsightSourceType,
+    window: InsightWindow,
+    limit: i64,
+) -> Result<Vec<GetInsightSource>, Error> {
+    diesel::sql_query(
+        "SELECT source, SUM(view_count)::INT8 AS view_count \
+        FROM user_insight_sources \
+        WHERE user_id = $1 \
+            AND source_type = $2 \
+            AND created_bucket >= DATE_TRUNC('hour', $3) \
+            AND created_bucket < $4 \
+        GROUP BY source \
+        ORDER BY view_count DESC, source \
+        LIMIT $5",
+    )
+    .bind::<Int4, _>(user_id)
+    .bind::<Text, _>(source_type.as_str())
+    .bind::<Timestamp, _>(window.from)
+    .bind::<Timestamp, _>(window.to)
+    .bind::<Int8, _>(limit)
+    .load::<GetInsightSource>(conn)
+    .map_err(Error::DieselError)
+}
+
+pub async fn update_link_insight_sources(
+    conn: &mut PgConnection,
+    update_source: UpdateLinkInsightSource,
+) -> Result<(), Error> {
+    use crate::schema::link_insight_sources::dsl::*;
+    diesel::insert_into(link_insight_sources)
+        .values(&update_source)
+        .on_conflict((link_id, created_bucket, source))
+        .do_update()
+        .set(click_count.eq(click_count + diesel::upsert::excluded(click_count)))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Error::DieselError)
+}
+
+// gets the link and source pairs with the most clicks in the window over all links
+// of the user, most clicked first
+pub async fn get_top_link_insight_sources(
+    conn: &mut PgConnection,
+    user_id: i32,
+    window: InsightWindow,
+    limit: i64,
+) -> Result<Vec<GetLinkInsightSource>, Error> {
+    diesel::sql_query(
+        "SELECT link_insight_sources.link_id, \
+            link_insight_sources.source, \
+            SUM(link_insight_sources.click_count)::INT8 AS click_count \
+        FROM link_insight_sources \
+        INNER JOIN links ON links.id = link_insight_sources.link_id \
+        WHERE links.user_id = $1 \
+            AND link_insight_sources.created_bucket >= DATE_TRUNC('hour', $2) \
+            AND link_insight_sources.created_bucket < $3 \
+        GROUP BY link_insight_sources.link_id, link_insight_sources.source \
+        ORDER BY click_count DESC, link_insight_sources.link_id, link_insight_sources.source \
+        LIMIT $4",
+    )
+    .bind::<Int4, _>(user_id)
+    .bind::<Timestamp, _>(window.from)
+    .bind::<Timestamp, _>(window.to)
+    .bind::<Int8, _>(limit)
+    .load::<GetLinkInsightSource>(conn)
+    .map_err(Error::DieselError)
+}
@@ -4,9 +4,15 @@ use diesel::{
     SelectableHelper,
 };
 
+use std::collections::HashMap;
+
+use crate::helpers::validation::validate_link_update_window;
 use crate::models::images::GetImage;
-use crate::models::links::{GetLink, InsertLink, UpdateLink};
-use crate::schema;
+use crate::models::links::{
+    GetLink, InsertLink, LinkBatchOperation, LinkBatchResult, LinkRef, UpdateLink,
+};
+use crate::schema::{self, images};
+use crate::types::error::{AssociationErrors, Error};
 
 // create a link from a link model instance
 pub async fn create(
@@ -20,6 +26,7 @@ pub async fn create(
         .get_result(conn)
 }
 
+// moves the link in front of new_position_id, or to the end if there is none
 pub async fn reorder_link(
     conn: &mut PgConnection,
     curr_link_id: i32,
@@ -46,7 +53,7 @@ pub async fn get_link_by_id(
         .first::<GetLink>(conn)
 }
 
-// get all user links
+// get all user links in order
 pub async fn get_user_links_by_id(
     conn: &mut PgConnection,
     userid: i32,
@@ -56,7 +63,8 @@ pub async fn get_user_links_by_id(
 
     diesel::QueryDsl::left_join(links.filter(user_id.eq(userid)), dsl::images)
         .select((GetLink::as_select(), Option::<GetImage>::as_select()))
-        .distinct_on(schema::links::id)
+        .distinct_on((schema::links::position, schema::links::id))
+        .order_by((schema::links::position, schema::links::id))
         .load::<(GetLink, Option<GetImage>)>(conn)
 }
 
@@ -86,17 +94,19 @@ pub async fn link_id_belongs_to_user(
         .map(|count| count > 0)
 }
 
+// applies all the given fields in one transaction and returns the updated link
 pub async fn update_link_by_id(
     conn: &mut PgConnection,
     update_link: &UpdateLink,
     link_id: i32,
-) -> Result<bool, diesel::result::Error> {
+) -> Result<GetLink, diesel::result::Error> {
     use crate::schema::links::dsl::*;
-    diesel::update(links.filter(id.eq(link_id)))
-        .set(update_link)
-        .returning(id)
-        .get_result::<i32>(conn)
-        .map(|v| v == link_id)
+    conn.transaction(|c| {
+        diesel::update(links.filter(id.eq(link_id)))
+            .set(update_link)
+            .returning(GetLink::as_returning())
+            .get_result::<GetLink>(c)
+    })
 }
 
 pub async fn delete_link_by_id(
@@ -109,3 +119,181 @@ pub async fn delete_link_by_id(
         .get_result::<i32>(conn)
         .map(|res| res == link_id)
 }
+
+// ids of all users that have links
+pub async fn get_link_owner_ids(
+    conn: &mut PgConnection,
+) -> Result<Vec<i32>, diesel::result::Error> {
+    use crate::schema::links::dsl::*;
+    links
+        .select(user_id)
+        .distinct()
+        .order_by(user_id)
+        .load::<i32>(conn)
+}
+
+// get all user links by position without images
+pub async fn get_user_link_order(
+    conn: &mut PgConnection,
+    userid: i32,
+) -> Result<Vec<GetLink>, diesel::result::Error> {
+    use crate::schema::links::dsl::*;
+    links
+        .filter(user_id.eq(userid))
+        .select(GetLink::as_select())
+        .order_by((position, id))
+        .load::<GetLink>(conn)
+}
+
+// takes the user's links out of their sections, keeping their position
+pub async fn clear_link_sections(
+    conn: &mut PgConnection,
+    userid: i32,
+    link_ids: &[i32],
+) -> Result<usize, diesel::result::Error> {
+    use crate::schema::links::dsl::*;
+    diesel::update(links.filter(user_id.eq(userid).and(id.eq_any(link_ids))))
+        .set(section_id.eq(None::<i32>))
+        .execute(conn)
+}
+
+// resolves the link of a batch operation, it has to belong to the user
+fn resolve_link_ref(
+    conn: &mut PgConnection,
+    userid: i32,
+    link_ref: &LinkRef,
+    temp_ids: &HashMap<String, i32>,
+) -> Result<i32, Error> {
+    use crate::schema::links::dsl::*;
+    let link_id = match link_ref {
+        LinkRef::Id(link_id) => *link_id,
+        LinkRef::Temp(temp_id) => match temp_ids.get(temp_id) {
+            Some(link_id) => *link_id,
+            None => return Err(Error::NotFoundError(format!("Link {}", temp_id))),
+        },
+    };
+
+    let is_user_link = links
+        .filter(id.eq(link_id).and(user_id.eq(userid)))
+        .count()
+        .get_result::<i64>(conn)?
+        > 0;
+    if !is_user_link {
+        return Err(Error::AssociationError(
+            AssociationErrors::LinkDoesNotBelongToUser,
+        ));
+    }
+    Ok(link_id)
+}
+
+// links can only be put into sections of the same user
+fn assert_user_section(
+    conn: &mut PgConnection,
+    userid: i32,
+    section_id: Option<i32>,
+) -> Result<(), Error> {
+    use crate::schema::link_sections;
+    let Some(section_id) = section_id else {
+        return Ok(());
+    };
+    let is_user_section = link_sections::table
+        .filter(
+            link_sections::id
+                .eq(section_id)
+                .and(link_sections::user_id.eq(userid)),
+        )
+        .count()
+        .get_result::<i64>(conn)?
+        > 0;
+    if !is_user_section {
+        return Err(Error::AssociationError(
+            AssociationErrors::SectionDoesNotBelongToUser,
+        ));
+    }
+    Ok(())
+}
+
+fn apply_link_batch_operation(
+    conn: &mut PgConnection,
+    userid: i32,
+    operation: &LinkBatchOperation,
+    result: &mut LinkBatchResult,
+) -> Result<(), Error> {
+    use crate::schema::links::dsl::*;
+    match operation {
+        LinkBatchOperation::Create {
+            temp_id,
+            insert_link,
+        } => {
+            assert_user_section(conn, userid, insert_link.section_id)?;
+            let link_id = diesel::insert_into(links)
+                .values(insert_link)
+                .returning(id)
+                .get_result::<i32>(conn)?;
+            if let Some(temp_id) = temp_id {
+                result.temp_ids.insert(temp_id.clone(), link_id);
+            }
+        }
+        LinkBatchOperation::Update {
+            link_ref,
+            update_link,
+        } => {
+            let link_id = resolve_link_ref(conn, userid, link_ref, &result.temp_ids)?;
+            let link = links
+                .filter(id.eq(link_id))
+                .select(GetLink::as_select())
+                .first::<GetLink>(conn)?;
+            validate_link_update_window(&link, update_link)?;
+            assert_user_section(conn, userid, update_link.section_id.flatten())?;
+            diesel::update(links.filter(id.eq(link_id)))
+                .set(update_link)
+                .execute(conn)?;
+        }
+        LinkBatchOperation::Delete { link_ref } => {
+            let link_id = resolve_link_ref(conn, userid, link_ref, &result.temp_ids)?;
+            let deleted_images = diesel::delete(images::table.filter(images::link_id.eq(link_id)))
+                .returning(GetImage::as_returning())
+                .get_results::<GetImage>(conn)?;
+            result
+                .deleted_image_filenames
+                .extend(deleted_images.iter().flat_map(|image| image.filenames()));
+            diesel::delete(links.filter(id.eq(link_id))).execute(conn)?;
+        }
+        LinkBatchOperation::Move {
+            link_ref,
+            new_position,
+        } => {
+            let link_id = resolve_link_ref(conn, userid, link_ref, &result.temp_ids)?;
+            let new_position_id = match new_position {
+                Some(new_position) => Some(resolve_link_ref(
+                    conn,
+                    userid,
+                    new_position,
+                    &result.temp_ids,
+                )?),
+                None => None,
+            };
+            diesel::sql_query("SELECT reorder_link($1, $2)")
+                .bind::<Integer, _>(link_id)
+                .bind::<Nullable<Integer>, _>(new_position_id)
+                .execute(conn)?;
+        }
+    }
+    Ok(())
+}
+
+// applies the operations of the user in order, either all of them or none
+pub async fn apply_link_batch(
+    conn: &mut PgConnection,
+    userid: i32,
+    operations: &[LinkBatchOperation],
+) -> Result<LinkBatchResult, Error> {
+    conn.transaction(|c| {
+        let mut result = LinkBatchResult::default();
+        for (index, operation) in operations.iter().enumerate() {
+            apply_link_batch_operation(c, userid, operation, &mut result)
+                .map_err(|e| Error::BatchOperationError(index, Box::new(e)))?;
+        }
+        Ok(result)
+    })
+}
@@ -1,11 +1,18 @@
 pub mod connection;
 pub mod follow;
+pub mod health;
 pub mod image;
 pub mod insight;
 pub mod link;
 pub mod notifications;
+pub mod rate_limit;
 pub mod reset;
+pub mod section;
+pub mod session;
+pub mod two_factor;
+pub mod upload;
 pub mod user;
+pub mod verification;
 
 use diesel::{Connection, PgConnection};
 use dotenvy::dotenv;
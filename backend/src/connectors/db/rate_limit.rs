@@ -0,0 +1,111 @@
+use chrono::NaiveDateTime;
+use diesel::{
+    sql_types::{Nullable, Text, Timestamp},
+    Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl,
+    SelectableHelper,
+};
+
+use crate::{models::rate_limits::GetRateLimit, types::error::Error};
+
+// queries backing the postgres rate limit store
+
+pub async fn get_rate_limit(
+    conn: &mut PgConnection,
+    rate_limit_key: &str,
+) -> Result<Option<GetRateLimit>, Error> {
+    use crate::schema::rate_limits::dsl::*;
+    rate_limits
+        .filter(key.eq(rate_limit_key))
+        .select(GetRateLimit::as_select())
+        .first::<GetRateLimit>(conn)
+        .optional()
+        .map_err(Error::DieselError)
+}
+
+// counts an attempt as a failure before it is made. the row is locked from reading the failures
+// so far to counting the attempt, so that instances reserving at the same time count one after
+// the other. reserve is given the failures so far and returns the failures with the attempt,
+// or none when it has to wait and nothing is counted
+pub async fn reserve_rate_limit_attempt(
+    conn: &mut PgConnection,
+    rate_limit_key: &str,
+    now: NaiveDateTime,
+    reserve: impl FnOnce(Option<&GetRateLimit>) -> Option<i32>,
+) -> Result<(), Error> {
+    use crate::schema::rate_limits::dsl::*;
+    conn.transaction(|conn| {
+        // a row with no failures to lock, for keys that have none yet
+        diesel::insert_into(rate_limits)
+            .values((
+                key.eq(rate_limit_key),
+                failures.eq(0),
+                last_failure_at.eq(now),
+            ))
+            .on_conflict(key)
+            .do_nothing()
+            .execute(conn)?;
+        let current = rate_limits
+            .filter(key.eq(rate_limit_key))
+            .select(GetRateLimit::as_select())
+            .for_update()
+            .first::<GetRateLimit>(conn)?;
+        let current = Some(&current).filter(|current| current.failures > 0);
+        let Some(reserved_failures) = reserve(current) else {
+            return Ok(());
+        };
+        diesel::update(rate_limits.filter(key.eq(rate_limit_key)))
+            .set((failures.eq(reserved_failures), last_failure_at.eq(now)))
+            .execute(conn)
+            .map(|_| ())
+    })
+    .map_err(Error::DieselError)
+}
+
+// takes back an attempt reserved at reserved_at, putting back the last failure from before it
+// unless another attempt was reserved since. keys left without failures are removed
+pub async fn undo_rate_limit_attempt(
+    conn: &mut PgConnection,
+    rate_limit_key: &str,
+    previous_failure_at: Option<NaiveDateTime>,
+    reserved_at: NaiveDateTime,
+) -> Result<(), Error> {
+    conn.transaction(|conn| {
+        diesel::sql_query(
+            "UPDATE rate_limits SET \
+                failures = failures - 1, \
+                last_failure_at = CASE \
+                    WHEN last_failure_at = $3 THEN COALESCE($2, last_failure_at) \
+                    ELSE last_failure_at \
+                END \
+            WHERE key = $1",
+        )
+        .bind::<Text, _>(rate_limit_key)
+        .bind::<Nullable<Timestamp>, _>(previous_failure_at)
+        .bind::<Timestamp, _>(reserved_at)
+        .execute(conn)?;
+        diesel::sql_query("DELETE FROM rate_limits WHERE key = $1 AND failures <= 0")
+            .bind::<Text, _>(rate_limit_key)
+            .execute(conn)
+    })
+    .map(|_| ())
+    .map_err(Error::DieselError)
+}
+
+pub async fn delete_rate_limit(conn: &mut PgConnection, rate_limit_key: &str) -> Result<(), Error> {
+    use crate::schema::rate_limits::dsl::*;
+    diesel::delete(rate_limits.filter(key.eq(rate_limit_key)))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Error::DieselError)
+}
+
+// deletes the keys whose last failure is before the time, returns the number of rows removed
+pub async fn delete_stale_rate_limits(
+    conn: &mut PgConnection,
+    before: NaiveDateTime,
+) -> Result<usize, Error> {
+    use crate::schema::rate_limits::dsl::*;
+    diesel::delete(rate_limits.filter(last_failure_at.lt(before)))
+        .execute(conn)
+        .map_err(Error::DieselError)
+}
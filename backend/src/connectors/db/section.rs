@@ -0,0 +1,82 @@
+use diesel::sql_types::{Integer, Nullable};
+use diesel::{
+    BoolExpressionMethods, Connection, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    SelectableHelper,
+};
+
+use crate::models::sections::{GetLinkSection, InsertLinkSection, UpdateLinkSection};
+
+pub async fn create_section(
+    conn: &mut PgConnection,
+    section: &InsertLinkSection,
+) -> Result<GetLinkSection, diesel::result::Error> {
+    use crate::schema::link_sections;
+    diesel::insert_into(link_sections::table)
+        .values(section)
+        .returning(GetLinkSection::as_returning())
+        .get_result(conn)
+}
+
+// get all sections of the user in order
+pub async fn get_user_sections(
+    conn: &mut PgConnection,
+    userid: i32,
+) -> Result<Vec<GetLinkSection>, diesel::result::Error> {
+    use crate::schema::link_sections::dsl::*;
+    link_sections
+        .filter(user_id.eq(userid))
+        .select(GetLinkSection::as_select())
+        .order_by((position, id))
+        .load::<GetLinkSection>(conn)
+}
+
+pub async fn section_id_belongs_to_user(
+    conn: &mut PgConnection,
+    section_id: i32,
+    userid: i32,
+) -> Result<bool, diesel::result::Error> {
+    use crate::schema::link_sections::dsl::*;
+    link_sections
+        .filter(id.eq(section_id).and(user_id.eq(userid)))
+        .count()
+        .get_result::<i64>(conn)
+        .map(|count| count > 0)
+}
+
+pub async fn update_section_by_id(
+    conn: &mut PgConnection,
+    update_section: &UpdateLinkSection,
+    section_id: i32,
+) -> Result<GetLinkSection, diesel::result::Error> {
+    use crate::schema::link_sections::dsl::*;
+    diesel::update(link_sections.filter(id.eq(section_id)))
+        .set(update_section)
+        .returning(GetLinkSection::as_returning())
+        .get_result::<GetLinkSection>(conn)
+}
+
+// moves the section in front of new_position_id, or to the end if there is none
+pub async fn reorder_section(
+    conn: &mut PgConnection,
+    section_id: i32,
+    new_position_id: Option<i32>,
+) -> Result<(), diesel::result::Error> {
+    conn.transaction(|c| {
+        diesel::sql_query("SELECT reorder_link_section($1, $2)")
+            .bind::<Integer, _>(section_id)
+            .bind::<Nullable<Integer>, _>(new_position_id)
+            .execute(c)
+    })
+    .map(|_| ())
+}
+
+// links of the section are kept without a section
+pub async fn delete_section_by_id(
+    conn: &mut PgConnection,
+    section_id: i32,
+) -> Result<bool, diesel::result::Error> {
+    use crate::schema::link_sections::dsl::*;
+    diesel::delete(link_sections.filter(id.eq(section_id)))
+        .execute(conn)
+        .map(|deleted| deleted > 0)
+}
@@ -0,0 +1,71 @@
+use chrono::NaiveDateTime;
+use diesel::{
+    BoolExpressionMethods, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    RunQueryDsl, SelectableHelper,
+};
+
+use crate::{
+    models::sessions::{GetSession, InsertSession},
+    types::error::Error,
+};
+
+// queries backing the postgres session store
+
+// get a session that has not expired yet
+pub async fn get_session_by_id(
+    conn: &mut PgConnection,
+    session_id: &str,
+    now: NaiveDateTime,
+) -> Result<Option<GetSession>, Error> {
+    use crate::schema::sessions::dsl::*;
+    sessions
+        .filter(id.eq(session_id))
+        .filter(expires_at.is_null().or(expires_at.gt(now)))
+        .select(GetSession::as_select())
+        .first::<GetSession>(conn)
+        .optional()
+        .map_err(Error::DieselError)
+}
+
+// insert the session or overwrite the stored one with the same id
+pub async fn upsert_session(
+    conn: &mut PgConnection,
+    insert_session: &InsertSession,
+) -> Result<(), Error> {
+    use crate::schema::sessions::dsl::*;
+    diesel::insert_into(sessions)
+        .values(insert_session)
+        .on_conflict(id)
+        .do_update()
+        .set(insert_session)
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Error::DieselError)
+}
+
+pub async fn delete_session_by_id(conn: &mut PgConnection, session_id: &str) -> Result<(), Error> {
+    use crate::schema::sessions::dsl::*;
+    diesel::delete(sessions.filter(id.eq(session_id)))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Error::DieselError)
+}
+
+pub async fn clear_sessions(conn: &mut PgConnection) -> Result<(), Error> {
+    use crate::schema::sessions::dsl::*;
+    diesel::delete(sessions)
+        .execute(conn)
+        .map(|_| ())
+        .map_err(Error::DieselError)
+}
+
+// returns the number of stale sessions removed
+pub async fn delete_expired_sessions(
+    conn: &mut PgConnection,
+    now: NaiveDateTime,
+) -> Result<usize, Error> {
+    use crate::schema::sessions::dsl::*;
+    diesel::delete(sessions.filter(expires_at.le(now)))
+        .execute(conn)
+        .map_err(Error::DieselError)
+}
@@ -0,0 +1,136 @@
+use diesel::{
+    BoolExpressionMethods, Connection, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl,
+    SelectableHelper,
+};
+
+use crate::models::two_factor::{GetTwoFactor, InsertRecoveryCode, InsertTwoFactor};
+
+// a user has one secret at a time, setting up again replaces the old one
+pub async fn replace_two_factor(
+    conn: &mut PgConnection,
+    new_two_factor: &InsertTwoFactor,
+) -> Result<GetTwoFactor, diesel::result::Error> {
+    use crate::schema::two_factor::dsl::*;
+    diesel::insert_into(two_factor)
+        .values(new_two_factor)
+        .on_conflict(user_id)
+        .do_update()
+        .set(new_two_factor)
+        .returning(GetTwoFactor::as_returning())
+        .get_result(conn)
+}
+
+pub async fn get_two_factor(
+    conn: &mut PgConnection,
+    uid: i32,
+) -> Result<GetTwoFactor, diesel::result::Error> {
+    use crate::schema::two_factor::dsl::*;
+    two_factor
+        .filter(user_id.eq(uid))
+        .select(GetTwoFactor::as_select())
+        .first::<GetTwoFactor>(conn)
+}
+
+pub async fn is_two_factor_enabled(
+    conn: &mut PgConnection,
+    uid: i32,
+) -> Result<bool, diesel::result::Error> {
+    use crate::schema::two_factor::dsl::*;
+    diesel::select(diesel::dsl::exists(
+        two_factor
+            .filter(user_id.eq(uid))
+            .filter(is_enabled.eq(true)),
+    ))
+    .get_result::<bool>(conn)
+}
+
+pub async fn enable_two_factor(
+    conn: &mut PgConnection,
+    uid: i32,
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::two_factor::dsl::*;
+    diesel::update(two_factor.filter(user_id.eq(uid)))
+        .set(is_enabled.eq(true))
+        .execute(conn)
+        .map(|_| ())
+}
+
+// records the time step of an accepted code. false when a code of the same or a later
+// step was already used, which also stops two requests from using one code at once
+pub async fn use_two_factor_step(
+    conn: &mut PgConnection,
+    uid: i32,
+    step: i64,
+) -> Result<bool, diesel::result::Error> {
+    use crate::schema::two_factor::dsl::*;
+    diesel::update(
+        two_factor
+            .filter(user_id.eq(uid))
+            .filter(last_used_step.is_null().or(last_used_step.lt(step))),
+    )
+    .set(last_used_step.eq(step))
+    .execute(conn)
+    .map(|updated| updated > 0)
+}
+
+// turns two factor authentication off, along with the recovery codes
+pub async fn delete_two_factor(
+    conn: &mut PgConnection,
+    uid: i32,
+) -> Result<(), diesel::result::Error> {
+    conn.transaction(|conn| {
+        diesel::delete(
+            crate::schema::recovery_codes::table
+                .filter(crate::schema::recovery_codes::user_id.eq(uid)),
+        )
+        .execute(conn)?;
+        diesel::delete(
+            crate::schema::two_factor::table.filter(crate::schema::two_factor::user_id.eq(uid)),
+        )
+        .execute(conn)?;
+        Ok(())
+    })
+}
+
+// the new codes replace all the codes of the user
+pub async fn replace_recovery_codes(
+    conn: &mut PgConnection,
+    uid: i32,
+    codes: &[InsertRecoveryCode],
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::recovery_codes::dsl::*;
+    conn.transaction(|conn| {
+        diesel::delete(recovery_codes.filter(user_id.eq(uid))).execute(conn)?;
+        diesel::insert_into(recovery_codes)
+            .values(codes)
+            .execute(conn)?;
+        Ok(())
+    })
+}
+
+// deletes the code, true when the user had it
+pub async fn use_recovery_code(
+    conn: &mut PgConnection,
+    uid: i32,
+    hash: &str,
+) -> Result<bool, diesel::result::Error> {
+    use crate::schema::recovery_codes::dsl::*;
+    diesel::delete(
+        recovery_codes
+            .filter(user_id.eq(uid))
+            .filter(code_hash.eq(hash)),
+    )
+    .execute(conn)
+    .map(|deleted| deleted > 0)
+}
+
+pub async fn count_recovery_codes(
+    conn: &mut PgConnection,
+    uid: i32,
+) -> Result<i64, diesel::result::Error> {
+    use crate::schema::recovery_codes::dsl::*;
+    recovery_codes
+        .filter(user_id.eq(uid))
+        .count()
+        .get_result::<i64>(conn)
+}
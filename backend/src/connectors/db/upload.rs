@@ -0,0 +1,66 @@
+use chrono::NaiveDateTime;
+use diesel::{
+    BoolExpressionMethods, ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper,
+};
+
+use crate::models::uploads::{GetUpload, InsertUpload};
+
+pub async fn create_upload(
+    conn: &mut PgConnection,
+    upload: &InsertUpload,
+) -> Result<GetUpload, diesel::result::Error> {
+    use crate::schema::uploads;
+    diesel::insert_into(uploads::table)
+        .values(upload)
+        .returning(GetUpload::as_returning())
+        .get_result(conn)
+}
+
+// the upload of the user with the token, if it has not expired by now
+pub async fn get_user_upload(
+    conn: &mut PgConnection,
+    userid: i32,
+    hash: &str,
+    now: NaiveDateTime,
+) -> Result<GetUpload, diesel::result::Error> {
+    use crate::schema::uploads::dsl::*;
+    uploads
+        .filter(
+            token_hash
+                .eq(hash)
+                .and(user_id.eq(userid))
+                .and(expires_at.gt(now)),
+        )
+        .select(GetUpload::as_select())
+        .first::<GetUpload>(conn)
+}
+
+pub async fn delete_upload(
+    conn: &mut PgConnection,
+    upload_id: i32,
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::uploads::dsl::*;
+    diesel::delete(uploads.filter(id.eq(upload_id)))
+        .execute(conn)
+        .map(|_| ())
+}
+
+// uploads that can still be completed
+pub async fn get_pending_uploads(
+    conn: &mut PgConnection,
+    now: NaiveDateTime,
+) -> Result<Vec<GetUpload>, diesel::result::Error> {
+    use crate::schema::uploads::dsl::*;
+    uploads
+        .filter(expires_at.gt(now))
+        .select(GetUpload::as_select())
+        .load::<GetUpload>(conn)
+}
+
+pub async fn delete_expired_uploads(
+    conn: &mut PgConnection,
+    now: NaiveDateTime,
+) -> Result<usize, diesel::result::Error> {
+    use crate::schema::uploads::dsl::*;
+    diesel::delete(uploads.filter(expires_at.le(now))).execute(conn)
+}
@@ -125,6 +125,21 @@ pub async fn update_user_by_id(
         .map(|v| v == user_id)
 }
 
+// sets the email of the user to a confirmed address
+pub async fn verify_user_email(
+    conn: &mut PgConnection,
+    user_id: i32,
+    verified_email: &str,
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::users::dsl::*;
+    use diesel::query_dsl::methods::FilterDsl;
+
+    diesel::update(FilterDsl::filter(users, id.eq(user_id)))
+        .set((email.eq(verified_email), email_verified.eq(true)))
+        .execute(conn)
+        .map(|_| ())
+}
+
 pub async fn get_queried_users(
     conn: &mut PgConnection,
     query: String,
@@ -0,0 +1,72 @@
+use chrono::NaiveDateTime;
+use diesel::{ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl, SelectableHelper};
+
+use crate::models::verification::{GetEmailVerification, InsertEmailVerification};
+
+// a user has one verification at a time, a new one replaces the old one
+// only replaces a request created at or before replace_before,
+// false is returned while a newer one is still in place
+pub async fn replace_email_verification(
+    conn: &mut PgConnection,
+    verification: &InsertEmailVerification,
+    replace_before: NaiveDateTime,
+) -> Result<bool, diesel::result::Error> {
+    use diesel::sql_types::{Integer, Text, Timestamp};
+    diesel::sql_query(
+        "INSERT INTO email_verification_request (user_id, email, token_hash, created_at) \
+        VALUES ($1, $2, $3, $4) \
+        ON CONFLICT (user_id) DO UPDATE SET \
+            email = EXCLUDED.email, \
+            token_hash = EXCLUDED.token_hash, \
+            created_at = EXCLUDED.created_at \
+        WHERE email_verification_request.created_at <= $5",
+    )
+    .bind::<Integer, _>(verification.user_id)
+    .bind::<Text, _>(&verification.email)
+    .bind::<Text, _>(&verification.token_hash)
+    .bind::<Timestamp, _>(verification.created_at)
+    .bind::<Timestamp, _>(replace_before)
+    .execute(conn)
+    .map(|replaced| replaced > 0)
+}
+
+pub async fn get_email_verification_by_token(
+    conn: &mut PgConnection,
+    hash: &str,
+) -> Result<GetEmailVerification, diesel::result::Error> {
+    use crate::schema::email_verification_request::dsl::*;
+    email_verification_request
+        .filter(token_hash.eq(hash))
+        .select(GetEmailVerification::as_select())
+        .first::<GetEmailVerification>(conn)
+}
+
+pub async fn get_user_email_verification(
+    conn: &mut PgConnection,
+    uid: i32,
+) -> Result<GetEmailVerification, diesel::result::Error> {
+    use crate::schema::email_verification_request::dsl::*;
+    email_verification_request
+        .filter(user_id.eq(uid))
+        .select(GetEmailVerification::as_select())
+        .first::<GetEmailVerification>(conn)
+}
+
+pub async fn delete_email_verification(
+    conn: &mut PgConnection,
+    uid: i32,
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::email_verification_request::dsl::*;
+    diesel::delete(email_verification_request.filter(user_id.eq(uid)))
+        .execute(conn)
+        .map(|_| ())
+}
+
+// links created before created_before have expired and can no longer be opened
+pub async fn delete_expired_email_verifications(
+    conn: &mut PgConnection,
+    created_before: NaiveDateTime,
+) -> Result<usize, diesel::result::Error> {
+    use crate::schema::email_verification_request::dsl::*;
+    diesel::delete(email_verification_request.filter(created_at.lt(created_before))).execute(conn)
+}
@@ -0,0 +1,164 @@
+use std::{env, fs, io, net::IpAddr, path::Path};
+
+use tide::log::{error, info};
+
+// a range of ip addresses that belong to a single country
+#[derive(Debug, Clone)]
+struct CountryRange {
+    start: u128,
+    end: u128,
+    country: String,
+}
+
+// country lookup backed by a locally bundled csv of ip ranges,
+// rows look like `start_ip,end_ip,country_code` (the format of the dbip country lite csv)
+#[derive(Debug, Clone, Default)]
+pub struct GeoIpDatabase {
+    // sorted by start, ranges do not overlap
+    ranges: Vec<CountryRange>,
+}
+
+// ipv4 addresses are compared in their ipv6 mapped form so both fit in one sorted list
+fn ip_to_u128(ip: IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(ip) => u128::from(ip.to_ipv6_mapped()),
+        IpAddr::V6(ip) => u128::from(ip),
+    }
+}
+
+fn parse_ip(field: &str) -> Option<u128> {
+    field
+        .trim()
+        .trim_matches('"')
+        .parse::<IpAddr>()
+        .ok()
+        .map(ip_to_u128)
+}
+
+impl GeoIpDatabase {
+    pub fn from_csv(contents: &str) -> io::Result<GeoIpDatabase> {
+        let mut ranges = Vec::<CountryRange>::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid_row = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid GeoIP row on line {}", index + 1),
+                )
+            };
+            let mut fields = line.split(',');
+            let start = fields.next().and_then(parse_ip).ok_or_else(invalid_row)?;
+            let end = fields.next().and_then(parse_ip).ok_or_else(invalid_row)?;
+            let country = fields
+                .next()
+                .map(|field| field.trim().trim_matches('"').to_uppercase())
+                .filter(|country| country.len() == 2)
+                .ok_or_else(invalid_row)?;
+            if start > end {
+                return Err(invalid_row());
+            }
+
+            ranges.push(CountryRange {
+                start,
+                end,
+                country,
+            });
+        }
+        ranges.sort_by_key(|range| range.start);
+
+        Ok(GeoIpDatabase { ranges })
+    }
+
+    pub fn open(path: &Path) -> io::Result<GeoIpDatabase> {
+        GeoIpDatabase::from_csv(&fs::read_to_string(path)?)
+    }
+
+    // loads the database at GEOIP_DATABASE_PATH, country lookups are disabled without one
+    pub fn from_env() -> GeoIpDatabase {
+        let path = match env::var("GEOIP_DATABASE_PATH") {
+            Ok(path) if !path.is_empty() => path,
+            _ => {
+                info!("No GeoIP database configured, countries will not be recorded");
+                return GeoIpDatabase::default();
+            }
+        };
+
+        match GeoIpDatabase::open(Path::new(&path)) {
+            Ok(database) => {
+                info!(
+                    "Loaded {} GeoIP ranges from {}",
+                    database.ranges.len(),
+                    path
+                );
+                database
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                info!(
+                    "No GeoIP database at {}, countries will not be recorded. \
+                    Run scripts/fetch_geoip.sh to download it",
+                    path
+                );
+                GeoIpDatabase::default()
+            }
+            Err(e) => {
+                error!("Failed to load GeoIP database from {}: {}", path, e);
+                GeoIpDatabase::default()
+            }
+        }
+    }
+
+    // two letter country code of the ip, if it is in any range
+    pub fn lookup(&self, ip: IpAddr) -> Option<&str> {
+        let ip = ip_to_u128(ip);
+        let after = self.ranges.partition_point(|range| range.start <= ip);
+        if after == 0 {
+            return None;
+        }
+        let range = &self.ranges[after - 1];
+        if ip <= range.end {
+            Some(&range.country)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::net::IpAddr;
+
+    use super::GeoIpDatabase;
+
+    const MOCK_CSV: &str = "\"1.0.0.0\",\"1.0.0.255\",\"AU\"\n\
+        1.0.1.0,1.0.3.255,cn\n\
+        \n\
+        2001:200::,2001:200:ffff:ffff:ffff:ffff:ffff:ffff,JP\n";
+
+    #[test]
+    fn it_looks_up_countries() {
+        let database = GeoIpDatabase::from_csv(MOCK_CSV).unwrap();
+        let lookup = |ip: &str| database.lookup(ip.parse::<IpAddr>().unwrap());
+        assert_eq!(lookup("1.0.0.0"), Some("AU"));
+        assert_eq!(lookup("1.0.0.255"), Some("AU"));
+        assert_eq!(lookup("1.0.2.7"), Some("CN"));
+        assert_eq!(lookup("2001:200::1"), Some("JP"));
+        assert_eq!(lookup("1.0.4.0"), None);
+        assert_eq!(lookup("0.255.255.255"), None);
+        assert_eq!(lookup("::1"), None);
+    }
+
+    #[test]
+    fn it_rejects_malformed_rows() {
+        assert!(GeoIpDatabase::from_csv("1.0.0.0,not an ip,AU").is_err());
+        assert!(GeoIpDatabase::from_csv("1.0.0.0,1.0.0.255").is_err());
+        assert!(GeoIpDatabase::from_csv("1.0.0.255,1.0.0.0,AU").is_err());
+    }
+}
@@ -0,0 +1,34 @@
+use std::{collections::HashMap, time::Duration};
+
+use async_trait::async_trait;
+use url::Url;
+
+use crate::connectors::health::prober::{LinkProbe, LinkProber};
+
+// answers with canned status codes instead of going to the network, used in tests
+#[derive(Debug, Clone, Default)]
+pub struct FixtureLinkProber {
+    status_codes: HashMap<String, u16>,
+}
+
+impl FixtureLinkProber {
+    pub fn new() -> FixtureLinkProber {
+        FixtureLinkProber::default()
+    }
+
+    // urls without a status code get no response
+    pub fn with_status(mut self, url: &str, status_code: u16) -> FixtureLinkProber {
+        self.status_codes.insert(url.to_string(), status_code);
+        self
+    }
+}
+
+#[async_trait]
+impl LinkProber for FixtureLinkProber {
+    async fn probe(&self, url: &Url) -> LinkProbe {
+        LinkProbe {
+            status_code: self.status_codes.get(url.as_str()).cloned(),
+            latency: Duration::from_millis(10),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::time::Instant;
+
+use async_trait::async_trait;
+use reqwest::{Client, Method};
+use tide::log::info;
+use url::Url;
+
+use crate::{
+    connectors::{
+        health::prober::{LinkProbe, LinkProber},
+        previews::http::public_http_client,
+    },
+    helpers::previews::check_preview_url,
+};
+
+// probes links over http(s) with the same protections as link previews
+pub struct HttpLinkProber {
+    client: Client,
+}
+
+impl HttpLinkProber {
+    pub fn new() -> HttpLinkProber {
+        HttpLinkProber {
+            client: public_http_client(),
+        }
+    }
+
+    async fn request(&self, method: Method, url: &Url) -> LinkProbe {
+        let start = Instant::now();
+        // the body is never read
+        let status_code = match self.client.request(method, url.clone()).send().await {
+            Ok(response) => Some(response.status().as_u16()),
+            Err(e) => {
+                info!("Health check of {} failed: {}", url, e);
+                None
+            }
+        };
+        LinkProbe {
+            status_code,
+            latency: start.elapsed(),
+        }
+    }
+}
+
+impl Default for HttpLinkProber {
+    fn default() -> HttpLinkProber {
+        HttpLinkProber::new()
+    }
+}
+
+#[async_trait]
+impl LinkProber for HttpLinkProber {
+    async fn probe(&self, url: &Url) -> LinkProbe {
+        if check_preview_url(url).is_err() {
+            return LinkProbe {
+                status_code: None,
+                latency: Default::default(),
+            };
+        }
+
+        // plenty of servers do not support HEAD, so failures are retried with GET
+        let probe = self.request(Method::HEAD, url).await;
+        if probe.is_healthy() {
+            return probe;
+        }
+        self.request(Method::GET, url).await
+    }
+}
@@ -0,0 +1,26 @@
+// defines the link prober trait which is used as an interface for link health checks
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use url::Url;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkProbe {
+    // none when there was no response at all
+    pub status_code: Option<u16>,
+    pub latency: Duration,
+}
+
+impl LinkProbe {
+    // redirects are followed, so only errors and missing responses are failures
+    pub fn is_healthy(&self) -> bool {
+        self.status_code
+            .is_some_and(|status_code| status_code < 400)
+    }
+}
+
+#[async_trait]
+pub trait LinkProber: Send + Sync {
+    async fn probe(&self, url: &Url) -> LinkProbe;
+}
@@ -0,0 +1,22 @@
+// defines the page fetcher trait which is used as an interface for link previews
+
+use async_trait::async_trait;
+use url::Url;
+
+use crate::types::error::Error;
+
+#[derive(Debug, Clone)]
+pub struct FetchedPage {
+    // url after following redirects
+    pub url: Url,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+    // whether the body was cut off at the size limit
+    pub truncated: bool,
+}
+
+#[async_trait]
+pub trait PageFetcher: Send + Sync {
+    // reads at most max_bytes of the body
+    async fn fetch(&self, url: &Url, max_bytes: usize) -> Result<FetchedPage, Error>;
+}
@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use url::Url;
+
+use crate::{
+    connectors::previews::fetcher::{FetchedPage, PageFetcher},
+    types::error::Error,
+};
+
+// serves canned pages instead of going to the network, used in tests
+#[derive(Debug, Clone, Default)]
+pub struct FixturePageFetcher {
+    pages: HashMap<String, (Option<String>, Vec<u8>)>,
+}
+
+impl FixturePageFetcher {
+    pub fn new() -> FixturePageFetcher {
+        FixturePageFetcher::default()
+    }
+
+    pub fn with_page(mut self, url: &str, content_type: &str, body: &[u8]) -> FixturePageFetcher {
+        self.pages.insert(
+            url.to_string(),
+            (Some(content_type.to_string()), body.to_vec()),
+        );
+        self
+    }
+}
+
+#[async_trait]
+impl PageFetcher for FixturePageFetcher {
+    async fn fetch(&self, url: &Url, max_bytes: usize) -> Result<FetchedPage, Error> {
+        let (content_type, body) = self
+            .pages
+            .get(url.as_str())
+            .ok_or_else(|| Error::PreviewError(format!("no fixture for {}", url)))?;
+        Ok(FetchedPage {
+            url: url.clone(),
+            content_type: content_type.clone(),
+            body: body.iter().take(max_bytes).cloned().collect(),
+            truncated: body.len() > max_bytes,
+        })
+    }
+}
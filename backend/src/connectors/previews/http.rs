@@ -0,0 +1,129 @@
+use std::{env, net::SocketAddr, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    header::CONTENT_TYPE,
+    redirect::Policy,
+    Client,
+};
+use url::Url;
+
+use crate::{
+    connectors::previews::fetcher::{FetchedPage, PageFetcher},
+    helpers::previews::{check_preview_url, is_public_ip},
+    types::error::Error,
+};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+// covers the whole request including reading the body
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REDIRECTS: usize = 5;
+
+// resolves hostnames to public addresses only, so that a link cannot make us
+// request services on our own network, including after a redirect
+struct PublicDnsResolver;
+
+impl Resolve for PublicDnsResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect::<Vec<SocketAddr>>();
+            if addrs.is_empty() {
+                return Err(
+                    format!("{} does not resolve to a public address", name.as_str()).into(),
+                );
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+// http client for urls given by users: public hosts only, timeouts and a bounded number of
+// redirects, every redirect is checked like the original url
+pub fn public_http_client() -> Client {
+    let user_agent = env::var("PREVIEW_USER_AGENT").unwrap_or(String::from("SaladBot/1.0"));
+    let redirect_policy = Policy::custom(|attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else if let Err(e) = check_preview_url(attempt.url()) {
+            attempt.error(e.to_string())
+        } else {
+            attempt.follow()
+        }
+    });
+    Client::builder()
+        .user_agent(user_agent)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .redirect(redirect_policy)
+        // a proxy would resolve the hostnames instead of our resolver
+        .no_proxy()
+        .dns_resolver(Arc::new(PublicDnsResolver))
+        .build()
+        .expect("unable to build http client")
+}
+
+// fetches pages over http(s) with timeouts, a size limit and ssrf protection
+pub struct HttpPageFetcher {
+    client: Client,
+}
+
+impl HttpPageFetcher {
+    pub fn new() -> HttpPageFetcher {
+        HttpPageFetcher {
+            client: public_http_client(),
+        }
+    }
+}
+
+impl Default for HttpPageFetcher {
+    fn default() -> HttpPageFetcher {
+        HttpPageFetcher::new()
+    }
+}
+
+#[async_trait]
+impl PageFetcher for HttpPageFetcher {
+    async fn fetch(&self, url: &Url, max_bytes: usize) -> Result<FetchedPage, Error> {
+        let preview_error = |e: reqwest::Error| Error::PreviewError(e.to_string());
+
+        let mut response = self
+            .client
+            .get(url.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(preview_error)?;
+
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        let final_url = response.url().clone();
+
+        // stop reading once the limit is reached instead of trusting content-length
+        let mut body = Vec::new();
+        let mut truncated = false;
+        while let Some(chunk) = response.chunk().await.map_err(preview_error)? {
+            let remaining = max_bytes - body.len();
+            if chunk.len() > remaining {
+                body.extend_from_slice(&chunk[..remaining]);
+                truncated = true;
+                break;
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(FetchedPage {
+            url: final_url,
+            content_type,
+            body,
+            truncated,
+        })
+    }
+}
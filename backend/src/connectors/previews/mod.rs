@@ -0,0 +1,3 @@
+pub mod fetcher;
+pub mod fixture;
+pub mod http;
@@ -0,0 +1,146 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+
+use crate::{
+    connectors::rate_limit::store::{
+        RateLimitEntry, RateLimitPolicy, RateLimitReservation, RateLimitStore,
+    },
+    types::error::Error,
+};
+
+// rate limit store that keeps the failures in memory, which only limits a single instance
+// and forgets everything on restart
+#[derive(Debug, Default)]
+pub struct MemoryRateLimitStore {
+    entries: Mutex<HashMap<String, RateLimitEntry>>,
+}
+
+impl MemoryRateLimitStore {
+    pub fn new() -> MemoryRateLimitStore {
+        MemoryRateLimitStore::default()
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn get(&self, key: &str) -> Result<Option<RateLimitEntry>, Error> {
+        Ok(self.entries.lock().unwrap().get(key).cloned())
+    }
+
+    // the lock is held from the check to the count
+    async fn reserve(
+        &self,
+        key: &str,
+        policy: &RateLimitPolicy,
+        now: NaiveDateTime,
+    ) -> Result<RateLimitReservation, Error> {
+        let mut entries = self.entries.lock().unwrap();
+        let reservation = RateLimitReservation::reserve(entries.get(key), policy, now);
+        if let Some(failures) = reservation.reserved_failures() {
+            entries.insert(
+                key.to_string(),
+                RateLimitEntry {
+                    failures,
+                    last_failure_at: now,
+                },
+            );
+        }
+        Ok(reservation)
+    }
+
+    async fn undo(
+        &self,
+        key: &str,
+        previous: Option<&RateLimitEntry>,
+        reserved_at: NaiveDateTime,
+    ) -> Result<(), Error> {
+        let mut entries = self.entries.lock().unwrap();
+        let Some(entry) = entries.get_mut(key) else {
+            return Ok(());
+        };
+        entry.failures -= 1;
+        // unless another attempt was reserved since
+        if entry.last_failure_at == reserved_at {
+            if let Some(previous) = previous {
+                entry.last_failure_at = previous.last_failure_at;
+            }
+        }
+        if entry.failures <= 0 {
+            entries.remove(key);
+        }
+        Ok(())
+    }
+
+    async fn reset(&self, key: &str) -> Result<(), Error> {
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn delete_stale(&self, before: NaiveDateTime) -> Result<usize, Error> {
+        let mut entries = self.entries.lock().unwrap();
+        let count = entries.len();
+        entries.retain(|_, entry| entry.last_failure_at >= before);
+        Ok(count - entries.len())
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use chrono::{DateTime, Duration, NaiveDateTime};
+
+    use super::MemoryRateLimitStore;
+    use crate::connectors::rate_limit::store::{
+        RateLimitEntry, RateLimitPolicy, RateLimitReservation, RateLimitStore,
+    };
+
+    fn at(secs: i64) -> NaiveDateTime {
+        DateTime::from_timestamp(secs, 0).unwrap().naive_utc()
+    }
+
+    #[tokio::test]
+    async fn it_counts_failures_within_the_window() {
+        let store = MemoryRateLimitStore::new();
+        let policy = RateLimitPolicy::default();
+        store.reserve("ip", &policy, at(100)).await.unwrap();
+        let reservation = store.reserve("ip", &policy, at(110)).await.unwrap();
+        assert_eq!(
+            reservation,
+            RateLimitReservation::Reserved(Some(RateLimitEntry {
+                failures: 1,
+                last_failure_at: at(100),
+            }))
+        );
+        let entry = store.get("ip").await.unwrap().unwrap();
+        assert_eq!(entry.failures, 2);
+        assert_eq!(entry.last_failure_at, at(110));
+
+        // an attempt that did not fail is taken back
+        let RateLimitReservation::Reserved(previous) =
+            store.reserve("ip", &policy, at(120)).await.unwrap()
+        else {
+            panic!("the attempt should have been reserved");
+        };
+        store.undo("ip", previous.as_ref(), at(120)).await.unwrap();
+        assert_eq!(store.get("ip").await.unwrap(), Some(entry));
+
+        // the last failure is before the window, so it starts over
+        let later = at(110) + policy.window + Duration::seconds(1);
+        store.reserve("ip", &policy, later).await.unwrap();
+        assert_eq!(store.get("ip").await.unwrap().unwrap().failures, 1);
+
+        store.reserve("user", &policy, later).await.unwrap();
+        store.reset("user").await.unwrap();
+        assert!(store.get("user").await.unwrap().is_none());
+
+        assert_eq!(
+            store
+                .delete_stale(later + Duration::seconds(1))
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(store.get("ip").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,109 @@
+use std::fmt;
+
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+
+use crate::{
+    connectors::{
+        db::{
+            connection::DBConnection,
+            rate_limit::{
+                delete_rate_limit, delete_stale_rate_limits, get_rate_limit,
+                reserve_rate_limit_attempt, undo_rate_limit_attempt,
+            },
+        },
+        rate_limit::store::{
+            RateLimitEntry, RateLimitPolicy, RateLimitReservation, RateLimitStore,
+        },
+    },
+    models::rate_limits::GetRateLimit,
+    types::{error::Error, state::TidePool},
+};
+
+// rate limit store that counts failures in the postgres rate_limits table
+// so that every replica sees the same failures
+#[derive(Clone)]
+pub struct PostgresRateLimitStore {
+    pool: TidePool,
+}
+
+impl fmt::Debug for PostgresRateLimitStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PostgresRateLimitStore").finish()
+    }
+}
+
+impl PostgresRateLimitStore {
+    pub fn new(pool: TidePool) -> PostgresRateLimitStore {
+        PostgresRateLimitStore { pool }
+    }
+
+    fn get_connection(&self) -> Result<DBConnection, Error> {
+        self.pool.get().map_err(|_| Error::ConnectionPoolError())
+    }
+}
+
+impl From<GetRateLimit> for RateLimitEntry {
+    fn from(rate_limit: GetRateLimit) -> RateLimitEntry {
+        RateLimitEntry {
+            failures: rate_limit.failures,
+            last_failure_at: rate_limit.last_failure_at,
+        }
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for PostgresRateLimitStore {
+    async fn get(&self, key: &str) -> Result<Option<RateLimitEntry>, Error> {
+        let mut conn = self.get_connection()?;
+        Ok(get_rate_limit(&mut conn, key)
+            .await?
+            .map(RateLimitEntry::from))
+    }
+
+    async fn reserve(
+        &self,
+        key: &str,
+        policy: &RateLimitPolicy,
+        now: NaiveDateTime,
+    ) -> Result<RateLimitReservation, Error> {
+        let mut conn = self.get_connection()?;
+        // decided while the row is locked
+        let mut reservation = None;
+        reserve_rate_limit_attempt(&mut conn, key, now, |current| {
+            let current = current.cloned().map(RateLimitEntry::from);
+            let decided = RateLimitReservation::reserve(current.as_ref(), policy, now);
+            let reserved_failures = decided.reserved_failures();
+            reservation = Some(decided);
+            reserved_failures
+        })
+        .await?;
+        Ok(reservation.expect("the reservation is decided in the transaction"))
+    }
+
+    async fn undo(
+        &self,
+        key: &str,
+        previous: Option<&RateLimitEntry>,
+        reserved_at: NaiveDateTime,
+    ) -> Result<(), Error> {
+        let mut conn = self.get_connection()?;
+        undo_rate_limit_attempt(
+            &mut conn,
+            key,
+            previous.map(|entry| entry.last_failure_at),
+            reserved_at,
+        )
+        .await
+    }
+
+    async fn reset(&self, key: &str) -> Result<(), Error> {
+        let mut conn = self.get_connection()?;
+        delete_rate_limit(&mut conn, key).await
+    }
+
+    async fn delete_stale(&self, before: NaiveDateTime) -> Result<usize, Error> {
+        let mut conn = self.get_connection()?;
+        delete_stale_rate_limits(&mut conn, before).await
+    }
+}
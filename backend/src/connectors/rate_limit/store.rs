@@ -0,0 +1,274 @@
+use std::{env, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use tide::log::{error, info};
+
+use crate::{
+    connectors::rate_limit::{memory::MemoryRateLimitStore, postgres::PostgresRateLimitStore},
+    types::{error::Error, state::TidePool},
+};
+
+// defines the store that failed attempts of rate limited routes are counted in
+
+// how often keys without recent failures are swept from the store
+pub const RATE_LIMIT_CLEANUP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+// how long a key has to wait after some number of failures
+#[derive(Debug, Clone)]
+pub struct RateLimitPolicy {
+    // failures that do not have to wait before the next attempt
+    pub free_failures: i32,
+    // wait after the first failure past the free ones, doubled for every failure after it
+    pub base_delay: chrono::Duration,
+    pub max_delay: chrono::Duration,
+    // failures after which the key is locked out
+    pub lockout_failures: i32,
+    pub lockout_duration: chrono::Duration,
+    // failures are forgotten once the last one is this old
+    pub window: chrono::Duration,
+}
+
+impl Default for RateLimitPolicy {
+    fn default() -> RateLimitPolicy {
+        RateLimitPolicy {
+            free_failures: 3,
+            base_delay: chrono::Duration::seconds(1),
+            max_delay: chrono::Duration::minutes(1),
+            lockout_failures: 10,
+            lockout_duration: chrono::Duration::minutes(15),
+            window: chrono::Duration::hours(1),
+        }
+    }
+}
+
+fn env_number(key: &str, default: i64) -> i64 {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .unwrap_or(default)
+}
+
+impl RateLimitPolicy {
+    // LOGIN_LOCKOUT_ATTEMPTS and LOGIN_LOCKOUT_MINUTES change the lockout of the default policy
+    pub fn from_env() -> RateLimitPolicy {
+        let default = RateLimitPolicy::default();
+        let lockout_failures = env_number("LOGIN_LOCKOUT_ATTEMPTS", 10).max(1) as i32;
+        let lockout_duration =
+            chrono::Duration::minutes(env_number("LOGIN_LOCKOUT_MINUTES", 15).max(1));
+        RateLimitPolicy {
+            free_failures: default.free_failures.min(lockout_failures - 1),
+            lockout_failures,
+            lockout_duration,
+            // a lockout is never forgotten before it ends
+            window: default.window.max(lockout_duration),
+            ..default
+        }
+    }
+
+    // wait after the given number of failures in a row
+    pub fn delay(&self, failures: i32) -> chrono::Duration {
+        if failures >= self.lockout_failures {
+            return self.lockout_duration;
+        }
+        if failures <= self.free_failures {
+            return chrono::Duration::zero();
+        }
+        let doublings = (failures - self.free_failures - 1).min(30) as u32;
+        self.base_delay
+            .checked_mul(1 << doublings)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+}
+
+// the failures of a key since they were last forgotten
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitEntry {
+    pub failures: i32,
+    pub last_failure_at: NaiveDateTime,
+}
+
+impl RateLimitEntry {
+    // how long the key has to wait before its next attempt, none if it can try now
+    pub fn retry_after(
+        &self,
+        policy: &RateLimitPolicy,
+        now: NaiveDateTime,
+    ) -> Option<chrono::Duration> {
+        if self.is_stale(policy, now) {
+            return None;
+        }
+        let retry_at = self.last_failure_at + policy.delay(self.failures);
+        (retry_at > now).then(|| retry_at - now)
+    }
+
+    pub fn is_stale(&self, policy: &RateLimitPolicy, now: NaiveDateTime) -> bool {
+        self.last_failure_at < now - policy.window
+    }
+}
+
+// an attempt is counted as a failure before it is made, so that attempts at the same time
+// cannot all pass the check before any of them fails
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateLimitReservation {
+    // the attempt was counted, with the failures from before it to undo it with.
+    // none when the key had no failures in the window
+    Reserved(Option<RateLimitEntry>),
+    // nothing was counted, the key has to wait this long
+    Wait(chrono::Duration),
+}
+
+impl RateLimitReservation {
+    // reserves an attempt on the failures of the key so far, which are none or stale
+    // when the key starts over
+    pub fn reserve(
+        current: Option<&RateLimitEntry>,
+        policy: &RateLimitPolicy,
+        now: NaiveDateTime,
+    ) -> RateLimitReservation {
+        let current = current.filter(|entry| !entry.is_stale(policy, now));
+        match current.and_then(|entry| entry.retry_after(policy, now)) {
+            Some(wait) => RateLimitReservation::Wait(wait),
+            None => RateLimitReservation::Reserved(current.cloned()),
+        }
+    }
+
+    // failures of the key with the reserved attempt, none when it has to wait
+    pub fn reserved_failures(&self) -> Option<i32> {
+        match self {
+            RateLimitReservation::Reserved(previous) => {
+                Some(previous.as_ref().map_or(0, |entry| entry.failures) + 1)
+            }
+            RateLimitReservation::Wait(_) => None,
+        }
+    }
+}
+
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<RateLimitEntry>, Error>;
+    // counts an attempt of the key as a failure in one step with checking that it does not
+    // have to wait, starting over if its last failure is older than the window
+    async fn reserve(
+        &self,
+        key: &str,
+        policy: &RateLimitPolicy,
+        now: NaiveDateTime,
+    ) -> Result<RateLimitReservation, Error>;
+    // takes back an attempt reserved at reserved_at that did not fail, previous is the
+    // entry the reservation returned
+    async fn undo(
+        &self,
+        key: &str,
+        previous: Option<&RateLimitEntry>,
+        reserved_at: NaiveDateTime,
+    ) -> Result<(), Error>;
+    // forgets the failures of the key, after it succeeded
+    async fn reset(&self, key: &str) -> Result<(), Error>;
+    // forgets every key whose last failure is before the time, returns how many were removed
+    async fn delete_stale(&self, before: NaiveDateTime) -> Result<usize, Error>;
+}
+
+// RATE_LIMIT_STORE picks the store, memory (default) or postgres for several instances
+pub fn rate_limit_store_from_env(pool: TidePool) -> Arc<dyn RateLimitStore> {
+    match env::var("RATE_LIMIT_STORE").unwrap_or_default().as_str() {
+        "postgres" => Arc::new(PostgresRateLimitStore::new(pool)),
+        _ => Arc::new(MemoryRateLimitStore::new()),
+    }
+}
+
+// periodically forgets keys whose failures are older than the window
+pub fn spawn_rate_limit_cleanup(
+    store: Arc<dyn RateLimitStore>,
+    window: chrono::Duration,
+    interval: Duration,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match store
+                .delete_stale(chrono::Utc::now().naive_utc() - window)
+                .await
+            {
+                Ok(removed) => info!("Removed {} stale rate limit keys", removed),
+                Err(e) => error!("Failed to clean up rate limit keys: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use chrono::{DateTime, Duration, NaiveDateTime};
+
+    use super::{RateLimitEntry, RateLimitPolicy, RateLimitReservation};
+
+    fn at(secs: i64) -> NaiveDateTime {
+        DateTime::from_timestamp(secs, 0).unwrap().naive_utc()
+    }
+
+    #[test]
+    fn it_backs_off_exponentially_then_locks_out() {
+        let policy = RateLimitPolicy::default();
+        let delays: Vec<i64> = (1..=11)
+            .map(|failures| policy.delay(failures).num_seconds())
+            .collect();
+        assert_eq!(delays, vec![0, 0, 0, 1, 2, 4, 8, 16, 32, 900, 900]);
+
+        let capped = RateLimitPolicy {
+            lockout_failures: 100,
+            ..RateLimitPolicy::default()
+        };
+        assert_eq!(capped.delay(50), Duration::minutes(1));
+    }
+
+    #[test]
+    fn it_waits_from_the_last_failure_until_the_window_passes() {
+        let policy = RateLimitPolicy::default();
+        let entry = RateLimitEntry {
+            failures: 10,
+            last_failure_at: at(1_000_000),
+        };
+        assert_eq!(
+            entry.retry_after(&policy, at(1_000_000 + 60)),
+            Some(Duration::minutes(14))
+        );
+        assert_eq!(entry.retry_after(&policy, at(1_000_000 + 15 * 60)), None);
+        assert!(!entry.is_stale(&policy, at(1_000_000 + 60 * 60)));
+        assert!(entry.is_stale(&policy, at(1_000_000 + 60 * 60 + 1)));
+
+        let free = RateLimitEntry {
+            failures: 3,
+            last_failure_at: at(1_000_000),
+        };
+        assert_eq!(free.retry_after(&policy, at(1_000_000)), None);
+    }
+
+    #[test]
+    fn it_only_reserves_attempts_that_do_not_have_to_wait() {
+        let policy = RateLimitPolicy::default();
+        let entry = RateLimitEntry {
+            failures: 10,
+            last_failure_at: at(1_000_000),
+        };
+        assert_eq!(
+            RateLimitReservation::reserve(Some(&entry), &policy, at(1_000_000 + 60)),
+            RateLimitReservation::Wait(Duration::minutes(14))
+        );
+        assert_eq!(
+            RateLimitReservation::reserve(Some(&entry), &policy, at(1_000_000 + 15 * 60)),
+            RateLimitReservation::Reserved(Some(entry.clone()))
+        );
+        // a stale entry starts over
+        assert_eq!(
+            RateLimitReservation::reserve(Some(&entry), &policy, at(1_000_000 + 2 * 60 * 60)),
+            RateLimitReservation::Reserved(None)
+        );
+        assert_eq!(
+            RateLimitReservation::reserve(None, &policy, at(1_000_000)),
+            RateLimitReservation::Reserved(None)
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use std::fmt;
+use std::time::Duration;
+
+use async_session::{async_trait, Result, Session, SessionStore};
+use chrono::Utc;
+use tide::log::{error, info};
+
+use crate::{
+    connectors::db::{
+        connection::DBConnection,
+        session::{
+            clear_sessions, delete_expired_sessions, delete_session_by_id, get_session_by_id,
+            upsert_session,
+        },
+    },
+    models::sessions::InsertSession,
+    types::{error::Error, state::TidePool},
+};
+
+// how often expired sessions are swept from the sessions table
+pub const SESSION_CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// session store that persists tide sessions in the postgres sessions table
+// so that sessions survive restarts and can be shared between replicas
+#[derive(Clone)]
+pub struct PostgresSessionStore {
+    pool: TidePool,
+}
+
+impl fmt::Debug for PostgresSessionStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PostgresSessionStore").finish()
+    }
+}
+
+impl PostgresSessionStore {
+    pub fn new(pool: TidePool) -> PostgresSessionStore {
+        PostgresSessionStore { pool }
+    }
+
+    fn get_connection(&self) -> std::result::Result<DBConnection, Error> {
+        self.pool.get().map_err(|_| Error::ConnectionPoolError())
+    }
+
+    // removes every session that has expired, returns the number of rows removed
+    pub async fn cleanup(&self) -> Result<usize> {
+        let mut conn = self.get_connection()?;
+        let removed = delete_expired_sessions(&mut conn, Utc::now().naive_utc()).await?;
+        Ok(removed)
+    }
+
+    // periodically removes expired sessions in the background
+    pub fn spawn_cleanup(&self, interval: Duration) {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match store.cleanup().await {
+                    Ok(removed) => info!("Removed {} expired sessions", removed),
+                    Err(e) => error!("Failed to clean up expired sessions: {}", e),
+                }
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl SessionStore for PostgresSessionStore {
+    async fn load_session(&self, cookie_value: String) -> Result<Option<Session>> {
+        let id = Session::id_from_cookie_value(&cookie_value)?;
+        let mut conn = self.get_connection()?;
+
+        let stored = match get_session_by_id(&mut conn, &id, Utc::now().naive_utc()).await? {
+            Some(stored) => stored,
+            None => return Ok(None),
+        };
+
+        let session: Session = serde_json::from_str(&stored.session)?;
+        Ok(session.validate())
+    }
+
+    async fn store_session(&self, session: Session) -> Result<Option<String>> {
+        let mut conn = self.get_connection()?;
+
+        let insert_session = InsertSession {
+            id: session.id().to_string(),
+            session: serde_json::to_string(&session)?,
+            expires_at: session.expiry().map(|expiry| expiry.naive_utc()),
+        };
+        upsert_session(&mut conn, &insert_session).await?;
+
+        session.reset_data_changed();
+        Ok(session.into_cookie_value())
+    }
+
+    async fn destroy_session(&self, session: Session) -> Result {
+        let mut conn = self.get_connection()?;
+        delete_session_by_id(&mut conn, session.id()).await?;
+        Ok(())
+    }
+
+    async fn clear_store(&self) -> Result {
+        let mut conn = self.get_connection()?;
+        clear_sessions(&mut conn).await?;
+        Ok(())
+    }
+}
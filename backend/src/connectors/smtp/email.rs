@@ -14,7 +14,11 @@ pub struct EmailService {
 impl EmailService {
     pub fn new() -> EmailService {
         let host = env::var("SMTP_HOST").expect("SMTP host not found in .env");
-        return EmailService { email_host: host };
+        return EmailService::with_host(host);
+    }
+
+    pub fn with_host(email_host: String) -> EmailService {
+        EmailService { email_host }
     }
 
     fn get_credentials() -> Credentials {
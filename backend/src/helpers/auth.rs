@@ -1,4 +1,6 @@
+use crate::connectors::db::follow::is_following_by_username;
 use crate::types::{error::Error, state::TideState};
+use diesel::PgConnection;
 use std::sync::Arc;
 use tide::Request;
 
@@ -15,3 +17,16 @@ pub fn get_session_username(req: &Request<Arc<TideState>>) -> Result<String, Err
         .get("username")
         .ok_or_else(|| Error::InvalidSessionError())
 }
+
+// private profiles, and everything on them, are only visible to the owner and followers
+pub async fn can_view_profile(
+    conn: &mut PgConnection,
+    session_username: String,
+    username: String,
+    is_private: bool,
+) -> Result<bool, Error> {
+    if session_username == username || !is_private {
+        return Ok(true);
+    }
+    is_following_by_username(conn, session_username, username).await
+}
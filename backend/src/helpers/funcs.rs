@@ -2,6 +2,7 @@ use crate::types::error::Error;
 use chrono::{NaiveDateTime, TimeDelta};
 use dotenvy::dotenv;
 use std::env;
+use url::Url;
 
 // various miscellaneous functions
 
@@ -15,6 +16,19 @@ pub fn get_url() -> String {
     ip_port
 }
 
+// gets the frontend url that links sent to users and shared profiles point to
+pub fn get_frontend_url() -> Option<Url> {
+    env::var("FRONTEND_URL")
+        .ok()
+        .filter(|url| !url.is_empty())
+        .or_else(|| {
+            env::var("CORS_WHITELIST_URLS")
+                .ok()
+                .and_then(|urls| urls.split(',').next().map(|url| url.trim().to_owned()))
+        })
+        .and_then(|url| Url::parse(&url).ok())
+}
+
 // takes one chrono timestamp and a time interval in chrono duration and returns whether its expired
 pub fn is_expired(time_before: NaiveDateTime, expiry_duration: TimeDelta) -> Result<bool, Error> {
     let now_secs = chrono::Local::now().timestamp();
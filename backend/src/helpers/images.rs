@@ -0,0 +1,644 @@
+use std::io::Cursor;
+
+use futures::AsyncReadExt;
+use image::{
+    codecs::jpeg::JpegEncoder, imageops::FilterType, metadata::Orientation, DynamicImage,
+    ImageDecoder, ImageFormat, ImageReader,
+};
+use tide::Request;
+use uuid::Uuid;
+
+use crate::{
+    models::images::{ImageRendition, ImageSrcset},
+    types::error::{Error, ImageErrors, RequestErrors},
+};
+
+// functions related to validating uploaded images and resizing them into renditions
+
+pub const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+// largest width and height in pixels
+pub const MAX_IMAGE_DIMENSION: u32 = 4096;
+// quality of re-encoded jpegs
+const JPEG_QUALITY: u8 = 90;
+// longest side of each rendition in pixels, images are never upscaled
+pub const RENDITION_SIZES: [u32; 3] = [64, 256, 1024];
+// the fallback rendition of this size is the plain src of an image
+const DEFAULT_RENDITION_SIZE: u32 = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageKind {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+}
+
+impl ImageKind {
+    fn from_format(format: ImageFormat) -> Option<ImageKind> {
+        match format {
+            ImageFormat::Png => Some(ImageKind::Png),
+            ImageFormat::Jpeg => Some(ImageKind::Jpeg),
+            ImageFormat::Gif => Some(ImageKind::Gif),
+            ImageFormat::WebP => Some(ImageKind::Webp),
+            _ => None,
+        }
+    }
+
+    fn format(&self) -> ImageFormat {
+        match self {
+            ImageKind::Png => ImageFormat::Png,
+            ImageKind::Jpeg => ImageFormat::Jpeg,
+            ImageKind::Gif => ImageFormat::Gif,
+            ImageKind::Webp => ImageFormat::WebP,
+        }
+    }
+
+    pub fn from_ext(ext: &str) -> Option<ImageKind> {
+        match ext.to_lowercase().as_str() {
+            "png" => Some(ImageKind::Png),
+            "jpg" | "jpeg" => Some(ImageKind::Jpeg),
+            "gif" => Some(ImageKind::Gif),
+            "webp" => Some(ImageKind::Webp),
+            _ => None,
+        }
+    }
+
+    pub fn from_content_type(content_type: &str) -> Option<ImageKind> {
+        match content_type.to_lowercase().as_str() {
+            "image/png" => Some(ImageKind::Png),
+            "image/jpeg" => Some(ImageKind::Jpeg),
+            "image/gif" => Some(ImageKind::Gif),
+            "image/webp" => Some(ImageKind::Webp),
+            _ => None,
+        }
+    }
+
+    pub fn ext(&self) -> &'static str {
+        match self {
+            ImageKind::Png => "png",
+            ImageKind::Jpeg => "jpg",
+            ImageKind::Gif => "gif",
+            ImageKind::Webp => "webp",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ImageKind::Png => "image/png",
+            ImageKind::Jpeg => "image/jpeg",
+            ImageKind::Gif => "image/gif",
+            ImageKind::Webp => "image/webp",
+        }
+    }
+}
+
+// an image that is safe to store, without its metadata
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidatedImage {
+    pub bytes: Vec<u8>,
+    pub kind: ImageKind,
+    pub width: u32,
+    pub height: u32,
+}
+
+// a rendition with the bytes to upload
+#[derive(Debug, Clone)]
+pub struct EncodedRendition {
+    pub rendition: ImageRendition,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageRenditions {
+    pub encoded: Vec<EncodedRendition>,
+    // used as img_src and filename of the images row
+    pub default: ImageRendition,
+}
+
+impl ImageRenditions {
+    pub fn renditions(&self) -> Vec<ImageRendition> {
+        self.encoded
+            .iter()
+            .map(|encoded| encoded.rendition.clone())
+            .collect()
+    }
+
+    // value of the images.renditions column
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self.renditions()).unwrap_or_default()
+    }
+
+    pub fn srcset(&self) -> ImageSrcset {
+        ImageSrcset::new(self.default.img_src.clone(), &self.renditions())
+    }
+}
+
+// jpeg segments are a marker followed by a big endian length that includes itself.
+// app1 holds exif and xmp, app13 holds iptc and com holds free text comments.
+// the image data after the first scan is kept as is
+fn strip_jpeg_metadata(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut stripped = bytes.get(..2)?.to_vec();
+    let mut pos = 2;
+    while pos < bytes.len() {
+        if bytes[pos] != 0xFF {
+            return None;
+        }
+        let marker = *bytes.get(pos + 1)?;
+        match marker {
+            // padding before a marker
+            0xFF => {
+                pos += 1;
+                continue;
+            }
+            // start of scan or end of image
+            0xDA | 0xD9 => {
+                stripped.extend_from_slice(&bytes[pos..]);
+                return Some(stripped);
+            }
+            // markers without a length
+            0x01 | 0xD0..=0xD7 => {
+                stripped.extend_from_slice(&bytes[pos..pos + 2]);
+                pos += 2;
+                continue;
+            }
+            _ => (),
+        }
+        let length = u16::from_be_bytes([*bytes.get(pos + 2)?, *bytes.get(pos + 3)?]) as usize;
+        let end = pos + 2 + length;
+        let segment = bytes.get(pos..end)?;
+        if !matches!(marker, 0xE1 | 0xED | 0xFE) {
+            stripped.extend_from_slice(segment);
+        }
+        pos = end;
+    }
+    Some(stripped)
+}
+
+// png chunks are a big endian length, a type, the data and a crc
+fn strip_png_metadata(bytes: &[u8]) -> Option<Vec<u8>> {
+    const METADATA_CHUNKS: [&[u8; 4]; 5] = [b"eXIf", b"tEXt", b"iTXt", b"zTXt", b"tIME"];
+    let mut stripped = bytes.get(..8)?.to_vec();
+    let mut pos = 8;
+    while pos < bytes.len() {
+        let length = u32::from_be_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let chunk_type = bytes.get(pos + 4..pos + 8)?;
+        let end = pos + 12 + length;
+        let chunk = bytes.get(pos..end)?;
+        if !METADATA_CHUNKS.iter().any(|name| chunk_type == *name) {
+            stripped.extend_from_slice(chunk);
+        }
+        pos = end;
+    }
+    Some(stripped)
+}
+
+// webp chunks are a fourcc, a little endian length and the data padded to an even length.
+// the extended header flags the exif and xmp chunks, so the flags are cleared with them
+fn strip_webp_metadata(bytes: &[u8]) -> Option<Vec<u8>> {
+    const EXIF_FLAG: u8 = 0x08;
+    const XMP_FLAG: u8 = 0x04;
+    let mut stripped = bytes.get(..12)?.to_vec();
+    let mut pos = 12;
+    while pos < bytes.len() {
+        let fourcc = bytes.get(pos..pos + 4)?;
+        let length = u32::from_le_bytes(bytes.get(pos + 4..pos + 8)?.try_into().ok()?) as usize;
+        let end = (pos + 8 + length + length % 2).min(bytes.len());
+        let chunk = bytes.get(pos..end)?;
+        if fourcc == b"VP8X" {
+            let flags_pos = stripped.len() + 8;
+            stripped.extend_from_slice(chunk);
+            *stripped.get_mut(flags_pos)? &= !(EXIF_FLAG | XMP_FLAG);
+        } else if fourcc != b"EXIF" && fourcc != b"XMP " {
+            stripped.extend_from_slice(chunk);
+        }
+        pos = end;
+    }
+    let riff_size = (stripped.len() - 8) as u32;
+    stripped[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Some(stripped)
+}
+
+fn strip_metadata(kind: ImageKind, bytes: &[u8]) -> Option<Vec<u8>> {
+    match kind {
+        ImageKind::Png => strip_png_metadata(bytes),
+        ImageKind::Jpeg => strip_jpeg_metadata(bytes),
+        ImageKind::Webp => strip_webp_metadata(bytes),
+        // gifs have no exif
+        ImageKind::Gif => Some(bytes.to_vec()),
+    }
+}
+
+// rotated jpegs would be shown sideways without their exif orientation,
+// so the rotation is applied to the pixels instead
+fn orient_jpeg(bytes: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+    let malformed = |_| Error::ImageError(ImageErrors::Malformed);
+    let mut decoder = ImageReader::with_format(Cursor::new(bytes), ImageFormat::Jpeg)
+        .into_decoder()
+        .map_err(malformed)?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    if orientation == Orientation::NoTransforms {
+        return Ok(None);
+    }
+
+    let mut image = DynamicImage::from_decoder(decoder).map_err(malformed)?;
+    image.apply_orientation(orientation);
+    let mut jpeg = Vec::new();
+    image
+        .to_rgb8()
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY))
+        .map_err(malformed)?;
+    Ok(Some(jpeg))
+}
+
+// reads an uploaded image from the request body, nothing past the size limit is read
+pub async fn read_image_body<State>(req: &mut Request<State>) -> Result<Vec<u8>, Error> {
+    if req.len().is_some_and(|len| len > MAX_IMAGE_BYTES) {
+        return Err(Error::ImageError(ImageErrors::TooLarge));
+    }
+    let mut bytes = Vec::new();
+    req.take_body()
+        .take(MAX_IMAGE_BYTES as u64 + 1)
+        .read_to_end(&mut bytes)
+        .await
+        .map_err(|_| Error::InvalidRequestError(RequestErrors::MalformedPayload))?;
+    if bytes.len() > MAX_IMAGE_BYTES {
+        return Err(Error::ImageError(ImageErrors::TooLarge));
+    }
+    Ok(bytes)
+}
+
+// the format is sniffed from the content instead of trusting the extension or content type
+pub fn validate_image(bytes: &[u8]) -> Result<ValidatedImage, Error> {
+    if bytes.len() > MAX_IMAGE_BYTES {
+        return Err(Error::ImageError(ImageErrors::TooLarge));
+    }
+    let kind = image::guess_format(bytes)
+        .ok()
+        .and_then(ImageKind::from_format)
+        .ok_or(Error::ImageError(ImageErrors::UnsupportedFormat))?;
+
+    // the header is enough to get the dimensions without decoding the image
+    let (width, height) = ImageReader::with_format(Cursor::new(bytes), kind.format())
+        .into_dimensions()
+        .map_err(|_| Error::ImageError(ImageErrors::Malformed))?;
+    if width > MAX_IMAGE_DIMENSION || height > MAX_IMAGE_DIMENSION {
+        return Err(Error::ImageError(ImageErrors::DimensionsTooLarge));
+    }
+
+    // re-encoded jpegs have no metadata left
+    if kind == ImageKind::Jpeg {
+        if let Some(jpeg) = orient_jpeg(bytes)? {
+            let (width, height) = ImageReader::with_format(Cursor::new(&jpeg), kind.format())
+                .into_dimensions()
+                .map_err(|_| Error::ImageError(ImageErrors::Malformed))?;
+            return Ok(ValidatedImage {
+                bytes: jpeg,
+                kind,
+                width,
+                height,
+            });
+        }
+    }
+
+    let bytes = strip_metadata(kind, bytes).ok_or(Error::ImageError(ImageErrors::Malformed))?;
+    Ok(ValidatedImage {
+        bytes,
+        kind,
+        width,
+        height,
+    })
+}
+
+// sizes smaller than the image, and the image itself when it is smaller than the largest size
+fn rendition_sizes(width: u32, height: u32) -> Vec<u32> {
+    let longest_side = width.max(height);
+    let mut sizes = RENDITION_SIZES
+        .into_iter()
+        .filter(|size| *size < longest_side)
+        .collect::<Vec<u32>>();
+    if RENDITION_SIZES.iter().any(|size| *size >= longest_side) {
+        sizes.push(longest_side);
+    }
+    sizes
+}
+
+// jpegs stay jpegs for browsers without webp, everything else becomes a png to keep transparency
+fn fallback_kind(kind: ImageKind) -> ImageKind {
+    match kind {
+        ImageKind::Jpeg => ImageKind::Jpeg,
+        _ => ImageKind::Png,
+    }
+}
+
+fn encode_rendition(image: &DynamicImage, kind: ImageKind) -> Result<Vec<u8>, Error> {
+    let malformed = |_| Error::ImageError(ImageErrors::Malformed);
+    let mut bytes = Cursor::new(Vec::new());
+    match kind {
+        ImageKind::Jpeg => image
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY))
+            .map_err(malformed)?,
+        // the webp encoder only takes 8 bit pixels
+        ImageKind::Webp => image
+            .to_rgba8()
+            .write_to(&mut bytes, ImageFormat::WebP)
+            .map_err(malformed)?,
+        _ => image
+            .write_to(&mut bytes, kind.format())
+            .map_err(malformed)?,
+    }
+    Ok(bytes.into_inner())
+}
+
+// resizes an image into a webp and a fallback rendition per size, stored under
+// derived keys of a new random name so that cdn caches never serve an old image.
+// only the first frame of an animated gif is kept
+pub fn make_renditions(image: &ValidatedImage, cdn_origin: &str) -> Result<ImageRenditions, Error> {
+    let decoded = image::load_from_memory_with_format(&image.bytes, image.kind.format())
+        .map_err(|_| Error::ImageError(ImageErrors::Malformed))?;
+    let name = Uuid::new_v4().to_string();
+    let fallback = fallback_kind(image.kind);
+
+    let mut encoded = Vec::new();
+    let mut default = None;
+    for size in rendition_sizes(image.width, image.height) {
+        let resized = if size < image.width.max(image.height) {
+            decoded.resize(size, size, FilterType::Lanczos3)
+        } else {
+            decoded.clone()
+        };
+        for kind in [ImageKind::Webp, fallback] {
+            let filename = format!("{}-{}.{}", name, size, kind.ext());
+            let rendition = ImageRendition {
+                width: resized.width(),
+                height: resized.height(),
+                content_type: kind.content_type().to_string(),
+                img_src: [cdn_origin, &filename].join("/"),
+                filename,
+            };
+            // the largest size up to the default size
+            if kind == fallback && (default.is_none() || size <= DEFAULT_RENDITION_SIZE) {
+                default = Some(rendition.clone());
+            }
+            encoded.push(EncodedRendition {
+                bytes: encode_rendition(&resized, kind)?,
+                rendition,
+            });
+        }
+    }
+
+    Ok(ImageRenditions {
+        encoded,
+        default: default.ok_or(Error::ImageError(ImageErrors::Malformed))?,
+    })
+}
+
+// decoding and resizing are cpu bound, so handlers run them on the blocking pool instead of
+// stalling the executor threads that serve other requests. a panic while decoding is treated
+// like an image that could not be read
+pub async fn spawn_validate_image(bytes: Vec<u8>) -> Result<ValidatedImage, Error> {
+    tokio::task::spawn_blocking(move || validate_image(&bytes))
+        .await
+        .unwrap_or(Err(Error::ImageError(ImageErrors::Malformed)))
+}
+
+pub async fn spawn_make_renditions(
+    image: ValidatedImage,
+    cdn_origin: String,
+) -> Result<ImageRenditions, Error> {
+    tokio::task::spawn_blocking(move || make_renditions(&image, &cdn_origin))
+        .await
+        .unwrap_or(Err(Error::ImageError(ImageErrors::Malformed)))
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::io::Cursor;
+
+    use image::{ImageFormat, RgbImage};
+
+    use crate::models::images::GetImage;
+
+    use super::{make_renditions, validate_image, ImageKind, MAX_IMAGE_DIMENSION};
+
+    fn encode(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        RgbImage::new(width, height)
+            .write_to(&mut bytes, format)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    // inserts an exif app1 segment after the start of image marker
+    fn with_jpeg_exif(jpeg: &[u8], exif: &[u8]) -> Vec<u8> {
+        let mut segment = vec![0xFF, 0xE1];
+        segment.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+        segment.extend_from_slice(exif);
+        [&jpeg[..2], &segment, &jpeg[2..]].concat()
+    }
+
+    // a big endian exif block with only an orientation tag
+    fn orientation_exif(orientation: u16) -> Vec<u8> {
+        let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01".to_vec();
+        exif.extend_from_slice(&[0x01, 0x12, 0x00, 0x03, 0, 0, 0, 1]);
+        exif.extend_from_slice(&orientation.to_be_bytes());
+        exif.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        exif
+    }
+
+    #[test]
+    fn it_sniffs_format_from_content() {
+        for (format, kind) in [
+            (ImageFormat::Png, ImageKind::Png),
+            (ImageFormat::Jpeg, ImageKind::Jpeg),
+            (ImageFormat::Gif, ImageKind::Gif),
+            (ImageFormat::WebP, ImageKind::Webp),
+        ] {
+            let image = validate_image(&encode(8, 4, format)).unwrap();
+            assert_eq!(image.kind, kind);
+            assert_eq!((image.width, image.height), (8, 4));
+            assert_eq!(
+                ImageKind::from_content_type(kind.content_type()),
+                Some(kind)
+            );
+        }
+        assert_eq!(ImageKind::Webp.content_type(), "image/webp");
+        assert_eq!(ImageKind::from_content_type("image/svg+xml"), None);
+    }
+
+    #[test]
+    fn it_rejects_non_images() {
+        assert!(validate_image(b"<svg xmlns=\"http://www.w3.org/2000/svg\"></svg>").is_err());
+        assert!(validate_image(b"GIF89a").is_err());
+        assert!(validate_image(&[]).is_err());
+    }
+
+    #[test]
+    fn it_rejects_large_images() {
+        assert!(validate_image(&encode(MAX_IMAGE_DIMENSION + 1, 1, ImageFormat::Png)).is_err());
+        let mut oversized = encode(8, 8, ImageFormat::Png);
+        oversized.resize(6 * 1024 * 1024, 0);
+        assert!(validate_image(&oversized).is_err());
+    }
+
+    #[test]
+    fn it_strips_jpeg_exif() {
+        let exif = [b"Exif\0\0".as_slice(), b"GPS secret location"].concat();
+        let jpeg = with_jpeg_exif(&encode(8, 8, ImageFormat::Jpeg), &exif);
+        let image = validate_image(&jpeg).unwrap();
+        assert!(!image.bytes.windows(6).any(|window| window == b"Exif\0\0"));
+        assert_eq!(image.bytes.len(), jpeg.len() - exif.len() - 4);
+        assert!(image::load_from_memory(&image.bytes).is_ok());
+    }
+
+    #[test]
+    fn it_strips_jpeg_comments() {
+        let jpeg = encode(8, 8, ImageFormat::Jpeg);
+        let comment = b"GPS secret location";
+        let mut segment = vec![0xFF, 0xFE];
+        segment.extend_from_slice(&((comment.len() + 2) as u16).to_be_bytes());
+        segment.extend_from_slice(comment);
+        let with_comment = [&jpeg[..2], &segment, &jpeg[2..]].concat();
+        let image = validate_image(&with_comment).unwrap();
+        assert!(!image
+            .bytes
+            .windows(comment.len())
+            .any(|window| window == comment));
+        assert_eq!(image.bytes.len(), jpeg.len());
+    }
+
+    #[test]
+    fn it_applies_jpeg_orientation() {
+        // rotated 90 degrees clockwise
+        let jpeg = with_jpeg_exif(&encode(8, 4, ImageFormat::Jpeg), &orientation_exif(6));
+        let image = validate_image(&jpeg).unwrap();
+        assert_eq!((image.width, image.height), (4, 8));
+        assert!(!image.bytes.windows(6).any(|window| window == b"Exif\0\0"));
+    }
+
+    #[test]
+    fn it_strips_png_text_chunks() {
+        let png = encode(8, 8, ImageFormat::Png);
+        // a text chunk right after the header chunk, the crc is not checked here
+        let mut chunk = 7u32.to_be_bytes().to_vec();
+        chunk.extend_from_slice(b"tEXtsecret!");
+        chunk.extend_from_slice(&[0, 0, 0, 0]);
+        let header_end = 8 + 12 + 13;
+        let with_text = [&png[..header_end], &chunk, &png[header_end..]].concat();
+        let image = validate_image(&with_text).unwrap();
+        assert_eq!(image.bytes, png);
+    }
+
+    #[test]
+    fn it_strips_webp_exif() {
+        let webp = encode(8, 8, ImageFormat::WebP);
+        // extended header with the exif flag set, then the image and an exif chunk
+        let mut vp8x = b"VP8X".to_vec();
+        vp8x.extend_from_slice(&10u32.to_le_bytes());
+        vp8x.extend_from_slice(&[0x08, 0, 0, 0, 7, 0, 0, 7, 0, 0]);
+        let mut exif = b"EXIF".to_vec();
+        exif.extend_from_slice(&5u32.to_le_bytes());
+        exif.extend_from_slice(b"GPS!!\0");
+        let mut extended = [&webp[..12], &vp8x, &webp[12..], &exif].concat();
+        let riff_size = (extended.len() - 8) as u32;
+        extended[4..8].copy_from_slice(&riff_size.to_le_bytes());
+
+        let image = validate_image(&extended).unwrap();
+        assert!(!image.bytes.windows(4).any(|window| window == b"EXIF"));
+        assert_eq!(image.bytes[20] & 0x08, 0);
+        assert_eq!(
+            u32::from_le_bytes(image.bytes[4..8].try_into().unwrap()) as usize,
+            image.bytes.len() - 8
+        );
+        assert!(image::load_from_memory(&image.bytes).is_ok());
+    }
+
+    #[test]
+    fn it_makes_webp_and_fallback_renditions() {
+        let image = validate_image(&encode(2000, 1000, ImageFormat::Png)).unwrap();
+        let renditions = make_renditions(&image, "https://cdn.example.com").unwrap();
+
+        let sizes = renditions
+            .encoded
+            .iter()
+            .map(|encoded| {
+                let decoded = image::load_from_memory(&encoded.bytes).unwrap();
+                assert_eq!(
+                    (decoded.width(), decoded.height()),
+                    (encoded.rendition.width, encoded.rendition.height)
+                );
+                (
+                    encoded.rendition.width,
+                    encoded.rendition.height,
+                    encoded.rendition.content_type.as_str(),
+                )
+            })
+            .collect::<Vec<(u32, u32, &str)>>();
+        assert_eq!(
+            sizes,
+            vec![
+                (64, 32, "image/webp"),
+                (64, 32, "image/png"),
+                (256, 128, "image/webp"),
+                (256, 128, "image/png"),
+                (1024, 512, "image/webp"),
+                (1024, 512, "image/png"),
+            ]
+        );
+
+        // the fallback of the default size is the plain src
+        assert_eq!(renditions.default.width, 256);
+        assert_eq!(renditions.default.content_type, "image/png");
+        assert!(renditions
+            .default
+            .img_src
+            .starts_with("https://cdn.example.com/"));
+        assert!(renditions.default.filename.ends_with("-256.png"));
+
+        let srcset = renditions.srcset();
+        assert_eq!(srcset.src, renditions.default.img_src);
+        let webp = srcset.sources.get("image/webp").unwrap();
+        assert!(webp.ends_with("-1024.webp 1024w"));
+        assert_eq!(webp.split(", ").count(), 3);
+    }
+
+    #[test]
+    fn it_does_not_upscale_small_images() {
+        let image = validate_image(&encode(100, 50, ImageFormat::Jpeg)).unwrap();
+        let renditions = make_renditions(&image, "https://cdn.example.com").unwrap();
+        let widths = renditions
+            .encoded
+            .iter()
+            .map(|encoded| encoded.rendition.width)
+            .collect::<Vec<u32>>();
+        assert_eq!(widths, vec![64, 64, 100, 100]);
+        // jpegs fall back to jpegs
+        assert_eq!(renditions.default.content_type, "image/jpeg");
+        assert_eq!(renditions.default.width, 100);
+    }
+
+    #[test]
+    fn it_reads_renditions_of_stored_images() {
+        let image = validate_image(&encode(300, 300, ImageFormat::Gif)).unwrap();
+        let renditions = make_renditions(&image, "https://cdn.example.com").unwrap();
+        let stored = GetImage {
+            id: 1,
+            img_src: renditions.default.img_src.clone(),
+            filename: renditions.default.filename.clone(),
+            user_id: Some(1),
+            link_id: None,
+            renditions: renditions.to_json(),
+        };
+        assert_eq!(stored.rendition_list(), renditions.renditions());
+        assert_eq!(stored.filenames().len(), 6);
+        assert_eq!(stored.srcset(), renditions.srcset());
+
+        // images from before renditions only have their original file
+        let legacy = GetImage {
+            renditions: serde_json::json!([]),
+            ..stored
+        };
+        assert_eq!(legacy.filenames(), vec![legacy.filename.clone()]);
+        assert!(legacy.srcset().sources.is_empty());
+    }
+}
@@ -1,142 +1,108 @@
-use crate::routes::links::get::GetImagedLink;
-use priority_queue::PriorityQueue;
-use std::{cmp::Reverse, collections::HashMap};
+use crate::models::links::GetLink;
 
 // functions related to links
 
-// we assume each GetLink element is unique (i.e link.id is unique) in the vector.
-pub fn linearise(links: &Vec<GetImagedLink>) -> Vec<GetImagedLink> {
-    let mut link_map = HashMap::<i32, GetImagedLink>::new();
-    let mut pq = PriorityQueue::<i32, Reverse<i32>>::new();
-    let mut linearised = Vec::<GetImagedLink>::new();
+// problems with how a single user's links are shown. the order itself is kept valid by the
+// unique positions of a user and the section foreign key, so these are links that the profile
+// would leave out or show somewhere else than their position
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkOrderIssue {
+    // the links of the profile are not the links of the user by position
+    ProfileOrderMismatch { expected: Vec<i32>, shown: Vec<i32> },
+    // the link is in a section that is not one of the user's, so no section shows it
+    ForeignSection { link_id: i32, section_id: i32 },
+}
 
-    for link in links {
-        link_map.insert(link.id, link.clone());
-        pq.push(link.id, Reverse(0));
-    }
+// links are the user's links sorted by position, shown_link_ids the links of the profile
+// in the order it shows them and section_ids the user's sections
+pub fn find_link_order_issues(
+    links: &[GetLink],
+    shown_link_ids: &[i32],
+    section_ids: &[i32],
+) -> Vec<LinkOrderIssue> {
+    let mut issues = Vec::<LinkOrderIssue>::new();
 
-    for link in links {
-        if link.next_id.is_some() {
-            pq.change_priority_by(&link.next_id.unwrap(), |prev| *prev = Reverse(prev.0 + 1));
-        }
+    let expected = links.iter().map(|link| link.id).collect::<Vec<i32>>();
+    if expected != shown_link_ids {
+        issues.push(LinkOrderIssue::ProfileOrderMismatch {
+            expected,
+            shown: shown_link_ids.to_vec(),
+        });
     }
 
-    while !pq.is_empty() {
-        let curr = pq.pop().unwrap();
-        let item = link_map.get(&curr.0).unwrap();
-        match item.next_id {
-            Some(curr_next_id) => {
-                pq.change_priority_by(&curr_next_id, |curr| *curr = Reverse(curr.0 - 1));
+    for link in links.iter() {
+        match link.section_id {
+            Some(section_id) if !section_ids.contains(&section_id) => {
+                issues.push(LinkOrderIssue::ForeignSection {
+                    link_id: link.id,
+                    section_id,
+                })
             }
-            None => {}
-        };
-        linearised.push(item.clone());
+            _ => (),
+        }
     }
 
-    linearised
+    issues
 }
 
 #[cfg(test)]
 mod unit_tests {
-    use crate::routes::links::get::GetImagedLink;
+    use crate::models::links::GetLink;
+
+    use super::{find_link_order_issues, LinkOrderIssue};
 
-    use super::linearise;
+    fn mock_link(id: i32, position: i64) -> GetLink {
+        GetLink {
+            id,
+            user_id: 0,
+            description: None,
+            title: None,
+            href: "".to_string(),
+            visible_from: None,
+            visible_until: None,
+            section_id: None,
+            position,
+        }
+    }
+
+    fn mock_section_link(id: i32, position: i64, section_id: i32) -> GetLink {
+        GetLink {
+            section_id: Some(section_id),
+            ..mock_link(id, position)
+        }
+    }
 
     #[test]
-    pub fn it_should_return_linearised() {
-        // 3 -> 4 -> 2 -> 1 -> 0
-        let unique_links = Vec::<GetImagedLink>::from([
-            GetImagedLink {
-                id: 0,
-                user_id: 0,
-                next_id: None,
-                description: None,
-                title: None,
-                href: "".to_string(),
-                img_src: None,
-            },
-            GetImagedLink {
-                id: 1,
-                user_id: 0,
-                next_id: Some(0),
-                description: None,
-                title: None,
-                href: "".to_string(),
-                img_src: None,
-            },
-            GetImagedLink {
-                id: 2,
-                user_id: 0,
-                next_id: Some(1),
-                description: None,
-                title: None,
-                href: "".to_string(),
-                img_src: None,
-            },
-            GetImagedLink {
-                id: 3,
-                user_id: 0,
-                next_id: Some(4),
-                description: None,
-                title: None,
-                href: "".to_string(),
-                img_src: None,
-            },
-            GetImagedLink {
-                id: 4,
-                user_id: 0,
-                next_id: Some(2),
-                description: None,
-                title: None,
-                href: "".to_string(),
-                img_src: None,
-            },
-        ]);
-        let result_ids: Vec<i32> = linearise(&unique_links)
-            .iter()
-            .map(|link| link.id)
-            .collect();
-        println!("got linearised ids: {:?}", result_ids);
-        assert_eq!(result_ids, [3, 4, 2, 1, 0]);
+    pub fn it_accepts_links_shown_by_position() {
+        let links = [
+            mock_link(3, -1024),
+            mock_section_link(1, 0, 7),
+            mock_link(2, 1),
+        ];
+        assert!(find_link_order_issues(&links, &[3, 1, 2], &[7]).is_empty());
+        assert!(find_link_order_issues(&[], &[], &[]).is_empty());
     }
 
     #[test]
-    pub fn it_should_return_linearised_2() {
-        //
-        let unique_links = Vec::<GetImagedLink>::from([
-            GetImagedLink {
-                id: 1,
-                user_id: 1,
-                next_id: Some(3),
-                description: None,
-                title: None,
-                href: "".to_string(),
-                img_src: None,
-            },
-            GetImagedLink {
-                id: 2,
-                user_id: 1,
-                next_id: Some(1),
-                description: None,
-                title: None,
-                href: "".to_string(),
-                img_src: None,
-            },
-            GetImagedLink {
-                id: 3,
-                user_id: 1,
-                next_id: None,
-                description: None,
-                title: None,
-                href: "".to_string(),
-                img_src: None,
-            },
-        ]);
-        let result_ids: Vec<i32> = linearise(&unique_links)
-            .iter()
-            .map(|link| link.id)
-            .collect();
-        println!("got linearised ids: {:?}", result_ids);
-        assert_eq!(result_ids, [2, 1, 3]);
+    pub fn it_finds_links_the_profile_does_not_show_in_place() {
+        let links = [
+            mock_link(1, 1024),
+            mock_section_link(2, 2048, 8),
+            mock_link(3, 3072),
+        ];
+        let issues = find_link_order_issues(&links, &[1, 3, 2, 2], &[7]);
+        assert_eq!(
+            issues,
+            [
+                LinkOrderIssue::ProfileOrderMismatch {
+                    expected: vec![1, 2, 3],
+                    shown: vec![1, 3, 2, 2],
+                },
+                LinkOrderIssue::ForeignSection {
+                    link_id: 2,
+                    section_id: 8
+                }
+            ]
+        );
     }
 }
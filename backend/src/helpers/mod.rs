@@ -1,11 +1,17 @@
 pub mod auth;
 pub mod errors;
 pub mod funcs;
+pub mod images;
 pub mod links;
 pub mod notifications;
 pub mod params;
+pub mod previews;
+pub mod qr;
 pub mod random;
 pub mod state;
+pub mod two_factor;
 pub mod validation;
+pub mod verification;
+pub mod visitors;
 
 // these are helpers functions for various logic and routes
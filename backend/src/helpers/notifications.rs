@@ -1,6 +1,7 @@
 use diesel::PgConnection;
 
 use crate::connectors::db::notifications::create_notification;
+use crate::models::links::GetLink;
 use crate::models::users::GetUser;
 use crate::types::error::Error;
 use crate::{connectors::db::user::get_user_by_id, models::notifications::InsertNotification};
@@ -11,9 +12,11 @@ use crate::{connectors::db::user::get_user_by_id, models::notifications::InsertN
 /*
 Accepted: 1
 Follow: 2
+Broken link: 3
 */
 pub const ACCEPTED_NOTIFICATION_TYPE: i32 = 1;
 pub const FOLLOW_REQUEST_TYPE: i32 = 2;
+pub const BROKEN_LINK_NOTIFICATION_TYPE: i32 = 3;
 
 fn accepted_notification_msg(trigger_name: String) -> String {
     let msg: String = trigger_name + " accepted your follow request";
@@ -83,3 +86,31 @@ pub async fn create_request_notification(
         Err(e) => return Err(e),
     }
 }
+
+fn broken_link_notification_msg(link: &GetLink, failures: i32) -> String {
+    let name = match &link.title {
+        Some(title) if !title.trim().is_empty() => title.clone(),
+        _ => link.href.clone(),
+    };
+    format!(
+        "Your link \"{}\" could not be reached {} times in a row",
+        name, failures
+    )
+}
+
+// the owner triggers the notification about their own link
+pub async fn create_broken_link_notification(
+    conn: &mut PgConnection,
+    link: &GetLink,
+    failures: i32,
+) -> Result<(), Error> {
+    let new_notif: InsertNotification = InsertNotification {
+        user_id: link.user_id,
+        trigger_id: link.user_id,
+        is_read: false,
+        created_at: chrono::Local::now().naive_local(),
+        notification_type: BROKEN_LINK_NOTIFICATION_TYPE,
+        msg: broken_link_notification_msg(link, failures),
+    };
+    create_notification(conn, new_notif).await.map(|_| ())
+}
@@ -1,6 +1,9 @@
-use crate::types::{
-    error::{Error, RequestErrors},
-    state::TideState,
+use crate::{
+    connectors::buckets::store::Bucket,
+    types::{
+        error::{Error, RequestErrors},
+        state::TideState,
+    },
 };
 use std::sync::Arc;
 use tide::Request;
@@ -14,8 +17,35 @@ pub fn extract_link_id_from_params(req: &Request<Arc<TideState>>) -> Result<i32,
         .map_err(|_| Error::InvalidRequestError(RequestErrors::MalformedParams))
 }
 
+pub fn extract_section_id_from_params(req: &Request<Arc<TideState>>) -> Result<i32, Error> {
+    req.param("section_id")
+        .map_err(|_| ())
+        .and_then(|section| section.parse().map_err(|_| ()))
+        .map_err(|_| Error::InvalidRequestError(RequestErrors::MalformedParams))
+}
+
 pub fn extract_username_from_params(req: &Request<Arc<TideState>>) -> Result<String, Error> {
     req.param("username")
         .map(|username| username.to_string())
         .map_err(|_| Error::InvalidRequestError(RequestErrors::MalformedParams))
 }
+
+pub fn extract_upload_token_from_params(req: &Request<Arc<TideState>>) -> Result<String, Error> {
+    req.param("token")
+        .map(|token| token.to_string())
+        .map_err(|_| Error::InvalidRequestError(RequestErrors::MalformedParams))
+}
+
+pub fn extract_object_from_params(
+    req: &Request<Arc<TideState>>,
+) -> Result<(Bucket, String), Error> {
+    let bucket = req
+        .param("bucket")
+        .ok()
+        .and_then(Bucket::from_name)
+        .ok_or(Error::InvalidRequestError(RequestErrors::MalformedParams))?;
+    let key = req
+        .param("key")
+        .map_err(|_| Error::InvalidRequestError(RequestErrors::MalformedParams))?;
+    Ok((bucket, key.to_string()))
+}
@@ -0,0 +1,358 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use scraper::{Html, Selector};
+use url::{Host, Url};
+
+use crate::{
+    connectors::previews::fetcher::PageFetcher,
+    helpers::images::{spawn_validate_image, ValidatedImage, MAX_IMAGE_BYTES},
+    types::error::Error,
+};
+
+// functions related to building link previews from open graph tags
+
+// open graph tags are in the head, the rest of a large page is not needed
+const MAX_PAGE_BYTES: usize = 512 * 1024;
+// same limits as the link payload validation
+const MAX_TITLE_LENGTH: usize = 100;
+const MAX_DESCRIPTION_LENGTH: usize = 300;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinkPreview {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<Url>,
+}
+
+// addresses that are reachable from the internet, everything else could be one of our own services
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ipv4(ip),
+            None => is_public_ipv6(ip),
+        },
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // 0.0.0.0/8
+        || a == 0
+        // carrier grade nat 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+        // benchmarking 198.18.0.0/15
+        || (a == 198 && (b == 18 || b == 19))
+        // reserved 240.0.0.0/4
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local()
+        // documentation 2001:db8::/32
+        || (segments[0] == 0x2001 && segments[1] == 0xdb8)
+        // addresses that embed an ipv4 address and can be routed to it by a gateway:
+        // ipv4 compatible ::a.b.c.d, nat64 64:ff9b::/96 and 64:ff9b:1::/48, 6to4 2002::/16
+        // and teredo 2001::/32
+        || segments[..6] == [0; 6]
+        || segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0]
+        || (segments[0] == 0x64 && segments[1] == 0xff9b && segments[2] == 1)
+        || segments[0] == 0x2002
+        || (segments[0] == 0x2001 && segments[1] == 0))
+}
+
+// only http(s) urls of public hosts are fetched
+pub fn check_preview_url(url: &Url) -> Result<(), Error> {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(Error::PreviewError(format!(
+            "unsupported scheme {}",
+            url.scheme()
+        )));
+    }
+    let is_public = match url.host() {
+        Some(Host::Ipv4(ip)) => is_public_ip(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_public_ip(IpAddr::V6(ip)),
+        // hostnames are checked again once they are resolved
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_lowercase();
+            domain != "localhost" && !domain.ends_with(".localhost")
+        }
+        None => false,
+    };
+    if !is_public {
+        return Err(Error::PreviewError(format!("{} is not a public host", url)));
+    }
+    Ok(())
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    text.chars().take(max_chars).collect()
+}
+
+// the content of the first meta tag with one of the given property or name attributes
+fn meta_content(document: &Html, keys: &[&str]) -> Option<String> {
+    let selector = Selector::parse("meta").unwrap();
+    keys.iter().find_map(|key| {
+        document.select(&selector).find_map(|meta| {
+            let element = meta.value();
+            let matches = element
+                .attr("property")
+                .or_else(|| element.attr("name"))
+                .is_some_and(|attr| attr.eq_ignore_ascii_case(key));
+            element
+                .attr("content")
+                .map(|content| content.trim())
+                .filter(|content| matches && !content.is_empty())
+                .map(|content| content.to_string())
+        })
+    })
+}
+
+// reads the open graph tags of a page, falling back to the title and description tags
+pub fn parse_open_graph(html: &str, page_url: &Url) -> LinkPreview {
+    let document = Html::parse_document(html);
+
+    let title = meta_content(&document, &["og:title", "twitter:title"]).or_else(|| {
+        let selector = Selector::parse("title").unwrap();
+        document
+            .select(&selector)
+            .next()
+            .map(|title| title.text().collect::<String>().trim().to_string())
+            .filter(|title| !title.is_empty())
+    });
+    let description = meta_content(
+        &document,
+        &["og:description", "twitter:description", "description"],
+    );
+    // relative image urls are resolved against the page
+    let image_url = meta_content(
+        &document,
+        &[
+            "og:image",
+            "og:image:url",
+            "og:image:secure_url",
+            "twitter:image",
+        ],
+    )
+    .and_then(|image| page_url.join(&image).ok())
+    .filter(|image_url| check_preview_url(image_url).is_ok());
+
+    LinkPreview {
+        title: title.map(|title| truncate_chars(&title, MAX_TITLE_LENGTH)),
+        description: description
+            .map(|description| truncate_chars(&description, MAX_DESCRIPTION_LENGTH)),
+        image_url,
+    }
+}
+
+// fetches the page behind href and reads its preview
+pub async fn fetch_link_preview(
+    fetcher: &dyn PageFetcher,
+    href: &str,
+) -> Result<LinkPreview, Error> {
+    let url = Url::parse(href).map_err(|e| Error::PreviewError(e.to_string()))?;
+    check_preview_url(&url)?;
+
+    let page = fetcher.fetch(&url, MAX_PAGE_BYTES).await?;
+    let is_html = page
+        .content_type
+        .as_deref()
+        .is_none_or(|content_type| content_type.contains("html"));
+    if !is_html {
+        return Err(Error::PreviewError(String::from("page is not html")));
+    }
+    Ok(parse_open_graph(
+        &String::from_utf8_lossy(&page.body),
+        &page.url,
+    ))
+}
+
+// fetches the preview image, which has to be a complete image that passes the upload validation
+pub async fn fetch_preview_image(
+    fetcher: &dyn PageFetcher,
+    image_url: &Url,
+) -> Result<ValidatedImage, Error> {
+    check_preview_url(image_url)?;
+
+    // one byte more than allowed to tell whether the image is too large
+    let image = fetcher.fetch(image_url, MAX_IMAGE_BYTES + 1).await?;
+    if image.truncated {
+        return Err(Error::PreviewError(String::from("image is too large")));
+    }
+    spawn_validate_image(image.body).await
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::{io::Cursor, net::IpAddr};
+
+    use image::{ImageFormat, RgbImage};
+    use url::Url;
+
+    use crate::{connectors::previews::fixture::FixturePageFetcher, helpers::images::ImageKind};
+
+    use super::{
+        check_preview_url, fetch_link_preview, fetch_preview_image, is_public_ip, parse_open_graph,
+        LinkPreview,
+    };
+
+    const PAGE: &str = r#"<!doctype html>
+        <html><head>
+            <title>Fallback title</title>
+            <meta property="og:title" content=" Salad ">
+            <meta property="og:description" content="Links in a bowl">
+            <meta property="og:image" content="/images/salad.png">
+        </head><body></body></html>"#;
+
+    #[test]
+    fn it_parses_open_graph_tags() {
+        let page_url = Url::parse("https://salad.example.com/about").unwrap();
+        assert_eq!(
+            parse_open_graph(PAGE, &page_url),
+            LinkPreview {
+                title: Some(String::from("Salad")),
+                description: Some(String::from("Links in a bowl")),
+                image_url: Some(Url::parse("https://salad.example.com/images/salad.png").unwrap()),
+            }
+        );
+    }
+
+    #[test]
+    fn it_falls_back_to_title_and_description_tags() {
+        let page_url = Url::parse("https://salad.example.com").unwrap();
+        let html = format!(
+            "<html><head><title> Salad </title>\
+            <meta name=\"description\" content=\"{}\">\
+            <meta property=\"og:image\" content=\"http://127.0.0.1/secret.png\">\
+            </head></html>",
+            "a".repeat(400)
+        );
+        let preview = parse_open_graph(&html, &page_url);
+        assert_eq!(preview.title, Some(String::from("Salad")));
+        assert_eq!(preview.description.map(|d| d.len()), Some(300));
+        // images on private hosts are dropped
+        assert_eq!(preview.image_url, None);
+    }
+
+    #[test]
+    fn it_rejects_private_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::127.0.0.1",
+            "::a9fe:a9fe",
+            "64:ff9b::7f00:1",
+            "64:ff9b:1::a9fe:a9fe",
+            "2002:7f00:1::",
+            "2001:0:4136:e378:8000:63bf:3fff:fdd2",
+        ] {
+            assert!(!is_public_ip(ip.parse::<IpAddr>().unwrap()), "{}", ip);
+        }
+        for ip in ["93.184.216.34", "2606:4700::1111"] {
+            assert!(is_public_ip(ip.parse::<IpAddr>().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn it_checks_preview_urls() {
+        for url in ["https://salad.example.com/page", "http://93.184.216.34/"] {
+            assert!(
+                check_preview_url(&Url::parse(url).unwrap()).is_ok(),
+                "{}",
+                url
+            );
+        }
+        for url in [
+            "file:///etc/passwd",
+            "ftp://salad.example.com",
+            "http://localhost:8080/admin",
+            "http://127.0.0.1/",
+            "http://[::1]/",
+            "http://169.254.169.254/latest/meta-data",
+        ] {
+            assert!(
+                check_preview_url(&Url::parse(url).unwrap()).is_err(),
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn it_fetches_preview_and_image_from_fixtures() {
+        let mut png = Cursor::new(Vec::new());
+        RgbImage::new(4, 4)
+            .write_to(&mut png, ImageFormat::Png)
+            .unwrap();
+        let fetcher = FixturePageFetcher::new()
+            .with_page(
+                "https://salad.example.com/about",
+                "text/html; charset=utf-8",
+                PAGE.as_bytes(),
+            )
+            .with_page(
+                "https://salad.example.com/images/salad.png",
+                // the format is sniffed from the content
+                "application/octet-stream",
+                png.get_ref(),
+            );
+
+        let preview = fetch_link_preview(&fetcher, "https://salad.example.com/about")
+            .await
+            .unwrap();
+        assert_eq!(preview.title, Some(String::from("Salad")));
+
+        let image = fetch_preview_image(&fetcher, &preview.image_url.unwrap())
+            .await
+            .unwrap();
+        assert_eq!(image.kind, ImageKind::Png);
+        assert_eq!((image.width, image.height), (4, 4));
+
+        // unknown pages and private hosts fail
+        assert!(
+            fetch_link_preview(&fetcher, "https://salad.example.com/missing")
+                .await
+                .is_err()
+        );
+        assert!(fetch_link_preview(&fetcher, "http://localhost/about")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn it_rejects_oversized_and_non_image_previews() {
+        let url = Url::parse("https://salad.example.com/big.png").unwrap();
+        let fetcher = FixturePageFetcher::new()
+            .with_page(url.as_str(), "image/png", &vec![0; 6 * 1024 * 1024])
+            .with_page(
+                "https://salad.example.com/page.png",
+                "text/html",
+                b"<html></html>",
+            );
+
+        assert!(fetch_preview_image(&fetcher, &url).await.is_err());
+        let page_url = Url::parse("https://salad.example.com/page.png").unwrap();
+        assert!(fetch_preview_image(&fetcher, &page_url).await.is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use std::io::Cursor;
+
+use image::{GrayImage, ImageFormat, Luma};
+use qrcode::{Color, EcLevel, QrCode};
+use serde::Deserialize;
+use tide::{Body, StatusCode};
+use validator::Validate;
+
+use crate::types::error::Error;
+
+// functions related to rendering qr codes
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum QrFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+impl QrFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            QrFormat::Png => "image/png",
+            QrFormat::Svg => "image/svg+xml",
+        }
+    }
+}
+
+// how much of the code can be damaged (or printed over) and still scan
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum QrErrorCorrection {
+    #[serde(alias = "l")]
+    L,
+    #[default]
+    #[serde(alias = "m")]
+    M,
+    #[serde(alias = "q")]
+    Q,
+    #[serde(alias = "h")]
+    H,
+}
+
+impl From<QrErrorCorrection> for EcLevel {
+    fn from(ecc: QrErrorCorrection) -> EcLevel {
+        match ecc {
+            QrErrorCorrection::L => EcLevel::L,
+            QrErrorCorrection::M => EcLevel::M,
+            QrErrorCorrection::Q => EcLevel::Q,
+            QrErrorCorrection::H => EcLevel::H,
+        }
+    }
+}
+
+fn default_qr_size() -> u32 {
+    256
+}
+
+// the quiet zone recommended by the qr code spec
+fn default_qr_margin() -> u32 {
+    4
+}
+
+#[derive(Deserialize, Validate, Clone, Copy, Debug)]
+pub struct QrQueryParams {
+    #[serde(default)]
+    pub format: QrFormat,
+    // smallest width and height of the image in pixels
+    #[serde(default = "default_qr_size")]
+    #[validate(range(min = 64, max = 2048, message = "size must be between 64 and 2048"))]
+    pub size: u32,
+    // quiet zone around the code in modules
+    #[serde(default = "default_qr_margin")]
+    #[validate(range(max = 16, message = "margin must be at most 16"))]
+    pub margin: u32,
+    #[serde(default)]
+    pub ecc: QrErrorCorrection,
+}
+
+// a grid of dark and light modules including the margin, scaled to at least the requested size
+struct QrGrid {
+    modules: Vec<bool>,
+    // width of the grid in modules
+    width: u32,
+    // size of a module in pixels
+    scale: u32,
+}
+
+impl QrGrid {
+    fn new(data: &str, params: &QrQueryParams) -> Result<QrGrid, Error> {
+        let code = QrCode::with_error_correction_level(data, params.ecc.into())
+            .map_err(|e| Error::QrCodeError(e.to_string()))?;
+        let code_width = code.width() as u32;
+        let width = code_width + 2 * params.margin;
+
+        let colors = code.to_colors();
+        let mut modules = vec![false; (width * width) as usize];
+        for y in 0..code_width {
+            for x in 0..code_width {
+                let is_dark = colors[(y * code_width + x) as usize] == Color::Dark;
+                modules[((y + params.margin) * width + x + params.margin) as usize] = is_dark;
+            }
+        }
+
+        Ok(QrGrid {
+            modules,
+            width,
+            scale: params.size.div_ceil(width),
+        })
+    }
+
+    fn is_dark(&self, x: u32, y: u32) -> bool {
+        self.modules[(y * self.width + x) as usize]
+    }
+
+    fn to_png(&self) -> Result<Vec<u8>, Error> {
+        let pixels = self.width * self.scale;
+        let image = GrayImage::from_fn(pixels, pixels, |x, y| {
+            if self.is_dark(x / self.scale, y / self.scale) {
+                Luma([0])
+            } else {
+                Luma([255])
+            }
+        });
+
+        let mut png = Cursor::new(Vec::new());
+        image
+            .write_to(&mut png, ImageFormat::Png)
+            .map_err(|e| Error::QrCodeError(e.to_string()))?;
+        Ok(png.into_inner())
+    }
+
+    // dark modules are drawn as a single path on a white background
+    fn to_svg(&self) -> String {
+        let pixels = self.width * self.scale;
+        let mut path = String::new();
+        for y in 0..self.width {
+            for x in 0..self.width {
+                if self.is_dark(x, y) {
+                    path.push_str(&format!("M{},{}h1v1h-1z", x, y));
+                }
+            }
+        }
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <svg xmlns=\"http://www.w3.org/2000/svg\" version=\"1.1\" \
+            width=\"{pixels}\" height=\"{pixels}\" viewBox=\"0 0 {width} {width}\" \
+            shape-rendering=\"crispEdges\">\
+            <rect width=\"{width}\" height=\"{width}\" fill=\"#fff\"/>\
+            <path d=\"{path}\" fill=\"#000\"/>\
+            </svg>\n",
+            pixels = pixels,
+            width = self.width,
+            path = path,
+        )
+    }
+}
+
+// renders the data as a qr code image in the requested format
+pub fn render_qr(data: &str, params: &QrQueryParams) -> Result<Vec<u8>, Error> {
+    let grid = QrGrid::new(data, params)?;
+    match params.format {
+        QrFormat::Png => grid.to_png(),
+        QrFormat::Svg => Ok(grid.to_svg().into_bytes()),
+    }
+}
+
+// responds with the rendered qr code image
+pub fn qr_response(data: &str, params: &QrQueryParams) -> tide::Result {
+    match render_qr(data, params) {
+        Ok(image) => Ok(tide::Response::builder(StatusCode::Ok)
+            .content_type(params.format.content_type())
+            .body(Body::from_bytes(image))
+            .build()),
+        Err(e) => e.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::{render_qr, QrErrorCorrection, QrFormat, QrQueryParams};
+
+    fn mock_params(format: QrFormat) -> QrQueryParams {
+        QrQueryParams {
+            format,
+            size: 100,
+            margin: 4,
+            ecc: QrErrorCorrection::M,
+        }
+    }
+
+    #[test]
+    fn it_renders_png_of_at_least_requested_size() {
+        let png = render_qr(
+            "https://salad.example.com/profiles/someone?src=qr",
+            &mock_params(QrFormat::Png),
+        );
+        assert!(png.is_ok(), "Failed to render png");
+        let image = image::load_from_memory(&png.unwrap()).unwrap();
+        assert_eq!(image.width(), image.height());
+        assert!(image.width() >= 100);
+    }
+
+    #[test]
+    fn it_renders_svg() {
+        let svg = render_qr("https://salad.example.com", &mock_params(QrFormat::Svg)).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.contains("<svg"));
+        assert!(svg.contains("<path d=\"M"));
+    }
+
+    #[test]
+    fn it_grows_with_margin() {
+        let mut params = mock_params(QrFormat::Svg);
+        params.margin = 0;
+        let without_margin = String::from_utf8(render_qr("salad", &params).unwrap()).unwrap();
+        params.margin = 4;
+        let with_margin = String::from_utf8(render_qr("salad", &params).unwrap()).unwrap();
+        // version 1 codes are 21 modules wide
+        assert!(without_margin.contains("viewBox=\"0 0 21 21\""));
+        assert!(with_margin.contains("viewBox=\"0 0 29 29\""));
+    }
+}
@@ -0,0 +1,184 @@
+use chrono::NaiveDateTime;
+use diesel::PgConnection;
+use rand::RngCore;
+use random_string::generate;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::{
+    connectors::db::two_factor::{use_recovery_code, use_two_factor_step},
+    models::two_factor::GetTwoFactor,
+    types::error::Error,
+};
+
+// functions for two factor authentication with totp codes and recovery codes
+
+pub const TOTP_ISSUER: &str = "Saladify";
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP: u64 = 30;
+// codes of the previous and next step are accepted for clocks that are slightly off
+const TOTP_SKEW: i64 = 1;
+const SECRET_LEN: usize = 20;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_CHARSET: &str = "abcdefghijkmnpqrstuvwxyz23456789";
+const RECOVERY_CODE_HALF_LEN: usize = 5;
+
+// a random 160 bit secret, base32 encoded for authenticator apps
+pub fn generate_totp_secret() -> String {
+    let mut secret = [0u8; SECRET_LEN];
+    rand::thread_rng().fill_bytes(&mut secret);
+    Secret::Raw(secret.to_vec()).to_encoded().to_string()
+}
+
+fn make_totp(secret: &str, username: &str) -> Result<TOTP, Error> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| Error::TotpError(e.to_string()))?;
+    TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        0,
+        TOTP_STEP,
+        secret,
+        Some(TOTP_ISSUER.to_string()),
+        username.to_string(),
+    )
+    .map_err(|e| Error::TotpError(e.to_string()))
+}
+
+// otpauth uri that authenticator apps read, usually from a qr code
+pub fn totp_uri(secret: &str, username: &str) -> Result<String, Error> {
+    Ok(make_totp(secret, username)?.get_url())
+}
+
+// the code of the secret at a time, for tests and for checking codes
+pub fn totp_code(secret: &str, time: NaiveDateTime) -> Result<String, Error> {
+    Ok(make_totp(secret, "")?.generate(time.and_utc().timestamp().max(0) as u64))
+}
+
+// time step of the code if it is valid around now
+pub fn find_totp_step(secret: &str, code: &str, now: NaiveDateTime) -> Result<Option<i64>, Error> {
+    let totp = make_totp(secret, "")?;
+    let now_step = now.and_utc().timestamp() / TOTP_STEP as i64;
+    for step in (now_step - TOTP_SKEW)..=(now_step + TOTP_SKEW) {
+        if step >= 0 && totp.generate(step as u64 * TOTP_STEP) == code {
+            return Ok(Some(step));
+        }
+    }
+    Ok(None)
+}
+
+// codes look like abcde-23456, without letters and digits that are easy to mix up
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            format!(
+                "{}-{}",
+                generate(RECOVERY_CODE_HALF_LEN, RECOVERY_CODE_CHARSET),
+                generate(RECOVERY_CODE_HALF_LEN, RECOVERY_CODE_CHARSET)
+            )
+        })
+        .collect()
+}
+
+// the hash ignores case, spaces and dashes so that the code can be typed loosely
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect();
+    sha256::digest(normalized)
+}
+
+fn is_totp_code(code: &str) -> bool {
+    code.len() == TOTP_DIGITS && code.chars().all(|c| c.is_ascii_digit())
+}
+
+// accepts a totp code or a recovery code of the user. each totp code works once
+// and recovery codes are deleted once used
+pub async fn check_two_factor_code(
+    conn: &mut PgConnection,
+    two_factor: &GetTwoFactor,
+    code: &str,
+    now: NaiveDateTime,
+) -> Result<(), Error> {
+    let code = code.trim();
+    if is_totp_code(code) {
+        if let Some(step) = find_totp_step(&two_factor.secret, code, now)? {
+            if use_two_factor_step(conn, two_factor.user_id, step).await? {
+                return Ok(());
+            }
+        }
+        return Err(Error::WrongTwoFactorCodeError());
+    }
+    if use_recovery_code(conn, two_factor.user_id, &hash_recovery_code(code)).await? {
+        return Ok(());
+    }
+    Err(Error::WrongTwoFactorCodeError())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use chrono::DateTime;
+
+    use super::*;
+
+    // secret and codes from the sha1 test vectors of rfc 6238
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    fn at(secs: i64) -> NaiveDateTime {
+        DateTime::from_timestamp(secs, 0).unwrap().naive_utc()
+    }
+
+    #[test]
+    fn it_generates_rfc_codes() {
+        assert_eq!(totp_code(RFC_SECRET, at(59)).unwrap(), "287082");
+        assert_eq!(totp_code(RFC_SECRET, at(1111111109)).unwrap(), "081804");
+        assert_eq!(totp_code(RFC_SECRET, at(2000000000)).unwrap(), "279037");
+    }
+
+    #[test]
+    fn it_accepts_codes_of_adjacent_steps_only() {
+        let code = totp_code(RFC_SECRET, at(1111111109)).unwrap();
+        let step = 1111111109 / 30;
+        assert_eq!(
+            find_totp_step(RFC_SECRET, &code, at(1111111109)).unwrap(),
+            Some(step)
+        );
+        assert_eq!(
+            find_totp_step(RFC_SECRET, &code, at(1111111109 + 30)).unwrap(),
+            Some(step)
+        );
+        assert_eq!(
+            find_totp_step(RFC_SECRET, &code, at(1111111109 + 90)).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn it_makes_otpauth_uris() {
+        let uri = totp_uri(RFC_SECRET, "someone").unwrap();
+        assert_eq!(
+            uri,
+            format!(
+                "otpauth://totp/Saladify:someone?secret={}&issuer=Saladify",
+                RFC_SECRET
+            )
+        );
+        assert_eq!(generate_totp_secret().len(), 32);
+    }
+
+    #[test]
+    fn it_hashes_recovery_codes_loosely() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert_eq!(
+            hash_recovery_code("abcde-23456"),
+            hash_recovery_code(" ABCDE 23456 ")
+        );
+        assert_ne!(
+            hash_recovery_code("abcde-23456"),
+            hash_recovery_code("abcde-23457")
+        );
+    }
+}
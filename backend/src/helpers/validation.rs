@@ -1,12 +1,17 @@
-use std::sync::Arc;
+use std::{borrow::Cow, env, sync::Arc};
 
-use serde::Deserialize;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Deserializer};
 use tide::Request;
-use validator::Validate;
+use url::Url;
+use validator::{Validate, ValidationError, ValidationErrors};
 
-use crate::types::{
-    error::{Error, RequestErrors},
-    state::TideState,
+use crate::{
+    models::links::{GetLink, UpdateLink},
+    types::{
+        error::{Error, RequestErrors},
+        state::TideState,
+    },
 };
 
 pub fn validate_query_params<Q: for<'a> Deserialize<'a> + Validate>(
@@ -21,3 +26,239 @@ pub fn validate_query_params<Q: for<'a> Deserialize<'a> + Validate>(
                 .map(|_| params)
         })
 }
+
+// links.href is a VARCHAR(255)
+pub const HREF_MAX_LENGTH: usize = 255;
+const ALLOWED_HREF_SCHEMES: [&str; 4] = ["http", "https", "mailto", "tel"];
+const DEFAULT_HREF_SCHEME: &str = "https://";
+// query parameters that only exist to track where a visitor came from
+const TRACKING_PARAM_PREFIXES: [&str; 1] = ["utm_"];
+const TRACKING_PARAMS: [&str; 8] = [
+    "fbclid", "gclid", "dclid", "msclkid", "mc_cid", "mc_eid", "igshid", "yclid",
+];
+
+fn href_error(code: &'static str, message: &'static str) -> ValidationError {
+    let mut error = ValidationError::new(code);
+    error.message = Some(Cow::Borrowed(message));
+    error
+}
+
+// whether tracking parameters should be removed from hrefs, configured in .env
+pub fn strips_href_tracking_params() -> bool {
+    env::var("STRIP_HREF_TRACKING_PARAMS")
+        .map(|value| value.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    TRACKING_PARAMS.contains(&key.as_str())
+        || TRACKING_PARAM_PREFIXES
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+}
+
+// "example.com" or "localhost:3000" have no scheme, "mailto:a@b.com" and "javascript:..." do
+fn has_scheme(href: &str) -> bool {
+    if href.contains("://") {
+        return true;
+    }
+    match href.split_once(':') {
+        Some((scheme, rest)) => {
+            let mut chars = scheme.chars();
+            chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-')
+                && !rest.starts_with(|c: char| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+// hrefs stored before they were validated can still be missing a scheme,
+// the old frontend rendered "example.com" as "//example.com"
+pub fn href_with_scheme(href: &str) -> Cow<'_, str> {
+    let href = href.trim();
+    if href.starts_with("//") {
+        Cow::Owned("https:".to_string() + href)
+    } else if has_scheme(href) {
+        Cow::Borrowed(href)
+    } else {
+        Cow::Owned(DEFAULT_HREF_SCHEME.to_string() + href)
+    }
+}
+
+// normalizes a user provided href so that it is safe to store and render
+// only http, https, mailto and tel are allowed, a missing scheme defaults to https
+// and internationalized hosts are converted to punycode
+pub fn normalize_href(href: &str, strip_tracking_params: bool) -> Result<String, ValidationError> {
+    let href = href.trim();
+    if href.is_empty() {
+        return Err(href_error("href_empty", "Href cannot be empty"));
+    }
+
+    let candidate = if has_scheme(href) {
+        href.to_string()
+    } else {
+        DEFAULT_HREF_SCHEME.to_string() + href
+    };
+
+    let mut url = Url::parse(&candidate)
+        .map_err(|_| href_error("href_invalid", "Href is not a valid url"))?;
+
+    if !ALLOWED_HREF_SCHEMES.contains(&url.scheme()) {
+        return Err(href_error(
+            "href_scheme",
+            "Href must be a http, https, mailto or tel link",
+        ));
+    }
+
+    if (url.scheme() == "http" || url.scheme() == "https") && url.host_str().is_none() {
+        return Err(href_error("href_invalid", "Href is not a valid url"));
+    }
+
+    if strip_tracking_params && url.query().is_some() {
+        let kept_pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(key, _)| !is_tracking_param(key))
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect();
+        if kept_pairs.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(kept_pairs);
+        }
+    }
+
+    let normalized = url.to_string();
+    if normalized.len() > HREF_MAX_LENGTH {
+        return Err(href_error(
+            "href_length",
+            "Href must be at most 255 characters",
+        ));
+    }
+    Ok(normalized)
+}
+
+// validates and normalizes the href field of a request payload
+pub fn validate_href(href: &str) -> Result<String, Error> {
+    normalize_href(href, strips_href_tracking_params()).map_err(|e| {
+        let mut errors = ValidationErrors::new();
+        errors.add("href", e);
+        Error::ValidationError(errors)
+    })
+}
+
+// links can be scheduled, but a window has to be open for some time
+pub fn validate_visibility_window(
+    visible_from: Option<NaiveDateTime>,
+    visible_until: Option<NaiveDateTime>,
+) -> Result<(), Error> {
+    match (visible_from, visible_until) {
+        (Some(from), Some(until)) if from >= until => {
+            let mut e = ValidationError::new("invalid_visibility_window");
+            e.message = Some(Cow::Borrowed("visible_from must be before visible_until"));
+            let mut errors = ValidationErrors::new();
+            errors.add("visible_until", e);
+            Err(Error::ValidationError(errors))
+        }
+        _ => Ok(()),
+    }
+}
+
+// checks the window a link ends up with after the update
+pub fn validate_link_update_window(link: &GetLink, update_link: &UpdateLink) -> Result<(), Error> {
+    validate_visibility_window(
+        update_link.visible_from.unwrap_or(link.visible_from),
+        update_link.visible_until.unwrap_or(link.visible_until),
+    )
+}
+
+// for PATCH payloads, a missing field is None and an explicit null is Some(None)
+pub fn deserialize_nullable<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use super::{href_with_scheme, normalize_href};
+
+    #[test]
+    pub fn it_keeps_valid_hrefs() {
+        assert_eq!(
+            normalize_href("https://example.com/shop?item=1", false).unwrap(),
+            "https://example.com/shop?item=1"
+        );
+        assert_eq!(
+            normalize_href("mailto:hello@example.com", false).unwrap(),
+            "mailto:hello@example.com"
+        );
+        assert_eq!(
+            normalize_href("tel:+6512345678", false).unwrap(),
+            "tel:+6512345678"
+        );
+    }
+
+    #[test]
+    pub fn it_adds_missing_scheme() {
+        assert_eq!(
+            normalize_href("example.com/about", false).unwrap(),
+            "https://example.com/about"
+        );
+        assert_eq!(
+            normalize_href("  localhost:3000 ", false).unwrap(),
+            "https://localhost:3000/"
+        );
+    }
+
+    #[test]
+    pub fn it_adds_scheme_to_legacy_hrefs() {
+        assert_eq!(href_with_scheme("example.com"), "https://example.com");
+        assert_eq!(href_with_scheme("//example.com/a"), "https://example.com/a");
+        assert_eq!(href_with_scheme("localhost:3000"), "https://localhost:3000");
+        assert_eq!(href_with_scheme("http://example.com"), "http://example.com");
+        assert_eq!(href_with_scheme("mailto:a@b.com"), "mailto:a@b.com");
+    }
+
+    #[test]
+    pub fn it_rejects_disallowed_schemes() {
+        assert!(normalize_href("javascript:alert(1)", false).is_err());
+        assert!(normalize_href("JavaScript:alert(1)", false).is_err());
+        assert!(normalize_href("data:text/html,hi", false).is_err());
+        assert!(normalize_href("ftp://example.com", false).is_err());
+        assert!(normalize_href("", false).is_err());
+        assert!(normalize_href("https://", false).is_err());
+    }
+
+    #[test]
+    pub fn it_punycodes_idn_hosts() {
+        assert_eq!(
+            normalize_href("https://bücher.de/", false).unwrap(),
+            "https://xn--bcher-kva.de/"
+        );
+    }
+
+    #[test]
+    pub fn it_strips_tracking_params_when_configured() {
+        let href = "https://example.com/?utm_source=ig&id=3&fbclid=abc";
+        assert_eq!(
+            normalize_href(href, true).unwrap(),
+            "https://example.com/?id=3"
+        );
+        assert_eq!(
+            normalize_href("https://example.com/?utm_medium=x", true).unwrap(),
+            "https://example.com/"
+        );
+        assert_eq!(normalize_href(href, false).unwrap(), href);
+    }
+
+    #[test]
+    pub fn it_rejects_hrefs_over_column_limit() {
+        let href = format!("https://example.com/{}", "a".repeat(250));
+        let error = normalize_href(&href, false).unwrap_err();
+        assert_eq!(error.code, "href_length");
+    }
+}
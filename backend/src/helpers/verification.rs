@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use diesel::PgConnection;
+use tide::log::{error, info};
+use url::Url;
+
+use crate::{
+    connectors::{
+        db::verification::{
+            delete_expired_email_verifications, get_user_email_verification,
+            replace_email_verification,
+        },
+        smtp::smtp_service::SMTPService,
+    },
+    helpers::random::make_random_string,
+    models::verification::InsertEmailVerification,
+    types::{error::Error, state::TidePool},
+};
+
+// functions for confirming the email address of a user
+
+// 1 day
+pub const EMAIL_VERIFICATION_DURATION: chrono::TimeDelta = chrono::Duration::hours(24);
+// a new link can be asked for once a minute
+pub const EMAIL_VERIFICATION_RESEND_COOLDOWN: chrono::TimeDelta = chrono::Duration::minutes(1);
+pub const EMAIL_VERIFICATION_CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const TOKEN_LEN: usize = 32;
+const EMAIL_VERIFICATION_SUBJECT: &str = "Saladify Email Verification";
+
+// link to the frontend page that confirms the address with the token
+pub fn email_verification_link(frontend_url: &Url, token: &str) -> Result<String, Error> {
+    let mut url = frontend_url
+        .join("auth/verify-email")
+        .map_err(|_| Error::InvalidResponseError())?;
+    url.query_pairs_mut().append_pair("token", token);
+    Ok(url.to_string())
+}
+
+// emails a link that sets the email of the user to the address once opened,
+// only the hash of its token is stored and any earlier link stops working,
+// unless the earlier link was sent less than cooldown ago
+pub async fn send_email_verification(
+    conn: &mut PgConnection,
+    email_service: &(dyn SMTPService + Sync),
+    frontend_url: &Url,
+    user_id: i32,
+    email: &str,
+    cooldown: chrono::TimeDelta,
+) -> Result<(), Error> {
+    let token = make_random_string(TOKEN_LEN);
+    let link = email_verification_link(frontend_url, &token)?;
+    let now = chrono::Local::now().naive_local();
+    let verification = InsertEmailVerification {
+        user_id,
+        email: email.to_string(),
+        token_hash: sha256::digest(&token),
+        created_at: now,
+    };
+    if !replace_email_verification(conn, &verification, now - cooldown).await? {
+        let sent_at = get_user_email_verification(conn, user_id).await?.created_at;
+        let wait = (sent_at + cooldown - now).num_seconds().max(1);
+        return Err(Error::TooManyRequestsError(wait as u64));
+    }
+
+    email_service.send_email(
+        email.to_string(),
+        EMAIL_VERIFICATION_SUBJECT.to_string(),
+        format!("Confirm your email address by opening this link: {}", link),
+    )
+}
+
+// periodically deletes verification links that have expired
+pub fn spawn_email_verification_cleanup(pool: TidePool, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let mut conn = match pool.get() {
+                Ok(conn) => conn,
+                Err(e) => {
+                    error!(
+                        "Failed to get a connection for email verification cleanup: {}",
+                        e
+                    );
+                    continue;
+                }
+            };
+            let created_before = chrono::Local::now().naive_local() - EMAIL_VERIFICATION_DURATION;
+            match delete_expired_email_verifications(&mut conn, created_before).await {
+                Ok(removed) => info!("Removed {} expired email verifications", removed),
+                Err(e) => error!("Failed to clean up email verifications: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,381 @@
+use std::{
+    env,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use chrono::NaiveDate;
+use ipnet::IpNet;
+use once_cell::sync::Lazy;
+use tide::Request;
+use url::Url;
+
+use crate::{
+    helpers::{auth::get_session_user_id, random::make_random_string},
+    types::state::TideState,
+};
+
+// functions related to identifying profile visitors
+
+// user_insight_sources.source is a VARCHAR(255)
+const SOURCE_MAX_LENGTH: usize = 255;
+// query parameter that attributes a profile view to a share channel
+pub const SHARE_SOURCE_PARAM: &str = "src";
+// query parameter with the referrer of the profile page, the Referer of api requests
+// is always the frontend itself
+pub const REFERRER_PARAM: &str = "ref";
+
+// secret mixed into the daily salt, random per process unless it is configured
+// so that every replica hashes the same visitor to the same key
+static VISITOR_HASH_SECRET: Lazy<String> = Lazy::new(|| {
+    env::var("VISITOR_HASH_SECRET")
+        .ok()
+        .filter(|secret| !secret.is_empty())
+        .unwrap_or_else(|| make_random_string(32))
+});
+
+// the salt changes every day so that hashes of the same visitor cannot be linked across days
+pub fn anonymous_visitor_key(secret: &str, day: NaiveDate, ip: &str, user_agent: &str) -> String {
+    let daily_salt = sha256::digest(format!("{}:{}", secret, day));
+    sha256::digest(format!("{}:{}:{}", daily_salt, ip, user_agent))
+}
+
+// proxies in front of the backend as comma separated ips or ranges like 172.16.0.0/12,
+// configured in .env. forwarded headers are only believed when the request comes from one of them.
+// it has to be set, behind a proxy every client would otherwise share the address of the proxy,
+// so `none` has to be written out when clients connect to the backend directly
+pub fn trusted_proxies_from_env() -> Vec<IpNet> {
+    let proxies = env::var("TRUSTED_PROXIES").unwrap_or_default();
+    let proxies = proxies.trim();
+    if proxies.is_empty() {
+        panic!("TRUSTED_PROXIES is not set, list the proxies in front of the backend or set it to none");
+    }
+    if proxies.eq_ignore_ascii_case("none") {
+        return Vec::new();
+    }
+    proxies
+        .split(',')
+        .map(|proxy| parse_trusted_proxy(proxy).expect("Invalid address in TRUSTED_PROXIES"))
+        .collect()
+}
+
+// a single address is a range of its own
+pub fn parse_trusted_proxy(proxy: &str) -> Option<IpNet> {
+    let proxy = proxy.trim();
+    proxy
+        .parse::<IpNet>()
+        .ok()
+        .or_else(|| proxy.parse::<IpAddr>().ok().map(IpNet::from))
+}
+
+fn is_trusted(ip: &IpAddr, trusted_proxies: &[IpNet]) -> bool {
+    trusted_proxies.iter().any(|proxy| proxy.contains(ip))
+}
+
+// the peer address, unless it is a trusted proxy. then X-Forwarded-For is read from the right,
+// as every proxy appends the address it got the request from, up to the first untrusted address
+pub fn client_ip(
+    peer: Option<IpAddr>,
+    forwarded_for: Option<&str>,
+    trusted_proxies: &[IpNet],
+) -> Option<IpAddr> {
+    let peer = peer?;
+    if !is_trusted(&peer, trusted_proxies) {
+        return Some(peer);
+    }
+    let Some(forwarded_for) = forwarded_for else {
+        return Some(peer);
+    };
+    let mut client = peer;
+    for hop in forwarded_for.rsplit(',') {
+        let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+            break;
+        };
+        client = ip;
+        if !is_trusted(&ip, trusted_proxies) {
+            break;
+        }
+    }
+    Some(client)
+}
+
+// clients can send any forwarded header, so the address of the connection is used
+// unless it is a trusted proxy
+pub fn get_client_ip(req: &Request<Arc<TideState>>) -> Option<IpAddr> {
+    let peer = req.peer_addr().and_then(|addr| {
+        addr.parse::<SocketAddr>()
+            .map(|addr| addr.ip())
+            .or_else(|_| addr.parse::<IpAddr>())
+            .ok()
+    });
+    let forwarded_for = req.header("X-Forwarded-For").map(|values| values.as_str());
+    client_ip(peer, forwarded_for, &req.state().trusted_proxies)
+}
+
+// logged in visitors are keyed by their user id, anonymous ones by a hash of ip and user agent
+pub fn get_visitor_key(req: &Request<Arc<TideState>>, day: NaiveDate) -> String {
+    if let Ok(user_id) = get_session_user_id(req) {
+        return format!("user:{}", user_id);
+    }
+
+    let ip = get_client_ip(req)
+        .map(|ip| ip.to_string())
+        .unwrap_or_default();
+    let user_agent = req
+        .header("User-Agent")
+        .map(|values| values.as_str())
+        .unwrap_or("");
+
+    anonymous_visitor_key(&VISITOR_HASH_SECRET, day, &ip, user_agent)
+}
+
+// hosts of our own frontend, navigating within the site is not a referral
+static INTERNAL_HOSTS: Lazy<Vec<String>> = Lazy::new(|| {
+    env::var("CORS_WHITELIST_URLS")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|url| Url::parse(url.trim()).ok())
+        .filter_map(|url| url.host_str().map(|host| host.to_owned()))
+        .collect()
+});
+
+// share channels are lowercase words like twitter or qr
+pub fn share_source(src: &str) -> Option<String> {
+    let src = src.trim().to_lowercase();
+    let is_valid = !src.is_empty()
+        && src.len() <= SOURCE_MAX_LENGTH
+        && src
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    is_valid.then_some(src)
+}
+
+// host of the referring page without the www prefix, unless it is one of our own hosts
+pub fn referrer_host(referer: &str, internal_hosts: &[String]) -> Option<String> {
+    let url = Url::parse(referer).ok()?;
+    let host = url.host_str()?.to_lowercase();
+    if internal_hosts.contains(&host) {
+        return None;
+    }
+    let host = host.strip_prefix("www.").unwrap_or(&host).to_owned();
+    (host.len() <= SOURCE_MAX_LENGTH).then_some(host)
+}
+
+// the share channel of the ?src= parameter, if any
+pub fn get_share_source(req: &Request<Arc<TideState>>) -> Option<String> {
+    req.url()
+        .query_pairs()
+        .find(|(key, _)| key == SHARE_SOURCE_PARAM)
+        .and_then(|(_, src)| share_source(&src))
+}
+
+// an explicit ?src= share parameter wins over the ?ref= referrer the frontend passes on
+pub fn get_referrer_source(req: &Request<Arc<TideState>>) -> Option<String> {
+    get_share_source(req).or_else(|| {
+        req.url()
+            .query_pairs()
+            .find(|(key, _)| key == REFERRER_PARAM)
+            .and_then(|(_, referrer)| referrer_host(&referrer, &INTERNAL_HOSTS))
+    })
+}
+
+// canonical url of a profile, views through it are attributed to the share channel
+pub fn canonical_share_url(frontend_url: &Url, username: &str, src: &str) -> Option<String> {
+    let mut share_url = frontend_url.clone();
+    share_url.set_query(None);
+    share_url.set_fragment(None);
+    share_url
+        .path_segments_mut()
+        .ok()?
+        .pop_if_empty()
+        .extend(["profiles", username]);
+    share_url
+        .query_pairs_mut()
+        .append_pair(SHARE_SOURCE_PARAM, src);
+    Some(share_url.to_string())
+}
+
+// url of the tracked redirect of a link, served by the backend behind the frontend /api proxy
+pub fn canonical_link_url(frontend_url: &Url, link_id: i32, src: &str) -> Option<String> {
+    let mut link_url = frontend_url.clone();
+    link_url.set_query(None);
+    link_url.set_fragment(None);
+    link_url
+        .path_segments_mut()
+        .ok()?
+        .pop_if_empty()
+        .extend(["api", "r", &link_id.to_string()]);
+    link_url
+        .query_pairs_mut()
+        .append_pair(SHARE_SOURCE_PARAM, src);
+    Some(link_url.to_string())
+}
+
+#[cfg(test)]
+mod unit_tests {
+    use std::net::IpAddr;
+
+    use chrono::NaiveDate;
+
+    use url::Url;
+
+    use super::{
+        anonymous_visitor_key, canonical_link_url, canonical_share_url, client_ip,
+        parse_trusted_proxy, referrer_host, share_source,
+    };
+
+    #[test]
+    fn it_only_trusts_forwarded_ips_from_trusted_proxies() {
+        let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+        let proxies = [
+            parse_trusted_proxy("10.0.0.1").unwrap(),
+            parse_trusted_proxy("10.0.0.2").unwrap(),
+        ];
+
+        // anyone else can make up the header
+        assert_eq!(
+            client_ip(Some(ip("203.0.113.9")), Some("198.51.100.1"), &proxies),
+            Some(ip("203.0.113.9"))
+        );
+        assert_eq!(
+            client_ip(Some(ip("203.0.113.9")), Some("198.51.100.1"), &[]),
+            Some(ip("203.0.113.9"))
+        );
+        // addresses the client put in front of the real one are skipped
+        assert_eq!(
+            client_ip(
+                Some(ip("10.0.0.1")),
+                Some("198.51.100.1, 203.0.113.9, 10.0.0.2"),
+                &proxies
+            ),
+            Some(ip("203.0.113.9"))
+        );
+        assert_eq!(
+            client_ip(Some(ip("10.0.0.1")), None, &proxies),
+            Some(ip("10.0.0.1"))
+        );
+        assert_eq!(client_ip(None, Some("198.51.100.1"), &proxies), None);
+    }
+
+    #[test]
+    fn it_trusts_proxies_in_a_range() {
+        let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+        let proxies = [
+            parse_trusted_proxy("172.16.0.0/12").unwrap(),
+            parse_trusted_proxy("::1").unwrap(),
+        ];
+
+        // the frontend container forwards for nginx on the docker gateway
+        assert_eq!(
+            client_ip(
+                Some(ip("172.18.0.3")),
+                Some("203.0.113.9, 172.18.0.1"),
+                &proxies
+            ),
+            Some(ip("203.0.113.9"))
+        );
+        assert_eq!(
+            client_ip(Some(ip("::1")), Some("203.0.113.9"), &proxies),
+            Some(ip("203.0.113.9"))
+        );
+        assert_eq!(
+            client_ip(Some(ip("172.32.0.1")), Some("203.0.113.9"), &proxies),
+            Some(ip("172.32.0.1"))
+        );
+        assert_eq!(parse_trusted_proxy("not an ip"), None);
+    }
+
+    #[test]
+    fn it_hashes_same_visitor_to_same_key_on_same_day() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let key = anonymous_visitor_key("secret", day, "127.0.0.1", "firefox");
+        assert_eq!(
+            key,
+            anonymous_visitor_key("secret", day, "127.0.0.1", "firefox")
+        );
+        assert_eq!(key.len(), 64);
+        assert!(!key.contains("127.0.0.1"));
+    }
+
+    #[test]
+    fn it_rotates_visitor_key_daily() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let next_day = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        assert_ne!(
+            anonymous_visitor_key("secret", day, "127.0.0.1", "firefox"),
+            anonymous_visitor_key("secret", next_day, "127.0.0.1", "firefox")
+        );
+    }
+
+    #[test]
+    fn it_distinguishes_visitors() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let key = anonymous_visitor_key("secret", day, "127.0.0.1", "firefox");
+        assert_ne!(
+            key,
+            anonymous_visitor_key("secret", day, "127.0.0.2", "firefox")
+        );
+        assert_ne!(
+            key,
+            anonymous_visitor_key("secret", day, "127.0.0.1", "chrome")
+        );
+        assert_ne!(
+            key,
+            anonymous_visitor_key("other secret", day, "127.0.0.1", "firefox")
+        );
+    }
+
+    #[test]
+    fn it_extracts_referrer_host() {
+        let internal_hosts = vec![String::from("salad.example.com")];
+        assert_eq!(
+            referrer_host("https://www.Twitter.com/some/post?id=1", &internal_hosts),
+            Some(String::from("twitter.com"))
+        );
+        assert_eq!(
+            referrer_host(
+                "https://salad.example.com/profiles/someone",
+                &internal_hosts
+            ),
+            None
+        );
+        assert_eq!(referrer_host("not a url", &internal_hosts), None);
+    }
+
+    #[test]
+    fn it_validates_share_source() {
+        assert_eq!(share_source(" Twitter "), Some(String::from("twitter")));
+        assert_eq!(share_source("qr"), Some(String::from("qr")));
+        assert_eq!(share_source(""), None);
+        assert_eq!(share_source("<script>"), None);
+    }
+
+    #[test]
+    fn it_builds_canonical_share_url() {
+        let frontend_url = Url::parse("https://salad.example.com").unwrap();
+        assert_eq!(
+            canonical_share_url(&frontend_url, "someone", "twitter"),
+            Some(String::from(
+                "https://salad.example.com/profiles/someone?src=twitter"
+            ))
+        );
+
+        // usernames are escaped and existing paths are kept
+        let frontend_url = Url::parse("https://example.com/salad/?a=b").unwrap();
+        assert_eq!(
+            canonical_share_url(&frontend_url, "some one", "qr"),
+            Some(String::from(
+                "https://example.com/salad/profiles/some%20one?src=qr"
+            ))
+        );
+    }
+
+    #[test]
+    fn it_builds_canonical_link_url() {
+        let frontend_url = Url::parse("https://salad.example.com/").unwrap();
+        assert_eq!(
+            canonical_link_url(&frontend_url, 42, "qr"),
+            Some(String::from("https://salad.example.com/api/r/42?src=qr"))
+        );
+    }
+}
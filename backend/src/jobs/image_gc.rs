@@ -0,0 +1,246 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::PgConnection;
+use tide::log::{error, info};
+
+use crate::{
+    connectors::{
+        buckets::store::{Bucket, ObjectStore},
+        db::{
+            image::{delete_detached_images, get_all_images},
+            upload::{delete_expired_uploads, get_pending_uploads},
+        },
+    },
+    helpers::images::ImageKind,
+    models::images::GetImage,
+    types::{error::Error, state::TidePool},
+};
+
+#[derive(Debug, Clone)]
+pub struct ImageGcConfig {
+    // how often the buckets are reconciled with the images table
+    pub interval: Duration,
+    // objects younger than this are kept, their row may not be committed yet
+    pub grace_period: chrono::Duration,
+    // only reports what would be deleted, unless turned off it is a dry run
+    pub dry_run: bool,
+}
+
+fn env_number(key: &str, default: i64) -> i64 {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .unwrap_or(default)
+}
+
+pub fn image_gc_grace_period() -> chrono::Duration {
+    chrono::Duration::hours(env_number("IMAGE_GC_GRACE_HOURS", 24).max(1))
+}
+
+impl ImageGcConfig {
+    // none unless the job is turned on with an interval
+    pub fn from_env() -> Option<ImageGcConfig> {
+        let interval_hours = env_number("IMAGE_GC_INTERVAL_HOURS", 0);
+        if interval_hours <= 0 {
+            return None;
+        }
+        Some(ImageGcConfig {
+            interval: Duration::from_secs(interval_hours as u64 * 60 * 60),
+            grace_period: image_gc_grace_period(),
+            dry_run: !env::var("IMAGE_GC_DRY_RUN").is_ok_and(|dry_run| dry_run == "false"),
+        })
+    }
+}
+
+// a file of an image row that is not in its bucket
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingImageObject {
+    pub image_id: i32,
+    pub bucket: Bucket,
+    pub key: String,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ImageGcReport {
+    pub objects_checked: usize,
+    // objects that no image or pending upload refers to, past the grace period
+    pub orphaned_objects: Vec<(Bucket, String)>,
+    pub objects_deleted: usize,
+    pub missing_objects: Vec<MissingImageObject>,
+    // rows of deleted users, which refer to nothing
+    pub detached_images: usize,
+    pub expired_uploads: usize,
+}
+
+fn image_bucket(image: &GetImage) -> Option<Bucket> {
+    match (image.user_id, image.link_id) {
+        (_, Some(_)) => Some(Bucket::LinkImages),
+        (Some(_), None) => Some(Bucket::ProfileImages),
+        (None, None) => None,
+    }
+}
+
+// only objects that look like the images the app stores can be orphans,
+// anything else in the buckets was not put there by the app
+fn is_image_key(key: &str) -> bool {
+    key.rsplit_once('.')
+        .is_some_and(|(_, ext)| ImageKind::from_ext(ext).is_some())
+}
+
+// lists the buckets and compares them with the images table. the table is read before the
+// buckets so that images created in between are only seen as objects, which the grace period keeps.
+// only objects are deleted, rows are left to delete_stale_image_rows
+pub async fn collect_orphaned_images(
+    conn: &mut PgConnection,
+    object_store: &dyn ObjectStore,
+    grace_period: chrono::Duration,
+    dry_run: bool,
+    now: NaiveDateTime,
+) -> Result<ImageGcReport, Error> {
+    let mut report = ImageGcReport::default();
+
+    // files of images and uploads that can still be completed, per bucket
+    let images = get_all_images(conn).await?;
+    let mut referenced = HashMap::<Bucket, HashSet<String>>::new();
+    for image in images.iter() {
+        match image_bucket(image) {
+            Some(bucket) => referenced
+                .entry(bucket)
+                .or_default()
+                .extend(image.filenames()),
+            None => report.detached_images += 1,
+        }
+    }
+    for upload in get_pending_uploads(conn, now).await? {
+        let bucket = match upload.link_id {
+            Some(_) => Bucket::LinkImages,
+            None => Bucket::ProfileImages,
+        };
+        referenced
+            .entry(bucket)
+            .or_default()
+            .insert(upload.object_key);
+    }
+
+    let mut stored = HashMap::<Bucket, HashSet<String>>::new();
+    for bucket in Bucket::ALL {
+        let referenced_keys = referenced.entry(bucket).or_default();
+        let stored_keys = stored.entry(bucket).or_default();
+        for object in object_store.list(bucket).await? {
+            report.objects_checked += 1;
+            stored_keys.insert(object.key.clone());
+            if referenced_keys.contains(&object.key)
+                || object.last_modified > now - grace_period
+                || !is_image_key(&object.key)
+            {
+                continue;
+            }
+            if !dry_run {
+                match object_store.delete(bucket, &object.key).await {
+                    Ok(()) => report.objects_deleted += 1,
+                    Err(e) => error!("Failed to delete orphaned object {}: {}", object.key, e),
+                }
+            }
+            report.orphaned_objects.push((bucket, object.key));
+        }
+    }
+
+    for image in images.iter() {
+        let Some(bucket) = image_bucket(image) else {
+            continue;
+        };
+        for key in image.filenames() {
+            if !stored[&bucket].contains(&key) {
+                report.missing_objects.push(MissingImageObject {
+                    image_id: image.id,
+                    bucket,
+                    key,
+                });
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+// deletes uploads that can no longer be completed and images of deleted users
+pub async fn delete_stale_image_rows(
+    conn: &mut PgConnection,
+    report: &mut ImageGcReport,
+    now: NaiveDateTime,
+) -> Result<(), Error> {
+    report.expired_uploads = delete_expired_uploads(conn, now).await?;
+    if report.detached_images > 0 {
+        delete_detached_images(conn).await?;
+    }
+    Ok(())
+}
+
+// a full run of the gc, with dry run nothing is deleted
+pub async fn run_image_gc(
+    conn: &mut PgConnection,
+    object_store: &dyn ObjectStore,
+    grace_period: chrono::Duration,
+    dry_run: bool,
+    now: NaiveDateTime,
+) -> Result<ImageGcReport, Error> {
+    let mut report =
+        collect_orphaned_images(conn, object_store, grace_period, dry_run, now).await?;
+    if !dry_run {
+        delete_stale_image_rows(conn, &mut report, now).await?;
+    }
+    Ok(report)
+}
+
+// periodically deletes orphaned images in the background
+pub fn spawn_image_gc(pool: TidePool, object_store: Arc<dyn ObjectStore>, config: ImageGcConfig) {
+    tokio::spawn(async move {
+        // the first run is one interval after startup, not right away
+        let mut ticker = tokio::time::interval_at(
+            tokio::time::Instant::now() + config.interval,
+            config.interval,
+        );
+        loop {
+            ticker.tick().await;
+            let mut conn = match pool.get() {
+                Ok(conn) => conn,
+                Err(e) => {
+                    error!("Failed to get a connection for image gc: {}", e);
+                    continue;
+                }
+            };
+            let result = run_image_gc(
+                &mut conn,
+                object_store.as_ref(),
+                config.grace_period,
+                config.dry_run,
+                Utc::now().naive_utc(),
+            )
+            .await;
+            match result {
+                Ok(report) => {
+                    for missing in report.missing_objects.iter() {
+                        error!(
+                            "Image {} is missing its object: {:?}",
+                            missing.image_id, missing
+                        );
+                    }
+                    info!(
+                        "Image gc: {} objects checked, {} orphaned, {} deleted, {} missing",
+                        report.objects_checked,
+                        report.orphaned_objects.len(),
+                        report.objects_deleted,
+                        report.missing_objects.len()
+                    );
+                }
+                Err(e) => error!("Failed to collect orphaned images: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,172 @@
+use std::{env, sync::Arc, time::Duration};
+
+use chrono::{NaiveDateTime, Utc};
+use futures::future::join_all;
+use tide::log::{error, info};
+use tokio::time::MissedTickBehavior;
+use url::Url;
+
+use crate::{
+    connectors::{
+        db::health::{get_links_due_for_check, mark_link_health_notified, record_link_check},
+        health::prober::{LinkProbe, LinkProber},
+    },
+    helpers::notifications::create_broken_link_notification,
+    models::{health::LinkHealthCheck, links::GetLink},
+    types::{error::Error, state::TidePool},
+};
+
+// links checked at the same time
+const CHECK_CONCURRENCY: usize = 10;
+
+#[derive(Debug, Clone)]
+pub struct LinkHealthConfig {
+    // how often the job looks for links to check
+    pub interval: Duration,
+    // how long a check result is kept before the link is checked again
+    pub recheck_after: chrono::Duration,
+    // links loaded and checked at once, a run goes on with the next batch until none are due
+    pub batch_size: i64,
+    // failures in a row before the owner is notified
+    pub failure_threshold: i32,
+}
+
+fn env_number(key: &str, default: i64) -> i64 {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .unwrap_or(default)
+}
+
+pub fn link_health_failure_threshold() -> i32 {
+    env_number("LINK_HEALTH_FAILURE_THRESHOLD", 3).max(1) as i32
+}
+
+impl LinkHealthConfig {
+    // none when the checks are turned off with an interval of 0
+    pub fn from_env() -> Option<LinkHealthConfig> {
+        let interval_minutes = env_number("LINK_HEALTH_CHECK_INTERVAL_MINUTES", 60);
+        if interval_minutes <= 0 {
+            return None;
+        }
+        Some(LinkHealthConfig {
+            interval: Duration::from_secs(interval_minutes as u64 * 60),
+            recheck_after: chrono::Duration::hours(env_number("LINK_HEALTH_RECHECK_HOURS", 24)),
+            batch_size: env_number("LINK_HEALTH_BATCH_SIZE", 100).max(1),
+            failure_threshold: link_health_failure_threshold(),
+        })
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct LinkHealthReport {
+    pub links_checked: usize,
+    pub links_failed: usize,
+    pub owners_notified: usize,
+}
+
+// none when the href is not a web url that parses, those links are skipped instead of counted as broken
+async fn probe_link(prober: &dyn LinkProber, link: &GetLink) -> Option<LinkProbe> {
+    let url = Url::parse(&link.href)
+        .ok()
+        .filter(|url| url.scheme() == "http" || url.scheme() == "https")?;
+    Some(prober.probe(&url).await)
+}
+
+// probes the links and records the results, owners are notified once per run of failures.
+// a connection is only taken once all the probes are done
+pub async fn check_links_health(
+    pool: &TidePool,
+    prober: &dyn LinkProber,
+    links: &[GetLink],
+    failure_threshold: i32,
+    now: NaiveDateTime,
+) -> Result<LinkHealthReport, Error> {
+    // a few links are probed at the same time, in the order of the links
+    let mut probes = Vec::with_capacity(links.len());
+    for chunk in links.chunks(CHECK_CONCURRENCY) {
+        probes.extend(join_all(chunk.iter().map(|link| probe_link(prober, link))).await);
+    }
+
+    let mut conn = pool.get().map_err(|_| Error::ConnectionPoolError())?;
+    let mut report = LinkHealthReport::default();
+    for (link, probe) in links.iter().zip(probes) {
+        let Some(probe) = probe else {
+            continue;
+        };
+        let check = LinkHealthCheck {
+            link_id: link.id,
+            status_code: probe.status_code.map(i32::from),
+            latency_ms: probe.latency.as_millis().min(i32::MAX as u128) as i32,
+            checked_at: now,
+            is_healthy: probe.is_healthy(),
+        };
+        // the link may have been deleted while it was being checked
+        let health = match record_link_check(&mut conn, &check).await {
+            Ok(health) => health,
+            Err(e) => {
+                error!("Failed to record health of link {}: {}", link.id, e);
+                continue;
+            }
+        };
+        report.links_checked += 1;
+        if check.is_healthy {
+            continue;
+        }
+        report.links_failed += 1;
+
+        if health.consecutive_failures >= failure_threshold && !health.is_notified {
+            create_broken_link_notification(&mut conn, link, health.consecutive_failures).await?;
+            mark_link_health_notified(&mut conn, link.id).await?;
+            report.owners_notified += 1;
+        }
+    }
+    Ok(report)
+}
+
+// checks the links that are due batch by batch, least recently checked first
+pub async fn run_link_health_checks(
+    pool: &TidePool,
+    prober: &dyn LinkProber,
+    config: &LinkHealthConfig,
+) -> Result<LinkHealthReport, Error> {
+    let now = Utc::now().naive_utc();
+    let mut report = LinkHealthReport::default();
+    loop {
+        let links = {
+            let mut conn = pool.get().map_err(|_| Error::ConnectionPoolError())?;
+            get_links_due_for_check(&mut conn, now - config.recheck_after, config.batch_size)
+                .await?
+        };
+        let batch = check_links_health(pool, prober, &links, config.failure_threshold, now).await?;
+        report.links_checked += batch.links_checked;
+        report.links_failed += batch.links_failed;
+        report.owners_notified += batch.owners_notified;
+        // checked links are not due anymore, hrefs that do not parse stay due and are skipped
+        // every time, so a batch without a single check means the rest are all like that
+        if (links.len() as i64) < config.batch_size || batch.links_checked == 0 {
+            break;
+        }
+    }
+    Ok(report)
+}
+
+// periodically checks links in the background
+pub fn spawn_link_health_checks(
+    pool: TidePool,
+    prober: Arc<dyn LinkProber>,
+    config: LinkHealthConfig,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(config.interval);
+        // a run can take longer than the interval when many links are due
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match run_link_health_checks(&pool, prober.as_ref(), &config).await {
+                Ok(report) => info!("Link health checks: {:?}", report),
+                Err(e) => error!("Failed to check link health: {}", e),
+            }
+        }
+    });
+}
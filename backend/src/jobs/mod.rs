@@ -0,0 +1,2 @@
+pub mod image_gc;
+pub mod link_health;
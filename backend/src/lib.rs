@@ -6,7 +6,10 @@ pub mod routes {
     pub mod notifications;
     pub mod profiles;
     pub mod search;
+    pub mod sections;
     pub mod settings;
+    pub mod storage;
+    pub mod uploads;
 }
 
 // These are custom structs for handling errors and responses
@@ -25,8 +28,22 @@ pub mod tests;
 pub mod connectors {
     pub mod buckets;
     pub mod db;
+    pub mod geoip;
+    pub mod health;
+    pub mod previews;
+    pub mod rate_limit;
+    pub mod sessions;
     pub mod smtp;
 }
 
 // this the database schema
 pub mod schema;
+
+// these are the subcommands that can be run instead of the server
+pub mod commands;
+
+// these are the background jobs that run next to the server
+pub mod jobs;
+
+// these are the middlewares wrapped around routes
+pub mod middleware;
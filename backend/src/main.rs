@@ -1,18 +1,39 @@
-use aws_config::meta::region::RegionProviderChain;
-use aws_config::BehaviorVersion;
-use aws_sdk_s3::{self as s3};
 use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::PgConnection;
 use dotenvy::dotenv;
 use http_types::headers::HeaderValue;
-use saladify::connectors::buckets::file::setup_buckets;
+use saladify::commands::run_command;
+use saladify::connectors::buckets::store::{
+    is_local_object_store, object_store_from_env, ObjectStore,
+};
 use saladify::connectors::db::connection::start_connection;
+use saladify::connectors::geoip::database::GeoIpDatabase;
+use saladify::connectors::health::http::HttpLinkProber;
+use saladify::connectors::previews::http::HttpPageFetcher;
+use saladify::connectors::rate_limit::store::{
+    rate_limit_store_from_env, spawn_rate_limit_cleanup, RateLimitPolicy,
+    RATE_LIMIT_CLEANUP_INTERVAL,
+};
+use saladify::connectors::sessions::postgres_store::{
+    PostgresSessionStore, SESSION_CLEANUP_INTERVAL,
+};
 use saladify::connectors::smtp::email::EmailService;
 use saladify::helpers::funcs;
+use saladify::helpers::verification::{
+    spawn_email_verification_cleanup, EMAIL_VERIFICATION_CLEANUP_INTERVAL,
+};
+use saladify::helpers::visitors::trusted_proxies_from_env;
+use saladify::jobs::image_gc::{spawn_image_gc, ImageGcConfig};
+use saladify::jobs::link_health::{spawn_link_health_checks, LinkHealthConfig};
+use saladify::middleware::rate_limit::{RateLimit, RateLimitKey};
 use saladify::routes::auth::login::{is_logged_in, login};
 use saladify::routes::auth::logout::logout;
 use saladify::routes::auth::register::register;
 use saladify::routes::auth::reset_password::{check_password_code, get_email, reset_password};
+use saladify::routes::auth::two_factor::login_two_factor;
+use saladify::routes::auth::verify_email::{
+    get_email_verification_status, resend_email_verification, verify_email,
+};
 use saladify::routes::follow::create::create_outbound_follow_request;
 use saladify::routes::follow::delete::{
     delete_follower, delete_following, delete_outbound_follow_request,
@@ -21,26 +42,39 @@ use saladify::routes::follow::get::{
     get_follow_status, get_followers, get_following, get_pending_follows,
 };
 use saladify::routes::follow::update::settle_inbound_follow_request;
+use saladify::routes::insights::export::export_insights;
 use saladify::routes::insights::get::get_insights;
+use saladify::routes::links::batch::batch_links;
 use saladify::routes::links::create::add_link;
 use saladify::routes::links::delete::{delete_link_picture, delete_links};
-use saladify::routes::links::get::get_links;
-use saladify::routes::links::update::{
-    reorder_links, update_link_bio, update_link_href, update_link_picture, update_link_title,
-};
+use saladify::routes::links::get::{get_link_qr, get_links, redirect_link};
+use saladify::routes::links::update::{reorder_links, update_link, update_link_picture};
 use saladify::routes::notifications::{
     delete::delete_all_notifications, get::get_notifications, update::read_notification,
 };
-use saladify::routes::profiles::get::{get_profile, get_username};
+use saladify::routes::profiles::get::{get_profile, get_profile_qr, get_username};
+use saladify::routes::profiles::share::share_profile;
 use saladify::routes::profiles::update::{update_display_profile, update_profile_image};
 use saladify::routes::search::get::search_users;
+use saladify::routes::sections::{
+    create::add_section,
+    delete::delete_section,
+    update::{reorder_sections, update_section},
+};
 use saladify::routes::settings::settings::{
     change_email, change_password, change_username, update_privacy,
 };
+use saladify::routes::settings::two_factor::{
+    confirm_two_factor, disable_two_factor, get_two_factor_status, regenerate_recovery_codes,
+    setup_two_factor,
+};
+use saladify::routes::storage::{get::get_object, update::put_object};
+use saladify::routes::uploads::{complete::complete_image_upload, create::create_image_upload};
 use saladify::types::state::TideState;
 use std::env;
 use std::sync::Arc;
 use tide::security::{CorsMiddleware, Origin};
+use tide::sessions::{MemoryStore, SessionMiddleware};
 
 // Migration to DB tables creation
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
@@ -56,22 +90,21 @@ async fn main() -> tide::Result<()> {
     let mut conn = start_connection().await;
     conn.run_pending_migrations(MIGRATIONS).unwrap();
 
-    // setup aws s3 client
-    let region_provider = RegionProviderChain::default_provider().or_else("ap-southeast-2");
-    let config = aws_config::defaults(BehaviorVersion::latest())
-        .region(region_provider)
-        .load()
-        .await;
-    let s3_client = s3::Client::new(&config);
-
-    // configure s3 buckets
-    setup_buckets(
-        &s3_client,
-        env::var("AWS_REGION")
-            .expect("No aws region found")
-            .as_str(),
-    )
-    .await;
+    // run a subcommand like `saladify check-links --repair` instead of the server
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    if !args.is_empty() {
+        return run_command(&mut conn, &args).await;
+    }
+
+    // proxies in front of the backend, refuses to start when TRUSTED_PROXIES is not set
+    let trusted_proxies = trusted_proxies_from_env();
+
+    // setup the object store and its buckets, s3 unless OBJECT_STORE is local
+    let object_store: Arc<dyn ObjectStore> = object_store_from_env().await.into();
+    object_store
+        .setup()
+        .await
+        .expect("Failed to set up object store buckets");
 
     // App State
     // Diesel
@@ -80,11 +113,41 @@ async fn main() -> tide::Result<()> {
     let pool = Pool::builder()
         .build(pool_manager)
         .expect("Failed to build connection pool");
+    let session_pool = pool.clone();
+
+    // failed logins and password reset codes, counted in memory unless RATE_LIMIT_STORE is postgres
+    let rate_limit_store = rate_limit_store_from_env(pool.clone());
+    let rate_limit_policy = RateLimitPolicy::from_env();
+    spawn_rate_limit_cleanup(
+        rate_limit_store.clone(),
+        rate_limit_policy.window,
+        RATE_LIMIT_CLEANUP_INTERVAL,
+    );
+    let rate_limit = |scope: &'static str, key: RateLimitKey| {
+        RateLimit::new(scope, rate_limit_store.clone(), rate_limit_policy.clone()).by(key)
+    };
+
+    // verification links that can no longer be opened
+    spawn_email_verification_cleanup(pool.clone(), EMAIL_VERIFICATION_CLEANUP_INTERVAL);
+
+    // check links for broken hrefs in the background
+    if let Some(config) = LinkHealthConfig::from_env() {
+        spawn_link_health_checks(pool.clone(), Arc::new(HttpLinkProber::new()), config);
+    }
+
+    // delete objects that no image refers to anymore
+    if let Some(config) = ImageGcConfig::from_env() {
+        spawn_image_gc(pool.clone(), object_store.clone(), config);
+    }
+
     let tide_state = Arc::new(TideState {
         tide_pool: pool,
-        s3_client,
+        object_store,
         tempdir: tempfile::tempdir()?,
         email_service: EmailService::new(),
+        geoip: GeoIpDatabase::from_env(),
+        page_fetcher: Box::new(HttpPageFetcher::new()),
+        trusted_proxies,
     });
 
     // create app
@@ -98,7 +161,11 @@ async fn main() -> tide::Result<()> {
         .collect::<Vec<String>>();
 
     let cors = CorsMiddleware::new()
-        .allow_methods("GET, POST, OPTIONS, PUT".parse::<HeaderValue>().unwrap())
+        .allow_methods(
+            "GET, POST, OPTIONS, PUT, PATCH"
+                .parse::<HeaderValue>()
+                .unwrap(),
+        )
         .allow_origin(Origin::from(whitelist_urls.clone()))
         .allow_credentials(false);
     app.with(cors);
@@ -109,14 +176,23 @@ async fn main() -> tide::Result<()> {
     );
 
     // session middleware
-    // words from the documentation
-    // DO NOT USE MEMORY STORE IN PRODUCTION USE A PROPER EXTERNAL DATASTORE
-    app.with(tide::sessions::SessionMiddleware::new(
-        tide::sessions::MemoryStore::new(),
-        env::var("TIDE_SECRET")
-            .expect("Tide Key not found")
-            .as_bytes(),
-    ));
+    let tide_secret = env::var("TIDE_SECRET").expect("Tide Key not found");
+    match env::var("SESSION_STORE").unwrap_or_default().as_str() {
+        // words from the documentation
+        // DO NOT USE MEMORY STORE IN PRODUCTION USE A PROPER EXTERNAL DATASTORE
+        "memory" => app.with(SessionMiddleware::new(
+            MemoryStore::new(),
+            tide_secret.as_bytes(),
+        )),
+        _ => {
+            let session_store = PostgresSessionStore::new(session_pool);
+            session_store.spawn_cleanup(SESSION_CLEANUP_INTERVAL);
+            app.with(SessionMiddleware::new(
+                session_store,
+                tide_secret.as_bytes(),
+            ))
+        }
+    };
 
     // set up logging middleware, default log level is 'info'
     femme::start();
@@ -125,13 +201,23 @@ async fn main() -> tide::Result<()> {
     // setup routes
 
     // auth
-    app.at("/login").post(login);
+    app.at("/login")
+        .with(rate_limit("login", RateLimitKey::BodyField("username")))
+        .post(login);
+    app.at("/login/two-factor")
+        .with(rate_limit(
+            "two-factor",
+            RateLimitKey::SessionField("two_factor_user_id"),
+        ))
+        .post(login_two_factor);
     app.at("/register").post(register);
     app.at("/logout").get(logout);
     app.at("/logged-in").get(is_logged_in);
 
     // profile
     app.at("/profiles/:username").get(get_profile);
+    app.at("/profiles/:username/share").post(share_profile);
+    app.at("/profiles/:username/qr").get(get_profile_qr);
     app.at("/profiles/display").put(update_display_profile);
     app.at("/profiles/image/:ext").put(update_profile_image);
 
@@ -139,13 +225,22 @@ async fn main() -> tide::Result<()> {
     app.at("/links/:username").get(get_links);
     app.at("/links").post(add_link);
     app.at("/links/reorder").post(reorder_links);
-    app.at("/links/title/:link_id").put(update_link_title);
-    app.at("/links/bio/:link_id").put(update_link_bio);
-    app.at("/links/href/:link_id").put(update_link_href);
+    app.at("/links/batch").post(batch_links);
     app.at("/links/:link_id/image/:ext")
         .put(update_link_picture);
     app.at("/links/:link_id/image").delete(delete_link_picture);
-    app.at("/links/:link_id").delete(delete_links);
+    app.at("/links/:link_id")
+        .patch(update_link)
+        .delete(delete_links);
+    app.at("/links/:link_id/qr").get(get_link_qr);
+    app.at("/r/:link_id").get(redirect_link);
+
+    // link sections
+    app.at("/sections").post(add_section);
+    app.at("/sections/reorder").post(reorder_sections);
+    app.at("/sections/:section_id")
+        .patch(update_section)
+        .delete(delete_section);
 
     // follow
     app.at("/follow").put(settle_inbound_follow_request);
@@ -163,14 +258,51 @@ async fn main() -> tide::Result<()> {
 
     // password reset
     app.at("/get-email").post(get_email);
-    app.at("/password-code").post(check_password_code);
-    app.at("/reset-password").post(reset_password);
+    // both check the reset code, so they share their failures
+    app.at("/password-code")
+        .with(rate_limit(
+            "password-reset",
+            RateLimitKey::BodyField("email"),
+        ))
+        .post(check_password_code);
+    app.at("/reset-password")
+        .with(rate_limit(
+            "password-reset",
+            RateLimitKey::BodyField("email"),
+        ))
+        .post(reset_password);
 
     // settings
     app.at("/change-username").post(change_username);
     app.at("/change-password").post(change_password);
     app.at("/change-email").post(change_email);
+    app.at("/verify-email")
+        .get(get_email_verification_status)
+        .post(verify_email);
+    app.at("/verify-email/resend")
+        .post(resend_email_verification);
     app.at("/update-privacy").post(update_privacy);
+    app.at("/two-factor").get(get_two_factor_status);
+    app.at("/two-factor/setup").post(setup_two_factor);
+    // codes are only six digits, so guessing them is limited like at login
+    app.at("/two-factor/enable")
+        .with(rate_limit(
+            "two-factor-settings",
+            RateLimitKey::SessionField("user_id"),
+        ))
+        .post(confirm_two_factor);
+    app.at("/two-factor/disable")
+        .with(rate_limit(
+            "two-factor-settings",
+            RateLimitKey::SessionField("user_id"),
+        ))
+        .post(disable_two_factor);
+    app.at("/two-factor/recovery-codes")
+        .with(rate_limit(
+            "two-factor-settings",
+            RateLimitKey::SessionField("user_id"),
+        ))
+        .post(regenerate_recovery_codes);
 
     // notifications
     app.at("/notifications").delete(delete_all_notifications);
@@ -180,11 +312,24 @@ async fn main() -> tide::Result<()> {
     // search
     app.at("/search").get(search_users);
 
+    // images uploaded straight to the object store
+    app.at("/uploads").post(create_image_upload);
+    app.at("/uploads/:token/complete")
+        .post(complete_image_upload);
+
+    // objects of the local object store, s3 objects are served by the cdn
+    if is_local_object_store() {
+        app.at("/storage/:bucket/:key")
+            .get(get_object)
+            .put(put_object);
+    }
+
     // misc
     app.at("get-username").get(get_username);
 
     // analytics
     app.at("/insights").get(get_insights);
+    app.at("/insights/export").get(export_insights);
 
     // attach to IP and port
     app.listen(funcs::get_url()).await?;
@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use chrono::{NaiveDateTime, Utc};
+use futures::AsyncReadExt;
+use tide::{log::error, Body, Next, Request};
+
+use crate::{
+    connectors::rate_limit::store::{
+        RateLimitEntry, RateLimitPolicy, RateLimitReservation, RateLimitStore,
+    },
+    helpers::visitors::get_client_ip,
+    types::{
+        error::{Error, RequestErrors},
+        state::TideState,
+    },
+};
+
+// bodies with keys are small json objects like a login, they are read before the route
+const MAX_KEYED_BODY_BYTES: usize = 16 * 1024;
+
+// what the failed attempts of a rate limited route are counted by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimitKey {
+    // the client ip
+    Ip,
+    // a string field of the json body, like the username of a login
+    BodyField(&'static str),
+    // a value in the session, like the user of a pending two factor login
+    SessionField(&'static str),
+}
+
+impl RateLimitKey {
+    // keys of accounts are forgotten once an attempt succeeds, an ip keeps its failures
+    // so that one good account does not reset the attempts on every other one
+    fn is_account(&self) -> bool {
+        !matches!(self, RateLimitKey::Ip)
+    }
+}
+
+// counts the failed attempts of a route, a 400 response, and answers with a 429 and
+// Retry-After while any of its keys has to wait. the wait grows with every failure
+// until the key is locked out
+pub struct RateLimit {
+    // routes with the same scope share their failures
+    scope: &'static str,
+    keys: Vec<RateLimitKey>,
+    policy: RateLimitPolicy,
+    store: Arc<dyn RateLimitStore>,
+}
+
+impl RateLimit {
+    // limits by the client ip, more keys can be added with by
+    pub fn new(
+        scope: &'static str,
+        store: Arc<dyn RateLimitStore>,
+        policy: RateLimitPolicy,
+    ) -> RateLimit {
+        RateLimit {
+            scope,
+            keys: vec![RateLimitKey::Ip],
+            policy,
+            store,
+        }
+    }
+
+    pub fn by(mut self, key: RateLimitKey) -> RateLimit {
+        self.keys.push(key);
+        self
+    }
+
+    // the values are hashed so that the store does not keep ips, usernames or emails
+    fn store_key(&self, key: RateLimitKey, value: &str) -> String {
+        let kind = match key {
+            RateLimitKey::Ip => "ip",
+            RateLimitKey::BodyField(field) | RateLimitKey::SessionField(field) => field,
+        };
+        sha256::digest(format!("{}:{}:{}", self.scope, kind, value))
+    }
+
+    // takes back attempts that did not fail
+    async fn undo(
+        &self,
+        reserved: &[(RateLimitKey, &str, Option<RateLimitEntry>)],
+        reserved_at: NaiveDateTime,
+    ) {
+        for (_, key, previous) in reserved.iter() {
+            if let Err(e) = self.store.undo(key, previous.as_ref(), reserved_at).await {
+                error!("Failed to undo a rate limited attempt: {}", e);
+            }
+        }
+    }
+
+    // store keys of the request, the body is put back for the route
+    async fn request_keys(
+        &self,
+        req: &mut Request<Arc<TideState>>,
+    ) -> Result<Vec<(RateLimitKey, String)>, Error> {
+        let mut body_json = None;
+        if self
+            .keys
+            .iter()
+            .any(|key| matches!(key, RateLimitKey::BodyField(_)))
+        {
+            let malformed = || Error::InvalidRequestError(RequestErrors::MalformedPayload);
+            if req.len().is_some_and(|len| len > MAX_KEYED_BODY_BYTES) {
+                return Err(malformed());
+            }
+            let mime = req.content_type();
+            let mut bytes = Vec::new();
+            req.take_body()
+                .take(MAX_KEYED_BODY_BYTES as u64 + 1)
+                .read_to_end(&mut bytes)
+                .await
+                .map_err(|_| malformed())?;
+            if bytes.len() > MAX_KEYED_BODY_BYTES {
+                return Err(malformed());
+            }
+            body_json = serde_json::from_slice::<serde_json::Value>(&bytes).ok();
+            let mut body = Body::from_bytes(bytes);
+            if let Some(mime) = mime {
+                body.set_mime(mime);
+            }
+            req.set_body(body);
+        }
+
+        let mut keys = Vec::new();
+        for key in self.keys.iter() {
+            let value = match key {
+                RateLimitKey::Ip => get_client_ip(req).map(|ip| ip.to_string()),
+                // accounts are matched like the routes match them, ignoring case
+                RateLimitKey::BodyField(field) => body_json
+                    .as_ref()
+                    .and_then(|body| body.get(*field)?.as_str().map(str::to_lowercase)),
+                RateLimitKey::SessionField(field) => req
+                    .session()
+                    .get::<serde_json::Value>(field)
+                    .map(|value| value.to_string()),
+            };
+            if let Some(value) = value {
+                keys.push((*key, self.store_key(*key, &value)));
+            }
+        }
+        Ok(keys)
+    }
+}
+
+#[tide::utils::async_trait]
+impl tide::Middleware<Arc<TideState>> for RateLimit {
+    async fn handle(
+        &self,
+        mut req: Request<Arc<TideState>>,
+        next: Next<'_, Arc<TideState>>,
+    ) -> tide::Result {
+        let keys = match self.request_keys(&mut req).await {
+            Ok(keys) => keys,
+            Err(e) => return e.into_response(),
+        };
+
+        // every key counts the attempt as a failure up front, so that attempts at the same
+        // time cannot all get in before the first one fails
+        let now = Utc::now().naive_utc();
+        let mut reserved = Vec::new();
+        let mut retry_after = None;
+        for (kind, key) in keys.iter() {
+            match self.store.reserve(key, &self.policy, now).await {
+                Ok(RateLimitReservation::Reserved(previous)) => {
+                    reserved.push((*kind, key.as_str(), previous))
+                }
+                // the longest wait of the keys
+                Ok(RateLimitReservation::Wait(wait)) => {
+                    retry_after = retry_after.max(Some(wait));
+                }
+                Err(e) => {
+                    self.undo(&reserved, now).await;
+                    return e.into_response();
+                }
+            }
+        }
+        if let Some(wait) = retry_after {
+            self.undo(&reserved, now).await;
+            // rounded up so that retrying right on time is allowed
+            let seconds = (wait.num_milliseconds() as u64).div_ceil(1000);
+            return Error::TooManyRequestsError(seconds.max(1)).into_response();
+        }
+
+        let res = next.run(req).await;
+
+        // a 400 is a failure, which is already counted
+        if res.status().is_success() {
+            let (accounts, ips): (Vec<_>, Vec<_>) = reserved
+                .into_iter()
+                .partition(|(kind, _, _)| kind.is_account());
+            for (_, key, _) in accounts.iter() {
+                if let Err(e) = self.store.reset(key).await {
+                    error!("Failed to reset a rate limited key: {}", e);
+                }
+            }
+            self.undo(&ips, now).await;
+        } else if res.status() != 400 {
+            self.undo(&reserved, now).await;
+        }
+        Ok(res)
+    }
+}
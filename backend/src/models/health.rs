@@ -0,0 +1,68 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::Serialize;
+
+#[derive(Queryable, Selectable, Serialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::link_health)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct GetLinkHealth {
+    pub link_id: i32,
+    pub status_code: Option<i32>,
+    pub latency_ms: i32,
+    pub checked_at: NaiveDateTime,
+    pub consecutive_failures: i32,
+    pub is_notified: bool,
+}
+
+impl GetLinkHealth {
+    pub fn badge(&self, failure_threshold: i32) -> LinkHealthBadge {
+        LinkHealthBadge {
+            status: LinkHealthStatus::from_failures(self.consecutive_failures, failure_threshold),
+            status_code: self.status_code,
+            latency_ms: self.latency_ms,
+            checked_at: self.checked_at,
+            consecutive_failures: self.consecutive_failures,
+        }
+    }
+}
+
+// result of checking a single link
+#[derive(Debug, Clone)]
+pub struct LinkHealthCheck {
+    pub link_id: i32,
+    pub status_code: Option<i32>,
+    pub latency_ms: i32,
+    pub checked_at: NaiveDateTime,
+    pub is_healthy: bool,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkHealthStatus {
+    Healthy,
+    // failed recently but not often enough to be reported
+    Failing,
+    Broken,
+}
+
+impl LinkHealthStatus {
+    pub fn from_failures(consecutive_failures: i32, failure_threshold: i32) -> LinkHealthStatus {
+        if consecutive_failures == 0 {
+            LinkHealthStatus::Healthy
+        } else if consecutive_failures < failure_threshold {
+            LinkHealthStatus::Failing
+        } else {
+            LinkHealthStatus::Broken
+        }
+    }
+}
+
+// health of a link as shown to its owner
+#[derive(Serialize, Debug, Clone)]
+pub struct LinkHealthBadge {
+    pub status: LinkHealthStatus,
+    pub status_code: Option<i32>,
+    pub latency_ms: i32,
+    pub checked_at: NaiveDateTime,
+    pub consecutive_failures: i32,
+}
@@ -1,4 +1,7 @@
+use std::collections::BTreeMap;
+
 use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
 
 #[derive(Queryable, Selectable)]
 #[diesel(table_name = crate::schema::images)]
@@ -9,6 +12,68 @@ pub struct GetImage {
     pub filename: String,
     pub user_id: Option<i32>,
     pub link_id: Option<i32>,
+    // list of ImageRendition
+    pub renditions: serde_json::Value,
+}
+
+impl GetImage {
+    // images uploaded before renditions have none
+    pub fn rendition_list(&self) -> Vec<ImageRendition> {
+        serde_json::from_value(self.renditions.clone()).unwrap_or_default()
+    }
+
+    // every file of the image in its bucket
+    pub fn filenames(&self) -> Vec<String> {
+        let renditions = self.rendition_list();
+        if renditions.is_empty() {
+            return vec![self.filename.clone()];
+        }
+        renditions
+            .into_iter()
+            .map(|rendition| rendition.filename)
+            .collect()
+    }
+
+    pub fn srcset(&self) -> ImageSrcset {
+        ImageSrcset::new(self.img_src.clone(), &self.rendition_list())
+    }
+}
+
+// a resized copy of an uploaded image
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImageRendition {
+    pub width: u32,
+    pub height: u32,
+    pub content_type: String,
+    pub filename: String,
+    pub img_src: String,
+}
+
+// src is for clients without srcset support,
+// sources maps a content type to a srcset like "a.webp 64w, b.webp 256w"
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ImageSrcset {
+    pub src: String,
+    pub sources: BTreeMap<String, String>,
+}
+
+impl ImageSrcset {
+    pub fn new(src: String, renditions: &[ImageRendition]) -> ImageSrcset {
+        let mut sources = BTreeMap::<String, Vec<String>>::new();
+        for rendition in renditions {
+            sources
+                .entry(rendition.content_type.clone())
+                .or_default()
+                .push(format!("{} {}w", rendition.img_src, rendition.width));
+        }
+        ImageSrcset {
+            src,
+            sources: sources
+                .into_iter()
+                .map(|(content_type, srcset)| (content_type, srcset.join(", ")))
+                .collect(),
+        }
+    }
 }
 
 #[derive(Queryable, Insertable)]
@@ -18,6 +83,7 @@ pub struct InsertProfileImage {
     pub img_src: String,
     pub filename: String,
     pub user_id: i32,
+    pub renditions: serde_json::Value,
 }
 
 #[derive(Queryable, Insertable)]
@@ -27,6 +93,7 @@ pub struct InsertLinkImage {
     pub img_src: String,
     pub filename: String,
     pub link_id: i32,
+    pub renditions: serde_json::Value,
 }
 
 #[derive(AsChangeset)]
@@ -36,4 +103,5 @@ pub struct UpdateImage {
     pub link_id: Option<i32>,
     pub filename: Option<String>,
     pub img_src: Option<String>,
+    pub renditions: Option<serde_json::Value>,
 }
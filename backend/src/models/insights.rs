@@ -1,13 +1,72 @@
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Queryable, Serialize, Selectable, Debug, Clone)]
+// size of the buckets insights are grouped into when they are read back
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InsightGranularity {
+    Hour,
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl InsightGranularity {
+    // postgres date_trunc field, also used to build the bucket interval
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InsightGranularity::Hour => "hour",
+            InsightGranularity::Day => "day",
+            InsightGranularity::Week => "week",
+            InsightGranularity::Month => "month",
+        }
+    }
+
+    // shortest possible length of one bucket
+    pub fn min_duration(&self) -> chrono::Duration {
+        match self {
+            InsightGranularity::Hour => chrono::Duration::hours(1),
+            InsightGranularity::Day => chrono::Duration::days(1),
+            InsightGranularity::Week => chrono::Duration::weeks(1),
+            InsightGranularity::Month => chrono::Duration::days(28),
+        }
+    }
+}
+
+// what a profile view source describes
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InsightSourceType {
+    Referrer,
+    Country,
+}
+
+impl InsightSourceType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InsightSourceType::Referrer => "referrer",
+            InsightSourceType::Country => "country",
+        }
+    }
+}
+
+// half open time window [from, to) that insights are read for
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct InsightWindow {
+    pub from: NaiveDateTime,
+    pub to: NaiveDateTime,
+    pub granularity: InsightGranularity,
+}
+
+#[derive(Queryable, QueryableByName, Serialize, Selectable, Debug, Clone)]
 #[diesel(table_name = crate::schema::user_insights)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct GetUserInsight {
     pub user_id: i32,
     pub view_count: i32,
+    pub unique_view_count: i32,
     pub follow_count: i32,
     pub unfollow_count: i32,
     pub follow_request_count: i32,
@@ -21,6 +80,7 @@ pub struct UpdateUserInsight {
     pub user_id: i32,
     pub created_at: NaiveDateTime,
     pub view_count: Option<i32>,
+    pub unique_view_count: Option<i32>,
     pub follow_count: Option<i32>,
     pub unfollow_count: Option<i32>,
     pub follow_request_count: Option<i32>,
@@ -41,6 +101,7 @@ impl Increment<UpdateUserInsight> for UpdateUserInsight {
             user_id,
             created_at,
             view_count: Some(1),
+            unique_view_count: None,
             follow_count: None,
             unfollow_count: None,
             follow_request_count: None,
@@ -53,6 +114,7 @@ impl Increment<UpdateUserInsight> for UpdateUserInsight {
             user_id,
             created_at,
             view_count: None,
+            unique_view_count: None,
             follow_count: Some(1),
             unfollow_count: None,
             follow_request_count: None,
@@ -65,6 +127,7 @@ impl Increment<UpdateUserInsight> for UpdateUserInsight {
             user_id,
             created_at,
             view_count: None,
+            unique_view_count: None,
             follow_count: None,
             unfollow_count: Some(1),
             follow_request_count: None,
@@ -80,6 +143,7 @@ impl Increment<UpdateUserInsight> for UpdateUserInsight {
             user_id,
             created_at,
             view_count: None,
+            unique_view_count: None,
             follow_count: None,
             unfollow_count: None,
             follow_request_count: Some(1),
@@ -92,6 +156,7 @@ impl Increment<UpdateUserInsight> for UpdateUserInsight {
             user_id,
             created_at,
             view_count: None,
+            unique_view_count: None,
             follow_count: None,
             unfollow_count: None,
             follow_request_count: None,
@@ -99,3 +164,121 @@ impl Increment<UpdateUserInsight> for UpdateUserInsight {
         }
     }
 }
+
+#[derive(Queryable, QueryableByName, Serialize, Selectable, Debug, Clone)]
+#[diesel(table_name = crate::schema::link_insights)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct GetLinkInsight {
+    pub link_id: i32,
+    pub click_count: i32,
+    pub created_bucket: NaiveDateTime,
+}
+
+#[derive(AsChangeset, Insertable)]
+#[diesel(table_name = crate::schema::link_insights)]
+pub struct UpdateLinkInsight {
+    pub link_id: i32,
+    pub created_at: NaiveDateTime,
+    pub click_count: Option<i32>,
+}
+
+impl UpdateLinkInsight {
+    pub fn increment_click_count(link_id: i32, created_at: NaiveDateTime) -> UpdateLinkInsight {
+        UpdateLinkInsight {
+            link_id,
+            created_at,
+            click_count: Some(1),
+        }
+    }
+}
+
+// a visitor seen by a profile in an hourly bucket
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::profile_visitors)]
+pub struct InsertProfileVisitor {
+    pub user_id: i32,
+    pub visitor_key: String,
+    pub created_at: NaiveDateTime,
+}
+
+// a share of a profile by a viewer in an hourly bucket
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::profile_shares)]
+pub struct InsertProfileShare {
+    pub user_id: i32,
+    pub visitor_key: String,
+    pub channel: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::user_insight_sources)]
+pub struct UpdateUserInsightSource {
+    pub user_id: i32,
+    pub source_type: String,
+    pub source: String,
+    pub view_count: i32,
+    pub created_at: NaiveDateTime,
+}
+
+impl UpdateUserInsightSource {
+    pub fn increment_view_count(
+        user_id: i32,
+        source_type: InsightSourceType,
+        source: String,
+        created_at: NaiveDateTime,
+    ) -> UpdateUserInsightSource {
+        UpdateUserInsightSource {
+            user_id,
+            source_type: source_type.as_str().to_owned(),
+            source,
+            view_count: 1,
+            created_at,
+        }
+    }
+}
+
+// total views of a single source over a window
+#[derive(QueryableByName, Serialize, Debug, Clone)]
+pub struct GetInsightSource {
+    #[diesel(sql_type = diesel::sql_types::Varchar)]
+    pub source: String,
+    #[diesel(sql_type = diesel::sql_types::Int8)]
+    pub view_count: i64,
+}
+
+// a click on a link through a share channel, like a qr code
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::link_insight_sources)]
+pub struct UpdateLinkInsightSource {
+    pub link_id: i32,
+    pub source: String,
+    pub click_count: i32,
+    pub created_at: NaiveDateTime,
+}
+
+impl UpdateLinkInsightSource {
+    pub fn increment_click_count(
+        link_id: i32,
+        source: String,
+        created_at: NaiveDateTime,
+    ) -> UpdateLinkInsightSource {
+        UpdateLinkInsightSource {
+            link_id,
+            source,
+            click_count: 1,
+            created_at,
+        }
+    }
+}
+
+// total clicks of a link through a single source over a window
+#[derive(QueryableByName, Serialize, Debug, Clone)]
+pub struct GetLinkInsightSource {
+    #[diesel(sql_type = diesel::sql_types::Int4)]
+    pub link_id: i32,
+    #[diesel(sql_type = diesel::sql_types::Varchar)]
+    pub source: String,
+    #[diesel(sql_type = diesel::sql_types::Int8)]
+    pub click_count: i64,
+}
@@ -1,5 +1,8 @@
+use chrono::NaiveDateTime;
 use diesel::prelude::*;
-use serde::Serialize;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
 
 #[derive(Queryable, Selectable, Insertable, Serialize, Debug, Clone)]
 #[diesel(table_name = crate::schema::links)]
@@ -7,29 +10,114 @@ use serde::Serialize;
 pub struct GetLink {
     pub id: i32,
     pub user_id: i32,
-    pub next_id: Option<i32>,
     pub description: Option<String>,
     pub title: Option<String>,
     pub href: String,
+    pub visible_from: Option<NaiveDateTime>,
+    pub visible_until: Option<NaiveDateTime>,
+    // links are ordered by ascending position
+    pub position: i64,
+    // links without a section are shown above every section
+    pub section_id: Option<i32>,
+}
+
+impl GetLink {
+    pub fn status_at(&self, now: NaiveDateTime) -> LinkStatus {
+        LinkStatus::at(self.visible_from, self.visible_until, now)
+    }
 }
 
+// new links are positioned after the last link of the user
 #[derive(Debug, Queryable, Selectable, Insertable)]
 #[diesel(table_name = crate::schema::links)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct InsertLink {
     pub user_id: i32,
-    pub next_id: Option<i32>,
     pub description: Option<String>,
     pub title: Option<String>,
     pub href: String,
+    pub visible_from: Option<NaiveDateTime>,
+    pub visible_until: Option<NaiveDateTime>,
+    pub section_id: Option<i32>,
 }
 
-#[derive(AsChangeset)]
+// Some(None) clears the visibility bounds and the section
+#[derive(AsChangeset, Debug)]
 #[diesel(table_name = crate::schema::links)]
 pub struct UpdateLink {
     pub user_id: Option<i32>,
-    pub next_id: Option<i32>,
     pub description: Option<String>,
     pub title: Option<String>,
     pub href: Option<String>,
+    pub visible_from: Option<Option<NaiveDateTime>>,
+    pub visible_until: Option<Option<NaiveDateTime>>,
+    pub section_id: Option<Option<i32>>,
+}
+
+// whether a link is inside of its visibility window
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkStatus {
+    // before visible_from
+    Scheduled,
+    Visible,
+    // at or after visible_until
+    Expired,
+}
+
+impl LinkStatus {
+    pub fn at(
+        visible_from: Option<NaiveDateTime>,
+        visible_until: Option<NaiveDateTime>,
+        now: NaiveDateTime,
+    ) -> LinkStatus {
+        if visible_from.is_some_and(|from| now < from) {
+            LinkStatus::Scheduled
+        } else if visible_until.is_some_and(|until| now >= until) {
+            LinkStatus::Expired
+        } else {
+            LinkStatus::Visible
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        *self == LinkStatus::Visible
+    }
+}
+
+// a link in a batch is either an existing link or one created earlier in the same batch
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum LinkRef {
+    Id(i32),
+    Temp(String),
+}
+
+// validated operation of a batch, applied in order
+#[derive(Debug)]
+pub enum LinkBatchOperation {
+    Create {
+        temp_id: Option<String>,
+        insert_link: InsertLink,
+    },
+    Update {
+        link_ref: LinkRef,
+        update_link: UpdateLink,
+    },
+    Delete {
+        link_ref: LinkRef,
+    },
+    // moves the link in front of new_position, or to the end
+    Move {
+        link_ref: LinkRef,
+        new_position: Option<LinkRef>,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct LinkBatchResult {
+    // temporary id of every created link to its id
+    pub temp_ids: HashMap<String, i32>,
+    // images of deleted links that still have to be removed from the bucket
+    pub deleted_image_filenames: Vec<String>,
 }
@@ -1,7 +1,14 @@
 pub mod follows;
+pub mod health;
 pub mod images;
 pub mod insights;
 pub mod links;
 pub mod notifications;
+pub mod rate_limits;
 pub mod reset;
+pub mod sections;
+pub mod sessions;
+pub mod two_factor;
+pub mod uploads;
 pub mod users;
+pub mod verification;
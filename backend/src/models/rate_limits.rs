@@ -0,0 +1,12 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+// failed attempts of one rate limited key
+#[derive(Queryable, QueryableByName, Selectable, Debug, Clone, PartialEq)]
+#[diesel(table_name = crate::schema::rate_limits)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct GetRateLimit {
+    pub key: String,
+    pub failures: i32,
+    pub last_failure_at: NaiveDateTime,
+}
@@ -0,0 +1,32 @@
+use diesel::prelude::*;
+use serde::Serialize;
+
+#[derive(Queryable, Selectable, Serialize, Debug, Clone)]
+#[diesel(table_name = crate::schema::link_sections)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct GetLinkSection {
+    pub id: i32,
+    pub user_id: i32,
+    pub title: String,
+    // whether visitors see the section closed at first
+    pub is_collapsed: bool,
+    // sections are ordered by ascending position
+    pub position: i64,
+}
+
+// new sections are positioned after the last section of the user
+#[derive(Insertable, Debug)]
+#[diesel(table_name = crate::schema::link_sections)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct InsertLinkSection {
+    pub user_id: i32,
+    pub title: String,
+    pub is_collapsed: bool,
+}
+
+#[derive(AsChangeset, Debug)]
+#[diesel(table_name = crate::schema::link_sections)]
+pub struct UpdateLinkSection {
+    pub title: Option<String>,
+    pub is_collapsed: Option<bool>,
+}
@@ -0,0 +1,24 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+// this is for persisted tide sessions
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = crate::schema::sessions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct GetSession {
+    pub id: String,
+    // serialized tide session
+    pub session: String,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable, AsChangeset, Debug)]
+#[diesel(table_name = crate::schema::sessions)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(treat_none_as_null = true)]
+pub struct InsertSession {
+    pub id: String,
+    pub session: String,
+    pub expires_at: Option<NaiveDateTime>,
+}
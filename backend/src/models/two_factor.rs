@@ -0,0 +1,35 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+// the totp secret of a user, only asked for at login once enabled
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = crate::schema::two_factor)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct GetTwoFactor {
+    pub id: i32,
+    pub user_id: i32,
+    pub secret: String,
+    pub is_enabled: bool,
+    pub last_used_step: Option<i64>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset, Debug)]
+#[diesel(table_name = crate::schema::two_factor)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(treat_none_as_null = true)]
+pub struct InsertTwoFactor {
+    pub user_id: i32,
+    pub secret: String,
+    pub is_enabled: bool,
+    pub last_used_step: Option<i64>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = crate::schema::recovery_codes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct InsertRecoveryCode {
+    pub user_id: i32,
+    pub code_hash: String,
+}
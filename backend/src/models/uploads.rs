@@ -0,0 +1,28 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+#[derive(Queryable, Selectable, Debug, Clone)]
+#[diesel(table_name = crate::schema::uploads)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct GetUpload {
+    pub id: i32,
+    pub token_hash: String,
+    pub user_id: i32,
+    // the link the image is for, none for the profile image
+    pub link_id: Option<i32>,
+    pub object_key: String,
+    pub content_type: String,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = crate::schema::uploads)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct InsertUpload {
+    pub token_hash: String,
+    pub user_id: i32,
+    pub link_id: Option<i32>,
+    pub object_key: String,
+    pub content_type: String,
+    pub expires_at: NaiveDateTime,
+}
@@ -13,6 +13,8 @@ pub struct GetUser {
     pub is_private: bool,
     pub salt: String,
     pub display_name: String,
+    // whether the address in email was confirmed
+    pub email_verified: bool,
 }
 
 #[derive(Queryable, Insertable)]
@@ -0,0 +1,25 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+// an address waiting to be confirmed through the link emailed to it
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = crate::schema::email_verification_request)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct GetEmailVerification {
+    pub id: i32,
+    pub user_id: i32,
+    // replaces the email of the user once confirmed
+    pub email: String,
+    pub token_hash: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset, Debug)]
+#[diesel(table_name = crate::schema::email_verification_request)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct InsertEmailVerification {
+    pub user_id: i32,
+    pub email: String,
+    pub token_hash: String,
+    pub created_at: NaiveDateTime,
+}
@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use bcrypt::verify;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use tide::Request;
 use validator::Validate;
@@ -17,7 +18,7 @@ use crate::{
     },
 };
 
-use super::init_session;
+use super::login_or_require_two_factor;
 
 #[derive(Serialize)]
 pub struct ResultBody {
@@ -90,10 +91,20 @@ pub async fn login(mut req: Request<Arc<TideState>>) -> tide::Result {
                 // login the user
                 let user_id = get_user_id_from_name(&mut conn, &username).await;
 
-                // insert user_id into the session
-                init_session(req.session_mut(), user_id, &username);
-
-                return Response::empty().into_response();
+                // insert user_id into the session, unless a two factor code is needed first
+                let now = Utc::now().naive_utc();
+                return match login_or_require_two_factor(
+                    &mut conn,
+                    req.session_mut(),
+                    user_id,
+                    username,
+                    now,
+                )
+                .await
+                {
+                    Ok(body) => Response::new(body).into_response(),
+                    Err(e) => e.into_response(),
+                };
             } else {
                 // password is incorrect
                 return Error::WrongPasswordError().into_response();
@@ -1,13 +1,34 @@
+use chrono::NaiveDateTime;
+use diesel::PgConnection;
+use serde::Serialize;
+
+use crate::connectors::db::two_factor::is_two_factor_enabled;
+use crate::types::error::Error;
+
 pub mod login;
 pub mod logout;
 pub mod register;
 pub mod reset_password;
+pub mod two_factor;
+pub mod verify_email;
 
 // password cost
 pub const PASSWORD_COST: u32 = 10;
 
+// time to give the two factor code after the password
+pub const TWO_FACTOR_LOGIN_DURATION: chrono::TimeDelta = chrono::Duration::minutes(5);
+
+// body of the routes that log in, the session is only logged in when no code is required
+#[derive(Serialize)]
+pub struct LoginBody {
+    pub two_factor_required: bool,
+}
+
 // init session when logged in
 pub fn init_session(session: &mut tide::sessions::Session, user_id: i32, username: &String) {
+    session.remove("two_factor_user_id");
+    session.remove("two_factor_username");
+    session.remove("two_factor_started_at");
     session
         .insert("user_id", user_id)
         .expect("Error serializing user_id");
@@ -15,3 +36,60 @@ pub fn init_session(session: &mut tide::sessions::Session, user_id: i32, usernam
         .insert("username", username)
         .expect("Error serializing username");
 }
+
+// remembers a login that is waiting for its two factor code, the session is not logged in until then
+pub fn init_two_factor_session(
+    session: &mut tide::sessions::Session,
+    user_id: i32,
+    username: &String,
+    now: NaiveDateTime,
+) {
+    session.remove("user_id");
+    session.remove("username");
+    session
+        .insert("two_factor_user_id", user_id)
+        .expect("Error serializing two_factor_user_id");
+    session
+        .insert("two_factor_username", username)
+        .expect("Error serializing two_factor_username");
+    session
+        .insert("two_factor_started_at", now.and_utc().timestamp())
+        .expect("Error serializing two_factor_started_at");
+}
+
+// user id and username of the login waiting for its two factor code
+pub fn get_two_factor_session(
+    session: &tide::sessions::Session,
+    now: NaiveDateTime,
+) -> Result<(i32, String), Error> {
+    let (Some(user_id), Some(username), Some(started_at)) = (
+        session.get::<i32>("two_factor_user_id"),
+        session.get::<String>("two_factor_username"),
+        session.get::<i64>("two_factor_started_at"),
+    ) else {
+        return Err(Error::TwoFactorLoginExpiredError());
+    };
+    if now.and_utc().timestamp() > started_at + TWO_FACTOR_LOGIN_DURATION.num_seconds() {
+        return Err(Error::TwoFactorLoginExpiredError());
+    }
+    Ok((user_id, username))
+}
+
+// logs the user in, or asks for a two factor code first if the user has it enabled
+pub async fn login_or_require_two_factor(
+    conn: &mut PgConnection,
+    session: &mut tide::sessions::Session,
+    user_id: i32,
+    username: &String,
+    now: NaiveDateTime,
+) -> Result<LoginBody, Error> {
+    let two_factor_required = is_two_factor_enabled(conn, user_id).await?;
+    if two_factor_required {
+        init_two_factor_session(session, user_id, username, now);
+    } else {
+        init_session(session, user_id, username);
+    }
+    Ok(LoginBody {
+        two_factor_required,
+    })
+}
@@ -5,6 +5,7 @@ use crate::{
         connection::DBConnection,
         user::{check_user_exists, create, get_user_id_from_name},
     },
+    helpers::{funcs::get_frontend_url, verification::send_email_verification},
     models::users::InsertUser,
     types::{
         error::{Error, RequestErrors},
@@ -115,6 +116,26 @@ pub async fn register(mut req: Request<Arc<TideState>>) -> tide::Result {
     let _user = create(&mut conn, &new_user).await;
     let user_id = get_user_id_from_name(&mut conn, &username).await;
 
+    // the address is used right away but password resets wait until it is confirmed,
+    // the link can be sent again from the settings if it does not arrive
+    match get_frontend_url() {
+        Some(frontend_url) => {
+            if let Err(e) = send_email_verification(
+                &mut conn,
+                &state.email_service,
+                &frontend_url,
+                user_id,
+                email,
+                chrono::TimeDelta::zero(),
+            )
+            .await
+            {
+                log::error!("Error sending email verification: {}", e);
+            }
+        }
+        None => log::error!("No valid FRONTEND_URL to build verification links with"),
+    }
+
     // log the user in
     // insert user_id, username into the session
     init_session(req.session_mut(), user_id, &username);
@@ -21,7 +21,7 @@ use std::sync::Arc;
 use tide::Request;
 use validator::Validate;
 
-use super::{init_session, PASSWORD_COST};
+use super::{login_or_require_two_factor, PASSWORD_COST};
 
 // less important less cost
 // consts
@@ -100,6 +100,11 @@ pub async fn get_email(mut req: Request<Arc<TideState>>) -> tide::Result {
         Err(e) => return e.into_response(),
     };
 
+    // codes are only sent to addresses the user has confirmed
+    if !user.email_verified {
+        return Error::EmailNotVerifiedError().into_response();
+    }
+
     // make random code
     let code = make_random_string(HASH_LEN);
     // hash random code
@@ -323,7 +328,17 @@ pub async fn reset_password(mut req: Request<Arc<TideState>>) -> tide::Result {
         Err(e) => return e.into_response(),
     };
 
-    init_session(req.session_mut(), user.id, &user.username);
-
-    return Response::empty().into_response();
+    // a reset password does not skip the two factor code
+    match login_or_require_two_factor(
+        &mut conn,
+        req.session_mut(),
+        user.id,
+        &user.username,
+        chrono::Utc::now().naive_utc(),
+    )
+    .await
+    {
+        Ok(body) => Response::new(body).into_response(),
+        Err(e) => e.into_response(),
+    }
 }
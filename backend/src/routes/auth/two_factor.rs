@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use serde::Deserialize;
+use tide::Request;
+
+use crate::{
+    connectors::db::two_factor::get_two_factor,
+    helpers::{state::get_connection, two_factor::check_two_factor_code},
+    types::{
+        error::{Error, RequestErrors},
+        response::Response,
+        state::TideState,
+    },
+};
+
+use super::{get_two_factor_session, init_session};
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorCodeParams {
+    // a totp code or a recovery code
+    pub code: String,
+}
+
+// post route for the second step of the login, after the password was correct
+pub async fn login_two_factor(mut req: Request<Arc<TideState>>) -> tide::Result {
+    let code_params: TwoFactorCodeParams = match req.body_json().await {
+        Ok(params) => params,
+        Err(_e) => {
+            return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response()
+        }
+    };
+
+    let now = Utc::now().naive_utc();
+    let (user_id, username) = match get_two_factor_session(req.session(), now) {
+        Ok(pending) => pending,
+        Err(e) => return e.into_response(),
+    };
+
+    let mut conn = get_connection(&mut req);
+
+    let two_factor = match get_two_factor(&mut conn, user_id).await {
+        Ok(two_factor) => two_factor,
+        // turned off since the password step
+        Err(diesel::result::Error::NotFound) => {
+            return Error::TwoFactorLoginExpiredError().into_response()
+        }
+        Err(e) => return Error::DieselError(e).into_response(),
+    };
+
+    match check_two_factor_code(&mut conn, &two_factor, &code_params.code, now).await {
+        Ok(()) => {}
+        Err(e) => return e.into_response(),
+    }
+
+    init_session(req.session_mut(), user_id, &username);
+    Response::empty().into_response()
+}
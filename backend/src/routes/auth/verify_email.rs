@@ -0,0 +1,152 @@
+use crate::connectors::db::user::{get_user_by_id, verify_user_email};
+use crate::connectors::db::verification::{
+    delete_email_verification, get_email_verification_by_token, get_user_email_verification,
+};
+use crate::helpers::auth::get_session_user_id;
+use crate::helpers::funcs::{get_frontend_url, is_expired};
+use crate::helpers::state::get_connection;
+use crate::helpers::verification::{
+    send_email_verification, EMAIL_VERIFICATION_DURATION, EMAIL_VERIFICATION_RESEND_COOLDOWN,
+};
+use crate::types::error::{Error, RequestErrors};
+use crate::types::response::Response;
+use crate::types::state::TideState;
+use diesel::result::DatabaseErrorKind;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tide::{log::error, Request};
+
+#[derive(Debug, Deserialize)]
+struct VerifyEmailParams {
+    token: String,
+}
+
+#[derive(Debug, Serialize)]
+struct EmailVerificationStatusBody {
+    email: String,
+    email_verified: bool,
+    // address of an email change that was not confirmed yet
+    pending_email: Option<String>,
+}
+
+// post route that confirms an address with the token of its verification link.
+// the user does not have to be logged in as the link may be opened on another device
+pub async fn verify_email(mut req: Request<Arc<TideState>>) -> tide::Result {
+    // get payload
+    let verify_params: VerifyEmailParams = match req.body_json().await {
+        Ok(params) => params,
+        Err(_e) => {
+            return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response()
+        }
+    };
+
+    let mut conn = get_connection(&mut req);
+
+    let verification =
+        match get_email_verification_by_token(&mut conn, &sha256::digest(&verify_params.token))
+            .await
+        {
+            Ok(verification) => verification,
+            Err(diesel::result::Error::NotFound) => {
+                return Error::WrongEmailVerificationTokenError().into_response()
+            }
+            Err(e) => return Error::DieselError(e).into_response(),
+        };
+
+    // check if expired
+    match is_expired(verification.created_at, EMAIL_VERIFICATION_DURATION) {
+        Ok(true) => return Error::EmailVerificationExpiredError().into_response(),
+        Ok(false) => {}
+        Err(e) => return e.into_response(),
+    }
+
+    // the address may have been taken by another user since the link was sent
+    match verify_user_email(&mut conn, verification.user_id, &verification.email).await {
+        Ok(_) => {}
+        Err(diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            return Error::DuplicateEmailError().into_response()
+        }
+        Err(e) => return Error::DieselError(e).into_response(),
+    }
+
+    match delete_email_verification(&mut conn, verification.user_id).await {
+        Ok(_) => Response::empty().into_response(),
+        Err(e) => Error::DieselError(e).into_response(),
+    }
+}
+
+// get route for the email of the user and whether it is verified
+pub async fn get_email_verification_status(mut req: Request<Arc<TideState>>) -> tide::Result {
+    // get user_id from session
+    let user_id = match get_session_user_id(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    let mut conn = get_connection(&mut req);
+
+    let user = match get_user_by_id(&mut conn, user_id).await {
+        Ok(user) => user,
+        Err(e) => return Error::DieselError(e).into_response(),
+    };
+    let pending_email = match get_user_email_verification(&mut conn, user_id).await {
+        Ok(verification) if verification.email != user.email => Some(verification.email),
+        Ok(_) | Err(diesel::result::Error::NotFound) => None,
+        Err(e) => return Error::DieselError(e).into_response(),
+    };
+
+    Response::new(EmailVerificationStatusBody {
+        email: user.email,
+        email_verified: user.email_verified,
+        pending_email,
+    })
+    .into_response()
+}
+
+// post route that sends the verification link again,
+// to the pending address of an email change or else to the unverified address of the user
+pub async fn resend_email_verification(mut req: Request<Arc<TideState>>) -> tide::Result {
+    // get user_id from session
+    let user_id = match get_session_user_id(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    let mut conn = get_connection(&mut req);
+
+    let user = match get_user_by_id(&mut conn, user_id).await {
+        Ok(user) => user,
+        Err(e) => return Error::DieselError(e).into_response(),
+    };
+    let email = match get_user_email_verification(&mut conn, user_id).await {
+        Ok(verification) => verification.email,
+        Err(diesel::result::Error::NotFound) => {
+            if user.email_verified {
+                return Error::EmailAlreadyVerifiedError().into_response();
+            }
+            user.email
+        }
+        Err(e) => return Error::DieselError(e).into_response(),
+    };
+
+    let frontend_url = match get_frontend_url() {
+        Some(url) => url,
+        None => {
+            error!("No valid FRONTEND_URL to build verification links with");
+            return Error::InvalidResponseError().into_response();
+        }
+    };
+    match send_email_verification(
+        &mut conn,
+        &req.state().email_service,
+        &frontend_url,
+        user_id,
+        &email,
+        EMAIL_VERIFICATION_RESEND_COOLDOWN,
+    )
+    .await
+    {
+        Ok(_) => Response::empty().into_response(),
+        Err(e) => e.into_response(),
+    }
+}
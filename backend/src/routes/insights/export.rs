@@ -0,0 +1,212 @@
+use std::{io, sync::Arc};
+
+use chrono::Utc;
+use futures::TryStreamExt;
+use http_types::mime;
+use serde::Deserialize;
+use tide::{log::error, Body, Request, StatusCode};
+use validator::Validate;
+
+use crate::{
+    connectors::db::insight::get_user_insights_page,
+    helpers::{
+        auth::{get_session_user_id, get_session_username},
+        validation::validate_query_params,
+    },
+    models::insights::GetUserInsight,
+    types::state::{TidePool, TideState},
+};
+
+// number of buckets read from the db at a time
+const EXPORT_PAGE_SIZE: i64 = 500;
+// number of pages that can be waiting to be sent to the client
+const EXPORT_BUFFERED_PAGES: usize = 2;
+
+const CSV_HEADER: &str = "user_id,view_count,unique_view_count,follow_count,unfollow_count,\
+    follow_request_count,share_count,created_bucket\n";
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InsightExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl InsightExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            InsightExportFormat::Csv => "csv",
+            InsightExportFormat::Json => "json",
+        }
+    }
+
+    fn mime(&self) -> mime::Mime {
+        match self {
+            InsightExportFormat::Csv => mime::Mime::from("text/csv; charset=utf-8"),
+            InsightExportFormat::Json => mime::JSON,
+        }
+    }
+
+    // written before the first row
+    pub fn header(&self) -> &'static str {
+        match self {
+            InsightExportFormat::Csv => CSV_HEADER,
+            InsightExportFormat::Json => "[",
+        }
+    }
+
+    // written after the last row
+    pub fn footer(&self) -> &'static str {
+        match self {
+            InsightExportFormat::Csv => "",
+            InsightExportFormat::Json => "]\n",
+        }
+    }
+
+    // serializes a single bucket, json rows after the first one are comma separated
+    pub fn row(&self, insight: &GetUserInsight, is_first: bool) -> io::Result<String> {
+        match self {
+            InsightExportFormat::Csv => Ok(format!(
+                "{},{},{},{},{},{},{},{}\n",
+                insight.user_id,
+                insight.view_count,
+                insight.unique_view_count,
+                insight.follow_count,
+                insight.unfollow_count,
+                insight.follow_request_count,
+                insight.share_count,
+                insight.created_bucket.format("%Y-%m-%dT%H:%M:%S"),
+            )),
+            InsightExportFormat::Json => {
+                let row = serde_json::to_string(insight)?;
+                Ok(if is_first { row } else { format!(",{}", row) })
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ExportInsightQueryParams {
+    #[serde(default)]
+    format: InsightExportFormat,
+}
+
+// reads the history page by page and sends every serialized page into the channel,
+// stops early if the client goes away
+async fn produce_export(
+    pool: TidePool,
+    user_id: i32,
+    format: InsightExportFormat,
+    sender: async_std::channel::Sender<io::Result<Vec<u8>>>,
+) {
+    let mut conn = match pool.get() {
+        Ok(conn) => conn,
+        Err(e) => {
+            error!("Failed to get connection for insight export: {}", e);
+            let _ = sender.send(Err(io::Error::other(e))).await;
+            return;
+        }
+    };
+
+    if sender.send(Ok(format.header().into())).await.is_err() {
+        return;
+    }
+
+    let mut after_bucket = None;
+    let mut is_first = true;
+    loop {
+        let page = match get_user_insights_page(&mut conn, user_id, after_bucket, EXPORT_PAGE_SIZE)
+            .await
+        {
+            Ok(page) => page,
+            Err(e) => {
+                error!("Failed to read insights for export: {}", e);
+                let _ = sender.send(Err(io::Error::other(e))).await;
+                return;
+            }
+        };
+        if page.is_empty() {
+            break;
+        }
+
+        let mut chunk = String::new();
+        for insight in page.iter() {
+            match format.row(insight, is_first) {
+                Ok(row) => chunk.push_str(&row),
+                Err(e) => {
+                    let _ = sender.send(Err(e)).await;
+                    return;
+                }
+            }
+            is_first = false;
+        }
+        if sender.send(Ok(chunk.into_bytes())).await.is_err() {
+            return;
+        }
+
+        if (page.len() as i64) < EXPORT_PAGE_SIZE {
+            break;
+        }
+        after_bucket = page.last().map(|insight| insight.created_bucket);
+    }
+
+    let _ = sender.send(Ok(format.footer().into())).await;
+}
+
+pub async fn export_insights(req: Request<Arc<TideState>>) -> tide::Result {
+    // extract user id
+    let user_id = match get_session_user_id(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+    let username = match get_session_username(&req) {
+        Ok(username) => username,
+        Err(e) => return e.into_response(),
+    };
+
+    // validate query params
+    let ExportInsightQueryParams { format } =
+        match validate_query_params::<ExportInsightQueryParams>(&req) {
+            Ok(queries) => queries,
+            Err(e) => return e.into_response(),
+        };
+
+    // rows are streamed through a bounded channel so that only a few pages are held in memory
+    let (sender, receiver) = async_std::channel::bounded(EXPORT_BUFFERED_PAGES);
+    tokio::spawn(produce_export(
+        req.state().tide_pool.clone(),
+        user_id,
+        format,
+        sender,
+    ));
+
+    let mut body = Body::from_reader(receiver.into_async_read(), None);
+    body.set_mime(format.mime());
+
+    // usernames are not restricted, keep the header value plain ascii
+    let safe_username = username
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    let filename = format!(
+        "{}-insights-{}.{}",
+        safe_username,
+        Utc::now().format("%Y-%m-%d"),
+        format.extension()
+    );
+
+    Ok(tide::Response::builder(StatusCode::Ok)
+        .header(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", filename),
+        )
+        .body(body)
+        .build())
+}
@@ -1,48 +1,143 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use tide::Request;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 use crate::{
-    connectors::db::insight::get_user_insights,
+    connectors::db::insight::{
+        count_profile_visitors_in_window, get_link_insights_in_window,
+        get_top_link_insight_sources, get_top_user_insight_sources, get_user_insights_in_window,
+    },
     helpers::{
         auth::get_session_user_id, state::get_connection, validation::validate_query_params,
     },
-    models::insights::GetUserInsight,
+    models::insights::{
+        GetInsightSource, GetLinkInsight, GetLinkInsightSource, GetUserInsight, InsightGranularity,
+        InsightSourceType, InsightWindow,
+    },
     types::{response::Response, state::TideState},
 };
 
+// window used when from is not given
+const DEFAULT_INSIGHT_WINDOW_DAYS: i64 = 30;
+// upper bound on the length of a single series
+const MAX_INSIGHT_BUCKETS: i32 = 1000;
+// number of referrers, countries and link sources returned when top is not given
+const DEFAULT_TOP_SOURCES: i64 = 5;
+
 #[derive(Deserialize, Validate)]
-pub struct GetInsightQueryParams {}
+#[validate(schema(function = "validate_insight_window"))]
+pub struct GetInsightQueryParams {
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    granularity: InsightGranularity,
+    // number of referrers, countries and link sources to return
+    #[validate(range(min = 1, max = 50, message = "top must be between 1 and 50"))]
+    top: Option<i64>,
+}
+
+impl GetInsightQueryParams {
+    // fills in the defaults, the window ends now and spans the last 30 days
+    pub fn window(&self, now: NaiveDateTime) -> InsightWindow {
+        let to = self.to.map(|to| to.naive_utc()).unwrap_or(now);
+        let from = self
+            .from
+            .map(|from| from.naive_utc())
+            .unwrap_or(to - chrono::Duration::days(DEFAULT_INSIGHT_WINDOW_DAYS));
+        InsightWindow {
+            from,
+            to,
+            granularity: self.granularity,
+        }
+    }
+}
+
+fn validate_insight_window(params: &GetInsightQueryParams) -> Result<(), ValidationError> {
+    let window = params.window(Utc::now().naive_utc());
+    if window.from >= window.to {
+        let mut e = ValidationError::new("invalid_insight_window");
+        e.message = Some("from must be before to".into());
+        return Err(e);
+    }
+    if (window.to - window.from).num_seconds()
+        > window.granularity.min_duration().num_seconds() * MAX_INSIGHT_BUCKETS as i64
+    {
+        let mut e = ValidationError::new("insight_window_too_large");
+        e.message = Some(
+            format!(
+                "The window spans more than {} buckets, use a coarser granularity",
+                MAX_INSIGHT_BUCKETS
+            )
+            .into(),
+        );
+        return Err(e);
+    }
+    Ok(())
+}
 
 #[derive(Serialize)]
 pub struct GetInsightResponsePayload {
+    window: InsightWindow,
     total_profile_views: i32,
+    total_unique_profile_views: i32,
+    total_follows: i32,
+    total_unfollows: i32,
+    total_follow_requests: i32,
+    total_shares: i32,
     interval_views: Vec<(NaiveDateTime, i32)>,
+    interval_unique_views: Vec<(NaiveDateTime, i32)>,
     interval_follows: Vec<(NaiveDateTime, i32)>,
     interval_unfollows: Vec<(NaiveDateTime, i32)>,
     interval_follow_requests: Vec<(NaiveDateTime, i32)>,
     interval_shares: Vec<(NaiveDateTime, i32)>,
+    total_link_clicks: i32,
+    interval_link_clicks: Vec<LinkClickSeries>,
+    top_referrers: Vec<GetInsightSource>,
+    top_countries: Vec<GetInsightSource>,
+    // link clicks through share channels, like qr codes
+    top_link_sources: Vec<GetLinkInsightSource>,
+}
+
+#[derive(Serialize)]
+pub struct LinkClickSeries {
+    link_id: i32,
+    interval_clicks: Vec<(NaiveDateTime, i32)>,
 }
 
 impl GetInsightResponsePayload {
-    fn from_get_user_insights(mut user_insights: Vec<GetUserInsight>) -> GetInsightResponsePayload {
+    fn from_insights(
+        window: InsightWindow,
+        total_unique_profile_views: i32,
+        mut user_insights: Vec<GetUserInsight>,
+        mut link_insights: Vec<GetLinkInsight>,
+        top_referrers: Vec<GetInsightSource>,
+        top_countries: Vec<GetInsightSource>,
+        top_link_sources: Vec<GetLinkInsightSource>,
+    ) -> GetInsightResponsePayload {
         // sort ascending
-        user_insights.sort_by(|a, b| a.created_bucket.cmp(&b.created_bucket));
+        user_insights.sort_by_key(|insight| insight.created_bucket);
+        link_insights.sort_by_key(|insight| insight.created_bucket);
 
-        let total_profile_views = user_insights
-            .iter()
-            .map(|insight| insight.view_count)
-            .reduce(|a, b| a + b)
-            .unwrap_or(0);
+        // totals over the whole window
+        let total_profile_views = user_insights.iter().map(|i| i.view_count).sum();
+        let total_follows = user_insights.iter().map(|i| i.follow_count).sum();
+        let total_unfollows = user_insights.iter().map(|i| i.unfollow_count).sum();
+        let total_follow_requests = user_insights.iter().map(|i| i.follow_request_count).sum();
+        let total_shares = user_insights.iter().map(|i| i.share_count).sum();
 
         let interval_views = user_insights
             .iter()
             .map(|insight| (insight.created_bucket, insight.view_count))
             .collect::<Vec<(NaiveDateTime, i32)>>();
 
+        let interval_unique_views = user_insights
+            .iter()
+            .map(|insight| (insight.created_bucket, insight.unique_view_count))
+            .collect::<Vec<(NaiveDateTime, i32)>>();
+
         let interval_follows = user_insights
             .iter()
             .map(|insight| (insight.created_bucket, insight.follow_count))
@@ -63,13 +158,46 @@ impl GetInsightResponsePayload {
             .map(|insight| (insight.created_bucket, insight.share_count))
             .collect::<Vec<(NaiveDateTime, i32)>>();
 
+        let total_link_clicks = link_insights
+            .iter()
+            .map(|insight| insight.click_count)
+            .sum();
+
+        // group the click buckets by link, ordered by link id
+        let mut link_clicks = BTreeMap::<i32, Vec<(NaiveDateTime, i32)>>::new();
+        for insight in link_insights.iter() {
+            link_clicks
+                .entry(insight.link_id)
+                .or_default()
+                .push((insight.created_bucket, insight.click_count));
+        }
+        let interval_link_clicks = link_clicks
+            .into_iter()
+            .map(|(link_id, interval_clicks)| LinkClickSeries {
+                link_id,
+                interval_clicks,
+            })
+            .collect::<Vec<LinkClickSeries>>();
+
         GetInsightResponsePayload {
+            window,
             total_profile_views,
+            total_unique_profile_views,
+            total_follows,
+            total_unfollows,
+            total_follow_requests,
+            total_shares,
             interval_views,
+            interval_unique_views,
             interval_follows,
             interval_unfollows,
             interval_shares,
             interval_follow_requests,
+            total_link_clicks,
+            interval_link_clicks,
+            top_referrers,
+            top_countries,
+            top_link_sources,
         }
     }
 }
@@ -82,17 +210,74 @@ pub async fn get_insights(mut req: Request<Arc<TideState>>) -> tide::Result {
     };
 
     // validate query params
-    let _ = match validate_query_params::<GetInsightQueryParams>(&req) {
-        Ok(queries) => queries,
+    let (window, top) = match validate_query_params::<GetInsightQueryParams>(&req) {
+        Ok(queries) => (
+            queries.window(Utc::now().naive_utc()),
+            queries.top.unwrap_or(DEFAULT_TOP_SOURCES),
+        ),
+        Err(e) => return e.into_response(),
+    };
+
+    let mut conn = get_connection(&mut req);
+
+    let user_insights = match get_user_insights_in_window(&mut conn, user_id, window).await {
+        Ok(user_insights) => user_insights,
+        Err(e) => return e.into_response(),
+    };
+
+    // visitors of several buckets are only counted once over the whole window
+    let total_unique_profile_views =
+        match count_profile_visitors_in_window(&mut conn, user_id, window).await {
+            Ok(total_unique_profile_views) => total_unique_profile_views,
+            Err(e) => return e.into_response(),
+        };
+
+    let link_insights = match get_link_insights_in_window(&mut conn, user_id, window).await {
+        Ok(link_insights) => link_insights,
         Err(e) => return e.into_response(),
     };
 
-    let mut conn = &mut get_connection(&mut req);
+    let top_referrers = match get_top_user_insight_sources(
+        &mut conn,
+        user_id,
+        InsightSourceType::Referrer,
+        window,
+        top,
+    )
+    .await
+    {
+        Ok(top_referrers) => top_referrers,
+        Err(e) => return e.into_response(),
+    };
 
-    let payload = match get_user_insights(&mut conn, user_id).await {
-        Ok(user_insights) => GetInsightResponsePayload::from_get_user_insights(user_insights),
+    let top_countries = match get_top_user_insight_sources(
+        &mut conn,
+        user_id,
+        InsightSourceType::Country,
+        window,
+        top,
+    )
+    .await
+    {
+        Ok(top_countries) => top_countries,
         Err(e) => return e.into_response(),
     };
 
+    let top_link_sources = match get_top_link_insight_sources(&mut conn, user_id, window, top).await
+    {
+        Ok(top_link_sources) => top_link_sources,
+        Err(e) => return e.into_response(),
+    };
+
+    let payload = GetInsightResponsePayload::from_insights(
+        window,
+        total_unique_profile_views,
+        user_insights,
+        link_insights,
+        top_referrers,
+        top_countries,
+        top_link_sources,
+    );
+
     Response::new(payload).into_response()
 }
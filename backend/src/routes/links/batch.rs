@@ -0,0 +1,158 @@
+use std::{collections::HashMap, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use tide::{log::error, Request};
+use validator::{Validate, ValidationError};
+
+use crate::{
+    connectors::{buckets::store::Bucket, db::link::apply_link_batch},
+    helpers::auth::get_session_user_id,
+    models::links::{LinkBatchOperation, LinkRef},
+    routes::links::{create::CreateLinkParams, update::UpdateLinkPayload},
+    types::{
+        error::{Error, RequestErrors},
+        response::Response,
+        state::TideState,
+    },
+};
+
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum LinkBatchOperationPayload {
+    Create {
+        // lets later operations of the batch refer to the new link
+        temp_id: Option<String>,
+        #[serde(flatten)]
+        link: CreateLinkParams,
+    },
+    Update {
+        link_id: LinkRef,
+        #[serde(flatten)]
+        changes: UpdateLinkPayload,
+    },
+    Delete {
+        link_id: LinkRef,
+    },
+    Move {
+        link_id: LinkRef,
+        new_position_id: Option<LinkRef>,
+    },
+}
+
+#[derive(Deserialize, Validate)]
+#[validate(schema(function = "validate_unique_temp_ids"))]
+struct LinkBatchPayload {
+    // applied in order
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "A batch must have between 1 and 100 operations"
+    ))]
+    operations: Vec<LinkBatchOperationPayload>,
+}
+
+fn validate_unique_temp_ids(payload: &LinkBatchPayload) -> Result<(), ValidationError> {
+    let mut temp_ids = Vec::<&String>::new();
+    for operation in payload.operations.iter() {
+        if let LinkBatchOperationPayload::Create {
+            temp_id: Some(temp_id),
+            ..
+        } = operation
+        {
+            if temp_ids.contains(&temp_id) {
+                let mut e = ValidationError::new("duplicate_temp_id");
+                e.message = Some(format!("temp_id {} is used more than once", temp_id).into());
+                return Err(e);
+            }
+            temp_ids.push(temp_id);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct LinkBatchResponseBody {
+    // temporary id of every created link to its id
+    temp_ids: HashMap<String, i32>,
+}
+
+// POST end point that applies create, update, delete and move operations of links in one transaction
+pub async fn batch_links(mut req: Request<Arc<TideState>>) -> tide::Result {
+    // extract user id from session
+    let user_id = match get_session_user_id(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    // get payload
+    let batch_payload: LinkBatchPayload = match req.body_json().await {
+        Ok(payload) => payload,
+        Err(e) => {
+            error!("Error occurred in parsing: {:?}", e);
+            return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response();
+        }
+    };
+
+    // validate payload
+    if let Err(e) = batch_payload.validate() {
+        return Error::ValidationError(e).into_response();
+    }
+
+    // validate every operation before anything is applied
+    let mut operations = Vec::<LinkBatchOperation>::with_capacity(batch_payload.operations.len());
+    for (index, operation) in batch_payload.operations.into_iter().enumerate() {
+        let operation = match operation {
+            LinkBatchOperationPayload::Create { temp_id, link } => link
+                .into_insert_link(user_id)
+                .map(|insert_link| LinkBatchOperation::Create {
+                    temp_id,
+                    insert_link,
+                }),
+            LinkBatchOperationPayload::Update { link_id, changes } => changes
+                .into_update_link()
+                .map(|update_link| LinkBatchOperation::Update {
+                    link_ref: link_id,
+                    update_link,
+                }),
+            LinkBatchOperationPayload::Delete { link_id } => {
+                Ok(LinkBatchOperation::Delete { link_ref: link_id })
+            }
+            LinkBatchOperationPayload::Move {
+                link_id,
+                new_position_id,
+            } => Ok(LinkBatchOperation::Move {
+                link_ref: link_id,
+                new_position: new_position_id,
+            }),
+        };
+        match operation {
+            Ok(operation) => operations.push(operation),
+            Err(e) => return Error::BatchOperationError(index, Box::new(e)).into_response(),
+        }
+    }
+
+    // get connection state
+    let state = req.state();
+    let mut conn = state.tide_pool.get().unwrap();
+
+    let result = match apply_link_batch(&mut conn, user_id, &operations).await {
+        Ok(result) => result,
+        Err(e) => return e.into_response(),
+    };
+
+    // images of deleted links are removed once the batch is committed
+    for filename in result.deleted_image_filenames {
+        if let Err(e) = state
+            .object_store
+            .delete(Bucket::LinkImages, &filename)
+            .await
+        {
+            error!("Error in deleting image from the object store: {:?}", e);
+        }
+    }
+
+    Response::new(LinkBatchResponseBody {
+        temp_ids: result.temp_ids,
+    })
+    .into_response()
+}
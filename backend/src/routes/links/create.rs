@@ -1,25 +1,75 @@
 use std::sync::Arc;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use tide::{log::error, Request};
+use tide::{
+    log::{error, info},
+    Request,
+};
+use url::Url;
 use validator::Validate;
 
 use crate::{
-    connectors::db::{self, connection::DBConnection},
-    helpers::auth::get_session_user_id,
-    models::links::InsertLink,
+    connectors::{
+        buckets::{
+            file::{delete_images, upload_renditions},
+            store::Bucket,
+        },
+        db::{
+            self, connection::DBConnection, image::create_link_image,
+            section::section_id_belongs_to_user,
+        },
+    },
+    helpers::{
+        auth::get_session_user_id,
+        images::{spawn_make_renditions, ImageRenditions},
+        previews::{fetch_link_preview, fetch_preview_image},
+        validation::{validate_href, validate_visibility_window},
+    },
+    models::{images::InsertLinkImage, links::InsertLink},
     types::{
-        error::{Error, RequestErrors},
+        error::{AssociationErrors, Error, RequestErrors, S3Errors},
         response::Response,
         state::TideState,
     },
 };
 
 #[derive(Deserialize, Serialize, Validate)]
-struct CreateLinkParams {
+pub struct CreateLinkParams {
+    #[validate(length(max = 100, message = "Title must be at most 100 characters"))]
     title: Option<String>,
+    #[validate(length(max = 300, message = "Description must be at most 300 characters"))]
     bio: Option<String>,
     href: String,
+    // the link is hidden from visitors outside of this window
+    visible_from: Option<DateTime<Utc>>,
+    visible_until: Option<DateTime<Utc>>,
+    // the link is put at the end of the section
+    section_id: Option<i32>,
+}
+
+impl CreateLinkParams {
+    // validates and normalizes the payload into a new link of the user
+    pub fn into_insert_link(self, user_id: i32) -> Result<InsertLink, Error> {
+        self.validate().map_err(Error::ValidationError)?;
+
+        // validate and normalize href
+        let href = validate_href(&self.href)?;
+
+        let visible_from = self.visible_from.map(|from| from.naive_utc());
+        let visible_until = self.visible_until.map(|until| until.naive_utc());
+        validate_visibility_window(visible_from, visible_until)?;
+
+        Ok(InsertLink {
+            user_id,
+            description: self.bio,
+            title: self.title,
+            href,
+            visible_from,
+            visible_until,
+            section_id: self.section_id,
+        })
+    }
 }
 
 // POST end point for adding a link
@@ -42,29 +92,118 @@ pub async fn add_link(mut req: Request<Arc<TideState>>) -> tide::Result {
     }
 
     // validate payload
-    match link_params.validate() {
-        Err(e) => return Error::ValidationError(e).into_response(),
-        _ => (),
-    }
+    let mut insert_link = match link_params.into_insert_link(user_id) {
+        Ok(insert_link) => insert_link,
+        Err(e) => return e.into_response(),
+    };
 
     let state = req.state();
-    let mut conn: DBConnection = state.tide_pool.get().unwrap();
 
-    // add to database
-    let insert_link = InsertLink {
-        user_id,
-        next_id: None,
-        description: link_params.bio,
-        title: link_params.title,
-        href: link_params.href,
+    // assert the section belongs to the user
+    if let Some(section_id) = insert_link.section_id {
+        let mut conn: DBConnection = state.tide_pool.get().unwrap();
+        match section_id_belongs_to_user(&mut conn, section_id, user_id).await {
+            Ok(true) => (),
+            Ok(false) => {
+                return Error::AssociationError(AssociationErrors::SectionDoesNotBelongToUser)
+                    .into_response()
+            }
+            Err(e) => return Error::DieselError(e).into_response(),
+        }
+    }
+
+    // new links have no image, so the preview of the page is always fetched.
+    // fail silently, the link is still created without a preview
+    let preview = match fetch_link_preview(state.page_fetcher.as_ref(), &insert_link.href).await {
+        Ok(preview) => Some(preview),
+        Err(e) => {
+            info!("No preview for {}: {}", insert_link.href, e);
+            None
+        }
     };
+    if let Some(preview) = &preview {
+        fill_empty_field(&mut insert_link.title, &preview.title);
+        fill_empty_field(&mut insert_link.description, &preview.description);
+    }
+    let preview_image = match preview.and_then(|preview| preview.image_url) {
+        Some(image_url) => match upload_preview_image(state, &image_url).await {
+            Ok(renditions) => Some(renditions),
+            Err(e) => {
+                error!("Failed to add preview image of {}: {}", insert_link.href, e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    // the connection is only taken once the slow fetches are done
+    let mut conn: DBConnection = state.tide_pool.get().unwrap();
 
-    match db::link::create(&mut conn, &insert_link).await {
-        Ok(_) => Response::empty().into_response(),
+    // add to database
+    let link = match db::link::create(&mut conn, &insert_link).await {
+        Ok(link) => link,
         Err(e) => {
             // failed to create link
             error!("Error creating link. {:?}, Error: {}", insert_link, e);
+            if let Some(renditions) = &preview_image {
+                discard_preview_image(state, renditions).await;
+            }
             return Error::DieselError(e).into_response();
         }
+    };
+
+    if let Some(renditions) = preview_image {
+        // create src href in db
+        let payload = InsertLinkImage {
+            img_src: renditions.default.img_src.clone(),
+            filename: renditions.default.filename.clone(),
+            link_id: link.id,
+            renditions: renditions.to_json(),
+        };
+        if let Err(e) = create_link_image(&mut conn, &payload).await {
+            error!("Failed to add preview image of link {}: {}", link.id, e);
+            discard_preview_image(state, &renditions).await;
+        }
+    }
+
+    Response::empty().into_response()
+}
+
+// fields the user left blank are taken from the preview
+fn fill_empty_field(field: &mut Option<String>, preview_field: &Option<String>) {
+    if field.as_deref().is_none_or(|value| value.trim().is_empty()) {
+        if let Some(preview_field) = preview_field {
+            *field = Some(preview_field.clone());
+        }
+    }
+}
+
+// copies the preview image into the link image bucket, the images row is created with the link
+async fn upload_preview_image(
+    state: &TideState,
+    image_url: &Url,
+) -> Result<ImageRenditions, Error> {
+    let image = fetch_preview_image(state.page_fetcher.as_ref(), image_url).await?;
+    let object_store = state.object_store.as_ref();
+    let renditions =
+        spawn_make_renditions(image, object_store.base_url(Bucket::LinkImages)).await?;
+
+    if let Err(msg) = upload_renditions(object_store, Bucket::LinkImages, &renditions).await {
+        error!("upload to the object store failed with error: {}", msg);
+        return Err(Error::S3Error(S3Errors::FailedToUploadImage));
+    }
+    Ok(renditions)
+}
+
+// removes an uploaded preview image that no link ended up referring to
+async fn discard_preview_image(state: &TideState, renditions: &ImageRenditions) {
+    let filenames = renditions
+        .renditions()
+        .into_iter()
+        .map(|rendition| rendition.filename)
+        .collect();
+    if let Err(e) = delete_images(state.object_store.as_ref(), Bucket::LinkImages, filenames).await
+    {
+        error!("Error deleting preview image: {}", e);
     }
 }
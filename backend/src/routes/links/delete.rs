@@ -4,7 +4,7 @@ use tide::{log::error, Request};
 
 use crate::{
     connectors::{
-        buckets::file::delete_s3_link_image,
+        buckets::{file::delete_images, store::Bucket},
         db::{
             image::{delete_link_image, get_link_image},
             link::{delete_link_by_id, link_id_belongs_to_user},
@@ -34,7 +34,7 @@ pub async fn delete_link_picture(req: Request<Arc<TideState>>) -> tide::Result {
     // get connection state
     let state = req.state();
     let mut conn = state.tide_pool.get().unwrap();
-    let s3_client = &state.s3_client;
+    let object_store = state.object_store.as_ref();
 
     // assert link_id belongs to user_id
     match link_id_belongs_to_user(&mut conn, link_id, user_id).await {
@@ -59,11 +59,11 @@ pub async fn delete_link_picture(req: Request<Arc<TideState>>) -> tide::Result {
         Err(e) => return Error::DieselError(e).into_response(),
     };
 
-    // delete image from s3
-    match delete_s3_link_image(s3_client, img_obj.filename).await {
+    // delete image from the object store
+    match delete_images(object_store, Bucket::LinkImages, img_obj.filenames()).await {
         Ok(_) => Response::empty().into_response(),
         Err(e) => {
-            error!("Unable to delete link from s3: {}", e);
+            error!("Unable to delete link from the object store: {}", e);
             return Error::S3Error(S3Errors::FailedToDeleteImage).into_response();
         }
     }
@@ -103,10 +103,18 @@ pub async fn delete_links(req: Request<Arc<TideState>>) -> tide::Result {
     // delete image for link
     match get_link_image(&mut conn, link_id).await {
         Ok(link) => {
-            let delete_result = delete_s3_link_image(&state.s3_client, link.filename).await;
+            let delete_result = delete_images(
+                state.object_store.as_ref(),
+                Bucket::LinkImages,
+                link.filenames(),
+            )
+            .await;
             let delete_db_result = delete_link_image(&mut conn, link_id).await;
             if delete_result.is_err() {
-                error!("Error in deleting image from s3: {:?}", delete_result.err());
+                error!(
+                    "Error in deleting image from the object store: {:?}",
+                    delete_result.err()
+                );
             }
             if delete_db_result.is_err() {
                 error!(
@@ -1,20 +1,49 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
+use chrono::{NaiveDateTime, Utc};
 use serde::Serialize;
-use tide::{log::error, Request};
+use tide::{log::error, Redirect, Request};
 
 use crate::{
     connectors::db::{
         follow::is_following_by_username,
-        link::get_user_links_by_id,
-        user::{check_username_present, get_user_profile_by_username},
+        health::get_user_link_health,
+        insight::{update_link_insight_sources, update_link_insights},
+        link::{get_link_by_id, get_user_links_by_id},
+        section::get_user_sections,
+        user::{check_username_present, get_user_by_id, get_user_profile_by_username},
     },
-    helpers::{auth::get_session_username, links::linearise, params::extract_username_from_params},
+    helpers::{
+        auth::{can_view_profile, get_session_username},
+        funcs::get_frontend_url,
+        params::{extract_link_id_from_params, extract_username_from_params},
+        qr::{qr_response, QrQueryParams},
+        validation::{href_with_scheme, validate_query_params},
+        visitors::{canonical_link_url, get_share_source},
+    },
+    jobs::link_health::link_health_failure_threshold,
+    models::{
+        health::LinkHealthBadge,
+        images::ImageSrcset,
+        insights::{UpdateLinkInsight, UpdateLinkInsightSource},
+        links::LinkStatus,
+        sections::GetLinkSection,
+    },
+    routes::profiles::share::ShareChannel,
     types::{error::Error, response::Response, state::TideState},
 };
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Default)]
 struct GetLinksResponseBody {
+    // links without a section, shown above the sections
+    links: Vec<GetImagedLink>,
+    sections: Vec<GetLinkSectionWithLinks>,
+}
+
+#[derive(Debug, Serialize)]
+struct GetLinkSectionWithLinks {
+    #[serde(flatten)]
+    section: GetLinkSection,
     links: Vec<GetImagedLink>,
 }
 
@@ -22,11 +51,18 @@ struct GetLinksResponseBody {
 pub struct GetImagedLink {
     pub id: i32,
     pub user_id: i32,
-    pub next_id: Option<i32>,
     pub description: Option<String>,
     pub title: Option<String>,
     pub href: String,
-    pub img_src: Option<String>,
+    pub img_srcset: Option<ImageSrcset>,
+    pub visible_from: Option<NaiveDateTime>,
+    pub visible_until: Option<NaiveDateTime>,
+    // only owners get links that are not visible
+    pub status: LinkStatus,
+    pub position: i64,
+    pub section_id: Option<i32>,
+    // latest health check, only for owners
+    pub health: Option<LinkHealthBadge>,
 }
 
 pub async fn get_links(req: Request<Arc<TideState>>) -> tide::Result {
@@ -60,35 +96,212 @@ pub async fn get_links(req: Request<Arc<TideState>>) -> tide::Result {
         match is_following_by_username(&mut conn, session_username, username).await {
             Ok(true) => (),
             // if origin is not owner and querying a private profile, return empty links
-            Ok(false) => {
-                return Response::new(GetLinksResponseBody { links: Vec::new() }).into_response()
-            }
+            Ok(false) => return Response::new(GetLinksResponseBody::default()).into_response(),
             Err(e) => return e.into_response(),
         }
     }
     // otherwise either owner or querying a public profile.
-    // Thus, get all links and return
-    match get_user_links_by_id(&mut conn, profile.id).await {
-        Ok(links) => Response::new(GetLinksResponseBody {
-            links: linearise(
-                &links
+    // Thus, get all links and return, hiding links outside of their window from non-owners
+    let sections = match get_user_sections(&mut conn, profile.id).await {
+        Ok(sections) => sections,
+        Err(e) => {
+            error!("Error in retrieving user sections by id: {}", e);
+            return Error::DieselError(e).into_response();
+        }
+    };
+
+    // owners see whether their links are broken
+    let mut link_health = HashMap::new();
+    if is_owner {
+        match get_user_link_health(&mut conn, profile.id).await {
+            Ok(health) => {
+                let failure_threshold = link_health_failure_threshold();
+                link_health = health
                     .into_iter()
-                    .map(|link| GetImagedLink {
-                        id: link.0.id,
-                        user_id: link.0.user_id,
-                        next_id: link.0.next_id,
-                        description: link.0.description,
-                        title: link.0.title,
-                        href: link.0.href,
-                        img_src: link.1.map(|img| img.img_src),
-                    })
-                    .collect::<Vec<GetImagedLink>>(),
-            ),
-        })
-        .into_response(),
+                    .map(|health| (health.link_id, health.badge(failure_threshold)))
+                    .collect::<HashMap<i32, LinkHealthBadge>>();
+            }
+            // fail silently
+            Err(e) => error!("Error in retrieving link health: {}", e),
+        }
+    }
+
+    let now = Utc::now().naive_utc();
+    let links = match get_user_links_by_id(&mut conn, profile.id).await {
+        Ok(links) => links
+            .into_iter()
+            .map(|link| GetImagedLink {
+                status: link.0.status_at(now),
+                id: link.0.id,
+                user_id: link.0.user_id,
+                description: link.0.description,
+                title: link.0.title,
+                href: link.0.href,
+                img_srcset: link.1.map(|img| img.srcset()),
+                visible_from: link.0.visible_from,
+                visible_until: link.0.visible_until,
+                position: link.0.position,
+                section_id: link.0.section_id,
+                health: link_health.remove(&link.0.id),
+            })
+            .filter(|link| is_owner || link.status.is_visible())
+            .collect::<Vec<GetImagedLink>>(),
         Err(e) => {
             error!("Error in retrieving user links by id: {}", e);
-            Error::DieselError(e).into_response()
+            return Error::DieselError(e).into_response();
+        }
+    };
+
+    // group links by section, keeping the order of the links within each section
+    let (links, mut sectioned_links): (Vec<GetImagedLink>, Vec<GetImagedLink>) = links
+        .into_iter()
+        .partition(|link| link.section_id.is_none());
+    let sections = sections
+        .into_iter()
+        .map(|section| {
+            let (section_links, rest) = sectioned_links
+                .drain(..)
+                .partition(|link| link.section_id == Some(section.id));
+            sectioned_links = rest;
+            GetLinkSectionWithLinks {
+                section,
+                links: section_links,
+            }
+        })
+        // visitors do not see sections without visible links
+        .filter(|section| is_owner || !section.links.is_empty())
+        .collect::<Vec<GetLinkSectionWithLinks>>();
+
+    Response::new(GetLinksResponseBody { links, sections }).into_response()
+}
+
+// GET endpoint that records a click on the link and redirects to its href
+pub async fn redirect_link(req: Request<Arc<TideState>>) -> tide::Result {
+    // get session username from session or default to ""
+    let session_username = get_session_username(&req).unwrap_or("".to_string());
+
+    // extract link id
+    let link_id = match extract_link_id_from_params(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    // get connection state
+    let state = req.state();
+    let mut conn = state.tide_pool.get().unwrap();
+
+    let link = match get_link_by_id(&mut conn, link_id).await {
+        Ok(link) => link,
+        Err(_) => return Error::NotFoundError(String::from("Link")).into_response(),
+    };
+
+    let owner = match get_user_by_id(&mut conn, link.user_id).await {
+        Ok(user) => user,
+        Err(e) => return Error::DieselError(e).into_response(),
+    };
+
+    let is_owner = session_username == owner.username;
+
+    // links outside of their window only exist for the owner
+    if !is_owner && !link.status_at(Utc::now().naive_utc()).is_visible() {
+        return Error::NotFoundError(String::from("Link")).into_response();
+    }
+
+    // links on private profiles are only reachable by the owner and followers
+    match can_view_profile(
+        &mut conn,
+        session_username,
+        owner.username,
+        owner.is_private,
+    )
+    .await
+    {
+        Ok(true) => (),
+        Ok(false) => return Error::NotFoundError(String::from("Link")).into_response(),
+        Err(e) => return e.into_response(),
+    }
+
+    // update click count if not owner
+    if !is_owner {
+        let increment_clicks =
+            UpdateLinkInsight::increment_click_count(link.id, Utc::now().naive_utc());
+
+        // fail silently
+        if let Err(e) = update_link_insights(&mut conn, increment_clicks).await {
+            error!("Failed to increment click count for link insights {:?}", e);
+        }
+
+        // clicks through shared link urls (like qr codes) are attributed to the share channel,
+        // they are link clicks and not profile views so they are kept apart from the referrers
+        if let Some(source) = get_share_source(&req) {
+            let update_source = UpdateLinkInsightSource::increment_click_count(
+                link.id,
+                source,
+                Utc::now().naive_utc(),
+            );
+
+            // fail silently
+            if let Err(e) = update_link_insight_sources(&mut conn, update_source).await {
+                error!("Failed to update click sources for link insights {:?}", e);
+            }
+        }
+    }
+
+    Ok(Redirect::new(href_with_scheme(&link.href)).into())
+}
+
+// GET end point for a qr code of the link, scans go through the tracked redirect
+pub async fn get_link_qr(req: Request<Arc<TideState>>) -> tide::Result {
+    let session_username = get_session_username(&req).unwrap_or("".to_string());
+
+    let link_id = match extract_link_id_from_params(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    let qr_params = match validate_query_params::<QrQueryParams>(&req) {
+        Ok(params) => params,
+        Err(e) => return e.into_response(),
+    };
+
+    let state = req.state();
+    let mut conn = state.tide_pool.get().unwrap();
+
+    let link = match get_link_by_id(&mut conn, link_id).await {
+        Ok(link) => link,
+        Err(_) => return Error::NotFoundError(String::from("Link")).into_response(),
+    };
+
+    let owner = match get_user_by_id(&mut conn, link.user_id).await {
+        Ok(user) => user,
+        Err(e) => return Error::DieselError(e).into_response(),
+    };
+
+    // owners can print codes of scheduled links ahead of time
+    if session_username != owner.username && !link.status_at(Utc::now().naive_utc()).is_visible() {
+        return Error::NotFoundError(String::from("Link")).into_response();
+    }
+
+    match can_view_profile(
+        &mut conn,
+        session_username,
+        owner.username,
+        owner.is_private,
+    )
+    .await
+    {
+        Ok(true) => (),
+        Ok(false) => return Error::NotFoundError(String::from("Link")).into_response(),
+        Err(e) => return e.into_response(),
+    }
+
+    match get_frontend_url().and_then(|frontend_url| {
+        canonical_link_url(&frontend_url, link.id, ShareChannel::Qr.as_str())
+    }) {
+        Some(link_url) => qr_response(&link_url, &qr_params),
+        None => {
+            error!("No valid FRONTEND_URL to build link urls with");
+            Error::InvalidResponseError().into_response()
         }
     }
 }
@@ -1,24 +1,35 @@
-use std::{env, sync::Arc};
+use std::sync::Arc;
 
-use aws_sdk_s3::primitives::ByteStream;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use tide::{
     log::{error, info},
     Request,
 };
-use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 use crate::{
     connectors::{
-        buckets::file::{delete_s3_link_image, update_s3_link_image},
+        buckets::{
+            file::{delete_images, upload_renditions},
+            store::Bucket,
+        },
         db::{
             image::{create_link_image, delete_link_image, get_link_image},
             link::{get_user_link_by_id, link_id_belongs_to_user, reorder_link, update_link_by_id},
+            section::section_id_belongs_to_user,
         },
     },
-    helpers::{auth::get_session_user_id, params::extract_link_id_from_params},
-    models::{images::InsertLinkImage, links::UpdateLink},
+    helpers::{
+        auth::get_session_user_id,
+        images::{read_image_body, spawn_make_renditions, spawn_validate_image},
+        params::extract_link_id_from_params,
+        validation::{deserialize_nullable, validate_href, validate_link_update_window},
+    },
+    models::{
+        images::{ImageSrcset, InsertLinkImage},
+        links::{GetLink, UpdateLink},
+    },
     types::{
         error::{AssociationErrors, Error, RequestErrors, S3Errors},
         response::Response,
@@ -26,24 +37,76 @@ use crate::{
     },
 };
 
+// any subset of the link fields can be updated at once
 #[derive(Debug, Deserialize, Validate, Serialize)]
-struct UpdateTitlePayload {
-    title: String,
+#[validate(schema(
+    function = "validate_has_link_changes",
+    message = "At least one of title, description, href, visible_from, visible_until or section_id must be provided"
+))]
+pub struct UpdateLinkPayload {
+    #[validate(length(max = 100, message = "Title must be at most 100 characters"))]
+    title: Option<String>,
+    #[serde(alias = "bio")]
+    #[validate(length(max = 300, message = "Description must be at most 300 characters"))]
+    description: Option<String>,
+    // validated and normalized by validate_href
+    href: Option<String>,
+    // null removes the bound
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    visible_from: Option<Option<DateTime<Utc>>>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    visible_until: Option<Option<DateTime<Utc>>>,
+    // null moves the link out of its section
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    section_id: Option<Option<i32>>,
 }
 
-#[derive(Debug, Deserialize, Validate, Serialize)]
-struct UpdateBioPayload {
-    bio: String,
+impl UpdateLinkPayload {
+    // validates the provided fields, fields that are not provided are left untouched
+    pub fn into_update_link(self) -> Result<UpdateLink, Error> {
+        self.validate().map_err(Error::ValidationError)?;
+
+        // validate and normalize href if provided
+        let href = self.href.as_deref().map(validate_href).transpose()?;
+
+        Ok(UpdateLink {
+            user_id: None,
+            description: self.description,
+            title: self.title,
+            href,
+            visible_from: self
+                .visible_from
+                .map(|from| from.map(|from| from.naive_utc())),
+            visible_until: self
+                .visible_until
+                .map(|until| until.map(|until| until.naive_utc())),
+            section_id: self.section_id,
+        })
+    }
 }
 
-#[derive(Debug, Deserialize, Validate, Serialize)]
-struct UpdateHrefPayload {
-    href: String,
+fn validate_has_link_changes(payload: &UpdateLinkPayload) -> Result<(), ValidationError> {
+    if payload.title.is_none()
+        && payload.description.is_none()
+        && payload.href.is_none()
+        && payload.visible_from.is_none()
+        && payload.visible_until.is_none()
+        && payload.section_id.is_none()
+    {
+        return Err(ValidationError::new("no_link_changes"));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+struct UpdateLinkResponseBody {
+    link: GetLink,
 }
 
 #[derive(Debug, Serialize)]
 struct UploadLinkResponseBody {
     href: String,
+    srcset: ImageSrcset,
 }
 
 #[derive(Debug, Deserialize)]
@@ -51,8 +114,8 @@ struct ReorderLinksPayload {
     link_id: i32,
     new_position_id: Option<i32>,
 }
-// TODO: combine update link title, bio & href into the same endpoint
-pub async fn update_link_title(mut req: Request<Arc<TideState>>) -> tide::Result {
+// PATCH endpoint that updates the given link fields together
+pub async fn update_link(mut req: Request<Arc<TideState>>) -> tide::Result {
     // extract user id from session
     let user_id = match get_session_user_id(&req) {
         Ok(id) => id,
@@ -60,24 +123,21 @@ pub async fn update_link_title(mut req: Request<Arc<TideState>>) -> tide::Result
     };
 
     // extract link id
-    let link_id = match req.param("link_id").and_then(|id| {
-        id.parse::<i32>()
-            .map_err(|_| tide::Error::from_str(400, "Invalid link_id provided."))
-    }) {
+    let link_id = match extract_link_id_from_params(&req) {
         Ok(id) => id,
-        Err(err) => return Err(err),
+        Err(e) => return e.into_response(),
     };
 
-    // extract title payload body
-    let update_title: UpdateTitlePayload = match req.body_json().await {
-        Ok(title_obj) => title_obj,
+    // extract update payload body
+    let update_payload: UpdateLinkPayload = match req.body_json().await {
+        Ok(payload) => payload,
         _ => return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response(),
     };
 
-    // validate title
-    match update_title.validate() {
-        Err(e) => return Error::ValidationError(e).into_response(),
-        _ => (),
+    // validate every provided field
+    let update_link = match update_payload.into_update_link() {
+        Ok(update_link) => update_link,
+        Err(e) => return e.into_response(),
     };
 
     // get connection state
@@ -85,84 +145,40 @@ pub async fn update_link_title(mut req: Request<Arc<TideState>>) -> tide::Result
     let mut conn = state.tide_pool.get().unwrap();
 
     // assert user link with link_id exists
-    match link_id_belongs_to_user(&mut conn, link_id, user_id).await {
-        Ok(result) => {
-            if !result {
-                return Error::AssociationError(AssociationErrors::LinkDoesNotBelongToUser)
-                    .into_response();
-            }
+    let link = match get_user_link_by_id(&mut conn, link_id, user_id).await {
+        Ok(link) => link,
+        Err(diesel::result::Error::NotFound) => {
+            return Error::AssociationError(AssociationErrors::LinkDoesNotBelongToUser)
+                .into_response()
         }
         Err(e) => return Error::DieselError(e).into_response(),
     };
 
-    // update the link
-    let update_link = UpdateLink {
-        user_id: None,
-        next_id: None,
-        description: None,
-        title: Some(update_title.title),
-        href: None,
-    };
-
-    let _result = match update_link_by_id(&mut conn, &update_link, link_id).await {
-        Ok(result) => result,
-        Err(e) => return Error::DieselError(e).into_response(),
-    };
-    Response::empty().into_response()
-}
-
-pub async fn update_link_bio(mut req: Request<Arc<TideState>>) -> tide::Result {
-    // extract user id from session
-    let user_id = match get_session_user_id(&req) {
-        Ok(id) => id,
-        Err(e) => return e.into_response(),
-    };
-
-    // extract link id
-    let link_id = match extract_link_id_from_params(&req) {
-        Ok(id) => id,
-        Err(e) => return e.into_response(),
-    };
-
-    // extract title payload body
-    let update_bio: UpdateBioPayload = match req.body_json().await {
-        Ok(title_obj) => title_obj,
-        _ => return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response(),
-    };
-
-    // validate title
-    match update_bio.validate() {
-        Err(e) => return Error::ValidationError(e).into_response(),
-        _ => (),
-    };
-
-    // get connection state
-    let state = req.state();
-    let mut conn = state.tide_pool.get().unwrap();
-    // check user link with link_id exists
-    match get_user_link_by_id(&mut conn, link_id, user_id).await {
-        Ok(_) => (),
-        _ => return Error::NotFoundError(String::from("Link")).into_response(),
-    };
+    // the window is validated against the bounds that are not being changed
+    if let Err(e) = validate_link_update_window(&link, &update_link) {
+        return e.into_response();
+    }
 
-    // update the link
-    let update_bio = UpdateLink {
-        user_id: None,
-        next_id: None,
-        title: None,
-        description: Some(update_bio.bio),
-        href: None,
-    };
+    // assert the new section belongs to the user
+    if let Some(Some(section_id)) = update_link.section_id {
+        match section_id_belongs_to_user(&mut conn, section_id, user_id).await {
+            Ok(true) => (),
+            Ok(false) => {
+                return Error::AssociationError(AssociationErrors::SectionDoesNotBelongToUser)
+                    .into_response()
+            }
+            Err(e) => return Error::DieselError(e).into_response(),
+        }
+    }
 
-    let _result = match update_link_by_id(&mut conn, &update_bio, link_id).await {
-        Ok(result) => result,
-        Err(err) => return Error::DieselError(err).into_response(),
-    };
-    Response::empty().into_response()
+    match update_link_by_id(&mut conn, &update_link, link_id).await {
+        Ok(link) => Response::new(UpdateLinkResponseBody { link }).into_response(),
+        Err(e) => Error::DieselError(e).into_response(),
+    }
 }
 
-pub async fn update_link_href(mut req: Request<Arc<TideState>>) -> tide::Result {
-    // extract user id from session
+pub async fn update_link_picture(mut req: Request<Arc<TideState>>) -> tide::Result {
+    // get user id from session
     let user_id = match get_session_user_id(&req) {
         Ok(id) => id,
         Err(e) => return e.into_response(),
@@ -174,71 +190,29 @@ pub async fn update_link_href(mut req: Request<Arc<TideState>>) -> tide::Result
         Err(e) => return e.into_response(),
     };
 
-    // extract href payload body
-    let updated_href: UpdateHrefPayload = match req.body_json().await {
-        Ok(title_obj) => title_obj,
-        _ => return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response(),
-    };
-
-    // validate href
-    match updated_href.validate() {
-        Err(e) => return Error::ValidationError(e).into_response(),
-        _ => (),
-    };
-
-    // get connection state
-    let state = req.state();
-    let mut conn = state.tide_pool.get().unwrap();
-
-    // check user link with link_id exists
-    match get_user_link_by_id(&mut conn, link_id, user_id).await {
-        Ok(_) => (),
-        _ => return Error::NotFoundError(String::from("Link")).into_response(),
-    };
-
-    // update the link
-    let update_link = UpdateLink {
-        user_id: None,
-        next_id: None,
-        description: None,
-        title: None,
-        href: Some(updated_href.href),
-    };
-
-    let _result = match update_link_by_id(&mut conn, &update_link, link_id).await {
-        Ok(result) => result,
-        Err(e) => return Error::DieselError(e).into_response(),
-    };
-
-    Response::empty().into_response()
-}
-
-pub async fn update_link_picture(mut req: Request<Arc<TideState>>) -> tide::Result {
-    // get user id from session
-    let user_id = match get_session_user_id(&req) {
-        Ok(id) => id,
+    // process the req body as bytes, up to the size limit of images
+    let bytes = match read_image_body(&mut req).await {
+        Ok(bytes) => bytes,
         Err(e) => return e.into_response(),
     };
 
-    // extract link id
-    let link_id = match extract_link_id_from_params(&req) {
-        Ok(id) => id,
+    // the :ext param is not trusted, the format is sniffed from the image itself
+    let image = match spawn_validate_image(bytes).await {
+        Ok(image) => image,
         Err(e) => return e.into_response(),
     };
 
-    // get :name from params
-    let image_name = match req.param("ext") {
-        Ok(ext) => [Uuid::new_v4().to_string(), ext.to_string()].join("."),
-        Err(err) => return Err(err),
-    };
-
-    // process the req body as bytes
-    let bytes = req.body_bytes().await;
+    // resize into renditions on the cdn, before taking a connection from the pool
+    let state = req.state();
+    let object_store = state.object_store.as_ref();
+    let renditions =
+        match spawn_make_renditions(image, object_store.base_url(Bucket::LinkImages)).await {
+            Ok(renditions) => renditions,
+            Err(e) => return e.into_response(),
+        };
 
     // get connection state
-    let state = req.state();
     let mut conn = state.tide_pool.get().unwrap();
-    let s3_client = &state.s3_client;
 
     // assert link_id belongs to user_id
     match link_id_belongs_to_user(&mut conn, link_id, user_id).await {
@@ -251,11 +225,11 @@ pub async fn update_link_picture(mut req: Request<Arc<TideState>>) -> tide::Resu
         Err(e) => return Error::DieselError(e).into_response(),
     }
 
-    // remove previous file; if any
+    // remove previous files; if any
     match get_link_image(&mut conn, link_id).await {
         Ok(img) => {
-            // remove from s3 if present
-            let result = delete_s3_link_image(s3_client, img.filename).await;
+            // remove from the object store if present
+            let result = delete_images(object_store, Bucket::LinkImages, img.filenames()).await;
             let result_db = delete_link_image(&mut conn, link_id).await;
             if result.is_err() {
                 error!("Error deleting link image: {}", result.unwrap_err());
@@ -269,38 +243,30 @@ pub async fn update_link_picture(mut req: Request<Arc<TideState>>) -> tide::Resu
         Err(_msg) => (),
     }
 
-    // get uploaded file as bytes
-    if bytes.is_err() {
-        error!("failed to get bytes from request body.");
-        return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response();
-    }
-    // upload file to s3
-    match update_s3_link_image(
-        s3_client,
-        image_name.clone(),
-        ByteStream::from(bytes.unwrap()),
-    )
-    .await
-    {
+    // upload files to the object store
+    match upload_renditions(object_store, Bucket::LinkImages, &renditions).await {
         Ok(()) => (),
         Err(msg) => {
-            error!("upload to s3 failed with error: {}", msg);
+            error!("upload to the object store failed with error: {}", msg);
             return Error::S3Error(S3Errors::FailedToUploadImage).into_response();
         }
     }
-    // create cdn href
-    let link_cdn_origin = env::var("LINK_IMAGE_CDN").expect("unable to process cdn");
-    let cdn_href = [link_cdn_origin, image_name.clone()].join("/");
     // create src href in db
+    let cdn_href = renditions.default.img_src.clone();
     let payload = InsertLinkImage {
         img_src: cdn_href.clone(),
-        filename: image_name.clone(),
+        filename: renditions.default.filename.clone(),
         link_id,
+        renditions: renditions.to_json(),
     };
     info!("creating cdn href.. {}", cdn_href.clone());
 
     match create_link_image(&mut conn, &payload).await {
-        Ok(_) => Response::new(UploadLinkResponseBody { href: cdn_href }).into_response(),
+        Ok(_) => Response::new(UploadLinkResponseBody {
+            href: cdn_href,
+            srcset: renditions.srcset(),
+        })
+        .into_response(),
         Err(e) => Error::DieselError(e).into_response(),
     }
 }
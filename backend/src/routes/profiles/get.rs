@@ -11,11 +11,25 @@ use crate::{
     connectors::db::{
         follow::{get_follower_count, get_following_count, is_following_by_username},
         image::get_profile_image,
-        insight::update_user_insights,
+        insight::{record_profile_visitor, update_user_insight_sources, update_user_insights},
         user::{check_username_present, get_user_profile_by_username},
     },
-    helpers::params::extract_username_from_params,
-    models::insights::{Increment, UpdateUserInsight},
+    helpers::{
+        auth::{can_view_profile, get_session_username},
+        funcs::get_frontend_url,
+        params::extract_username_from_params,
+        qr::{qr_response, QrQueryParams},
+        validation::validate_query_params,
+        visitors::{canonical_share_url, get_client_ip, get_referrer_source, get_visitor_key},
+    },
+    models::{
+        images::ImageSrcset,
+        insights::{
+            Increment, InsertProfileVisitor, InsightSourceType, UpdateUserInsight,
+            UpdateUserInsightSource,
+        },
+    },
+    routes::profiles::share::ShareChannel,
     types::{error::Error, response::Response, state::TideState},
 };
 
@@ -37,7 +51,7 @@ struct GetProfileResponseBody {
     display_name: String,
     bio: String,
     is_owner: bool,
-    picture: String,
+    picture: Option<ImageSrcset>,
     following: Option<i64>,
     followers: Option<i64>,
     is_private: bool,
@@ -45,7 +59,11 @@ struct GetProfileResponseBody {
 }
 
 impl GetProfileResponseBody {
-    fn private_body(display_name: String, id: i32, picture: String) -> GetProfileResponseBody {
+    fn private_body(
+        display_name: String,
+        id: i32,
+        picture: Option<ImageSrcset>,
+    ) -> GetProfileResponseBody {
         GetProfileResponseBody {
             display_name,
             bio: String::from(""),
@@ -108,8 +126,8 @@ pub async fn get_profile(req: Request<Arc<TideState>>) -> tide::Result {
                     Ok(true) => {
                         let picture = get_profile_image(&mut conn, profile.id)
                             .await
-                            .map(|img| img.img_src)
-                            .unwrap_or(String::from(""));
+                            .map(|img| img.srcset())
+                            .ok();
 
                         let follower_count = get_follower_count(&mut conn, profile.id).await.ok();
                         let following_count = get_following_count(&mut conn, profile.id).await.ok();
@@ -129,8 +147,8 @@ pub async fn get_profile(req: Request<Arc<TideState>>) -> tide::Result {
                     Ok(false) => {
                         let picture = get_profile_image(&mut conn, profile.id)
                             .await
-                            .map(|img| img.img_src)
-                            .unwrap_or(String::from(""));
+                            .map(|img| img.srcset())
+                            .ok();
 
                         GetProfileResponseBody::private_body(
                             profile.display_name,
@@ -149,20 +167,60 @@ pub async fn get_profile(req: Request<Arc<TideState>>) -> tide::Result {
 
                 // update view count if not owner
                 if !is_owner {
-                    let increment_views =
-                        UpdateUserInsight::increment_view_count(profile.id, Utc::now().naive_utc());
+                    let now = Utc::now().naive_utc();
+                    let mut increment_views =
+                        UpdateUserInsight::increment_view_count(profile.id, now);
+
+                    // only the first view of a visitor in a bucket counts as a unique view
+                    let profile_visitor = InsertProfileVisitor {
+                        user_id: profile.id,
+                        visitor_key: get_visitor_key(&req, now.date()),
+                        created_at: now,
+                    };
+                    match record_profile_visitor(&mut conn, profile_visitor).await {
+                        Ok(true) => increment_views.unique_view_count = Some(1),
+                        Ok(false) => {}
+                        Err(e) => log::error!("Failed to record profile visitor {:?}", e),
+                    }
 
                     // fail silently
                     if let Err(e) = update_user_insights(&mut conn, increment_views).await {
                         log::error!("Failed to increment view count for user insights {:?}", e);
                     }
+
+                    // break the view down by referrer and country when they are known
+                    let country = get_client_ip(&req)
+                        .and_then(|ip| state.geoip.lookup(ip))
+                        .map(|country| country.to_owned());
+                    let update_sources = [
+                        (InsightSourceType::Referrer, get_referrer_source(&req)),
+                        (InsightSourceType::Country, country),
+                    ]
+                    .into_iter()
+                    .filter_map(|(source_type, source)| {
+                        source.map(|source| {
+                            UpdateUserInsightSource::increment_view_count(
+                                profile.id,
+                                source_type,
+                                source,
+                                now,
+                            )
+                        })
+                    })
+                    .collect::<Vec<UpdateUserInsightSource>>();
+                    if !update_sources.is_empty() {
+                        if let Err(e) = update_user_insight_sources(&mut conn, update_sources).await
+                        {
+                            log::error!("Failed to update view sources for user insights {:?}", e);
+                        }
+                    }
                 }
 
                 // get cdn_href from db
                 let picture = get_profile_image(&mut conn, profile.id)
                     .await
-                    .map(|img| img.img_src)
-                    .unwrap_or(String::from(""));
+                    .map(|img| img.srcset())
+                    .ok();
 
                 let follower_count = get_follower_count(&mut conn, profile.id).await.ok();
                 let following_count = get_following_count(&mut conn, profile.id).await.ok();
@@ -186,3 +244,49 @@ pub async fn get_profile(req: Request<Arc<TideState>>) -> tide::Result {
     };
     Response::new(res_body).into_response()
 }
+
+// GET end point for a qr code of the profile, scans are attributed to the qr share channel
+pub async fn get_profile_qr(req: Request<Arc<TideState>>) -> tide::Result {
+    let username = match extract_username_from_params(&req) {
+        Ok(name) => name,
+        Err(e) => return e.into_response(),
+    };
+
+    let qr_params = match validate_query_params::<QrQueryParams>(&req) {
+        Ok(params) => params,
+        Err(e) => return e.into_response(),
+    };
+
+    let session_username = get_session_username(&req).unwrap_or("".to_string());
+
+    let state = req.state();
+    let mut conn = state.tide_pool.get().unwrap();
+
+    let profile = match get_user_profile_by_username(&mut conn, &username).await {
+        Ok(profile) => profile,
+        Err(_) => return Error::NotFoundError(String::from("User")).into_response(),
+    };
+
+    match can_view_profile(
+        &mut conn,
+        session_username,
+        username.clone(),
+        profile.is_private,
+    )
+    .await
+    {
+        Ok(true) => (),
+        Ok(false) => return Error::NotFoundError(String::from("User")).into_response(),
+        Err(e) => return e.into_response(),
+    }
+
+    match get_frontend_url().and_then(|frontend_url| {
+        canonical_share_url(&frontend_url, &username, ShareChannel::Qr.as_str())
+    }) {
+        Some(share_url) => qr_response(&share_url, &qr_params),
+        None => {
+            error!("No valid FRONTEND_URL to build share urls with");
+            Error::InvalidResponseError().into_response()
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tide::{log::error, Request};
+
+use crate::{
+    connectors::db::{
+        insight::{record_profile_share, update_user_insights},
+        user::get_user_profile_by_username,
+    },
+    helpers::{
+        auth::{can_view_profile, get_session_username},
+        funcs::get_frontend_url,
+        params::extract_username_from_params,
+        visitors::{canonical_share_url, get_visitor_key},
+    },
+    models::insights::{Increment, InsertProfileShare, UpdateUserInsight},
+    types::{
+        error::{Error, RequestErrors},
+        response::Response,
+        state::TideState,
+    },
+};
+
+// where a profile was shared to
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShareChannel {
+    CopyLink,
+    Qr,
+    Twitter,
+    Facebook,
+    Linkedin,
+    Whatsapp,
+    Telegram,
+    Reddit,
+    Email,
+    Other,
+}
+
+impl ShareChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShareChannel::CopyLink => "copy-link",
+            ShareChannel::Qr => "qr",
+            ShareChannel::Twitter => "twitter",
+            ShareChannel::Facebook => "facebook",
+            ShareChannel::Linkedin => "linkedin",
+            ShareChannel::Whatsapp => "whatsapp",
+            ShareChannel::Telegram => "telegram",
+            ShareChannel::Reddit => "reddit",
+            ShareChannel::Email => "email",
+            ShareChannel::Other => "other",
+        }
+    }
+}
+
+// tracking parameter of shares without a channel
+const DEFAULT_SHARE_SOURCE: &str = "share";
+
+#[derive(Deserialize, Default)]
+struct ShareProfilePayload {
+    channel: Option<ShareChannel>,
+}
+
+#[derive(Serialize)]
+struct ShareProfileResponseBody {
+    share_url: String,
+    // false when the viewer already shared the profile recently
+    recorded: bool,
+}
+
+// POST end point for sharing a profile
+pub async fn share_profile(mut req: Request<Arc<TideState>>) -> tide::Result {
+    let username = match extract_username_from_params(&req) {
+        Ok(name) => name,
+        Err(e) => return e.into_response(),
+    };
+
+    // the body is optional
+    let share_payload = match req.body_string().await {
+        Ok(body) if body.trim().is_empty() => ShareProfilePayload::default(),
+        Ok(body) => match serde_json::from_str::<ShareProfilePayload>(&body) {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!("Error occurred in parsing: {:?}", e);
+                return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response();
+            }
+        },
+        Err(_) => {
+            return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response()
+        }
+    };
+
+    let session_username = get_session_username(&req).unwrap_or("".to_string());
+
+    let state = req.state();
+    let mut conn = state.tide_pool.get().unwrap();
+
+    let profile = match get_user_profile_by_username(&mut conn, &username).await {
+        Ok(profile) => profile,
+        Err(_) => return Error::NotFoundError(String::from("User")).into_response(),
+    };
+
+    // private profiles can only be shared by the owner and followers
+    match can_view_profile(
+        &mut conn,
+        session_username,
+        username.clone(),
+        profile.is_private,
+    )
+    .await
+    {
+        Ok(true) => (),
+        Ok(false) => return Error::NotFoundError(String::from("User")).into_response(),
+        Err(e) => return e.into_response(),
+    }
+
+    let src = share_payload
+        .channel
+        .map(|channel| channel.as_str())
+        .unwrap_or(DEFAULT_SHARE_SOURCE);
+    let share_url = match get_frontend_url()
+        .and_then(|frontend_url| canonical_share_url(&frontend_url, &username, src))
+    {
+        Some(share_url) => share_url,
+        None => {
+            error!("No valid FRONTEND_URL to build share urls with");
+            return Error::InvalidResponseError().into_response();
+        }
+    };
+
+    // each viewer counts at most once per bucket, so switching channels does not add shares
+    let now = Utc::now().naive_utc();
+    let profile_share = InsertProfileShare {
+        user_id: profile.id,
+        visitor_key: get_visitor_key(&req, now.date()),
+        channel: src.to_owned(),
+        created_at: now,
+    };
+    let recorded = match record_profile_share(&mut conn, profile_share).await {
+        Ok(recorded) => recorded,
+        Err(e) => {
+            // fail silently
+            error!("Failed to record profile share {:?}", e);
+            false
+        }
+    };
+
+    if recorded {
+        let increment_shares = UpdateUserInsight::increment_share_count(profile.id, now);
+
+        // fail silently
+        if let Err(e) = update_user_insights(&mut conn, increment_shares).await {
+            error!("Failed to increment share count for user insights {:?}", e);
+        }
+    }
+
+    Response::new(ShareProfileResponseBody {
+        share_url,
+        recorded,
+    })
+    .into_response()
+}
@@ -1,23 +1,30 @@
-use std::{env, sync::Arc};
+use std::sync::Arc;
 
-use aws_sdk_s3::primitives::ByteStream;
 use tide::{
     log::{error, info},
     Request,
 };
-use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
     connectors::{
-        buckets::file::{delete_s3_profile_image, update_s3_profile_image},
+        buckets::{
+            file::{delete_images, upload_renditions},
+            store::Bucket,
+        },
         db::{
             image::{create_profile_image, delete_profile_image, get_profile_image},
             user::update_user_by_id,
         },
     },
-    helpers::auth::get_session_user_id,
-    models::{images::InsertProfileImage, users::UpdateUser},
+    helpers::{
+        auth::get_session_user_id,
+        images::{read_image_body, spawn_make_renditions, spawn_validate_image},
+    },
+    models::{
+        images::{ImageSrcset, InsertProfileImage},
+        users::UpdateUser,
+    },
     types::{
         error::{Error, RequestErrors, S3Errors},
         response::Response,
@@ -34,6 +41,7 @@ struct UpdateDisplayProfilePayload {
 #[derive(Debug, serde::Serialize)]
 struct UploadProfileImageResponseBody {
     href: String,
+    srcset: ImageSrcset,
 }
 
 // update profile response body
@@ -76,25 +84,35 @@ pub async fn update_profile_image(mut req: Request<Arc<TideState>>) -> tide::Res
         Err(e) => return e.into_response(),
     };
 
-    // get :name from params
-    let image_name = match req.param("ext") {
-        Ok(ext) => [Uuid::new_v4().to_string(), ext.to_string()].join("."),
-        Err(err) => return Err(err),
+    // process the req body as bytes, up to the size limit of images
+    let bytes = match read_image_body(&mut req).await {
+        Ok(bytes) => bytes,
+        Err(e) => return e.into_response(),
+    };
+
+    // the :ext param is not trusted, the format is sniffed from the image itself
+    let image = match spawn_validate_image(bytes).await {
+        Ok(image) => image,
+        Err(e) => return e.into_response(),
     };
 
-    // process the req body as bytes
-    let bytes = req.body_bytes().await;
+    // resize into renditions on the cdn, before taking a connection from the pool
+    let state = req.state();
+    let object_store = state.object_store.as_ref();
+    let renditions =
+        match spawn_make_renditions(image, object_store.base_url(Bucket::ProfileImages)).await {
+            Ok(renditions) => renditions,
+            Err(e) => return e.into_response(),
+        };
 
     // get connection state
-    let state = req.state();
     let mut conn = state.tide_pool.get().unwrap();
-    let s3_client = &state.s3_client;
 
-    // remove previous file; if any
+    // remove previous files; if any
     match get_profile_image(&mut conn, user_id).await {
         Ok(img) => {
-            // remove from s3 if present
-            let result = delete_s3_profile_image(s3_client, img.filename).await;
+            // remove from the object store if present
+            let result = delete_images(object_store, Bucket::ProfileImages, img.filenames()).await;
             let db_result = delete_profile_image(&mut conn, user_id).await;
             if result.is_err() {
                 error!("Error deleting profile image: {}", result.unwrap_err());
@@ -108,41 +126,30 @@ pub async fn update_profile_image(mut req: Request<Arc<TideState>>) -> tide::Res
         Err(_msg) => (),
     }
 
-    // get uploaded file as bytes
-    if bytes.is_err() {
-        error!("failed to get bytes from request body.");
-        return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response();
-    }
-
-    // upload file to s3
-    match update_s3_profile_image(
-        s3_client,
-        image_name.clone(),
-        ByteStream::from(bytes.unwrap()),
-    )
-    .await
-    {
+    // upload files to the object store
+    match upload_renditions(object_store, Bucket::ProfileImages, &renditions).await {
         Ok(()) => (),
         Err(msg) => {
-            error!("upload to s3 failed with error: {}", msg);
+            error!("upload to the object store failed with error: {}", msg);
             return Error::S3Error(S3Errors::FailedToUploadImage).into_response();
         }
     }
-    // create cdn href
-    let profile_cdn_origin = env::var("PROFILE_IMAGE_CDN").expect("unable to process cdn");
-    let cdn_href = [profile_cdn_origin, image_name.clone()].join("/");
     // create src href in db
+    let cdn_href = renditions.default.img_src.clone();
     let payload = InsertProfileImage {
         img_src: cdn_href.clone(),
-        filename: image_name.clone(),
+        filename: renditions.default.filename.clone(),
         user_id,
+        renditions: renditions.to_json(),
     };
     info!("creating cdn href.. {}", cdn_href.clone());
 
     match create_profile_image(&mut conn, &payload).await {
-        Ok(_img) => {
-            Response::new(UploadProfileImageResponseBody { href: cdn_href }).into_response()
-        }
+        Ok(_img) => Response::new(UploadProfileImageResponseBody {
+            href: cdn_href,
+            srcset: renditions.srcset(),
+        })
+        .into_response(),
         Err(e) => Error::DieselError(e).into_response(),
     }
 }
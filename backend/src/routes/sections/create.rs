@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tide::{log::error, Request};
+use validator::Validate;
+
+use crate::{
+    connectors::db::section::create_section,
+    helpers::auth::get_session_user_id,
+    models::sections::{GetLinkSection, InsertLinkSection},
+    types::{
+        error::{Error, RequestErrors},
+        response::Response,
+        state::TideState,
+    },
+};
+
+#[derive(Deserialize, Serialize, Validate)]
+struct CreateSectionPayload {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Title must be between 1 and 100 characters"
+    ))]
+    title: String,
+    #[serde(default)]
+    is_collapsed: bool,
+}
+
+#[derive(Serialize)]
+struct CreateSectionResponseBody {
+    section: GetLinkSection,
+}
+
+// POST end point for adding a section after the user's other sections
+pub async fn add_section(mut req: Request<Arc<TideState>>) -> tide::Result {
+    // extract user id from session
+    let user_id = match get_session_user_id(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    // get payload
+    let section_payload: CreateSectionPayload = match req.body_json().await {
+        Ok(payload) => payload,
+        Err(e) => {
+            error!("Error occurred in parsing: {:?}", e);
+            return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response();
+        }
+    };
+
+    // validate payload
+    if let Err(e) = section_payload.validate() {
+        return Error::ValidationError(e).into_response();
+    }
+
+    let state = req.state();
+    let mut conn = state.tide_pool.get().unwrap();
+
+    let insert_section = InsertLinkSection {
+        user_id,
+        title: section_payload.title,
+        is_collapsed: section_payload.is_collapsed,
+    };
+
+    match create_section(&mut conn, &insert_section).await {
+        Ok(section) => Response::new(CreateSectionResponseBody { section }).into_response(),
+        Err(e) => {
+            error!("Error creating section. {:?}, Error: {}", insert_section, e);
+            Error::DieselError(e).into_response()
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use tide::{log::error, Request};
+
+use crate::{
+    connectors::db::section::{delete_section_by_id, section_id_belongs_to_user},
+    helpers::{auth::get_session_user_id, params::extract_section_id_from_params},
+    types::{
+        error::{AssociationErrors, Error},
+        response::Response,
+        state::TideState,
+    },
+};
+
+// DELETE endpoint for a section, its links are kept outside of any section
+pub async fn delete_section(req: Request<Arc<TideState>>) -> tide::Result {
+    // get user id from session
+    let user_id = match get_session_user_id(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    // extract section id
+    let section_id = match extract_section_id_from_params(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    // get connection state
+    let state = req.state();
+    let mut conn = state.tide_pool.get().unwrap();
+
+    // assert section_id belongs to user_id
+    match section_id_belongs_to_user(&mut conn, section_id, user_id).await {
+        Ok(true) => (),
+        Ok(false) => {
+            return Error::AssociationError(AssociationErrors::SectionDoesNotBelongToUser)
+                .into_response()
+        }
+        Err(e) => return Error::DieselError(e).into_response(),
+    }
+
+    match delete_section_by_id(&mut conn, section_id).await {
+        Ok(_) => Response::empty().into_response(),
+        Err(e) => {
+            error!("Error in deleting section: {:?}", e);
+            Error::DieselError(e).into_response()
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tide::Request;
+use validator::{Validate, ValidationError};
+
+use crate::{
+    connectors::db::section::{reorder_section, section_id_belongs_to_user, update_section_by_id},
+    helpers::{auth::get_session_user_id, params::extract_section_id_from_params},
+    models::sections::{GetLinkSection, UpdateLinkSection},
+    types::{
+        error::{AssociationErrors, Error, RequestErrors},
+        response::Response,
+        state::TideState,
+    },
+};
+
+#[derive(Debug, Deserialize, Validate, Serialize)]
+#[validate(schema(
+    function = "validate_has_section_changes",
+    message = "At least one of title or is_collapsed must be provided"
+))]
+struct UpdateSectionPayload {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Title must be between 1 and 100 characters"
+    ))]
+    title: Option<String>,
+    is_collapsed: Option<bool>,
+}
+
+fn validate_has_section_changes(payload: &UpdateSectionPayload) -> Result<(), ValidationError> {
+    if payload.title.is_none() && payload.is_collapsed.is_none() {
+        return Err(ValidationError::new("no_section_changes"));
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct UpdateSectionResponseBody {
+    section: GetLinkSection,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReorderSectionsPayload {
+    section_id: i32,
+    new_position_id: Option<i32>,
+}
+
+// PATCH endpoint that renames or collapses a section
+pub async fn update_section(mut req: Request<Arc<TideState>>) -> tide::Result {
+    // extract user id from session
+    let user_id = match get_session_user_id(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    // extract section id
+    let section_id = match extract_section_id_from_params(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    // extract update payload body
+    let update_payload: UpdateSectionPayload = match req.body_json().await {
+        Ok(payload) => payload,
+        _ => return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response(),
+    };
+
+    // validate every provided field
+    if let Err(e) = update_payload.validate() {
+        return Error::ValidationError(e).into_response();
+    }
+
+    // get connection state
+    let state = req.state();
+    let mut conn = state.tide_pool.get().unwrap();
+
+    // assert user section with section_id exists
+    match section_id_belongs_to_user(&mut conn, section_id, user_id).await {
+        Ok(true) => (),
+        Ok(false) => {
+            return Error::AssociationError(AssociationErrors::SectionDoesNotBelongToUser)
+                .into_response()
+        }
+        Err(e) => return Error::DieselError(e).into_response(),
+    };
+
+    let update_section = UpdateLinkSection {
+        title: update_payload.title,
+        is_collapsed: update_payload.is_collapsed,
+    };
+
+    match update_section_by_id(&mut conn, &update_section, section_id).await {
+        Ok(section) => Response::new(UpdateSectionResponseBody { section }).into_response(),
+        Err(e) => Error::DieselError(e).into_response(),
+    }
+}
+
+// POST endpoint that moves a section in front of another one or to the end
+pub async fn reorder_sections(mut req: Request<Arc<TideState>>) -> tide::Result {
+    // get user id from session
+    let user_id = match get_session_user_id(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    // get reordering sections
+    let reorder_payload: ReorderSectionsPayload = match req.body_json().await {
+        Ok(body) => body,
+        Err(_) => {
+            return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response()
+        }
+    };
+
+    // get connection state
+    let state = req.state();
+    let mut conn = state.tide_pool.get().unwrap();
+
+    // assert both sections belong to user_id
+    let section_ids = [
+        Some(reorder_payload.section_id),
+        reorder_payload.new_position_id,
+    ];
+    for section_id in section_ids.into_iter().flatten() {
+        match section_id_belongs_to_user(&mut conn, section_id, user_id).await {
+            Ok(true) => (),
+            Ok(false) => {
+                return Error::AssociationError(AssociationErrors::SectionDoesNotBelongToUser)
+                    .into_response()
+            }
+            Err(e) => return Error::DieselError(e).into_response(),
+        }
+    }
+
+    match reorder_section(
+        &mut conn,
+        reorder_payload.section_id,
+        reorder_payload.new_position_id,
+    )
+    .await
+    {
+        Ok(_) => Response::empty().into_response(),
+        Err(e) => Error::DieselError(e).into_response(),
+    }
+}
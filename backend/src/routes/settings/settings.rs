@@ -1,5 +1,7 @@
 use crate::connectors::db::user::{does_email_exist, does_username_exist};
+use crate::helpers::funcs::get_frontend_url;
 use crate::helpers::state::get_connection;
+use crate::helpers::verification::send_email_verification;
 use crate::routes::auth::PASSWORD_COST;
 use crate::{
     connectors::db::user::update_user_by_id,
@@ -168,22 +170,27 @@ pub async fn change_email(mut req: Request<Arc<TideState>>) -> tide::Result {
         Err(e) => return e.into_response(),
     }
 
-    let update_user = UpdateUser {
-        username: None,
-        password: None,
-        salt: None,
-        email: Some(change_email.email),
-        is_private: None,
-        bio: None,
-        display_name: None,
+    let frontend_url = match get_frontend_url() {
+        Some(url) => url,
+        None => {
+            log::error!("No valid FRONTEND_URL to build verification links with");
+            return Error::InvalidResponseError().into_response();
+        }
     };
-    // get connection state
-    let state = req.state();
-    let mut conn = state.tide_pool.get().unwrap();
-    // call orm
-    return match update_user_by_id(&mut conn, user_id, &update_user).await {
-        Ok(_result) => Response::empty().into_response(),
-        Err(err) => Error::DieselError(err).into_response(),
+
+    // the email of the user only changes once the new address is confirmed
+    return match send_email_verification(
+        &mut conn,
+        &req.state().email_service,
+        &frontend_url,
+        user_id,
+        &change_email.email,
+        chrono::TimeDelta::zero(),
+    )
+    .await
+    {
+        Ok(_) => Response::empty().into_response(),
+        Err(e) => e.into_response(),
     };
 }
 // change email
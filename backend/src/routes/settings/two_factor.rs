@@ -0,0 +1,311 @@
+use std::sync::Arc;
+
+use bcrypt::verify;
+use chrono::Utc;
+use diesel::PgConnection;
+use serde::{Deserialize, Serialize};
+use tide::Request;
+
+use crate::{
+    connectors::db::{
+        two_factor::{
+            count_recovery_codes, delete_two_factor, enable_two_factor, get_two_factor,
+            replace_recovery_codes, replace_two_factor,
+        },
+        user::get_password_salt_from_id,
+    },
+    helpers::{
+        auth::{get_session_user_id, get_session_username},
+        state::get_connection,
+        two_factor::{
+            check_two_factor_code, generate_recovery_codes, generate_totp_secret,
+            hash_recovery_code, totp_uri,
+        },
+    },
+    models::two_factor::{GetTwoFactor, InsertRecoveryCode, InsertTwoFactor},
+    routes::auth::two_factor::TwoFactorCodeParams,
+    types::{
+        error::{Error, RequestErrors},
+        response::Response,
+        state::TideState,
+    },
+};
+
+// turning two factor authentication off or making new recovery codes also needs the password,
+// so that a session left open is not enough
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorPasswordParams {
+    pub password: String,
+    // a totp code or a recovery code
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+struct TwoFactorStatusBody {
+    is_enabled: bool,
+    recovery_codes_left: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct TwoFactorSetupBody {
+    secret: String,
+    otpauth_uri: String,
+}
+
+// the codes are only shown once, only their hashes are stored
+#[derive(Debug, Serialize)]
+struct RecoveryCodesBody {
+    recovery_codes: Vec<String>,
+}
+
+async fn get_enabled_two_factor(
+    conn: &mut PgConnection,
+    user_id: i32,
+) -> Result<GetTwoFactor, Error> {
+    match get_two_factor(conn, user_id).await {
+        Ok(two_factor) if two_factor.is_enabled => Ok(two_factor),
+        Ok(_) | Err(diesel::result::Error::NotFound) => Err(Error::TwoFactorNotEnabledError()),
+        Err(e) => Err(Error::DieselError(e)),
+    }
+}
+
+async fn check_password(
+    conn: &mut PgConnection,
+    user_id: i32,
+    password: &str,
+) -> Result<(), Error> {
+    let (password_hash, _) = get_password_salt_from_id(conn, user_id).await;
+    match verify(password, &password_hash) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(Error::WrongPasswordError()),
+        Err(e) => Err(Error::HashError(e)),
+    }
+}
+
+// replaces the recovery codes of the user with new ones
+async fn renew_recovery_codes(
+    conn: &mut PgConnection,
+    user_id: i32,
+) -> Result<RecoveryCodesBody, Error> {
+    let recovery_codes = generate_recovery_codes();
+    let hashed_codes: Vec<InsertRecoveryCode> = recovery_codes
+        .iter()
+        .map(|code| InsertRecoveryCode {
+            user_id,
+            code_hash: hash_recovery_code(code),
+        })
+        .collect();
+    replace_recovery_codes(conn, user_id, &hashed_codes).await?;
+    Ok(RecoveryCodesBody { recovery_codes })
+}
+
+// get route for whether the user has two factor authentication on
+pub async fn get_two_factor_status(mut req: Request<Arc<TideState>>) -> tide::Result {
+    // get user_id from session
+    let user_id = match get_session_user_id(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    let mut conn = get_connection(&mut req);
+
+    let is_enabled = match get_two_factor(&mut conn, user_id).await {
+        Ok(two_factor) => two_factor.is_enabled,
+        Err(diesel::result::Error::NotFound) => false,
+        Err(e) => return Error::DieselError(e).into_response(),
+    };
+    let recovery_codes_left = match count_recovery_codes(&mut conn, user_id).await {
+        Ok(count) => count,
+        Err(e) => return Error::DieselError(e).into_response(),
+    };
+
+    Response::new(TwoFactorStatusBody {
+        is_enabled,
+        recovery_codes_left,
+    })
+    .into_response()
+}
+
+// post route that makes a new secret for the authenticator app,
+// it is only used at login once a code from the app is confirmed
+pub async fn setup_two_factor(mut req: Request<Arc<TideState>>) -> tide::Result {
+    // get user_id from session
+    let user_id = match get_session_user_id(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+    let username = match get_session_username(&req) {
+        Ok(username) => username,
+        Err(e) => return e.into_response(),
+    };
+
+    let mut conn = get_connection(&mut req);
+
+    match get_two_factor(&mut conn, user_id).await {
+        Ok(two_factor) if two_factor.is_enabled => {
+            return Error::TwoFactorAlreadyEnabledError().into_response()
+        }
+        Ok(_) | Err(diesel::result::Error::NotFound) => {}
+        Err(e) => return Error::DieselError(e).into_response(),
+    }
+
+    let secret = generate_totp_secret();
+    let otpauth_uri = match totp_uri(&secret, &username) {
+        Ok(uri) => uri,
+        Err(e) => return e.into_response(),
+    };
+    let new_two_factor = InsertTwoFactor {
+        user_id,
+        secret: secret.clone(),
+        is_enabled: false,
+        last_used_step: None,
+        created_at: Utc::now().naive_utc(),
+    };
+    match replace_two_factor(&mut conn, &new_two_factor).await {
+        Ok(_) => Response::new(TwoFactorSetupBody {
+            secret,
+            otpauth_uri,
+        })
+        .into_response(),
+        Err(e) => Error::DieselError(e).into_response(),
+    }
+}
+
+// post route that turns two factor authentication on with a code from the app
+// and gives back the recovery codes
+pub async fn confirm_two_factor(mut req: Request<Arc<TideState>>) -> tide::Result {
+    // get user_id from session
+    let user_id = match get_session_user_id(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    let code_params: TwoFactorCodeParams = match req.body_json().await {
+        Ok(params) => params,
+        Err(_e) => {
+            return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response()
+        }
+    };
+
+    let mut conn = get_connection(&mut req);
+
+    let two_factor = match get_two_factor(&mut conn, user_id).await {
+        Ok(two_factor) if two_factor.is_enabled => {
+            return Error::TwoFactorAlreadyEnabledError().into_response()
+        }
+        Ok(two_factor) => two_factor,
+        Err(diesel::result::Error::NotFound) => {
+            return Error::TwoFactorNotEnabledError().into_response()
+        }
+        Err(e) => return Error::DieselError(e).into_response(),
+    };
+
+    match check_two_factor_code(
+        &mut conn,
+        &two_factor,
+        &code_params.code,
+        Utc::now().naive_utc(),
+    )
+    .await
+    {
+        Ok(()) => {}
+        Err(e) => return e.into_response(),
+    }
+
+    match enable_two_factor(&mut conn, user_id).await {
+        Ok(()) => {}
+        Err(e) => return Error::DieselError(e).into_response(),
+    }
+    match renew_recovery_codes(&mut conn, user_id).await {
+        Ok(body) => Response::new(body).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+// post route that turns two factor authentication off, given the password and a code
+pub async fn disable_two_factor(mut req: Request<Arc<TideState>>) -> tide::Result {
+    // get user_id from session
+    let user_id = match get_session_user_id(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    let code_params: TwoFactorPasswordParams = match req.body_json().await {
+        Ok(params) => params,
+        Err(_e) => {
+            return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response()
+        }
+    };
+
+    let mut conn = get_connection(&mut req);
+
+    match check_password(&mut conn, user_id, &code_params.password).await {
+        Ok(()) => {}
+        Err(e) => return e.into_response(),
+    }
+
+    let two_factor = match get_enabled_two_factor(&mut conn, user_id).await {
+        Ok(two_factor) => two_factor,
+        Err(e) => return e.into_response(),
+    };
+    match check_two_factor_code(
+        &mut conn,
+        &two_factor,
+        &code_params.code,
+        Utc::now().naive_utc(),
+    )
+    .await
+    {
+        Ok(()) => {}
+        Err(e) => return e.into_response(),
+    }
+
+    match delete_two_factor(&mut conn, user_id).await {
+        Ok(()) => Response::empty().into_response(),
+        Err(e) => Error::DieselError(e).into_response(),
+    }
+}
+
+// post route that replaces the recovery codes, given the password and a code
+pub async fn regenerate_recovery_codes(mut req: Request<Arc<TideState>>) -> tide::Result {
+    // get user_id from session
+    let user_id = match get_session_user_id(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    let code_params: TwoFactorPasswordParams = match req.body_json().await {
+        Ok(params) => params,
+        Err(_e) => {
+            return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response()
+        }
+    };
+
+    let mut conn = get_connection(&mut req);
+
+    match check_password(&mut conn, user_id, &code_params.password).await {
+        Ok(()) => {}
+        Err(e) => return e.into_response(),
+    }
+
+    let two_factor = match get_enabled_two_factor(&mut conn, user_id).await {
+        Ok(two_factor) => two_factor,
+        Err(e) => return e.into_response(),
+    };
+    match check_two_factor_code(
+        &mut conn,
+        &two_factor,
+        &code_params.code,
+        Utc::now().naive_utc(),
+    )
+    .await
+    {
+        Ok(()) => {}
+        Err(e) => return e.into_response(),
+    }
+
+    match renew_recovery_codes(&mut conn, user_id).await {
+        Ok(body) => Response::new(body).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
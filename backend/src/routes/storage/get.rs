@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use tide::{Body, Request, StatusCode};
+
+use crate::{
+    helpers::{images::ImageKind, params::extract_object_from_params},
+    types::state::TideState,
+};
+
+// every upload gets a new key, so served objects never change
+const CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+// serves the objects of the local object store like the cdn in front of s3
+pub async fn get_object(req: Request<Arc<TideState>>) -> tide::Result {
+    let (bucket, key) = match extract_object_from_params(&req) {
+        Ok(object) => object,
+        Err(e) => return e.into_response(),
+    };
+
+    match req.state().object_store.get(bucket, &key).await {
+        Ok(bytes) => {
+            // only images are stored, anything else is served as plain bytes
+            let content_type = key
+                .rsplit_once('.')
+                .and_then(|(_, ext)| ImageKind::from_ext(ext))
+                .map(|kind| kind.content_type())
+                .unwrap_or("application/octet-stream");
+            Ok(tide::Response::builder(StatusCode::Ok)
+                .content_type(content_type)
+                .header("Cache-Control", CACHE_CONTROL)
+                .header("X-Content-Type-Options", "nosniff")
+                .body(Body::from_bytes(bytes))
+                .build())
+        }
+        Err(e) => e.into_response(),
+    }
+}
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tide::Request;
+
+use crate::{
+    helpers::{images::read_image_body, params::extract_object_from_params},
+    types::{
+        error::{Error, RequestErrors},
+        response::Response,
+        state::TideState,
+    },
+};
+
+#[derive(Debug, Deserialize)]
+struct PresignedPutQuery {
+    expires: i64,
+    signature: String,
+}
+
+// takes uploads that were presigned by the local object store
+pub async fn put_object(mut req: Request<Arc<TideState>>) -> tide::Result {
+    let (bucket, key) = match extract_object_from_params(&req) {
+        Ok(object) => object,
+        Err(e) => return e.into_response(),
+    };
+    let query = match req.query::<PresignedPutQuery>() {
+        Ok(query) => query,
+        Err(_) => {
+            return Error::InvalidRequestError(RequestErrors::MalformedParams).into_response()
+        }
+    };
+    // the content type is part of the signature
+    let content_type = req
+        .header("Content-Type")
+        .map(|values| values.as_str().to_string())
+        .unwrap_or_default();
+
+    let object_store = req.state().object_store.as_ref();
+    if let Err(e) = object_store.verify_presigned_put(
+        bucket,
+        &key,
+        &content_type,
+        query.expires,
+        &query.signature,
+    ) {
+        return e.into_response();
+    }
+
+    // only images are uploaded
+    let bytes = match read_image_body(&mut req).await {
+        Ok(bytes) => bytes,
+        Err(e) => return e.into_response(),
+    };
+
+    match req
+        .state()
+        .object_store
+        .put(bucket, &key, bytes, &content_type)
+        .await
+    {
+        Ok(()) => Response::empty().into_response(),
+        Err(e) => e.into_response(),
+    }
+}
@@ -0,0 +1,203 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use diesel::PgConnection;
+use serde::Serialize;
+use tide::{
+    log::{error, info},
+    Request,
+};
+
+use crate::{
+    connectors::{
+        buckets::{
+            file::{delete_images, upload_renditions},
+            store::{Bucket, ObjectStore},
+        },
+        db::{
+            image::{
+                create_link_image, create_profile_image, delete_link_image, delete_profile_image,
+                get_link_image, get_profile_image,
+            },
+            upload::{delete_upload, get_user_upload},
+        },
+    },
+    helpers::{
+        auth::get_session_user_id,
+        images::{spawn_make_renditions, spawn_validate_image, ValidatedImage, MAX_IMAGE_BYTES},
+        params::extract_upload_token_from_params,
+    },
+    models::{
+        images::{ImageSrcset, InsertLinkImage, InsertProfileImage},
+        uploads::GetUpload,
+    },
+    types::{
+        error::{Error, ImageErrors, S3Errors},
+        response::Response,
+        state::TideState,
+    },
+};
+
+#[derive(Debug, Serialize)]
+struct CompleteUploadResponseBody {
+    href: String,
+    srcset: ImageSrcset,
+}
+
+fn upload_bucket(upload: &GetUpload) -> Bucket {
+    match upload.link_id {
+        Some(_) => Bucket::LinkImages,
+        None => Bucket::ProfileImages,
+    }
+}
+
+// removes the uploaded object and the upload, failures are only logged
+async fn discard_upload(
+    conn: &mut PgConnection,
+    object_store: &dyn ObjectStore,
+    upload: &GetUpload,
+) {
+    if let Err(e) = object_store
+        .delete(upload_bucket(upload), &upload.object_key)
+        .await
+    {
+        error!("Error deleting upload {}: {}", upload.object_key, e);
+    }
+    if let Err(e) = delete_upload(conn, upload.id).await {
+        error!("Error deleting upload {} from db: {}", upload.id, e);
+    }
+}
+
+// the object has to be an image of the content type it was presigned for
+async fn check_uploaded_image(upload: &GetUpload, bytes: Vec<u8>) -> Result<ValidatedImage, Error> {
+    let image = spawn_validate_image(bytes).await?;
+    if image.kind.content_type() != upload.content_type {
+        return Err(Error::ImageError(ImageErrors::ContentTypeMismatch));
+    }
+    Ok(image)
+}
+
+// POST end point for attaching an uploaded image to the profile or link of the upload
+pub async fn complete_image_upload(req: Request<Arc<TideState>>) -> tide::Result {
+    // extract user id from session
+    let user_id = match get_session_user_id(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    let token = match extract_upload_token_from_params(&req) {
+        Ok(token) => token,
+        Err(e) => return e.into_response(),
+    };
+
+    let state = req.state();
+    let mut conn = state.tide_pool.get().unwrap();
+    let object_store = state.object_store.as_ref();
+
+    // expired uploads are treated as if they never existed
+    let upload = match get_user_upload(
+        &mut conn,
+        user_id,
+        &sha256::digest(&token),
+        Utc::now().naive_utc(),
+    )
+    .await
+    {
+        Ok(upload) => upload,
+        Err(diesel::result::Error::NotFound) => {
+            return Error::NotFoundError(String::from("Upload")).into_response()
+        }
+        Err(e) => return Error::DieselError(e).into_response(),
+    };
+    let bucket = upload_bucket(&upload);
+
+    // the upload is kept when the object is missing so that the client can still put it.
+    // presigned puts are not limited in size, anything past the limit is never read
+    let bytes = match object_store
+        .get_prefix(bucket, &upload.object_key, MAX_IMAGE_BYTES + 1)
+        .await
+    {
+        Ok(bytes) => bytes,
+        Err(e) => return e.into_response(),
+    };
+
+    // an object that is not the promised image cannot be fixed by completing again
+    let image = match check_uploaded_image(&upload, bytes).await {
+        Ok(image) => image,
+        Err(e) => {
+            discard_upload(&mut conn, object_store, &upload).await;
+            return e.into_response();
+        }
+    };
+
+    // resize into renditions on the cdn
+    let renditions = match spawn_make_renditions(image, object_store.base_url(bucket)).await {
+        Ok(renditions) => renditions,
+        Err(e) => {
+            discard_upload(&mut conn, object_store, &upload).await;
+            return e.into_response();
+        }
+    };
+
+    // remove previous files; if any
+    let previous_image = match upload.link_id {
+        Some(link_id) => get_link_image(&mut conn, link_id).await,
+        None => get_profile_image(&mut conn, user_id).await,
+    };
+    if let Ok(img) = previous_image {
+        // remove from the object store if present
+        let result = delete_images(object_store, bucket, img.filenames()).await;
+        let result_db = match upload.link_id {
+            Some(link_id) => delete_link_image(&mut conn, link_id).await,
+            None => delete_profile_image(&mut conn, user_id).await,
+        };
+        if let Err(e) = result {
+            error!("Error deleting previous image: {}", e);
+        }
+        if let Err(e) = result_db {
+            error!("Error deleting previous image from db: {:?}", e);
+        }
+    }
+
+    // upload files to the object store
+    if let Err(msg) = upload_renditions(object_store, bucket, &renditions).await {
+        error!("upload to the object store failed with error: {}", msg);
+        return Error::S3Error(S3Errors::FailedToUploadImage).into_response();
+    }
+
+    // create src href in db
+    let cdn_href = renditions.default.img_src.clone();
+    info!("creating cdn href.. {}", cdn_href.clone());
+    let created = match upload.link_id {
+        Some(link_id) => {
+            let payload = InsertLinkImage {
+                img_src: cdn_href.clone(),
+                filename: renditions.default.filename.clone(),
+                link_id,
+                renditions: renditions.to_json(),
+            };
+            create_link_image(&mut conn, &payload).await
+        }
+        None => {
+            let payload = InsertProfileImage {
+                img_src: cdn_href.clone(),
+                filename: renditions.default.filename.clone(),
+                user_id,
+                renditions: renditions.to_json(),
+            };
+            create_profile_image(&mut conn, &payload).await
+        }
+    };
+    if let Err(e) = created {
+        return Error::DieselError(e).into_response();
+    }
+
+    // the renditions replace the original upload
+    discard_upload(&mut conn, object_store, &upload).await;
+
+    Response::new(CompleteUploadResponseBody {
+        href: cdn_href,
+        srcset: renditions.srcset(),
+    })
+    .into_response()
+}
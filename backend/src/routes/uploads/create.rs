@@ -0,0 +1,131 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tide::{log::error, Request};
+use uuid::Uuid;
+
+use crate::{
+    connectors::{
+        buckets::store::{Bucket, PresignedUpload},
+        db::{link::link_id_belongs_to_user, upload::create_upload},
+    },
+    helpers::{auth::get_session_user_id, images::ImageKind, random::make_random_string},
+    models::uploads::InsertUpload,
+    types::{
+        error::{AssociationErrors, Error, ImageErrors, RequestErrors},
+        response::Response,
+        state::TideState,
+    },
+};
+
+// how long the client has to upload the image and complete the upload
+const UPLOAD_EXPIRY: Duration = Duration::from_secs(15 * 60);
+const UPLOAD_TOKEN_LENGTH: usize = 32;
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum UploadTarget {
+    Profile,
+    Link,
+}
+
+#[derive(Deserialize, Debug)]
+struct CreateUploadPayload {
+    target: UploadTarget,
+    // required when the target is a link
+    link_id: Option<i32>,
+    content_type: String,
+}
+
+#[derive(Serialize)]
+struct CreateUploadResponseBody {
+    token: String,
+    upload: PresignedUpload,
+    expires_at: NaiveDateTime,
+}
+
+// POST end point for uploading an image straight to the object store.
+// the image is only attached once the upload is completed with the token
+pub async fn create_image_upload(mut req: Request<Arc<TideState>>) -> tide::Result {
+    // extract user id from session
+    let user_id = match get_session_user_id(&req) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+
+    // get payload
+    let upload_payload: CreateUploadPayload = match req.body_json().await {
+        Ok(payload) => payload,
+        Err(e) => {
+            error!("Error occurred in parsing: {:?}", e);
+            return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response();
+        }
+    };
+
+    // the content type is checked against the uploaded object when completing
+    let kind = match ImageKind::from_content_type(&upload_payload.content_type) {
+        Some(kind) => kind,
+        None => return Error::ImageError(ImageErrors::UnsupportedFormat).into_response(),
+    };
+
+    let state = req.state();
+    let mut conn = state.tide_pool.get().unwrap();
+
+    let (bucket, link_id) = match (upload_payload.target, upload_payload.link_id) {
+        (UploadTarget::Profile, _) => (Bucket::ProfileImages, None),
+        (UploadTarget::Link, Some(link_id)) => {
+            // assert link_id belongs to user_id
+            match link_id_belongs_to_user(&mut conn, link_id, user_id).await {
+                Ok(true) => (),
+                Ok(false) => {
+                    return Error::AssociationError(AssociationErrors::LinkDoesNotBelongToUser)
+                        .into_response()
+                }
+                Err(e) => return Error::DieselError(e).into_response(),
+            }
+            (Bucket::LinkImages, Some(link_id))
+        }
+        (UploadTarget::Link, None) => {
+            return Error::InvalidRequestError(RequestErrors::MalformedPayload).into_response()
+        }
+    };
+
+    let object_key = format!("upload-{}.{}", Uuid::new_v4(), kind.ext());
+    let upload = match state
+        .object_store
+        .presign_put(bucket, &object_key, kind.content_type(), UPLOAD_EXPIRY)
+        .await
+    {
+        Ok(upload) => upload,
+        Err(e) => {
+            error!("Error presigning upload of {}: {}", object_key, e);
+            return e.into_response();
+        }
+    };
+
+    // only the hash of the token is stored
+    let token = make_random_string(UPLOAD_TOKEN_LENGTH);
+    let expires_at = Utc::now().naive_utc() + UPLOAD_EXPIRY;
+    let insert_upload = InsertUpload {
+        token_hash: sha256::digest(&token),
+        user_id,
+        link_id,
+        object_key,
+        content_type: kind.content_type().to_string(),
+        expires_at,
+    };
+
+    match create_upload(&mut conn, &insert_upload).await {
+        Ok(_) => Response::new(CreateUploadResponseBody {
+            token,
+            upload,
+            expires_at,
+        })
+        .into_response(),
+        Err(e) => {
+            error!("Error creating upload. {:?}, Error: {}", insert_upload, e);
+            Error::DieselError(e).into_response()
+        }
+    }
+}
@@ -1,5 +1,16 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    email_verification_request (id) {
+        id -> Int4,
+        user_id -> Int4,
+        email -> Varchar,
+        #[max_length = 64]
+        token_hash -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     follows (id) {
         id -> Int4,
@@ -15,6 +26,51 @@ diesel::table! {
         filename -> Varchar,
         user_id -> Nullable<Int4>,
         link_id -> Nullable<Int4>,
+        renditions -> Jsonb,
+    }
+}
+
+diesel::table! {
+    link_health (link_id) {
+        link_id -> Int4,
+        status_code -> Nullable<Int4>,
+        latency_ms -> Int4,
+        checked_at -> Timestamp,
+        consecutive_failures -> Int4,
+        is_notified -> Bool,
+    }
+}
+
+diesel::table! {
+    link_insight_sources (id) {
+        id -> Int4,
+        link_id -> Int4,
+        #[max_length = 255]
+        source -> Varchar,
+        click_count -> Int4,
+        created_at -> Timestamp,
+        created_bucket -> Timestamp,
+    }
+}
+
+diesel::table! {
+    link_insights (id) {
+        id -> Int4,
+        link_id -> Int4,
+        click_count -> Int4,
+        created_at -> Timestamp,
+        created_bucket -> Timestamp,
+    }
+}
+
+diesel::table! {
+    link_sections (id) {
+        id -> Int4,
+        user_id -> Int4,
+        #[max_length = 100]
+        title -> Varchar,
+        is_collapsed -> Bool,
+        position -> Int8,
     }
 }
 
@@ -22,11 +78,14 @@ diesel::table! {
     links (id) {
         id -> Int4,
         user_id -> Int4,
-        next_id -> Nullable<Int4>,
         description -> Nullable<Varchar>,
         title -> Nullable<Varchar>,
         #[max_length = 255]
         href -> Varchar,
+        visible_from -> Nullable<Timestamp>,
+        visible_until -> Nullable<Timestamp>,
+        position -> Int8,
+        section_id -> Nullable<Int4>,
     }
 }
 
@@ -50,6 +109,46 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    profile_shares (user_id, created_bucket, visitor_key) {
+        user_id -> Int4,
+        #[max_length = 64]
+        visitor_key -> Varchar,
+        #[max_length = 16]
+        channel -> Varchar,
+        created_at -> Timestamp,
+        created_bucket -> Timestamp,
+    }
+}
+
+diesel::table! {
+    profile_visitors (user_id, created_bucket, visitor_key) {
+        user_id -> Int4,
+        #[max_length = 64]
+        visitor_key -> Varchar,
+        created_at -> Timestamp,
+        created_bucket -> Timestamp,
+    }
+}
+
+diesel::table! {
+    rate_limits (key) {
+        #[max_length = 128]
+        key -> Varchar,
+        failures -> Int4,
+        last_failure_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    recovery_codes (id) {
+        id -> Int4,
+        user_id -> Int4,
+        #[max_length = 64]
+        code_hash -> Varchar,
+    }
+}
+
 diesel::table! {
     reset_password_request (id) {
         id -> Int4,
@@ -59,6 +158,41 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    sessions (id) {
+        id -> Varchar,
+        session -> Varchar,
+        expires_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    two_factor (id) {
+        id -> Int4,
+        user_id -> Int4,
+        #[max_length = 64]
+        secret -> Varchar,
+        is_enabled -> Bool,
+        last_used_step -> Nullable<Int8>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    uploads (id) {
+        id -> Int4,
+        #[max_length = 64]
+        token_hash -> Varchar,
+        user_id -> Int4,
+        link_id -> Nullable<Int4>,
+        #[max_length = 255]
+        object_key -> Varchar,
+        #[max_length = 32]
+        content_type -> Varchar,
+        expires_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     user_insights (id) {
         id -> Int4,
@@ -70,6 +204,21 @@ diesel::table! {
         share_count -> Int4,
         created_at -> Timestamp,
         created_bucket -> Timestamp,
+        unique_view_count -> Int4,
+    }
+}
+
+diesel::table! {
+    user_insight_sources (id) {
+        id -> Int4,
+        user_id -> Int4,
+        #[max_length = 16]
+        source_type -> Varchar,
+        #[max_length = 255]
+        source -> Varchar,
+        view_count -> Int4,
+        created_at -> Timestamp,
+        created_bucket -> Timestamp,
     }
 }
 
@@ -84,22 +233,49 @@ diesel::table! {
         is_private -> Bool,
         salt -> Varchar,
         display_name -> Varchar,
+        email_verified -> Bool,
     }
 }
 
+diesel::joinable!(email_verification_request -> users (user_id));
 diesel::joinable!(images -> links (link_id));
 diesel::joinable!(images -> users (user_id));
+diesel::joinable!(link_health -> links (link_id));
+diesel::joinable!(link_insight_sources -> links (link_id));
+diesel::joinable!(link_insights -> links (link_id));
+diesel::joinable!(link_sections -> users (user_id));
+diesel::joinable!(links -> link_sections (section_id));
 diesel::joinable!(links -> users (user_id));
+diesel::joinable!(profile_shares -> users (user_id));
+diesel::joinable!(profile_visitors -> users (user_id));
+diesel::joinable!(recovery_codes -> users (user_id));
 diesel::joinable!(reset_password_request -> users (user_id));
+diesel::joinable!(two_factor -> users (user_id));
+diesel::joinable!(uploads -> links (link_id));
+diesel::joinable!(uploads -> users (user_id));
+diesel::joinable!(user_insight_sources -> users (user_id));
 diesel::joinable!(user_insights -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    email_verification_request,
     follows,
     images,
+    link_health,
+    link_insight_sources,
+    link_insights,
+    link_sections,
     links,
     notifications,
     pending_follow_requests,
+    profile_shares,
+    profile_visitors,
+    rate_limits,
+    recovery_codes,
     reset_password_request,
+    sessions,
+    two_factor,
+    uploads,
+    user_insight_sources,
     user_insights,
     users,
 );
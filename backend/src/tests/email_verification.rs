@@ -0,0 +1,244 @@
+#[cfg(test)]
+mod email_verification_tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::{json, Value};
+    use tide::http::{Method, Request, Response, Url};
+
+    use crate::connectors::db;
+    use crate::connectors::db::mock_connection;
+    use crate::connectors::smtp::smtp_service::SMTPService;
+    use crate::helpers::verification::{
+        send_email_verification, EMAIL_VERIFICATION_RESEND_COOLDOWN,
+    };
+    use crate::models::verification::InsertEmailVerification;
+    use crate::routes::auth::{
+        reset_password::get_email,
+        verify_email::{get_email_verification_status, verify_email},
+    };
+    use crate::tests::{create_mock_app, create_mock_state, create_mock_user, delete_mock_user};
+    use crate::types::error::Error;
+    use crate::types::state::TideState;
+
+    // keeps the emails instead of sending them
+    #[derive(Default)]
+    struct RecordingEmailService {
+        sent: Mutex<Vec<(String, String)>>,
+    }
+
+    impl SMTPService for RecordingEmailService {
+        fn send_email(
+            &self,
+            to_email: String,
+            _subject: String,
+            body: String,
+        ) -> Result<(), Error> {
+            self.sent.lock().unwrap().push((to_email, body));
+            Ok(())
+        }
+    }
+
+    async fn send(
+        app: &tide::Server<Arc<TideState>>,
+        method: Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> Response {
+        let url = Url::parse(&format!("http://localhost{}", path)).unwrap();
+        let mut req = Request::new(method, url);
+        if let Some(body) = body {
+            req.set_body(body);
+        }
+        app.respond(req).await.unwrap()
+    }
+
+    #[tokio::test]
+    pub async fn it_changes_email_once_the_link_is_opened() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        assert!(!user.email_verified);
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = create_mock_app(create_mock_state(dir.path()), &user);
+        app.at("/verify-email")
+            .get(get_email_verification_status)
+            .post(verify_email);
+
+        let new_email = format!("new-{}", user.email);
+        let email_service = RecordingEmailService::default();
+        let frontend_url = Url::parse("http://localhost:5173").unwrap();
+        send_email_verification(
+            &mut conn,
+            &email_service,
+            &frontend_url,
+            user.id,
+            &new_email,
+            chrono::TimeDelta::zero(),
+        )
+        .await
+        .unwrap();
+
+        // the link goes to the new address
+        let (to_email, body) = email_service.sent.lock().unwrap().pop().unwrap();
+        assert_eq!(to_email, new_email);
+        let link = Url::parse(body.rsplit(' ').next().unwrap()).unwrap();
+        assert_eq!(link.path(), "/auth/verify-email");
+        let token = link
+            .query_pairs()
+            .find(|(key, _)| key == "token")
+            .unwrap()
+            .1
+            .to_string();
+
+        // the email is only pending until then
+        let mut res = send(&app, Method::Get, "/verify-email", None).await;
+        let status: Value = res.body_json().await.unwrap();
+        assert_eq!(status["payload"]["email"], user.email.as_str());
+        assert_eq!(status["payload"]["email_verified"], false);
+        assert_eq!(status["payload"]["pending_email"], new_email.as_str());
+
+        let res = send(
+            &app,
+            Method::Post,
+            "/verify-email",
+            Some(json!({ "token": token })),
+        )
+        .await;
+        assert_eq!(res.status(), 200);
+        let verified_user = db::user::get_user_by_id(&mut conn, user.id).await.unwrap();
+        assert_eq!(verified_user.email, new_email);
+        assert!(verified_user.email_verified);
+
+        // links only work once
+        let res = send(
+            &app,
+            Method::Post,
+            "/verify-email",
+            Some(json!({ "token": token })),
+        )
+        .await;
+        assert_eq!(res.status(), 400);
+
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_waits_before_sending_the_link_again() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let email_service = RecordingEmailService::default();
+        let frontend_url = Url::parse("http://localhost:5173").unwrap();
+
+        send_email_verification(
+            &mut conn,
+            &email_service,
+            &frontend_url,
+            user.id,
+            &user.email,
+            EMAIL_VERIFICATION_RESEND_COOLDOWN,
+        )
+        .await
+        .unwrap();
+        let sent = db::verification::get_user_email_verification(&mut conn, user.id)
+            .await
+            .unwrap();
+
+        // asking again right away keeps the first link
+        let res = send_email_verification(
+            &mut conn,
+            &email_service,
+            &frontend_url,
+            user.id,
+            &user.email,
+            EMAIL_VERIFICATION_RESEND_COOLDOWN,
+        )
+        .await;
+        match res {
+            Err(Error::TooManyRequestsError(wait)) => {
+                assert!(wait > 0 && wait <= 60, "waits {} seconds", wait)
+            }
+            res => panic!("expected too many requests, got {:?}", res),
+        }
+        assert_eq!(email_service.sent.lock().unwrap().len(), 1);
+        let kept = db::verification::get_user_email_verification(&mut conn, user.id)
+            .await
+            .unwrap();
+        assert_eq!(kept.token_hash, sent.token_hash);
+
+        // a new email address replaces it without waiting
+        send_email_verification(
+            &mut conn,
+            &email_service,
+            &frontend_url,
+            user.id,
+            &format!("new-{}", user.email),
+            chrono::TimeDelta::zero(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(email_service.sent.lock().unwrap().len(), 2);
+
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_rejects_expired_links() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = create_mock_app(create_mock_state(dir.path()), &user);
+        app.at("/verify-email").post(verify_email);
+
+        db::verification::replace_email_verification(
+            &mut conn,
+            &InsertEmailVerification {
+                user_id: user.id,
+                email: user.email.clone(),
+                token_hash: sha256::digest(format!("expired-{}", user.id)),
+                created_at: chrono::Local::now().naive_local() - chrono::Duration::days(2),
+            },
+            chrono::Local::now().naive_local(),
+        )
+        .await
+        .unwrap();
+
+        let mut res = send(
+            &app,
+            Method::Post,
+            "/verify-email",
+            Some(json!({ "token": format!("expired-{}", user.id) })),
+        )
+        .await;
+        assert_eq!(res.status(), 400);
+        let body: Value = res.body_json().await.unwrap();
+        assert_eq!(body["err"], "Email verification link expired");
+        assert!(
+            !db::user::get_user_by_id(&mut conn, user.id)
+                .await
+                .unwrap()
+                .email_verified
+        );
+
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_only_resets_passwords_of_verified_emails() {
+        let user = create_mock_user().await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = create_mock_app(create_mock_state(dir.path()), &user);
+        app.at("/get-email").post(get_email);
+
+        let mut res = send(
+            &app,
+            Method::Post,
+            "/get-email",
+            Some(json!({ "email": user.email })),
+        )
+        .await;
+        assert_eq!(res.status(), 400);
+        let body: Value = res.body_json().await.unwrap();
+        assert_eq!(body["err"], "Email has not been verified");
+
+        delete_mock_user(user.id).await;
+    }
+}
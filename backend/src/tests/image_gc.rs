@@ -0,0 +1,145 @@
+#[cfg(test)]
+mod image_gc_tests {
+    use chrono::{Duration, Utc};
+    use serde_json::json;
+
+    use crate::connectors::buckets::{
+        local::LocalObjectStore,
+        store::{Bucket, ObjectStore},
+    };
+    use crate::connectors::db;
+    use crate::connectors::db::mock_connection;
+    use crate::jobs::image_gc::{collect_orphaned_images, MissingImageObject};
+    use crate::models::{
+        images::{InsertLinkImage, InsertProfileImage},
+        uploads::InsertUpload,
+    };
+    use crate::tests::{create_mock_link, create_mock_user, delete_mock_user};
+
+    #[tokio::test]
+    pub async fn it_deletes_orphaned_objects_and_reports_missing_ones() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let link = create_mock_link(user.id).await;
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalObjectStore::new(dir.path(), "http://localhost/storage", "secret");
+        let keys = ["profile", "orphan", "upload"].map(|name| format!("{}-{}.png", name, user.id));
+        for key in keys.iter() {
+            store
+                .put(Bucket::ProfileImages, key, b"image".to_vec(), "image/png")
+                .await
+                .unwrap();
+        }
+        let [profile_key, orphan_key, upload_key] = keys;
+        // objects that are not images were not stored by the app
+        store
+            .put(
+                Bucket::ProfileImages,
+                "fixture.txt",
+                b"text".to_vec(),
+                "text/plain",
+            )
+            .await
+            .unwrap();
+
+        db::image::create_profile_image(
+            &mut conn,
+            &InsertProfileImage {
+                img_src: format!("http://localhost/storage/{}", profile_key),
+                filename: profile_key.clone(),
+                user_id: user.id,
+                renditions: json!([]),
+            },
+        )
+        .await
+        .unwrap();
+        // the files of the link image were never stored
+        let link_image = db::image::create_link_image(
+            &mut conn,
+            &InsertLinkImage {
+                img_src: format!("http://localhost/storage/link-{}.png", link.id),
+                filename: format!("link-{}.png", link.id),
+                link_id: link.id,
+                renditions: json!([]),
+            },
+        )
+        .await
+        .unwrap();
+        db::upload::create_upload(
+            &mut conn,
+            &InsertUpload {
+                token_hash: sha256::digest(format!("gc-{}", user.id)),
+                user_id: user.id,
+                link_id: None,
+                object_key: upload_key.clone(),
+                content_type: String::from("image/png"),
+                expires_at: Utc::now().naive_utc() + Duration::hours(1),
+            },
+        )
+        .await
+        .unwrap();
+
+        // new objects are kept for the grace period
+        let report = collect_orphaned_images(
+            &mut conn,
+            &store,
+            Duration::hours(1),
+            true,
+            Utc::now().naive_utc(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.objects_checked, 4);
+        assert!(report.orphaned_objects.is_empty());
+
+        // a dry run only reports
+        let report = collect_orphaned_images(
+            &mut conn,
+            &store,
+            Duration::zero(),
+            true,
+            Utc::now().naive_utc(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            report.orphaned_objects,
+            vec![(Bucket::ProfileImages, orphan_key.clone())]
+        );
+        assert_eq!(report.objects_deleted, 0);
+        assert!(report.missing_objects.contains(&MissingImageObject {
+            image_id: link_image.id,
+            bucket: Bucket::LinkImages,
+            key: format!("link-{}.png", link.id),
+        }));
+        assert!(store.get(Bucket::ProfileImages, &orphan_key).await.is_ok());
+
+        // only objects of this store are deleted, stale rows of other tests are left alone
+        let report = collect_orphaned_images(
+            &mut conn,
+            &store,
+            Duration::zero(),
+            false,
+            Utc::now().naive_utc(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.objects_deleted, 1);
+        assert!(store.get(Bucket::ProfileImages, &orphan_key).await.is_err());
+        // images and pending uploads keep their objects
+        for key in [profile_key, upload_key, String::from("fixture.txt")] {
+            assert!(store.get(Bucket::ProfileImages, &key).await.is_ok());
+        }
+
+        db::image::delete_profile_image(&mut conn, user.id)
+            .await
+            .unwrap();
+        db::image::delete_link_image(&mut conn, link.id)
+            .await
+            .unwrap();
+        db::link::delete_link_by_id(&mut conn, link.id)
+            .await
+            .unwrap();
+        delete_mock_user(user.id).await;
+    }
+}
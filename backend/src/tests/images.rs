@@ -0,0 +1,181 @@
+#[cfg(test)]
+mod images_tests {
+    use std::{io::Cursor, sync::Arc};
+
+    use image::{ImageFormat, RgbImage};
+    use serde_json::Value;
+    use tide::http::{Method, Request, Response, Url};
+
+    use crate::connectors::buckets::store::Bucket;
+    use crate::connectors::db;
+    use crate::connectors::db::mock_connection;
+    use crate::routes::{
+        links::{delete::delete_links, update::update_link_picture},
+        profiles::{get::get_profile, update::update_profile_image},
+        storage::get::get_object,
+    };
+    use crate::tests::{
+        create_mock_app, create_mock_link, create_mock_state, create_mock_user, delete_mock_user,
+    };
+    use crate::types::state::TideState;
+
+    fn encode_png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = Cursor::new(Vec::new());
+        RgbImage::new(width, height)
+            .write_to(&mut png, ImageFormat::Png)
+            .unwrap();
+        png.into_inner()
+    }
+
+    async fn send(
+        app: &tide::Server<Arc<TideState>>,
+        method: Method,
+        path: &str,
+        body: Option<Vec<u8>>,
+    ) -> Response {
+        let url = Url::parse(&format!("http://localhost{}", path)).unwrap();
+        let mut req = Request::new(method, url);
+        if let Some(body) = body {
+            req.set_body(body);
+        }
+        app.respond(req).await.unwrap()
+    }
+
+    #[tokio::test]
+    pub async fn it_stores_profile_image_renditions_on_disk() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let dir = tempfile::tempdir().unwrap();
+        let state = create_mock_state(dir.path());
+        let mut app = create_mock_app(state.clone(), &user);
+        app.at("/profiles/image/:ext").put(update_profile_image);
+        app.at("/profiles/:username").get(get_profile);
+        app.at("/storage/:bucket/:key").get(get_object);
+
+        let mut res = send(
+            &app,
+            Method::Put,
+            "/profiles/image/png",
+            Some(encode_png(600, 300)),
+        )
+        .await;
+        assert_eq!(res.status(), 200);
+        let body: Value = res.body_json().await.unwrap();
+        let href = body["payload"]["href"].as_str().unwrap().to_string();
+        assert!(href.starts_with("http://localhost/storage/profile-images-salad/"));
+        assert!(body["payload"]["srcset"]["sources"]["image/webp"].is_string());
+
+        // every rendition is in the bucket and served by the storage route
+        let image = db::image::get_profile_image(&mut conn, user.id)
+            .await
+            .unwrap();
+        assert_eq!(image.img_src, href);
+        let filenames = image.filenames();
+        assert_eq!(filenames.len(), 6);
+        for filename in filenames.iter() {
+            assert!(state
+                .object_store
+                .get(Bucket::ProfileImages, filename)
+                .await
+                .is_ok());
+        }
+        let mut res = send(
+            &app,
+            Method::Get,
+            &format!("/storage/profile-images-salad/{}", image.filename),
+            None,
+        )
+        .await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.content_type().unwrap().essence(), "image/png");
+        let served = res.body_bytes().await.unwrap();
+        assert_eq!(image::load_from_memory(&served).unwrap().width(), 256);
+
+        let mut res = send(
+            &app,
+            Method::Get,
+            &format!("/profiles/{}", user.username),
+            None,
+        )
+        .await;
+        let body: Value = res.body_json().await.unwrap();
+        assert_eq!(body["payload"]["picture"]["src"], href.as_str());
+
+        // a new image replaces the files of the old one
+        let res = send(
+            &app,
+            Method::Put,
+            "/profiles/image/png",
+            Some(encode_png(32, 32)),
+        )
+        .await;
+        assert_eq!(res.status(), 200);
+        for filename in filenames.iter() {
+            assert!(state
+                .object_store
+                .get(Bucket::ProfileImages, filename)
+                .await
+                .is_err());
+        }
+
+        // files that are not images are rejected
+        let res = send(
+            &app,
+            Method::Put,
+            "/profiles/image/png",
+            Some(b"<svg></svg>".to_vec()),
+        )
+        .await;
+        assert_eq!(res.status(), 400);
+
+        db::image::delete_profile_image(&mut conn, user.id)
+            .await
+            .unwrap();
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_deletes_link_image_renditions_with_the_link() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let link = create_mock_link(user.id).await;
+        let dir = tempfile::tempdir().unwrap();
+        let state = create_mock_state(dir.path());
+        let mut app = create_mock_app(state.clone(), &user);
+        app.at("/links/:link_id/image/:ext")
+            .put(update_link_picture);
+        app.at("/links/:link_id").delete(delete_links);
+
+        let res = send(
+            &app,
+            Method::Put,
+            &format!("/links/{}/image/webp", link.id),
+            Some(encode_png(64, 64)),
+        )
+        .await;
+        assert_eq!(res.status(), 200);
+        let filenames = db::image::get_link_image(&mut conn, link.id)
+            .await
+            .unwrap()
+            .filenames();
+        for filename in filenames.iter() {
+            assert!(state
+                .object_store
+                .get(Bucket::LinkImages, filename)
+                .await
+                .is_ok());
+        }
+
+        let res = send(&app, Method::Delete, &format!("/links/{}", link.id), None).await;
+        assert_eq!(res.status(), 200);
+        for filename in filenames.iter() {
+            assert!(state
+                .object_store
+                .get(Bucket::LinkImages, filename)
+                .await
+                .is_err());
+        }
+
+        delete_mock_user(user.id).await;
+    }
+}
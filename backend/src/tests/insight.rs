@@ -1,15 +1,34 @@
 #[cfg(test)]
 pub mod tests {
-    use chrono::Utc;
+    use chrono::{NaiveDate, NaiveDateTime, Utc};
 
     use crate::{
         connectors::db::{
-            insight::{get_user_insights, update_user_insights},
+            insight::{
+                count_profile_visitors_in_window, get_link_insights, get_link_insights_in_window,
+                get_top_link_insight_sources, get_top_user_insight_sources, get_user_insights,
+                get_user_insights_in_window, get_user_insights_page, record_profile_share,
+                record_profile_visitor, update_link_insight_sources, update_link_insights,
+                update_user_insight_sources, update_user_insights,
+            },
+            link::delete_link_by_id,
             mock_connection,
         },
-        models::insights::UpdateUserInsight,
-        tests::{create_mock_user, delete_mock_user},
+        models::insights::{
+            Increment, InsertProfileShare, InsertProfileVisitor, InsightGranularity,
+            InsightSourceType, InsightWindow, UpdateLinkInsight, UpdateLinkInsightSource,
+            UpdateUserInsight, UpdateUserInsightSource,
+        },
+        routes::{
+            insights::export::InsightExportFormat, links::get::redirect_link,
+            profiles::get::get_profile,
+        },
+        tests::{
+            create_mock_app, create_mock_link, create_mock_state, create_mock_user,
+            delete_mock_user,
+        },
     };
+    use tide::http::{Method, Request, Response, Url};
 
     #[tokio::test]
     pub async fn it_can_update_user_insights() {
@@ -20,6 +39,7 @@ pub mod tests {
         let insert_insight = UpdateUserInsight {
             user_id: user.id,
             view_count: Some(1),
+            unique_view_count: None,
             follow_count: None,
             unfollow_count: None,
             follow_request_count: None,
@@ -39,6 +59,7 @@ pub mod tests {
         let update_insight_with_same_user_id = UpdateUserInsight {
             user_id: user.id,
             view_count: Some(1),
+            unique_view_count: None,
             follow_count: None,
             unfollow_count: None,
             follow_request_count: None,
@@ -72,4 +93,619 @@ pub mod tests {
         // clean up
         delete_mock_user(user.id).await;
     }
+
+    #[tokio::test]
+    pub async fn it_can_update_link_insights() {
+        let user = create_mock_user().await;
+        let link = create_mock_link(user.id).await;
+        let other_link = create_mock_link(user.id).await;
+
+        let same_naive_date_time = Utc::now().naive_utc();
+        let mut conn = mock_connection().await;
+
+        // two clicks in the same bucket are merged
+        for _ in 0..2 {
+            let result = update_link_insights(
+                &mut conn,
+                UpdateLinkInsight::increment_click_count(link.id, same_naive_date_time),
+            )
+            .await;
+            assert!(result.is_ok(), "Failed to upsert link insights");
+        }
+        let result = update_link_insights(
+            &mut conn,
+            UpdateLinkInsight::increment_click_count(other_link.id, same_naive_date_time),
+        )
+        .await;
+        assert!(result.is_ok(), "Failed to upsert link insights");
+
+        let link_insights = get_link_insights(&mut conn, user.id).await;
+        assert!(link_insights.is_ok(), "Failed to get link insights");
+        let mut link_insights = link_insights.unwrap();
+        link_insights.sort_by_key(|insight| insight.link_id);
+        assert_eq!(link_insights.len(), 2, "Expected one bucket per link.");
+        assert_eq!(link_insights[0].link_id, link.id);
+        assert_eq!(link_insights[0].click_count, 2);
+        assert_eq!(link_insights[1].link_id, other_link.id);
+        assert_eq!(link_insights[1].click_count, 1);
+
+        // clean up
+        assert!(delete_link_by_id(&mut conn, link.id).await.unwrap());
+        assert!(delete_link_by_id(&mut conn, other_link.id).await.unwrap());
+        delete_mock_user(user.id).await;
+    }
+
+    fn mock_date_time(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2020, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    pub async fn it_buckets_insights_in_window() {
+        let user = create_mock_user().await;
+        let link = create_mock_link(user.id).await;
+        let unclicked_link = create_mock_link(user.id).await;
+        let mut conn = mock_connection().await;
+
+        // two hourly buckets on the first day, nothing on the second day, one view on the third
+        // and one view after the window
+        for created_at in [
+            mock_date_time(1, 10),
+            mock_date_time(1, 15),
+            mock_date_time(3, 0),
+            mock_date_time(4, 0),
+        ] {
+            update_user_insights(
+                &mut conn,
+                UpdateUserInsight::increment_view_count(user.id, created_at),
+            )
+            .await
+            .unwrap();
+            update_link_insights(
+                &mut conn,
+                UpdateLinkInsight::increment_click_count(link.id, created_at),
+            )
+            .await
+            .unwrap();
+        }
+
+        let window = InsightWindow {
+            from: mock_date_time(1, 0),
+            to: mock_date_time(4, 0),
+            granularity: InsightGranularity::Day,
+        };
+
+        let user_insights = get_user_insights_in_window(&mut conn, user.id, window).await;
+        assert!(
+            user_insights.is_ok(),
+            "Failed to get user insights in window"
+        );
+        let user_insights = user_insights.unwrap();
+        assert_eq!(
+            user_insights
+                .iter()
+                .map(|insight| (insight.created_bucket, insight.view_count))
+                .collect::<Vec<(NaiveDateTime, i32)>>(),
+            vec![
+                (mock_date_time(1, 0), 2),
+                (mock_date_time(2, 0), 0),
+                (mock_date_time(3, 0), 1)
+            ],
+            "Views were not bucketed by day with zero filled gaps"
+        );
+
+        let link_insights = get_link_insights_in_window(&mut conn, user.id, window).await;
+        assert!(
+            link_insights.is_ok(),
+            "Failed to get link insights in window"
+        );
+        let link_insights = link_insights.unwrap();
+        assert_eq!(link_insights.len(), 6, "Expected a full series per link");
+        let clicks = |link_id: i32| {
+            link_insights
+                .iter()
+                .filter(|insight| insight.link_id == link_id)
+                .map(|insight| insight.click_count)
+                .collect::<Vec<i32>>()
+        };
+        assert_eq!(clicks(link.id), vec![2, 0, 1]);
+        assert_eq!(clicks(unclicked_link.id), vec![0, 0, 0]);
+
+        // a window starting mid day cuts off the views of that day before from
+        let window = InsightWindow {
+            from: mock_date_time(1, 12),
+            to: mock_date_time(4, 0),
+            granularity: InsightGranularity::Day,
+        };
+        let user_insights = get_user_insights_in_window(&mut conn, user.id, window)
+            .await
+            .unwrap();
+        assert_eq!(
+            user_insights
+                .iter()
+                .map(|insight| (insight.created_bucket, insight.view_count))
+                .collect::<Vec<(NaiveDateTime, i32)>>(),
+            vec![
+                (mock_date_time(1, 12), 1),
+                (mock_date_time(2, 0), 0),
+                (mock_date_time(3, 0), 1)
+            ],
+            "First bucket was not clamped to the start of the window"
+        );
+        let link_insights = get_link_insights_in_window(&mut conn, user.id, window)
+            .await
+            .unwrap();
+        let clicks = |link_id: i32| {
+            link_insights
+                .iter()
+                .filter(|insight| insight.link_id == link_id)
+                .map(|insight| insight.click_count)
+                .collect::<Vec<i32>>()
+        };
+        assert_eq!(clicks(link.id), vec![1, 0, 1]);
+
+        // clean up
+        assert!(delete_link_by_id(&mut conn, link.id).await.unwrap());
+        assert!(delete_link_by_id(&mut conn, unclicked_link.id)
+            .await
+            .unwrap());
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_pages_through_insight_history_for_export() {
+        let user = create_mock_user().await;
+        let mut conn = mock_connection().await;
+
+        for day in 1..=5 {
+            update_user_insights(
+                &mut conn,
+                UpdateUserInsight::increment_share_count(user.id, mock_date_time(day, 12)),
+            )
+            .await
+            .unwrap();
+        }
+
+        // read two buckets at a time until the history runs out
+        let mut buckets = vec![];
+        let mut after_bucket = None;
+        loop {
+            let page = get_user_insights_page(&mut conn, user.id, after_bucket, 2).await;
+            assert!(page.is_ok(), "Failed to get page of user insights");
+            let page = page.unwrap();
+            assert!(page.len() <= 2, "Page is larger than the page size");
+            if page.is_empty() {
+                break;
+            }
+            after_bucket = page.last().map(|insight| insight.created_bucket);
+            buckets.extend(page);
+        }
+        assert_eq!(
+            buckets
+                .iter()
+                .map(|insight| insight.created_bucket)
+                .collect::<Vec<NaiveDateTime>>(),
+            (1..=5)
+                .map(|day| mock_date_time(day, 12))
+                .collect::<Vec<NaiveDateTime>>(),
+            "Pages did not cover the history in order"
+        );
+
+        // csv rows follow the header columns
+        let csv = InsightExportFormat::Csv;
+        assert_eq!(
+            csv.row(&buckets[0], true).unwrap(),
+            format!("{},0,0,0,0,0,1,2020-01-01T12:00:00\n", user.id)
+        );
+        assert!(csv
+            .header()
+            .starts_with("user_id,view_count,unique_view_count,"));
+
+        // json rows concatenate into a valid array
+        let json = InsightExportFormat::Json;
+        let mut exported = json.header().to_string();
+        for (index, insight) in buckets.iter().enumerate() {
+            exported.push_str(&json.row(insight, index == 0).unwrap());
+        }
+        exported.push_str(json.footer());
+        let parsed = serde_json::from_str::<Vec<serde_json::Value>>(&exported);
+        assert!(parsed.is_ok(), "Exported json is not a valid array");
+        assert_eq!(parsed.unwrap().len(), 5);
+
+        // clean up
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_keeps_the_hour_of_a_window_starting_mid_hour() {
+        let user = create_mock_user().await;
+        let link = create_mock_link(user.id).await;
+        let mut conn = mock_connection().await;
+
+        // everything is stored in the 10:00 bucket, the window starts at 10:30
+        let created_at = mock_date_time(1, 10) + chrono::Duration::minutes(15);
+        update_user_insights(
+            &mut conn,
+            UpdateUserInsight::increment_view_count(user.id, created_at),
+        )
+        .await
+        .unwrap();
+        update_link_insights(
+            &mut conn,
+            UpdateLinkInsight::increment_click_count(link.id, created_at),
+        )
+        .await
+        .unwrap();
+        record_profile_visitor(
+            &mut conn,
+            InsertProfileVisitor {
+                user_id: user.id,
+                visitor_key: String::from("user:1"),
+                created_at,
+            },
+        )
+        .await
+        .unwrap();
+        update_user_insight_sources(
+            &mut conn,
+            vec![UpdateUserInsightSource::increment_view_count(
+                user.id,
+                InsightSourceType::Referrer,
+                String::from("twitter.com"),
+                created_at,
+            )],
+        )
+        .await
+        .unwrap();
+        update_link_insight_sources(
+            &mut conn,
+            UpdateLinkInsightSource::increment_click_count(link.id, String::from("qr"), created_at),
+        )
+        .await
+        .unwrap();
+
+        let window = InsightWindow {
+            from: mock_date_time(1, 10) + chrono::Duration::minutes(30),
+            to: mock_date_time(2, 0),
+            granularity: InsightGranularity::Day,
+        };
+
+        let user_insights = get_user_insights_in_window(&mut conn, user.id, window)
+            .await
+            .unwrap();
+        assert_eq!(
+            user_insights
+                .iter()
+                .map(|insight| (
+                    insight.created_bucket,
+                    insight.view_count,
+                    insight.unique_view_count
+                ))
+                .collect::<Vec<(NaiveDateTime, i32, i32)>>(),
+            vec![(mock_date_time(1, 10), 1, 1)],
+            "Views of the hour the window starts in were left out"
+        );
+        let link_insights = get_link_insights_in_window(&mut conn, user.id, window)
+            .await
+            .unwrap();
+        assert_eq!(
+            link_insights
+                .iter()
+                .map(|insight| insight.click_count)
+                .collect::<Vec<i32>>(),
+            vec![1],
+            "Clicks of the hour the window starts in were left out"
+        );
+        assert_eq!(
+            count_profile_visitors_in_window(&mut conn, user.id, window)
+                .await
+                .unwrap(),
+            1,
+            "Visitors of the hour the window starts in were left out"
+        );
+        let top_referrers = get_top_user_insight_sources(
+            &mut conn,
+            user.id,
+            InsightSourceType::Referrer,
+            window,
+            5,
+        )
+        .await
+        .unwrap();
+        assert_eq!(top_referrers.len(), 1);
+        assert_eq!(top_referrers[0].view_count, 1);
+        let top_link_sources = get_top_link_insight_sources(&mut conn, user.id, window, 5)
+            .await
+            .unwrap();
+        assert_eq!(top_link_sources.len(), 1);
+        assert_eq!(top_link_sources[0].click_count, 1);
+
+        // clean up
+        assert!(delete_link_by_id(&mut conn, link.id).await.unwrap());
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_counts_each_visitor_once_per_bucket() {
+        let user = create_mock_user().await;
+        let mut conn = mock_connection().await;
+
+        let visit = |visitor_key: &str, created_at: NaiveDateTime| InsertProfileVisitor {
+            user_id: user.id,
+            visitor_key: visitor_key.to_owned(),
+            created_at,
+        };
+
+        // repeated views by the same visitor in the same hour are not unique
+        let first = record_profile_visitor(&mut conn, visit("user:1", mock_date_time(1, 10))).await;
+        assert!(first.is_ok(), "Failed to record profile visitor");
+        assert!(first.unwrap(), "First visit was not unique");
+        let refresh = record_profile_visitor(&mut conn, visit("user:1", mock_date_time(1, 10)))
+            .await
+            .unwrap();
+        assert!(!refresh, "Repeated visit was counted as unique");
+        let other = record_profile_visitor(&mut conn, visit("user:2", mock_date_time(1, 10)))
+            .await
+            .unwrap();
+        assert!(other, "Different visitor was not unique");
+
+        // the same visitor counts again in the next bucket
+        let next_hour = record_profile_visitor(&mut conn, visit("user:1", mock_date_time(1, 11)))
+            .await
+            .unwrap();
+        assert!(next_hour, "Visit in the next bucket was not unique");
+
+        // over a day the returning visitor is only counted once
+        let window = InsightWindow {
+            from: mock_date_time(1, 0),
+            to: mock_date_time(2, 0),
+            granularity: InsightGranularity::Day,
+        };
+        let total = count_profile_visitors_in_window(&mut conn, user.id, window).await;
+        assert!(total.is_ok(), "Failed to count profile visitors in window");
+        assert_eq!(total.unwrap(), 2, "Returning visitor was counted twice");
+        let user_insights = get_user_insights_in_window(&mut conn, user.id, window)
+            .await
+            .unwrap();
+        assert_eq!(
+            user_insights
+                .iter()
+                .map(|insight| insight.unique_view_count)
+                .collect::<Vec<i32>>(),
+            vec![2],
+            "Returning visitor was counted twice in the day bucket"
+        );
+
+        // clean up
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_ranks_top_view_sources_in_window() {
+        let user = create_mock_user().await;
+        let mut conn = mock_connection().await;
+
+        // twitter twice, github once and a country view in the window, reddit after the window
+        for (source_type, source, created_at) in [
+            (
+                InsightSourceType::Referrer,
+                "twitter.com",
+                mock_date_time(1, 10),
+            ),
+            (
+                InsightSourceType::Referrer,
+                "twitter.com",
+                mock_date_time(2, 10),
+            ),
+            (
+                InsightSourceType::Referrer,
+                "github.com",
+                mock_date_time(1, 10),
+            ),
+            (InsightSourceType::Country, "AU", mock_date_time(1, 10)),
+            (
+                InsightSourceType::Referrer,
+                "reddit.com",
+                mock_date_time(5, 10),
+            ),
+        ] {
+            let result = update_user_insight_sources(
+                &mut conn,
+                vec![UpdateUserInsightSource::increment_view_count(
+                    user.id,
+                    source_type,
+                    source.to_owned(),
+                    created_at,
+                )],
+            )
+            .await;
+            assert!(result.is_ok(), "Failed to update view sources");
+        }
+
+        let window = InsightWindow {
+            from: mock_date_time(1, 0),
+            to: mock_date_time(4, 0),
+            granularity: InsightGranularity::Day,
+        };
+        let top_referrers = get_top_user_insight_sources(
+            &mut conn,
+            user.id,
+            InsightSourceType::Referrer,
+            window,
+            5,
+        )
+        .await;
+        assert!(top_referrers.is_ok(), "Failed to get top referrers");
+        assert_eq!(
+            top_referrers
+                .unwrap()
+                .into_iter()
+                .map(|source| (source.source, source.view_count))
+                .collect::<Vec<(String, i64)>>(),
+            vec![
+                (String::from("twitter.com"), 2),
+                (String::from("github.com"), 1)
+            ],
+            "Referrers were not ranked by views in the window"
+        );
+
+        // limit is applied after ranking
+        let top_referrer = get_top_user_insight_sources(
+            &mut conn,
+            user.id,
+            InsightSourceType::Referrer,
+            window,
+            1,
+        )
+        .await
+        .unwrap();
+        assert_eq!(top_referrer.len(), 1);
+        assert_eq!(top_referrer[0].source, "twitter.com");
+
+        let top_countries =
+            get_top_user_insight_sources(&mut conn, user.id, InsightSourceType::Country, window, 5)
+                .await
+                .unwrap();
+        assert_eq!(top_countries.len(), 1);
+        assert_eq!(top_countries[0].source, "AU");
+
+        // clean up
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_rate_limits_shares_per_viewer() {
+        let user = create_mock_user().await;
+        let mut conn = mock_connection().await;
+
+        let share = |channel: &str, created_at: NaiveDateTime| InsertProfileShare {
+            user_id: user.id,
+            visitor_key: String::from("user:1"),
+            channel: channel.to_owned(),
+            created_at,
+        };
+
+        let first = record_profile_share(&mut conn, share("twitter", mock_date_time(1, 10))).await;
+        assert!(first.is_ok(), "Failed to record profile share");
+        assert!(first.unwrap(), "First share was not recorded");
+
+        // sharing again through the same channel in the same bucket is not counted
+        let repeated = record_profile_share(&mut conn, share("twitter", mock_date_time(1, 10)))
+            .await
+            .unwrap();
+        assert!(!repeated, "Repeated share was recorded");
+
+        // neither are shares through other channels in the same bucket
+        let other_channel = record_profile_share(&mut conn, share("qr", mock_date_time(1, 10)))
+            .await
+            .unwrap();
+        assert!(!other_channel, "Share through another channel was recorded");
+
+        // later buckets are counted
+        let next_hour = record_profile_share(&mut conn, share("twitter", mock_date_time(1, 11)))
+            .await
+            .unwrap();
+        assert!(next_hour, "Share in the next bucket was not recorded");
+
+        // clean up
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_records_referrer_passed_on_by_frontend() {
+        let mut conn = mock_connection().await;
+        let owner = create_mock_user().await;
+        let viewer = create_mock_user().await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = create_mock_app(create_mock_state(dir.path()), &viewer);
+        app.at("/profiles/:username").get(get_profile);
+
+        // the api request itself is always referred by the frontend
+        let url = Url::parse(&format!(
+            "http://localhost/profiles/{}?ref={}",
+            owner.username, "https%3A%2F%2Fwww.example.org%2Fpost"
+        ))
+        .unwrap();
+        let mut req = Request::new(Method::Get, url);
+        req.insert_header("Referer", "http://localhost:3000/search/users");
+        let res: Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), 200);
+
+        let now = Utc::now().naive_utc();
+        let window = InsightWindow {
+            from: now - chrono::Duration::days(1),
+            to: now + chrono::Duration::days(1),
+            granularity: InsightGranularity::Day,
+        };
+        let top_referrers = get_top_user_insight_sources(
+            &mut conn,
+            owner.id,
+            InsightSourceType::Referrer,
+            window,
+            5,
+        )
+        .await
+        .unwrap();
+        assert_eq!(top_referrers.len(), 1);
+        assert_eq!(top_referrers[0].source, "example.org");
+
+        delete_mock_user(owner.id).await;
+        delete_mock_user(viewer.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_attributes_shared_link_clicks_to_the_link() {
+        let mut conn = mock_connection().await;
+        let owner = create_mock_user().await;
+        let viewer = create_mock_user().await;
+        let link = create_mock_link(owner.id).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = create_mock_app(create_mock_state(dir.path()), &viewer);
+        app.at("/r/:link_id").get(redirect_link);
+
+        // a scan of the qr code of the link
+        let url = Url::parse(&format!("http://localhost/r/{}?src=qr", link.id)).unwrap();
+        let res: Response = app.respond(Request::new(Method::Get, url)).await.unwrap();
+        assert_eq!(res.status(), 302);
+
+        let now = Utc::now().naive_utc();
+        let window = InsightWindow {
+            from: now - chrono::Duration::days(1),
+            to: now + chrono::Duration::days(1),
+            granularity: InsightGranularity::Day,
+        };
+        let top_link_sources = get_top_link_insight_sources(&mut conn, owner.id, window, 5)
+            .await
+            .unwrap();
+        assert_eq!(
+            top_link_sources
+                .into_iter()
+                .map(|source| (source.link_id, source.source, source.click_count))
+                .collect::<Vec<(i32, String, i64)>>(),
+            vec![(link.id, String::from("qr"), 1)],
+            "Click was not attributed to the link"
+        );
+
+        // the click is not a profile view
+        let top_referrers = get_top_user_insight_sources(
+            &mut conn,
+            owner.id,
+            InsightSourceType::Referrer,
+            window,
+            5,
+        )
+        .await
+        .unwrap();
+        assert!(
+            top_referrers.is_empty(),
+            "Link click was counted as a profile referrer"
+        );
+
+        // clean up
+        assert!(delete_link_by_id(&mut conn, link.id).await.unwrap());
+        delete_mock_user(owner.id).await;
+        delete_mock_user(viewer.id).await;
+    }
 }
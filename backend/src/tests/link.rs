@@ -1,14 +1,25 @@
 #[cfg(test)]
 mod link_tests {
 
-    use crate::models::links::InsertLink;
+    use chrono::NaiveDate;
+    use serde_json::{json, Value};
+    use tide::http::{Method, Request, Response, Url};
+
+    use crate::models::{
+        images::GetImage,
+        links::{GetLink, InsertLink, LinkBatchOperation, LinkRef, LinkStatus, UpdateLink},
+    };
+    use crate::routes::links::{create::add_link, get::redirect_link};
+    use crate::types::error::{AssociationErrors, Error};
 
     use crate::connectors::db;
     // NOTE: execute 'diesel migration run' before unit tests to ensure the tables are loaded into psql
     // before running the unit tests.
 
     use crate::connectors::db::mock_connection;
-    use crate::tests::{create_mock_link, create_mock_user, delete_mock_user};
+    use crate::tests::{
+        create_mock_app, create_mock_link, create_mock_state, create_mock_user, delete_mock_user,
+    };
 
     #[tokio::test]
     pub async fn it_creates_link_in_db() {
@@ -17,10 +28,12 @@ mod link_tests {
         let user = create_mock_user().await;
         let link = InsertLink {
             user_id: user.id,
-            next_id: None,
             description: None,
             title: None,
             href: "http://test-mock.com".to_string(),
+            visible_from: None,
+            visible_until: None,
+            section_id: None,
         };
         let link = db::link::create(&mut conn, &link).await;
         assert!(link.is_ok());
@@ -65,4 +78,329 @@ mod link_tests {
             .unwrap());
         delete_mock_user(user.id).await;
     }
+
+    #[tokio::test]
+    pub async fn it_updates_only_provided_link_fields() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let mock_link = create_mock_link(user.id).await;
+
+        let update_link = UpdateLink {
+            user_id: None,
+            description: Some("new description".to_string()),
+            title: Some("new title".to_string()),
+            href: None,
+            visible_from: None,
+            visible_until: None,
+            section_id: None,
+        };
+        let link = db::link::update_link_by_id(&mut conn, &update_link, mock_link.id).await;
+        assert!(link.is_ok());
+        let link = link.unwrap();
+        assert_eq!(link.id, mock_link.id);
+        assert_eq!(link.title, Some("new title".to_string()));
+        assert_eq!(link.description, Some("new description".to_string()));
+        // href is left untouched
+        assert_eq!(link.href, mock_link.href);
+
+        assert!(db::link::delete_link_by_id(&mut conn, mock_link.id)
+            .await
+            .unwrap());
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_sets_and_clears_link_visibility_window() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let mock_link = create_mock_link(user.id).await;
+
+        let visible_from = NaiveDate::from_ymd_opt(2020, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let visible_until = NaiveDate::from_ymd_opt(2020, 1, 8)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let mut update_link = UpdateLink {
+            user_id: None,
+            description: None,
+            title: None,
+            href: None,
+            visible_from: Some(Some(visible_from)),
+            visible_until: Some(Some(visible_until)),
+            section_id: None,
+        };
+        let link = db::link::update_link_by_id(&mut conn, &update_link, mock_link.id)
+            .await
+            .unwrap();
+        assert_eq!(link.visible_from, Some(visible_from));
+        assert_eq!(link.visible_until, Some(visible_until));
+        assert_eq!(
+            link.status_at(visible_from - chrono::Duration::seconds(1)),
+            LinkStatus::Scheduled
+        );
+        assert_eq!(link.status_at(visible_from), LinkStatus::Visible);
+        assert_eq!(link.status_at(visible_until), LinkStatus::Expired);
+
+        // an empty window is rejected by the db
+        update_link.visible_from = Some(Some(visible_until));
+        assert!(
+            db::link::update_link_by_id(&mut conn, &update_link, mock_link.id)
+                .await
+                .is_err()
+        );
+
+        // null clears a bound and leaves the other one
+        update_link.visible_from = Some(None);
+        update_link.visible_until = None;
+        let link = db::link::update_link_by_id(&mut conn, &update_link, mock_link.id)
+            .await
+            .unwrap();
+        assert_eq!(link.visible_from, None);
+        assert_eq!(link.visible_until, Some(visible_until));
+
+        assert!(db::link::delete_link_by_id(&mut conn, mock_link.id)
+            .await
+            .unwrap());
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_orders_links_by_position() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let first = create_mock_link(user.id).await;
+        let second = create_mock_link(user.id).await;
+        let third = create_mock_link(user.id).await;
+
+        // new links are appended
+        assert!(first.position < second.position);
+        assert!(second.position < third.position);
+
+        let get_order = |links: Vec<(GetLink, Option<GetImage>)>| {
+            links.iter().map(|link| link.0.id).collect::<Vec<i32>>()
+        };
+
+        // moves in front of the given link
+        db::link::reorder_link(&mut conn, third.id, Some(first.id))
+            .await
+            .unwrap();
+        let links = db::link::get_user_links_by_id(&mut conn, user.id)
+            .await
+            .unwrap();
+        assert_eq!(get_order(links), [third.id, first.id, second.id]);
+
+        // moves to the end
+        db::link::reorder_link(&mut conn, first.id, None)
+            .await
+            .unwrap();
+        let links = db::link::get_user_links_by_id(&mut conn, user.id)
+            .await
+            .unwrap();
+        assert_eq!(get_order(links), [third.id, second.id, first.id]);
+
+        // links of other users cannot be used as a position
+        let other_user = create_mock_user().await;
+        let other_link = create_mock_link(other_user.id).await;
+        assert!(
+            db::link::reorder_link(&mut conn, first.id, Some(other_link.id))
+                .await
+                .is_err()
+        );
+
+        for link in [first, second, third, other_link] {
+            assert!(db::link::delete_link_by_id(&mut conn, link.id)
+                .await
+                .unwrap());
+        }
+        delete_mock_user(user.id).await;
+        delete_mock_user(other_user.id).await;
+    }
+
+    fn mock_insert_link(user_id: i32, title: &str) -> InsertLink {
+        InsertLink {
+            user_id,
+            description: None,
+            title: Some(title.to_string()),
+            href: "http://test-mock.com".to_string(),
+            visible_from: None,
+            visible_until: None,
+            section_id: None,
+        }
+    }
+
+    #[tokio::test]
+    pub async fn it_applies_link_batch_in_order() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let existing = create_mock_link(user.id).await;
+        let deleted = create_mock_link(user.id).await;
+
+        let operations = [
+            LinkBatchOperation::Create {
+                temp_id: Some("a".to_string()),
+                insert_link: mock_insert_link(user.id, "a"),
+            },
+            LinkBatchOperation::Create {
+                temp_id: Some("b".to_string()),
+                insert_link: mock_insert_link(user.id, "b"),
+            },
+            // temporary ids can be used by later operations
+            LinkBatchOperation::Move {
+                link_ref: LinkRef::Temp("b".to_string()),
+                new_position: Some(LinkRef::Id(existing.id)),
+            },
+            LinkBatchOperation::Update {
+                link_ref: LinkRef::Temp("a".to_string()),
+                update_link: UpdateLink {
+                    user_id: None,
+                    description: None,
+                    title: Some("renamed".to_string()),
+                    href: None,
+                    visible_from: None,
+                    visible_until: None,
+                    section_id: None,
+                },
+            },
+            LinkBatchOperation::Delete {
+                link_ref: LinkRef::Id(deleted.id),
+            },
+        ];
+        let result = db::link::apply_link_batch(&mut conn, user.id, &operations).await;
+        assert!(result.is_ok());
+        let result = result.unwrap();
+        assert_eq!(result.temp_ids.len(), 2);
+        let a = result.temp_ids["a"];
+        let b = result.temp_ids["b"];
+
+        let links = db::link::get_user_link_order(&mut conn, user.id)
+            .await
+            .unwrap();
+        assert_eq!(
+            links.iter().map(|link| link.id).collect::<Vec<i32>>(),
+            [b, existing.id, a]
+        );
+        assert_eq!(links[2].title, Some("renamed".to_string()));
+
+        for link_id in [a, b, existing.id] {
+            assert!(db::link::delete_link_by_id(&mut conn, link_id)
+                .await
+                .unwrap());
+        }
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_rolls_back_failed_link_batch() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let other_user = create_mock_user().await;
+        let other_link = create_mock_link(other_user.id).await;
+
+        let operations = [
+            LinkBatchOperation::Create {
+                temp_id: Some("a".to_string()),
+                insert_link: mock_insert_link(user.id, "a"),
+            },
+            LinkBatchOperation::Delete {
+                link_ref: LinkRef::Id(other_link.id),
+            },
+        ];
+        let result = db::link::apply_link_batch(&mut conn, user.id, &operations).await;
+        assert!(matches!(
+            result,
+            Err(Error::BatchOperationError(1, ref e))
+                if matches!(**e, Error::AssociationError(AssociationErrors::LinkDoesNotBelongToUser))
+        ));
+
+        // unknown temporary ids fail too
+        let operations = [LinkBatchOperation::Delete {
+            link_ref: LinkRef::Temp("missing".to_string()),
+        }];
+        let result = db::link::apply_link_batch(&mut conn, user.id, &operations).await;
+        assert!(matches!(result, Err(Error::BatchOperationError(0, _))));
+
+        // nothing was created and the other link is still there
+        assert!(db::link::get_user_link_order(&mut conn, user.id)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(db::link::get_link_by_id(&mut conn, other_link.id)
+            .await
+            .is_ok());
+
+        assert!(db::link::delete_link_by_id(&mut conn, other_link.id)
+            .await
+            .unwrap());
+        delete_mock_user(user.id).await;
+        delete_mock_user(other_user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_redirects_legacy_schemeless_hrefs_to_https() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        // inserted directly, like hrefs stored before they were validated
+        let link = db::link::create(
+            &mut conn,
+            &InsertLink {
+                href: "example.com/about".to_string(),
+                ..mock_insert_link(user.id, "legacy")
+            },
+        )
+        .await
+        .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = create_mock_app(create_mock_state(dir.path()), &user);
+        app.at("/r/:link_id").get(redirect_link);
+        let url = Url::parse(&format!("http://localhost/r/{}", link.id)).unwrap();
+        let res: Response = app.respond(Request::new(Method::Get, url)).await.unwrap();
+        assert_eq!(res.status(), 302);
+        assert_eq!(
+            res.header("Location").unwrap().as_str(),
+            "https://example.com/about"
+        );
+
+        assert!(db::link::delete_link_by_id(&mut conn, link.id)
+            .await
+            .unwrap());
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_rejects_too_long_link_title_and_bio() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = create_mock_app(create_mock_state(dir.path()), &user);
+        app.at("/links").post(add_link);
+
+        for (body, err) in [
+            (
+                json!({ "title": "a".repeat(101), "href": "https://example.com" }),
+                "Title must be at most 100 characters",
+            ),
+            (
+                json!({ "bio": "a".repeat(301), "href": "https://example.com" }),
+                "Description must be at most 300 characters",
+            ),
+        ] {
+            let url = Url::parse("http://localhost/links").unwrap();
+            let mut req = Request::new(Method::Post, url);
+            req.set_body(body);
+            let mut res: Response = app.respond(req).await.unwrap();
+            assert_eq!(res.status(), 400);
+            let body: Value = res.body_json().await.unwrap();
+            assert!(body["err"].as_str().unwrap().contains(err));
+        }
+        assert!(db::link::get_user_link_order(&mut conn, user.id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        delete_mock_user(user.id).await;
+    }
 }
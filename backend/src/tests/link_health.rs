@@ -0,0 +1,182 @@
+#[cfg(test)]
+mod link_health_tests {
+    use chrono::{Duration, Utc};
+
+    use crate::connectors::db;
+    use crate::connectors::db::mock_connection;
+    use crate::connectors::health::fixture::FixtureLinkProber;
+    use crate::helpers::notifications::BROKEN_LINK_NOTIFICATION_TYPE;
+    use crate::jobs::link_health::check_links_health;
+    use crate::models::health::LinkHealthStatus;
+    use crate::models::links::UpdateLink;
+    use crate::tests::{create_mock_link, create_mock_pool, create_mock_user, delete_mock_user};
+
+    #[tokio::test]
+    pub async fn it_notifies_owner_once_after_consecutive_failures() {
+        let mut conn = mock_connection().await;
+        let pool = create_mock_pool();
+        let user = create_mock_user().await;
+        let link = create_mock_link(user.id).await;
+        let links = vec![link.clone()];
+        let now = Utc::now().naive_utc();
+
+        // no response at all
+        let failing_prober = FixtureLinkProber::new();
+        for (check, expected_notified) in [0, 0, 1, 0].into_iter().enumerate() {
+            let report = check_links_health(
+                &pool,
+                &failing_prober,
+                &links,
+                3,
+                now + Duration::hours(check as i64),
+            )
+            .await
+            .unwrap();
+            assert_eq!(report.links_checked, 1);
+            assert_eq!(report.links_failed, 1);
+            assert_eq!(report.owners_notified, expected_notified);
+        }
+
+        let notifications = db::notifications::get_notifications_by_uid(&mut conn, user.id)
+            .await
+            .unwrap();
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(
+            notifications[0].notification_type,
+            BROKEN_LINK_NOTIFICATION_TYPE
+        );
+
+        let health = db::health::get_user_link_health(&mut conn, user.id)
+            .await
+            .unwrap();
+        assert_eq!(health.len(), 1);
+        assert_eq!(health[0].consecutive_failures, 4);
+        assert_eq!(health[0].status_code, None);
+        assert_eq!(health[0].badge(3).status, LinkHealthStatus::Broken);
+
+        // a healthy check resets the failures
+        let healthy_prober = FixtureLinkProber::new().with_status("http://test-mock.com/", 200);
+        let report =
+            check_links_health(&pool, &healthy_prober, &links, 3, now + Duration::hours(4))
+                .await
+                .unwrap();
+        assert_eq!(report.links_failed, 0);
+        let health = db::health::get_user_link_health(&mut conn, user.id)
+            .await
+            .unwrap();
+        assert_eq!(health[0].consecutive_failures, 0);
+        assert_eq!(health[0].status_code, Some(200));
+        assert!(!health[0].is_notified);
+        assert_eq!(health[0].badge(3).status, LinkHealthStatus::Healthy);
+
+        db::notifications::clear_notifications(&mut conn, user.id)
+            .await
+            .unwrap();
+        db::link::delete_link_by_id(&mut conn, link.id)
+            .await
+            .unwrap();
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_counts_error_statuses_as_failures() {
+        let mut conn = mock_connection().await;
+        let pool = create_mock_pool();
+        let user = create_mock_user().await;
+        let link = create_mock_link(user.id).await;
+        let now = Utc::now().naive_utc();
+
+        let prober = FixtureLinkProber::new().with_status("http://test-mock.com/", 404);
+        let report = check_links_health(&pool, &prober, std::slice::from_ref(&link), 3, now)
+            .await
+            .unwrap();
+        assert_eq!(report.links_failed, 1);
+        let health = db::health::get_user_link_health(&mut conn, user.id)
+            .await
+            .unwrap();
+        assert_eq!(health[0].status_code, Some(404));
+        assert_eq!(health[0].badge(3).status, LinkHealthStatus::Failing);
+
+        // checked links are not due again until the recheck window has passed
+        let due = db::health::get_links_due_for_check(&mut conn, now, i64::MAX)
+            .await
+            .unwrap();
+        assert!(due.iter().all(|due_link| due_link.id != link.id));
+        let due =
+            db::health::get_links_due_for_check(&mut conn, now + Duration::hours(1), i64::MAX)
+                .await
+                .unwrap();
+        assert!(due.iter().any(|due_link| due_link.id == link.id));
+
+        db::link::delete_link_by_id(&mut conn, link.id)
+            .await
+            .unwrap();
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_resets_health_when_href_changes() {
+        let mut conn = mock_connection().await;
+        let pool = create_mock_pool();
+        let user = create_mock_user().await;
+        let link = create_mock_link(user.id).await;
+        let now = Utc::now().naive_utc();
+
+        let report = check_links_health(
+            &pool,
+            &FixtureLinkProber::new(),
+            std::slice::from_ref(&link),
+            3,
+            now,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.links_failed, 1);
+
+        // the failures of the old href do not count against the new one
+        let update_link = UpdateLink {
+            user_id: None,
+            description: None,
+            title: None,
+            href: Some("https://test-mock.com/new".to_string()),
+            visible_from: None,
+            visible_until: None,
+            section_id: None,
+        };
+        let link = db::link::update_link_by_id(&mut conn, &update_link, link.id)
+            .await
+            .unwrap();
+        assert!(db::health::get_user_link_health(&mut conn, user.id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // links that are not web urls are not checked at all
+        let update_link = UpdateLink {
+            href: Some("mailto:test@test-mock.com".to_string()),
+            ..update_link
+        };
+        let link = db::link::update_link_by_id(&mut conn, &update_link, link.id)
+            .await
+            .unwrap();
+        let report = check_links_health(
+            &pool,
+            &FixtureLinkProber::new(),
+            std::slice::from_ref(&link),
+            3,
+            now,
+        )
+        .await
+        .unwrap();
+        assert_eq!(report.links_checked, 0);
+        let due = db::health::get_links_due_for_check(&mut conn, now, i64::MAX)
+            .await
+            .unwrap();
+        assert!(due.iter().all(|due_link| due_link.id != link.id));
+
+        db::link::delete_link_by_id(&mut conn, link.id)
+            .await
+            .unwrap();
+        delete_mock_user(user.id).await;
+    }
+}
@@ -0,0 +1,181 @@
+#[cfg(test)]
+mod link_order_tests {
+    use diesel::prelude::*;
+    use diesel::PgConnection;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::commands::check_links::{scan_link_order, LinkOrderReport};
+    use crate::connectors::db;
+    use crate::connectors::db::mock_connection;
+    use crate::helpers::links::{find_link_order_issues, LinkOrderIssue};
+    use crate::models::sections::InsertLinkSection;
+    use crate::tests::{create_mock_link, create_mock_user, delete_mock_user};
+
+    // the stored order has to match the expected one and be strictly increasing
+    async fn assert_link_order(conn: &mut PgConnection, user_id: i32, expected: &[i32]) {
+        let links = db::link::get_user_link_order(conn, user_id).await.unwrap();
+        assert_eq!(
+            links.iter().map(|link| link.id).collect::<Vec<i32>>(),
+            expected
+        );
+        assert!(links
+            .windows(2)
+            .all(|pair| pair[0].position < pair[1].position));
+    }
+
+    async fn delete_links(conn: &mut PgConnection, link_ids: &[i32]) {
+        for link_id in link_ids {
+            assert!(db::link::delete_link_by_id(conn, *link_id).await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    pub async fn it_keeps_link_order_valid_under_random_changes() {
+        let mut conn = mock_connection().await;
+
+        for seed in 0..4 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let user = create_mock_user().await;
+            let mut expected = Vec::<i32>::new();
+            for _ in 0..6 {
+                expected.push(create_mock_link(user.id).await.id);
+            }
+
+            for _ in 0..60 {
+                match rng.gen_range(0..10) {
+                    0 if !expected.is_empty() => {
+                        let link_id = expected.remove(rng.gen_range(0..expected.len()));
+                        delete_links(&mut conn, &[link_id]).await;
+                    }
+                    1 => expected.push(create_mock_link(user.id).await.id),
+                    _ if expected.len() > 1 => {
+                        let link_id = expected.remove(rng.gen_range(0..expected.len()));
+                        // a missing position moves the link to the end
+                        let new_position_id = if rng.gen_bool(0.2) {
+                            None
+                        } else {
+                            Some(expected[rng.gen_range(0..expected.len())])
+                        };
+                        db::link::reorder_link(&mut conn, link_id, new_position_id)
+                            .await
+                            .unwrap();
+                        match new_position_id.and_then(|id| expected.iter().position(|&x| x == id))
+                        {
+                            Some(index) => expected.insert(index, link_id),
+                            None => expected.push(link_id),
+                        }
+                    }
+                    _ => (),
+                }
+                assert_link_order(&mut conn, user.id, &expected).await;
+            }
+
+            delete_links(&mut conn, &expected).await;
+            delete_mock_user(user.id).await;
+        }
+    }
+
+    #[tokio::test]
+    pub async fn it_rebalances_when_positions_run_out() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let first = create_mock_link(user.id).await.id;
+        let second = create_mock_link(user.id).await.id;
+        let third = create_mock_link(user.id).await.id;
+
+        // every move halves the gap in front of the third link
+        for i in 0..40 {
+            let (moved, other) = if i % 2 == 0 {
+                (first, second)
+            } else {
+                (second, first)
+            };
+            db::link::reorder_link(&mut conn, moved, Some(third))
+                .await
+                .unwrap();
+            assert_link_order(&mut conn, user.id, &[other, moved, third]).await;
+        }
+
+        delete_links(&mut conn, &[first, second, third]).await;
+        delete_mock_user(user.id).await;
+    }
+
+    async fn get_link_issues(conn: &mut PgConnection, user_id: i32) -> Vec<LinkOrderIssue> {
+        let links = db::link::get_user_link_order(conn, user_id).await.unwrap();
+        let shown_link_ids = db::link::get_user_links_by_id(conn, user_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(link, _)| link.id)
+            .collect::<Vec<i32>>();
+        let section_ids = db::section::get_user_sections(conn, user_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|section| section.id)
+            .collect::<Vec<i32>>();
+        find_link_order_issues(&links, &shown_link_ids, &section_ids)
+    }
+
+    #[tokio::test]
+    pub async fn it_repairs_links_in_sections_of_other_users() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let other_user = create_mock_user().await;
+        let first = create_mock_link(user.id).await;
+        let second = create_mock_link(user.id).await;
+        let other_section = db::section::create_section(
+            &mut conn,
+            &InsertLinkSection {
+                user_id: other_user.id,
+                title: "other".to_string(),
+                is_collapsed: false,
+            },
+        )
+        .await
+        .unwrap();
+        assert!(get_link_issues(&mut conn, user.id).await.is_empty());
+
+        // the section foreign key does not allow this, rows from before it or restored
+        // without it can still look like this
+        diesel::sql_query("SET session_replication_role = replica")
+            .execute(&mut conn)
+            .unwrap();
+        {
+            use crate::schema::links::dsl::*;
+            diesel::update(links.filter(id.eq(second.id)))
+                .set(section_id.eq(other_section.id))
+                .execute(&mut conn)
+                .unwrap();
+        }
+        diesel::sql_query("SET session_replication_role = DEFAULT")
+            .execute(&mut conn)
+            .unwrap();
+        assert_eq!(
+            get_link_issues(&mut conn, user.id).await,
+            [LinkOrderIssue::ForeignSection {
+                link_id: second.id,
+                section_id: other_section.id
+            }]
+        );
+
+        // only the links of this user are checked and repaired
+        let report = scan_link_order(&mut conn, &[user.id], true).await.unwrap();
+        assert_eq!(
+            report,
+            LinkOrderReport {
+                users_checked: 1,
+                users_with_issues: 1,
+                users_repaired: 1,
+            }
+        );
+        assert!(get_link_issues(&mut conn, user.id).await.is_empty());
+        assert_link_order(&mut conn, user.id, &[first.id, second.id]).await;
+        let report = scan_link_order(&mut conn, &[user.id], true).await.unwrap();
+        assert_eq!(report.users_with_issues, 0);
+
+        delete_links(&mut conn, &[first.id, second.id]).await;
+        delete_mock_user(user.id).await;
+        delete_mock_user(other_user.id).await;
+    }
+}
@@ -1,18 +1,38 @@
 pub mod email;
+pub mod email_verification;
 pub mod follow;
+pub mod image_gc;
+pub mod images;
 pub mod insight;
 pub mod link;
+pub mod link_health;
+pub mod link_order;
 pub mod password_reset;
+pub mod rate_limit;
+pub mod section;
+pub mod session;
 pub mod testing;
+pub mod two_factor;
+pub mod uploads;
 
+use std::{env, path::Path, sync::Arc};
+
+use diesel::r2d2::{ConnectionManager, Pool};
 use random_string::generate;
+use tide::{sessions::SessionMiddleware, Next, Request};
 
 use crate::models::{
     links::{GetLink, InsertLink},
     users::{GetUser, InsertUser},
 };
 
-use crate::connectors::db;
+use crate::connectors::{
+    buckets::local::LocalObjectStore, db, geoip::database::GeoIpDatabase,
+    previews::fixture::FixturePageFetcher, smtp::email::EmailService,
+};
+use crate::helpers::visitors::parse_trusted_proxy;
+use crate::routes::auth::init_session;
+use crate::types::state::{TidePool, TideState};
 use diesel::prelude::*;
 // NOTE: execute 'diesel migration run' before unit tests to ensure the tables are loaded into psql
 // before running the unit tests.
@@ -48,10 +68,71 @@ pub async fn create_mock_link(user_id: i32) -> GetLink {
 
     let link = InsertLink {
         user_id,
-        next_id: None,
         description: None,
         title: None,
         href: "http://test-mock.com".to_string(),
+        visible_from: None,
+        visible_until: None,
+        section_id: None,
     };
     db::link::create(&mut conn, &link).await.unwrap()
 }
+
+pub fn create_mock_pool() -> TidePool {
+    dotenvy::dotenv().expect("No .env file found");
+    let database_url = env::var("DATABASE_URL").expect("No database url found");
+    Pool::builder()
+        .max_size(2)
+        .build(ConnectionManager::<PgConnection>::new(&database_url))
+        .expect("Failed to build connection pool")
+}
+
+// state of an app that stores its objects on disk in storage_dir
+pub fn create_mock_state(storage_dir: &Path) -> Arc<TideState> {
+    Arc::new(TideState {
+        tide_pool: create_mock_pool(),
+        object_store: Arc::new(LocalObjectStore::new(
+            storage_dir,
+            "http://localhost/storage",
+            "secret",
+        )),
+        tempdir: tempfile::tempdir().unwrap(),
+        email_service: EmailService::with_host(String::from("localhost")),
+        geoip: GeoIpDatabase::default(),
+        page_fetcher: Box::new(FixturePageFetcher::new()),
+        // the frontend server, as it is deployed next to the backend
+        trusted_proxies: vec![parse_trusted_proxy("10.0.0.0/8").unwrap()],
+    })
+}
+
+// logs every request in as the user
+struct MockSession {
+    user_id: i32,
+    username: String,
+}
+
+#[tide::utils::async_trait]
+impl tide::Middleware<Arc<TideState>> for MockSession {
+    async fn handle(
+        &self,
+        mut req: Request<Arc<TideState>>,
+        next: Next<'_, Arc<TideState>>,
+    ) -> tide::Result {
+        init_session(req.session_mut(), self.user_id, &self.username);
+        Ok(next.run(req).await)
+    }
+}
+
+// an app where every request is logged in as user
+pub fn create_mock_app(state: Arc<TideState>, user: &GetUser) -> tide::Server<Arc<TideState>> {
+    let mut app = tide::with_state(state);
+    app.with(SessionMiddleware::new(
+        tide::sessions::MemoryStore::new(),
+        b"a secret that is long enough for the session middleware",
+    ));
+    app.with(MockSession {
+        user_id: user.id,
+        username: user.username.clone(),
+    });
+    app
+}
@@ -0,0 +1,310 @@
+#[cfg(test)]
+mod rate_limit_tests {
+    use std::sync::Arc;
+
+    use chrono::{DateTime, Duration, NaiveDateTime};
+    use serde_json::{json, Value};
+    use tide::http::{Method, Request, Response, Url};
+    use tide::sessions::{MemoryStore, SessionMiddleware};
+
+    use crate::connectors::db;
+    use crate::connectors::db::mock_connection;
+    use crate::connectors::rate_limit::{
+        memory::MemoryRateLimitStore,
+        postgres::PostgresRateLimitStore,
+        store::{RateLimitEntry, RateLimitPolicy, RateLimitReservation, RateLimitStore},
+    };
+    use crate::middleware::rate_limit::{RateLimit, RateLimitKey};
+    use crate::models::users::{GetUser, UpdateUser};
+    use crate::routes::auth::login::login;
+    use crate::tests::{create_mock_state, create_mock_user, delete_mock_user};
+    use crate::types::state::TideState;
+
+    const PASSWORD: &str = "a12345678";
+
+    fn at(secs: i64) -> NaiveDateTime {
+        DateTime::from_timestamp(secs, 0).unwrap().naive_utc()
+    }
+
+    // one free failure, then a minute of waiting, locked out after the third
+    fn strict_policy() -> RateLimitPolicy {
+        RateLimitPolicy {
+            free_failures: 1,
+            base_delay: Duration::minutes(1),
+            max_delay: Duration::minutes(1),
+            lockout_failures: 3,
+            lockout_duration: Duration::minutes(15),
+            window: Duration::hours(1),
+        }
+    }
+
+    async fn create_user_with_password() -> GetUser {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let update_user = UpdateUser {
+            username: None,
+            password: Some(bcrypt::hash(PASSWORD, 4).unwrap()),
+            salt: None,
+            email: None,
+            is_private: None,
+            bio: None,
+            display_name: None,
+        };
+        db::user::update_user_by_id(&mut conn, user.id, &update_user)
+            .await
+            .unwrap();
+        user
+    }
+
+    async fn send_login_from(
+        app: &tide::Server<Arc<TideState>>,
+        peer: &str,
+        forwarded_for: &str,
+        username: &str,
+        password: &str,
+    ) -> Response {
+        let url = Url::parse("http://localhost/login").unwrap();
+        let mut req = Request::new(Method::Post, url);
+        req.set_peer_addr(Some(format!("{}:4000", peer)));
+        req.insert_header("x-forwarded-for", forwarded_for);
+        req.set_body(json!({ "username": username, "password": password }));
+        app.respond(req).await.unwrap()
+    }
+
+    async fn send_login(
+        app: &tide::Server<Arc<TideState>>,
+        ip: &str,
+        username: &str,
+        password: &str,
+    ) -> Response {
+        // only the peer address counts, the header is made up by the client
+        send_login_from(app, ip, "198.51.100.1", username, password).await
+    }
+
+    fn create_login_app(store: Arc<dyn RateLimitStore>) -> tide::Server<Arc<TideState>> {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = tide::with_state(create_mock_state(dir.path()));
+        app.with(SessionMiddleware::new(
+            MemoryStore::new(),
+            b"a secret that is long enough for the session middleware",
+        ));
+        app.at("/login")
+            .with(
+                RateLimit::new("login", store, strict_policy())
+                    .by(RateLimitKey::BodyField("username")),
+            )
+            .post(login);
+        app
+    }
+
+    #[tokio::test]
+    pub async fn it_limits_failed_logins_by_ip_and_username() {
+        let user = create_user_with_password().await;
+        let other_user = create_user_with_password().await;
+        let app = create_login_app(Arc::new(MemoryRateLimitStore::new()));
+
+        // the route still reads the body after the middleware
+        let mut res = send_login(&app, "203.0.113.1", &user.username, "wrong-password").await;
+        assert_eq!(res.status(), 400);
+        let body: Value = res.body_json().await.unwrap();
+        assert_eq!(body["err"], "Incorrect Password");
+        // a successful login forgets the failures of the username
+        let res = send_login(&app, "203.0.113.1", &user.username, PASSWORD).await;
+        assert_eq!(res.status(), 200);
+
+        for _ in 0..2 {
+            let res = send_login(&app, "203.0.113.2", &user.username, "wrong-password").await;
+            assert_eq!(res.status(), 400);
+        }
+        // the username has to wait, even with the right password and from another ip
+        let mut res = send_login(&app, "203.0.113.3", &user.username, PASSWORD).await;
+        assert_eq!(res.status(), 429);
+        let retry_after: u64 = res.header("retry-after").unwrap().as_str().parse().unwrap();
+        assert!(retry_after > 0 && retry_after <= 60);
+        let body: Value = res.body_json().await.unwrap();
+        assert_eq!(
+            body["err"],
+            format!("Too many attempts, try again in {} seconds", retry_after)
+        );
+        // and so does the ip, for every username
+        let res = send_login(&app, "203.0.113.2", &other_user.username, PASSWORD).await;
+        assert_eq!(res.status(), 429);
+        let res = send_login(&app, "203.0.113.3", &other_user.username, PASSWORD).await;
+        assert_eq!(res.status(), 200);
+
+        delete_mock_user(user.id).await;
+        delete_mock_user(other_user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_keeps_clients_behind_the_same_proxy_apart() {
+        let user = create_user_with_password().await;
+        let other_user = create_user_with_password().await;
+        let app = create_login_app(Arc::new(MemoryRateLimitStore::new()));
+
+        // both clients come through the frontend server, which is a trusted proxy
+        for _ in 0..2 {
+            let res = send_login_from(
+                &app,
+                "10.0.0.5",
+                "203.0.113.10",
+                &user.username,
+                "wrong-password",
+            )
+            .await;
+            assert_eq!(res.status(), 400);
+        }
+        let res = send_login_from(
+            &app,
+            "10.0.0.5",
+            "203.0.113.10",
+            &other_user.username,
+            PASSWORD,
+        )
+        .await;
+        assert_eq!(res.status(), 429);
+        // the other client is not locked out with the first one
+        let res = send_login_from(
+            &app,
+            "10.0.0.5",
+            "198.51.100.1, 203.0.113.11",
+            &other_user.username,
+            PASSWORD,
+        )
+        .await;
+        assert_eq!(res.status(), 200);
+
+        delete_mock_user(user.id).await;
+        delete_mock_user(other_user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_counts_failures_in_postgres() {
+        let mut conn = mock_connection().await;
+        let dir = tempfile::tempdir().unwrap();
+        let store = PostgresRateLimitStore::new(create_mock_state(dir.path()).tide_pool.clone());
+        let policy = strict_policy();
+        let key = format!("test-{}", uuid::Uuid::new_v4());
+
+        let now = at(1_000_000);
+        assert_eq!(
+            store.reserve(&key, &policy, now).await.unwrap(),
+            RateLimitReservation::Reserved(None)
+        );
+        let first = RateLimitEntry {
+            failures: 1,
+            last_failure_at: now,
+        };
+        assert_eq!(
+            store.reserve(&key, &policy, now).await.unwrap(),
+            RateLimitReservation::Reserved(Some(first))
+        );
+        // nothing is counted while the key has to wait
+        assert_eq!(
+            store.reserve(&key, &policy, now).await.unwrap(),
+            RateLimitReservation::Wait(Duration::minutes(1))
+        );
+        let second = store.get(&key).await.unwrap().unwrap();
+        assert_eq!(second.failures, 2);
+
+        let retry_at = now + Duration::minutes(1);
+        assert_eq!(
+            store.reserve(&key, &policy, retry_at).await.unwrap(),
+            RateLimitReservation::Reserved(Some(second.clone()))
+        );
+        let entry = store.get(&key).await.unwrap().unwrap();
+        assert_eq!(
+            entry.retry_after(&policy, retry_at),
+            Some(Duration::minutes(15))
+        );
+        // the attempt did not fail after all
+        store.undo(&key, Some(&second), retry_at).await.unwrap();
+        assert_eq!(store.get(&key).await.unwrap(), Some(second));
+
+        // failures older than the window are forgotten
+        let later = now + Duration::hours(2);
+        assert_eq!(
+            store.reserve(&key, &policy, later).await.unwrap(),
+            RateLimitReservation::Reserved(None)
+        );
+        assert_eq!(store.get(&key).await.unwrap().unwrap().failures, 1);
+        store.undo(&key, None, later).await.unwrap();
+        assert!(store.get(&key).await.unwrap().is_none());
+
+        store.reserve(&key, &policy, later).await.unwrap();
+        store.reset(&key).await.unwrap();
+        assert!(store.get(&key).await.unwrap().is_none());
+
+        store.reserve(&key, &policy, now).await.unwrap();
+        assert!(
+            store
+                .delete_stale(now + Duration::seconds(1))
+                .await
+                .unwrap()
+                >= 1
+        );
+        assert!(db::rate_limit::get_rate_limit(&mut conn, &key)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    // every attempt reserves before the route runs, so attempts at the same time
+    // cannot get past the free failures
+    async fn reserve_at_once(store: Arc<dyn RateLimitStore>, key: &str) -> usize {
+        let now = at(1_000_000);
+        let attempts = (0..8).map(|_| {
+            let store = store.clone();
+            let key = key.to_string();
+            tokio::spawn(async move { store.reserve(&key, &strict_policy(), now).await })
+        });
+        futures::future::join_all(attempts)
+            .await
+            .into_iter()
+            .map(|reservation| reservation.unwrap().unwrap())
+            .filter(|reservation| matches!(reservation, RateLimitReservation::Reserved(_)))
+            .count()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    pub async fn it_reserves_attempts_at_the_same_time_one_after_the_other() {
+        let key = format!("test-{}", uuid::Uuid::new_v4());
+        let memory: Arc<dyn RateLimitStore> = Arc::new(MemoryRateLimitStore::new());
+        assert_eq!(reserve_at_once(memory.clone(), &key).await, 2);
+        assert_eq!(memory.get(&key).await.unwrap().unwrap().failures, 2);
+
+        let dir = tempfile::tempdir().unwrap();
+        let postgres: Arc<dyn RateLimitStore> = Arc::new(PostgresRateLimitStore::new(
+            create_mock_state(dir.path()).tide_pool.clone(),
+        ));
+        assert_eq!(reserve_at_once(postgres.clone(), &key).await, 2);
+        assert_eq!(postgres.get(&key).await.unwrap().unwrap().failures, 2);
+        postgres.reset(&key).await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    pub async fn it_limits_failed_logins_sent_at_the_same_time() {
+        let user = create_user_with_password().await;
+        let app = Arc::new(create_login_app(Arc::new(MemoryRateLimitStore::new())));
+
+        let attempts = (0..8).map(|_| {
+            let app = app.clone();
+            let username = user.username.clone();
+            tokio::spawn(async move {
+                send_login(&app, "203.0.113.20", &username, "wrong-password")
+                    .await
+                    .status()
+            })
+        });
+        let statuses: Vec<u16> = futures::future::join_all(attempts)
+            .await
+            .into_iter()
+            .map(|status| status.unwrap() as u16)
+            .collect();
+        // only the free failure and the one after it reach the route
+        assert_eq!(statuses.iter().filter(|status| **status == 400).count(), 2);
+        assert_eq!(statuses.iter().filter(|status| **status == 429).count(), 6);
+
+        delete_mock_user(user.id).await;
+    }
+}
@@ -0,0 +1,152 @@
+#[cfg(test)]
+mod section_tests {
+    use crate::connectors::db;
+    use crate::connectors::db::mock_connection;
+    use crate::models::{
+        links::{LinkBatchOperation, LinkRef, UpdateLink},
+        sections::{InsertLinkSection, UpdateLinkSection},
+    };
+    use crate::tests::{create_mock_link, create_mock_user, delete_mock_user};
+    use crate::types::error::{AssociationErrors, Error};
+
+    fn mock_insert_section(user_id: i32, title: &str) -> InsertLinkSection {
+        InsertLinkSection {
+            user_id,
+            title: title.to_string(),
+            is_collapsed: false,
+        }
+    }
+
+    #[tokio::test]
+    pub async fn it_creates_updates_and_reorders_sections() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+
+        let mut section_ids = Vec::new();
+        for title in ["Music", "Videos", "Shop"] {
+            let section =
+                db::section::create_section(&mut conn, &mock_insert_section(user.id, title))
+                    .await
+                    .unwrap();
+            section_ids.push(section.id);
+        }
+
+        let update_section = UpdateLinkSection {
+            title: Some("Merch".to_string()),
+            is_collapsed: Some(true),
+        };
+        let section = db::section::update_section_by_id(&mut conn, &update_section, section_ids[2])
+            .await
+            .unwrap();
+        assert_eq!(section.title, "Merch");
+        assert!(section.is_collapsed);
+
+        // move the last section to the front, then the first one to the end
+        db::section::reorder_section(&mut conn, section_ids[2], Some(section_ids[0]))
+            .await
+            .unwrap();
+        db::section::reorder_section(&mut conn, section_ids[2], None)
+            .await
+            .unwrap();
+        db::section::reorder_section(&mut conn, section_ids[1], Some(section_ids[0]))
+            .await
+            .unwrap();
+        let sections = db::section::get_user_sections(&mut conn, user.id)
+            .await
+            .unwrap();
+        assert_eq!(
+            sections
+                .iter()
+                .map(|section| section.id)
+                .collect::<Vec<i32>>(),
+            vec![section_ids[1], section_ids[0], section_ids[2]]
+        );
+
+        // sections of other users are not owned
+        let other_user = create_mock_user().await;
+        assert!(
+            !db::section::section_id_belongs_to_user(&mut conn, section_ids[0], other_user.id)
+                .await
+                .unwrap()
+        );
+
+        delete_mock_user(other_user.id).await;
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_keeps_links_of_deleted_section() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let section =
+            db::section::create_section(&mut conn, &mock_insert_section(user.id, "Music"))
+                .await
+                .unwrap();
+        let mock_link = create_mock_link(user.id).await;
+
+        let update_link = UpdateLink {
+            user_id: None,
+            description: None,
+            title: None,
+            href: None,
+            visible_from: None,
+            visible_until: None,
+            section_id: Some(Some(section.id)),
+        };
+        let link = db::link::update_link_by_id(&mut conn, &update_link, mock_link.id)
+            .await
+            .unwrap();
+        assert_eq!(link.section_id, Some(section.id));
+
+        assert!(db::section::delete_section_by_id(&mut conn, section.id)
+            .await
+            .unwrap());
+        let link = db::link::get_link_by_id(&mut conn, mock_link.id)
+            .await
+            .unwrap();
+        assert_eq!(link.section_id, None);
+        assert_eq!(link.position, mock_link.position);
+
+        db::link::delete_link_by_id(&mut conn, mock_link.id)
+            .await
+            .unwrap();
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_rejects_foreign_section_in_link_batch() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let other_user = create_mock_user().await;
+        let section =
+            db::section::create_section(&mut conn, &mock_insert_section(other_user.id, "Music"))
+                .await
+                .unwrap();
+        let mock_link = create_mock_link(user.id).await;
+
+        let operations = vec![LinkBatchOperation::Update {
+            link_ref: LinkRef::Id(mock_link.id),
+            update_link: UpdateLink {
+                user_id: None,
+                description: None,
+                title: None,
+                href: None,
+                visible_from: None,
+                visible_until: None,
+                section_id: Some(Some(section.id)),
+            },
+        }];
+        let result = db::link::apply_link_batch(&mut conn, user.id, &operations).await;
+        assert!(matches!(
+            result,
+            Err(Error::BatchOperationError(0, e))
+                if matches!(*e, Error::AssociationError(AssociationErrors::SectionDoesNotBelongToUser))
+        ));
+
+        db::link::delete_link_by_id(&mut conn, mock_link.id)
+            .await
+            .unwrap();
+        delete_mock_user(other_user.id).await;
+        delete_mock_user(user.id).await;
+    }
+}
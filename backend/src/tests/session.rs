@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod session_tests {
+    use std::{env, time::Duration};
+
+    use async_session::{Session, SessionStore};
+    use chrono::Utc;
+    use diesel::r2d2::{ConnectionManager, Pool};
+    use diesel::PgConnection;
+    use dotenvy::dotenv;
+
+    use crate::connectors::sessions::postgres_store::PostgresSessionStore;
+
+    fn mock_store() -> PostgresSessionStore {
+        dotenv().expect("No .env file found");
+        let database_url = env::var("DATABASE_URL").expect("No database url found");
+        let pool = Pool::builder()
+            .max_size(1)
+            .build(ConnectionManager::<PgConnection>::new(database_url))
+            .expect("Failed to build connection pool");
+        PostgresSessionStore::new(pool)
+    }
+
+    #[tokio::test]
+    pub async fn it_stores_and_loads_session() {
+        let store = mock_store();
+        let mut session = Session::new();
+        session.insert("user_id", 1).unwrap();
+        let session_id = session.id().to_string();
+
+        let cookie_value = store.store_session(session).await.unwrap().unwrap();
+
+        let loaded = store.load_session(cookie_value.clone()).await.unwrap();
+        assert!(loaded.is_some(), "Stored session could not be loaded");
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.id(), session_id);
+        assert_eq!(loaded.get::<i32>("user_id"), Some(1));
+
+        // destroying removes it from the store
+        store.destroy_session(loaded).await.unwrap();
+        assert!(store.load_session(cookie_value).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    pub async fn it_updates_existing_session() {
+        let store = mock_store();
+        let mut session = Session::new();
+        session.insert("username", "before").unwrap();
+        let cookie_value = store.store_session(session).await.unwrap().unwrap();
+
+        let mut loaded = store
+            .load_session(cookie_value.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        loaded.insert("username", "after").unwrap();
+        store.store_session(loaded).await.unwrap();
+
+        let reloaded = store
+            .load_session(cookie_value.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reloaded.get::<String>("username").unwrap(), "after");
+
+        store.destroy_session(reloaded).await.unwrap();
+    }
+
+    #[tokio::test]
+    pub async fn it_does_not_load_or_keep_expired_session() {
+        let store = mock_store();
+        let mut session = Session::new();
+        session.set_expiry(Utc::now() - chrono::Duration::seconds(1));
+        let expired_cookie_value = store.store_session(session).await.unwrap().unwrap();
+
+        // expired sessions are never loaded
+        assert!(store
+            .load_session(expired_cookie_value)
+            .await
+            .unwrap()
+            .is_none());
+
+        let mut live_session = Session::new();
+        live_session.expire_in(Duration::from_secs(60));
+        let cookie_value = store.store_session(live_session).await.unwrap().unwrap();
+
+        // cleanup removes the expired session but keeps the live one
+        assert!(store.cleanup().await.unwrap() >= 1);
+        let live = store.load_session(cookie_value).await.unwrap();
+        assert!(live.is_some(), "Live session was removed by cleanup");
+
+        store.destroy_session(live.unwrap()).await.unwrap();
+    }
+}
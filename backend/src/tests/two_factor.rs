@@ -0,0 +1,399 @@
+#[cfg(test)]
+mod two_factor_tests {
+    use std::sync::Arc;
+
+    use chrono::{DateTime, NaiveDateTime, Utc};
+    use serde_json::{json, Value};
+    use tide::http::{Method, Request, Response, Url};
+    use tide::sessions::{MemoryStore, SessionMiddleware};
+
+    use crate::connectors::db;
+    use crate::connectors::db::mock_connection;
+    use crate::connectors::rate_limit::{
+        memory::MemoryRateLimitStore,
+        store::{RateLimitPolicy, RateLimitStore},
+    };
+    use crate::helpers::two_factor::{check_two_factor_code, hash_recovery_code, totp_code};
+    use crate::middleware::rate_limit::{RateLimit, RateLimitKey};
+    use crate::models::{
+        two_factor::{InsertRecoveryCode, InsertTwoFactor},
+        users::{GetUser, UpdateUser},
+    };
+    use crate::routes::auth::{
+        login::{is_logged_in, login},
+        two_factor::login_two_factor,
+    };
+    use crate::routes::settings::two_factor::{
+        confirm_two_factor, disable_two_factor, get_two_factor_status, regenerate_recovery_codes,
+        setup_two_factor,
+    };
+    use crate::tests::{create_mock_app, create_mock_state, create_mock_user, delete_mock_user};
+    use crate::types::error::Error;
+    use crate::types::state::TideState;
+
+    const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+    const PASSWORD: &str = "a12345678";
+
+    fn at(secs: i64) -> NaiveDateTime {
+        DateTime::from_timestamp(secs, 0).unwrap().naive_utc()
+    }
+
+    async fn enable_mock_two_factor(user_id: i32, recovery_codes: &[&str]) {
+        let mut conn = mock_connection().await;
+        db::two_factor::replace_two_factor(
+            &mut conn,
+            &InsertTwoFactor {
+                user_id,
+                secret: SECRET.to_string(),
+                is_enabled: true,
+                last_used_step: None,
+                created_at: Utc::now().naive_utc(),
+            },
+        )
+        .await
+        .unwrap();
+        let codes: Vec<InsertRecoveryCode> = recovery_codes
+            .iter()
+            .map(|code| InsertRecoveryCode {
+                user_id,
+                code_hash: hash_recovery_code(code),
+            })
+            .collect();
+        db::two_factor::replace_recovery_codes(&mut conn, user_id, &codes)
+            .await
+            .unwrap();
+    }
+
+    async fn send(
+        app: &tide::Server<Arc<TideState>>,
+        method: Method,
+        path: &str,
+        body: Option<Value>,
+        cookie: Option<&str>,
+    ) -> Response {
+        let url = Url::parse(&format!("http://localhost{}", path)).unwrap();
+        let mut req = Request::new(method, url);
+        if let Some(body) = body {
+            req.set_body(body);
+        }
+        if let Some(cookie) = cookie {
+            req.insert_header("cookie", cookie);
+        }
+        app.respond(req).await.unwrap()
+    }
+
+    #[tokio::test]
+    pub async fn it_checks_totp_and_recovery_codes_once() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        enable_mock_two_factor(user.id, &["abcde-23456"]).await;
+        let two_factor = db::two_factor::get_two_factor(&mut conn, user.id)
+            .await
+            .unwrap();
+
+        let now = at(1111111109);
+        let code = totp_code(SECRET, now).unwrap();
+        check_two_factor_code(&mut conn, &two_factor, &code, now)
+            .await
+            .unwrap();
+        // the same code cannot be used again, even in the next step
+        let replayed = check_two_factor_code(&mut conn, &two_factor, &code, at(1111111109 + 30));
+        assert!(matches!(
+            replayed.await,
+            Err(Error::WrongTwoFactorCodeError())
+        ));
+        // codes of older steps are not accepted after a newer one
+        let old_code = totp_code(SECRET, at(1111111109 - 30)).unwrap();
+        let old = check_two_factor_code(&mut conn, &two_factor, &old_code, now);
+        assert!(matches!(old.await, Err(Error::WrongTwoFactorCodeError())));
+        // and codes far from now never are
+        let later = at(1111111109 + 300);
+        let stale = check_two_factor_code(&mut conn, &two_factor, &code, later);
+        assert!(matches!(stale.await, Err(Error::WrongTwoFactorCodeError())));
+        let next_code = totp_code(SECRET, later).unwrap();
+        check_two_factor_code(&mut conn, &two_factor, &next_code, later)
+            .await
+            .unwrap();
+
+        // recovery codes work once
+        check_two_factor_code(&mut conn, &two_factor, "ABCDE 23456", later)
+            .await
+            .unwrap();
+        let reused = check_two_factor_code(&mut conn, &two_factor, "abcde-23456", later);
+        assert!(matches!(
+            reused.await,
+            Err(Error::WrongTwoFactorCodeError())
+        ));
+        assert_eq!(
+            db::two_factor::count_recovery_codes(&mut conn, user.id)
+                .await
+                .unwrap(),
+            0
+        );
+
+        delete_mock_user(user.id).await;
+    }
+
+    // an app that is only logged in through the login routes
+    fn create_login_app() -> tide::Server<Arc<TideState>> {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = tide::with_state(create_mock_state(dir.path()));
+        app.with(SessionMiddleware::new(
+            MemoryStore::new(),
+            b"a secret that is long enough for the session middleware",
+        ));
+        app.at("/login").post(login);
+        app.at("/login/two-factor").post(login_two_factor);
+        app.at("/logged-in").get(is_logged_in);
+        app
+    }
+
+    async fn create_user_with_password() -> GetUser {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let update_user = UpdateUser {
+            username: None,
+            password: Some(bcrypt::hash(PASSWORD, 4).unwrap()),
+            salt: None,
+            email: None,
+            is_private: None,
+            bio: None,
+            display_name: None,
+        };
+        db::user::update_user_by_id(&mut conn, user.id, &update_user)
+            .await
+            .unwrap();
+        user
+    }
+
+    #[tokio::test]
+    pub async fn it_asks_for_a_code_before_logging_in() {
+        let user = create_user_with_password().await;
+        enable_mock_two_factor(user.id, &["abcde-23456"]).await;
+        let app = create_login_app();
+
+        let credentials = json!({ "username": user.username, "password": PASSWORD });
+        let mut res = send(&app, Method::Post, "/login", Some(credentials), None).await;
+        assert_eq!(res.status(), 200);
+        let cookie = res
+            .header("set-cookie")
+            .unwrap()
+            .as_str()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+        let body: Value = res.body_json().await.unwrap();
+        assert_eq!(body["payload"]["two_factor_required"], true);
+
+        // not logged in until the code is given
+        let mut res = send(&app, Method::Get, "/logged-in", None, Some(&cookie)).await;
+        let body: Value = res.body_json().await.unwrap();
+        assert_eq!(body["payload"]["result"], false);
+
+        let wrong_code = json!({ "code": "000000" });
+        let res = send(
+            &app,
+            Method::Post,
+            "/login/two-factor",
+            Some(wrong_code),
+            Some(&cookie),
+        )
+        .await;
+        assert_eq!(res.status(), 400);
+
+        let recovery_code = json!({ "code": "abcde-23456" });
+        let res = send(
+            &app,
+            Method::Post,
+            "/login/two-factor",
+            Some(recovery_code),
+            Some(&cookie),
+        )
+        .await;
+        assert_eq!(res.status(), 200);
+        let mut res = send(&app, Method::Get, "/logged-in", None, Some(&cookie)).await;
+        let body: Value = res.body_json().await.unwrap();
+        assert_eq!(body["payload"]["result"], true);
+
+        // without a password step there is nothing to complete
+        let recovery_code = json!({ "code": "abcde-23456" });
+        let res = send(
+            &app,
+            Method::Post,
+            "/login/two-factor",
+            Some(recovery_code),
+            None,
+        )
+        .await;
+        assert_eq!(res.status(), 400);
+
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_enables_and_disables_two_factor_from_settings() {
+        let mut conn = mock_connection().await;
+        let user = create_user_with_password().await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = create_mock_app(create_mock_state(dir.path()), &user);
+        app.at("/two-factor").get(get_two_factor_status);
+        app.at("/two-factor/setup").post(setup_two_factor);
+        app.at("/two-factor/enable").post(confirm_two_factor);
+        app.at("/two-factor/disable").post(disable_two_factor);
+        app.at("/two-factor/recovery-codes")
+            .post(regenerate_recovery_codes);
+
+        let mut res = send(&app, Method::Post, "/two-factor/setup", None, None).await;
+        assert_eq!(res.status(), 200);
+        let body: Value = res.body_json().await.unwrap();
+        let secret = body["payload"]["secret"].as_str().unwrap().to_string();
+        let uri = Url::parse(body["payload"]["otpauth_uri"].as_str().unwrap()).unwrap();
+        assert_eq!(uri.scheme(), "otpauth");
+        assert!(uri
+            .query_pairs()
+            .any(|(key, value)| key == "secret" && value == secret));
+
+        // not used at login before a code from the app is confirmed
+        assert!(!db::two_factor::is_two_factor_enabled(&mut conn, user.id)
+            .await
+            .unwrap());
+        let wrong_code = json!({ "code": "000000" });
+        let res = send(
+            &app,
+            Method::Post,
+            "/two-factor/enable",
+            Some(wrong_code),
+            None,
+        )
+        .await;
+        assert_eq!(res.status(), 400);
+
+        let code = json!({ "code": totp_code(&secret, Utc::now().naive_utc()).unwrap() });
+        let mut res = send(&app, Method::Post, "/two-factor/enable", Some(code), None).await;
+        assert_eq!(res.status(), 200);
+        let body: Value = res.body_json().await.unwrap();
+        let recovery_codes = body["payload"]["recovery_codes"].as_array().unwrap();
+        assert_eq!(recovery_codes.len(), 10);
+        let first_code = recovery_codes[0].as_str().unwrap().to_string();
+
+        let mut res = send(&app, Method::Get, "/two-factor", None, None).await;
+        let body: Value = res.body_json().await.unwrap();
+        assert_eq!(body["payload"]["is_enabled"], true);
+        assert_eq!(body["payload"]["recovery_codes_left"], 10);
+
+        // a new secret cannot replace an enabled one
+        let res = send(&app, Method::Post, "/two-factor/setup", None, None).await;
+        assert_eq!(res.status(), 400);
+
+        // the password is needed as well as a code
+        let code = json!({ "password": "wrong-password", "code": first_code });
+        let res = send(
+            &app,
+            Method::Post,
+            "/two-factor/recovery-codes",
+            Some(code),
+            None,
+        )
+        .await;
+        assert_eq!(res.status(), 400);
+        let code = json!({ "code": first_code });
+        let res = send(
+            &app,
+            Method::Post,
+            "/two-factor/recovery-codes",
+            Some(code),
+            None,
+        )
+        .await;
+        assert_eq!(res.status(), 400);
+
+        // regenerating uses up a code and replaces all of them
+        let code = json!({ "password": PASSWORD, "code": first_code });
+        let mut res = send(
+            &app,
+            Method::Post,
+            "/two-factor/recovery-codes",
+            Some(code),
+            None,
+        )
+        .await;
+        assert_eq!(res.status(), 200);
+        let body: Value = res.body_json().await.unwrap();
+        let new_code = body["payload"]["recovery_codes"][0]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let old_code = json!({ "password": PASSWORD, "code": recovery_codes[1] });
+        let res = send(
+            &app,
+            Method::Post,
+            "/two-factor/disable",
+            Some(old_code),
+            None,
+        )
+        .await;
+        assert_eq!(res.status(), 400);
+
+        let code = json!({ "password": "wrong-password", "code": new_code });
+        let res = send(&app, Method::Post, "/two-factor/disable", Some(code), None).await;
+        assert_eq!(res.status(), 400);
+        assert!(db::two_factor::is_two_factor_enabled(&mut conn, user.id)
+            .await
+            .unwrap());
+
+        let code = json!({ "password": PASSWORD, "code": new_code });
+        let res = send(&app, Method::Post, "/two-factor/disable", Some(code), None).await;
+        assert_eq!(res.status(), 200);
+        assert!(db::two_factor::get_two_factor(&mut conn, user.id)
+            .await
+            .is_err());
+        assert_eq!(
+            db::two_factor::count_recovery_codes(&mut conn, user.id)
+                .await
+                .unwrap(),
+            0
+        );
+
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_limits_two_factor_codes_in_settings() {
+        let user = create_user_with_password().await;
+        enable_mock_two_factor(user.id, &["abcde-23456"]).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = create_mock_app(create_mock_state(dir.path()), &user);
+        let store: Arc<dyn RateLimitStore> = Arc::new(MemoryRateLimitStore::new());
+        let policy = RateLimitPolicy {
+            free_failures: 1,
+            lockout_failures: 2,
+            ..RateLimitPolicy::default()
+        };
+        app.at("/two-factor/disable")
+            .with(
+                RateLimit::new("two-factor-settings", store, policy)
+                    .by(RateLimitKey::SessionField("user_id")),
+            )
+            .post(disable_two_factor);
+
+        for _ in 0..2 {
+            let wrong_code = json!({ "password": PASSWORD, "code": "000000" });
+            let res = send(
+                &app,
+                Method::Post,
+                "/two-factor/disable",
+                Some(wrong_code),
+                None,
+            )
+            .await;
+            assert_eq!(res.status(), 400);
+        }
+        // even the right code has to wait
+        let code = json!({ "password": PASSWORD, "code": "abcde-23456" });
+        let res = send(&app, Method::Post, "/two-factor/disable", Some(code), None).await;
+        assert_eq!(res.status(), 429);
+
+        delete_mock_user(user.id).await;
+    }
+}
@@ -0,0 +1,322 @@
+#[cfg(test)]
+mod uploads_tests {
+    use std::{io::Cursor, sync::Arc};
+
+    use chrono::{Duration, Utc};
+    use image::{ImageFormat, RgbImage};
+    use serde_json::{json, Value};
+    use tide::http::{Body, Method, Request, Response, Url};
+
+    use crate::connectors::buckets::store::Bucket;
+    use crate::connectors::db;
+    use crate::connectors::db::mock_connection;
+    use crate::helpers::images::MAX_IMAGE_BYTES;
+    use crate::models::{uploads::InsertUpload, users::GetUser};
+    use crate::routes::{
+        storage::update::put_object,
+        uploads::{complete::complete_image_upload, create::create_image_upload},
+    };
+    use crate::tests::{
+        create_mock_app, create_mock_link, create_mock_state, create_mock_user, delete_mock_user,
+    };
+    use crate::types::state::TideState;
+
+    fn encode_png(width: u32, height: u32) -> Vec<u8> {
+        let mut png = Cursor::new(Vec::new());
+        RgbImage::new(width, height)
+            .write_to(&mut png, ImageFormat::Png)
+            .unwrap();
+        png.into_inner()
+    }
+
+    fn create_upload_app(state: Arc<TideState>, user: &GetUser) -> tide::Server<Arc<TideState>> {
+        let mut app = create_mock_app(state, user);
+        app.at("/uploads").post(create_image_upload);
+        app.at("/uploads/:token/complete")
+            .post(complete_image_upload);
+        app.at("/storage/:bucket/:key").put(put_object);
+        app
+    }
+
+    async fn send(
+        app: &tide::Server<Arc<TideState>>,
+        method: Method,
+        url: &str,
+        body: Option<Value>,
+    ) -> Response {
+        let url = Url::parse(&format!("http://localhost{}", url)).unwrap();
+        let mut req = Request::new(method, url);
+        if let Some(body) = body {
+            req.set_body(body);
+        }
+        app.respond(req).await.unwrap()
+    }
+
+    // puts the bytes to the presigned url of the upload the way a browser would
+    async fn put_presigned(
+        app: &tide::Server<Arc<TideState>>,
+        upload: &Value,
+        bytes: Vec<u8>,
+    ) -> Response {
+        let mut req = Request::new(
+            Method::Put,
+            Url::parse(upload["url"].as_str().unwrap()).unwrap(),
+        );
+        for (name, value) in upload["headers"].as_object().unwrap() {
+            req.insert_header(name.as_str(), value.as_str().unwrap());
+        }
+        req.set_body(bytes);
+        app.respond(req).await.unwrap()
+    }
+
+    #[tokio::test]
+    pub async fn it_completes_presigned_profile_image_uploads() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let dir = tempfile::tempdir().unwrap();
+        let state = create_mock_state(dir.path());
+        let app = create_upload_app(state.clone(), &user);
+
+        let mut res = send(
+            &app,
+            Method::Post,
+            "/uploads",
+            Some(json!({"target": "profile", "content_type": "image/png"})),
+        )
+        .await;
+        assert_eq!(res.status(), 200);
+        let body: Value = res.body_json().await.unwrap();
+        let token = body["payload"]["token"].as_str().unwrap().to_string();
+        let upload = body["payload"]["upload"].clone();
+        assert_eq!(upload["method"], "PUT");
+        assert_eq!(upload["headers"]["content-type"], "image/png");
+
+        // nothing to complete before the object is uploaded
+        let complete_url = format!("/uploads/{}/complete", token);
+        let res = send(&app, Method::Post, &complete_url, None).await;
+        assert_eq!(res.status(), 404);
+
+        let res = put_presigned(&app, &upload, encode_png(300, 300)).await;
+        assert_eq!(res.status(), 200);
+
+        let mut res = send(&app, Method::Post, &complete_url, None).await;
+        assert_eq!(res.status(), 200);
+        let body: Value = res.body_json().await.unwrap();
+        let image = db::image::get_profile_image(&mut conn, user.id)
+            .await
+            .unwrap();
+        assert_eq!(body["payload"]["href"], image.img_src.as_str());
+        assert!(body["payload"]["srcset"]["sources"]["image/webp"].is_string());
+        for filename in image.filenames().iter() {
+            assert!(state
+                .object_store
+                .get(Bucket::ProfileImages, filename)
+                .await
+                .is_ok());
+        }
+
+        // the original upload is gone and cannot be completed twice
+        let key = Url::parse(upload["url"].as_str().unwrap())
+            .unwrap()
+            .path_segments()
+            .unwrap()
+            .next_back()
+            .unwrap()
+            .to_string();
+        assert!(state
+            .object_store
+            .get(Bucket::ProfileImages, &key)
+            .await
+            .is_err());
+        let res = send(&app, Method::Post, &complete_url, None).await;
+        assert_eq!(res.status(), 400);
+
+        db::image::delete_profile_image(&mut conn, user.id)
+            .await
+            .unwrap();
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_rejects_link_uploads_that_are_not_the_declared_image() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let other_user = create_mock_user().await;
+        let link = create_mock_link(user.id).await;
+        let other_link = create_mock_link(other_user.id).await;
+        let dir = tempfile::tempdir().unwrap();
+        let state = create_mock_state(dir.path());
+        let app = create_upload_app(state.clone(), &user);
+
+        // only images, to links of the user
+        for payload in [
+            json!({"target": "link", "link_id": link.id, "content_type": "image/svg+xml"}),
+            json!({"target": "link", "link_id": other_link.id, "content_type": "image/png"}),
+            json!({"target": "link", "content_type": "image/png"}),
+        ] {
+            let res = send(&app, Method::Post, "/uploads", Some(payload)).await;
+            assert_eq!(res.status(), 400);
+        }
+
+        let mut res = send(
+            &app,
+            Method::Post,
+            "/uploads",
+            Some(json!({"target": "link", "link_id": link.id, "content_type": "image/jpeg"})),
+        )
+        .await;
+        assert_eq!(res.status(), 200);
+        let body: Value = res.body_json().await.unwrap();
+        let token = body["payload"]["token"].as_str().unwrap().to_string();
+        let upload = body["payload"]["upload"].clone();
+
+        // a png is put where a jpeg was promised
+        let res = put_presigned(&app, &upload, encode_png(64, 64)).await;
+        assert_eq!(res.status(), 200);
+        let complete_url = format!("/uploads/{}/complete", token);
+        let mut res = send(&app, Method::Post, &complete_url, None).await;
+        assert_eq!(res.status(), 400);
+        let body: Value = res.body_json().await.unwrap();
+        assert_eq!(
+            body["err"],
+            "Image does not match its declared content type."
+        );
+        assert!(db::image::get_link_image(&mut conn, link.id).await.is_err());
+
+        // the upload was discarded
+        let res = send(&app, Method::Post, &complete_url, None).await;
+        assert_eq!(res.status(), 400);
+
+        db::link::delete_link_by_id(&mut conn, link.id)
+            .await
+            .unwrap();
+        db::link::delete_link_by_id(&mut conn, other_link.id)
+            .await
+            .unwrap();
+        delete_mock_user(user.id).await;
+        delete_mock_user(other_user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_does_not_complete_expired_uploads() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let dir = tempfile::tempdir().unwrap();
+        let state = create_mock_state(dir.path());
+        let app = create_upload_app(state.clone(), &user);
+
+        state
+            .object_store
+            .put(
+                Bucket::ProfileImages,
+                "upload-expired.png",
+                encode_png(8, 8),
+                "image/png",
+            )
+            .await
+            .unwrap();
+        db::upload::create_upload(
+            &mut conn,
+            &InsertUpload {
+                token_hash: sha256::digest("expired"),
+                user_id: user.id,
+                link_id: None,
+                object_key: String::from("upload-expired.png"),
+                content_type: String::from("image/png"),
+                expires_at: Utc::now().naive_utc() - Duration::minutes(1),
+            },
+        )
+        .await
+        .unwrap();
+
+        let res = send(&app, Method::Post, "/uploads/expired/complete", None).await;
+        assert_eq!(res.status(), 400);
+        assert!(db::image::get_profile_image(&mut conn, user.id)
+            .await
+            .is_err());
+
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_rejects_uploads_over_the_size_limit() {
+        let mut conn = mock_connection().await;
+        let user = create_mock_user().await;
+        let dir = tempfile::tempdir().unwrap();
+        let state = create_mock_state(dir.path());
+        let app = create_upload_app(state.clone(), &user);
+
+        // presigned puts go straight to the store, so nothing stopped this object
+        let mut bytes = encode_png(8, 8);
+        bytes.resize(MAX_IMAGE_BYTES + 1024, 0);
+        let key = format!("upload-large-{}.png", user.id);
+        state
+            .object_store
+            .put(Bucket::ProfileImages, &key, bytes, "image/png")
+            .await
+            .unwrap();
+        let token = format!("large-{}", user.id);
+        db::upload::create_upload(
+            &mut conn,
+            &InsertUpload {
+                token_hash: sha256::digest(&token),
+                user_id: user.id,
+                link_id: None,
+                object_key: key.clone(),
+                content_type: String::from("image/png"),
+                expires_at: Utc::now().naive_utc() + Duration::minutes(10),
+            },
+        )
+        .await
+        .unwrap();
+
+        let complete_url = format!("/uploads/{}/complete", token);
+        let mut res = send(&app, Method::Post, &complete_url, None).await;
+        assert_eq!(res.status(), 400);
+        let body: Value = res.body_json().await.unwrap();
+        assert_eq!(body["err"], "Image must be at most 5MB.");
+        assert!(state
+            .object_store
+            .get(Bucket::ProfileImages, &key)
+            .await
+            .is_err());
+
+        delete_mock_user(user.id).await;
+    }
+
+    #[tokio::test]
+    pub async fn it_rejects_local_puts_over_the_size_limit() {
+        let user = create_mock_user().await;
+        let dir = tempfile::tempdir().unwrap();
+        let state = create_mock_state(dir.path());
+        let app = create_upload_app(state.clone(), &user);
+
+        let mut res = send(
+            &app,
+            Method::Post,
+            "/uploads",
+            Some(json!({"target": "profile", "content_type": "image/png"})),
+        )
+        .await;
+        let body: Value = res.body_json().await.unwrap();
+        let upload = body["payload"]["upload"].clone();
+
+        // a streamed body has no length to reject it by up front
+        let mut bytes = encode_png(8, 8);
+        bytes.resize(MAX_IMAGE_BYTES + 1024, 0);
+        let mut req = Request::new(
+            Method::Put,
+            Url::parse(upload["url"].as_str().unwrap()).unwrap(),
+        );
+        for (name, value) in upload["headers"].as_object().unwrap() {
+            req.insert_header(name.as_str(), value.as_str().unwrap());
+        }
+        req.set_body(Body::from_reader(futures::io::Cursor::new(bytes), None));
+        let mut res: Response = app.respond(req).await.unwrap();
+        assert_eq!(res.status(), 400);
+        let body: Value = res.body_json().await.unwrap();
+        assert_eq!(body["err"], "Image must be at most 5MB.");
+
+        delete_mock_user(user.id).await;
+    }
+}
@@ -63,6 +63,42 @@ pub enum Error {
     DuplicateEmailError(),
     #[error("Username already taken")]
     DuplicateUsernameError(),
+    #[error("Email has not been verified")]
+    EmailNotVerifiedError(),
+    #[error("Email is already verified")]
+    EmailAlreadyVerifiedError(),
+    #[error("Invalid email verification link")]
+    WrongEmailVerificationTokenError(),
+    #[error("Email verification link expired")]
+    EmailVerificationExpiredError(),
+    #[error("Invalid two factor code")]
+    WrongTwoFactorCodeError(),
+    #[error("Two factor authentication is already enabled")]
+    TwoFactorAlreadyEnabledError(),
+    #[error("Two factor authentication is not enabled")]
+    TwoFactorNotEnabledError(),
+    // the password step of the login was not done or is too old
+    #[error("Two factor login expired, please log in again")]
+    TwoFactorLoginExpiredError(),
+    // too many failed attempts, holds the seconds until the next attempt is allowed
+    #[error("Too many attempts, try again in {0} seconds")]
+    TooManyRequestsError(u64),
+    #[error("Failed to use totp secret: {0}")]
+    TotpError(String),
+    #[error("Failed to render QR code: {0}")]
+    QrCodeError(String),
+    // uploaded image is not an image we accept
+    #[error("{0}")]
+    ImageError(#[from] ImageErrors),
+    // anything to do with the object store behind the buckets
+    #[error("{0}")]
+    StorageError(#[from] StorageErrors),
+    // the page behind a link could not be previewed
+    #[error("Failed to fetch link preview: {0}")]
+    PreviewError(String),
+    // an operation of a batch failed and nothing was applied
+    #[error("Operation {0} failed: {1}")]
+    BatchOperationError(usize, Box<Error>),
 }
 
 impl Error {
@@ -77,6 +113,9 @@ impl Error {
             Error::EmailError(_) => StatusCode::InternalServerError,
             Error::AddressError(_) => StatusCode::InternalServerError,
             Error::DatetimeError() => StatusCode::InternalServerError,
+            Error::QrCodeError(_) => StatusCode::InternalServerError,
+            Error::TotpError(_) => StatusCode::InternalServerError,
+            Error::StorageError(StorageErrors::Backend(_)) => StatusCode::InternalServerError,
 
             // 4XX errors (These are checked)
             Error::ValidationError(_) => StatusCode::BadRequest,
@@ -94,17 +133,44 @@ impl Error {
             Error::NoPasswordResetError() => StatusCode::BadRequest,
             Error::DuplicateEmailError() => StatusCode::BadRequest,
             Error::DuplicateUsernameError() => StatusCode::BadRequest,
+            Error::EmailNotVerifiedError() => StatusCode::BadRequest,
+            Error::EmailAlreadyVerifiedError() => StatusCode::BadRequest,
+            Error::WrongEmailVerificationTokenError() => StatusCode::BadRequest,
+            Error::EmailVerificationExpiredError() => StatusCode::BadRequest,
+            Error::WrongTwoFactorCodeError() => StatusCode::BadRequest,
+            Error::TwoFactorAlreadyEnabledError() => StatusCode::BadRequest,
+            Error::TwoFactorNotEnabledError() => StatusCode::BadRequest,
+            Error::TwoFactorLoginExpiredError() => StatusCode::BadRequest,
+            Error::TooManyRequestsError(_) => StatusCode::TooManyRequests,
+            Error::PreviewError(_) => StatusCode::BadRequest,
+            Error::ImageError(_) => StatusCode::BadRequest,
+            Error::StorageError(StorageErrors::NotFound) => StatusCode::NotFound,
+            Error::StorageError(StorageErrors::InvalidKey) => StatusCode::BadRequest,
+            Error::StorageError(StorageErrors::InvalidSignature) => StatusCode::Forbidden,
+            // same status as the operation that failed
+            Error::BatchOperationError(_, ref e) => e.get_status_code(),
         }
     }
 
     pub fn into_response(self) -> tide::Result {
         let status_code = self.get_status_code();
 
-        if status_code == 400 {
+        if status_code == 400 || status_code == 429 {
+            // tells the client when it can try again
+            let retry_after = match self {
+                Error::TooManyRequestsError(seconds) => Some(seconds),
+                _ => None,
+            };
             return tide::Body::from_json(&ErrorBody {
                 err: self.to_string(),
             })
-            .map(|b| Response::builder(status_code).body(b).build())
+            .map(|b| {
+                let mut response = Response::builder(status_code).body(b);
+                if let Some(seconds) = retry_after {
+                    response = response.header("Retry-After", seconds.to_string());
+                }
+                response.build()
+            })
             .or_else(|e| {
                 Err(tide::Error::from_str(
                     StatusCode::InternalServerError,
@@ -126,6 +192,8 @@ struct ErrorBody {
 pub enum AssociationErrors {
     #[error("Link provided does not belong to the user.")]
     LinkDoesNotBelongToUser,
+    #[error("Section provided does not belong to the user.")]
+    SectionDoesNotBelongToUser,
     #[error("Invalid follow user specified.")]
     InvalidFollowUser,
     #[error("Notification does not belong to the user")]
@@ -141,6 +209,34 @@ pub enum S3Errors {
     FailedToDeleteImage,
 }
 
+#[derive(thiserror::Error, Debug)]
+#[error("...")]
+pub enum StorageErrors {
+    #[error("Object not found.")]
+    NotFound,
+    #[error("Invalid object key.")]
+    InvalidKey,
+    #[error("Upload url is invalid or has expired.")]
+    InvalidSignature,
+    #[error("Object store request failed: {0}")]
+    Backend(String),
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("...")]
+pub enum ImageErrors {
+    #[error("Image must be a png, jpeg, gif or webp.")]
+    UnsupportedFormat,
+    #[error("Image must be at most 5MB.")]
+    TooLarge,
+    #[error("Image must be at most 4096 by 4096 pixels.")]
+    DimensionsTooLarge,
+    #[error("Image could not be read.")]
+    Malformed,
+    #[error("Image does not match its declared content type.")]
+    ContentTypeMismatch,
+}
+
 #[derive(thiserror::Error, Debug)]
 #[error("...")]
 pub enum RequestErrors {
@@ -1,9 +1,13 @@
+use crate::connectors::buckets::store::ObjectStore;
+use crate::connectors::geoip::database::GeoIpDatabase;
+use crate::connectors::previews::fetcher::PageFetcher;
 use crate::connectors::smtp::email::EmailService;
 use crate::connectors::smtp::smtp_service::SMTPService;
-use aws_sdk_s3::{self as s3};
 use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::PgConnection;
+use ipnet::IpNet;
 use std::path::Path;
+use std::sync::Arc;
 use tempfile::TempDir;
 
 // this defines the state of the tide app
@@ -14,11 +18,18 @@ pub type TidePool = Pool<ConnectionManager<PgConnection>>;
 // this is actual state of the tide app as a struct
 pub struct TideState<T: SMTPService = EmailService> {
     pub tide_pool: TidePool,
-    pub s3_client: s3::Client,
+    // s3 or the local disk, depending on OBJECT_STORE. shared with the image gc job
+    pub object_store: Arc<dyn ObjectStore>,
     pub tempdir: TempDir,
     // might want to make this a dynamic type in the future
     // or make this generic, tried making it generic but broke everything because you have to change a million things
     pub email_service: T,
+    // country lookups for insights, empty when no database is configured
+    pub geoip: GeoIpDatabase,
+    // fetches the pages behind links for their previews
+    pub page_fetcher: Box<dyn PageFetcher>,
+    // proxies whose X-Forwarded-For is believed, see TRUSTED_PROXIES
+    pub trusted_proxies: Vec<IpNet>,
 }
 
 // this returns the path of the directory
@@ -66,7 +66,8 @@ export async function validateFetch<R, U extends object = {}>(
   if (!response.ok) {
     return await validatePayload<TError>(jsonBody, TErrorValidator)
       .then(({ err }) => {
-        if (response.status === 400) {
+        // 429 is too many failed attempts, the message says when to try again
+        if (response.status === 400 || response.status === 429) {
           addError(err, response.status);
         } else if (response.status === 403) {
           // forbidden page
@@ -0,0 +1,7 @@
+import type { TImageSrcset } from "$lib/scripts/validation/response";
+
+// webp renditions are preferred, images uploaded before renditions only have a src
+export const srcsetOf = (
+  image: TImageSrcset | null | undefined,
+): string | undefined =>
+  image?.sources["image/webp"] ?? Object.values(image?.sources ?? {})[0];
@@ -2,10 +2,9 @@ import type {
   TUpdateProfileQuery,
   TUpdateProfile,
   TCreateLinkPayload,
-  TUpdateLinkTitlePayload,
-  TUpdateLinkBioPayload,
-  TUpdateLinkHrefPayload,
+  TUpdateLinkPayload,
   TReorderPayload,
+  TLinkBatchPayload,
   TCreateFollowRequestPayload,
   TResetCodeBody,
   TResetPasswordBody,
@@ -16,6 +15,9 @@ import type {
   TUpdatePrivacyBody,
   TCompleteFollowRequestPayload,
   TReadNotification,
+  TVerifyEmailBody,
+  TTwoFactorCodeBody,
+  TTwoFactorPasswordBody,
 } from "./query.d.ts";
 import { invalidateAll } from "$app/navigation";
 import {
@@ -28,13 +30,29 @@ import {
   type TFollowStatus,
   type TFollowStatusResponsePayload,
   type TLink,
+  type TLinkBody,
   type TProfileBody,
   type TUpdateImageResponseBody,
   type TPaginatedFollowRequestProfile,
   TGetPaginatedFollowRequestProfileValidator,
   type TUserInsightResponsePayload,
+  type TInsightGranularity,
+  type TShareProfileResponseBody,
+  TShareProfileResponseBodyValidator,
+  type TLinkBatchResponseBody,
+  TLinkBatchResponseBodyValidator,
   UserInsightResponsePayloadValidator,
   type TNotificationsPayload,
+  type TEmailVerificationStatus,
+  TEmailVerificationStatusValidator,
+  type TLoginResponsePayload,
+  TLoginResponsePayloadValidator,
+  type TTwoFactorStatus,
+  TTwoFactorStatusValidator,
+  type TTwoFactorSetup,
+  TTwoFactorSetupValidator,
+  type TRecoveryCodes,
+  TRecoveryCodesValidator,
 } from "./validation/response.js";
 import {
   type TGetUsernamePayload,
@@ -54,12 +72,12 @@ const UPDATE_DISPLAY_PROFILE_ENDPOINT = PROFILES_PREFIX + "/display";
 const LINKS_PREFIX = "/api/links";
 const GET_LINKS_ENDPOINT = "/api/links";
 const ADD_LINKS_ENDPOINT = LINKS_PREFIX;
-const UPDATE_LINK_TITLE_ENDPOINT = "/api/links/title";
-const UPDATE_LINK_BIO_ENDPOINT = "/api/links/bio";
-const UPDATE_LINK_HREF_ENDPOINT = "/api/links/href";
+const UPDATE_LINK_ENDPOINT = "/api/links";
 const REORDER_LINK_ENDPOINT = "/api/links/reorder";
+const BATCH_LINKS_ENDPOINT = "/api/links/batch";
 const DELETE_LINK_ENDPOINT = "/api/links";
 const LOGIN_ENDPOINT = "/api/login";
+const LOGIN_TWO_FACTOR_ENDPOINT = "/api/login/two-factor";
 const LOGOUT_ENDPOINT = "/api/logout";
 const REGISTER_ENDPOINT = "/api/register";
 const GET_IS_LOGGED_IN_ENDPOINT = "/api/logged-in";
@@ -76,6 +94,13 @@ const CHECK_PASSWORD_CODE_ENDPOINT = "/api/password-code";
 const CHANGE_PASSWORD_ENDPOINT = "/api/change-password";
 const CHANGE_USERNAME_ENDPOINT = "/api/change-username";
 const CHANGE_EMAIL_ENDPOINT = "/api/change-email";
+const VERIFY_EMAIL_ENDPOINT = "/api/verify-email";
+const RESEND_EMAIL_VERIFICATION_ENDPOINT = "/api/verify-email/resend";
+const TWO_FACTOR_ENDPOINT = "/api/two-factor";
+const TWO_FACTOR_SETUP_ENDPOINT = "/api/two-factor/setup";
+const TWO_FACTOR_ENABLE_ENDPOINT = "/api/two-factor/enable";
+const TWO_FACTOR_DISABLE_ENDPOINT = "/api/two-factor/disable";
+const RECOVERY_CODES_ENDPOINT = "/api/two-factor/recovery-codes";
 const UPDATE_PRIVACY_ENDPOINT = "/api/update-privacy";
 const INSIGHT_ENDPOINT = "/api/insights";
 const NOTIFICATIONS_ENDPOINT = "/api/notifications";
@@ -85,7 +110,7 @@ type fetch = typeof fetch;
 /**
  * forms a POST query to the /login endpoint to validate and log in user
  * expects: status 400 with message on error and 200 on successful login
- * expects: response with a body of type { result: boolean, token: string }
+ * expects: response with a body of type { two_factor_required: boolean }, the user is only logged in when it is false
  * expects: if fetch promise is rejected, then response has the type { status: 400, message: error_message }
  * TODO: bearer/cookie based token + boolean result
  * @param username
@@ -94,15 +119,30 @@ type fetch = typeof fetch;
 export const login = async (
   username: string,
   password: string,
-): Promise<boolean> => {
+): Promise<TLoginResponsePayload | null> => {
   // validate request here
   return await validateFetch<
-    TStandardResponsePayload,
+    TLoginResponsePayload,
     { username: string; password: string }
   >(
     LOGIN_ENDPOINT,
     "POST",
     { username, password },
+    TLoginResponsePayloadValidator,
+  );
+};
+
+/**
+ * forms a POST query to the /login/two-factor endpoint with the code of a login that needs one
+ * @param query a totp code or a recovery code
+ */
+export const loginTwoFactor = async (
+  query: TTwoFactorCodeBody,
+): Promise<boolean> => {
+  return await validateFetch<TStandardResponsePayload>(
+    LOGIN_TWO_FACTOR_ENDPOINT,
+    "POST",
+    query,
     TStandardResponsePayloadValidator,
   ).then((p) => Boolean(p));
 };
@@ -145,9 +185,16 @@ export const updateProfile = async (updateQuery: TUpdateProfileQuery) => {
 export const getProfile = async (
   username: string,
   fetch: fetch,
+  src?: string | null,
+  referrer?: string | null,
 ): Promise<TProfileBody | null> => {
+  // src is the share channel the profile was opened from, ref the page that linked to it
+  const params = new URLSearchParams();
+  if (src) params.append("src", src);
+  if (referrer) params.append("ref", referrer);
+  const query = params.toString() ? `?${params.toString()}` : "";
   return await validateFetch<TProfileBody, { username: string }>(
-    `${PROFILES_PREFIX}/${username}`,
+    `${PROFILES_PREFIX}/${username}${query}`,
     "GET",
     { username },
     TProfileBodyValidator,
@@ -155,12 +202,28 @@ export const getProfile = async (
   );
 };
 
-export const getLinks = async (
+/**
+ * records a share of the profile and gets the link to share
+ * @param username
+ * @param channel where the profile is shared to, e.g. copy-link or qr
+ */
+export const shareProfile = async (
+  username: string,
+  channel?: string,
+): Promise<TShareProfileResponseBody | null> => {
+  return await validateFetch<TShareProfileResponseBody, { channel?: string }>(
+    `${PROFILES_PREFIX}/${username}/share`,
+    "POST",
+    { channel },
+    TShareProfileResponseBodyValidator,
+  );
+};
+
+export const getSectionedLinks = async (
   username: string,
   fetch: fetch,
-): Promise<TLink[]> => {
-  // todo change the function
-  return await validateFetch<{ links: TLink[] }>(
+): Promise<TLinkBody> => {
+  return await validateFetch<TLinkBody>(
     `${GET_LINKS_ENDPOINT}/${username}`,
     "GET",
     {},
@@ -169,13 +232,24 @@ export const getLinks = async (
   ).then((linkBody) => {
     // return links if can
     if (linkBody) {
-      return linkBody.links;
+      return linkBody;
     }
     // return nothing
-    return [];
+    return { links: [], sections: [] };
   });
 };
 
+// every link of the user in order, regardless of its section
+export const getLinks = async (
+  username: string,
+  fetch: fetch,
+): Promise<TLink[]> => {
+  const linkBody = await getSectionedLinks(username, fetch);
+  return linkBody.links
+    .concat(linkBody.sections.flatMap((section) => section.links))
+    .sort((a, b) => a.position - b.position);
+};
+
 export const getFollowStatus = async (
   targetUserId: number,
   fetch: fetch,
@@ -254,54 +328,17 @@ export const addLinks = async (query: TCreateLinkPayload): Promise<void> => {
   );
 };
 
-export const updateLinkTitle = async (
-  query: TUpdateLinkTitlePayload,
+// updates only the given fields of the link
+export const updateLink = async (
+  query: TUpdateLinkPayload,
   link_id: number,
 ) => {
   const payload = await validateFetch<
     TStandardResponsePayload,
-    TUpdateLinkTitlePayload
+    TUpdateLinkPayload
   >(
-    `${UPDATE_LINK_TITLE_ENDPOINT}/${link_id}`,
-    "PUT",
-    query,
-    TStandardResponsePayloadValidator,
-  );
-
-  if (payload !== null) {
-    await invalidateAll();
-  }
-};
-
-export const updateLinkBio = async (
-  query: TUpdateLinkBioPayload,
-  link_id: number,
-) => {
-  const payload = await validateFetch<
-    TStandardResponsePayload,
-    TUpdateLinkBioPayload
-  >(
-    `${UPDATE_LINK_BIO_ENDPOINT}/${link_id}`,
-    "PUT",
-    query,
-    TStandardResponsePayloadValidator,
-  );
-
-  if (payload !== null) {
-    await invalidateAll();
-  }
-};
-
-export const updateLinkHref = async (
-  query: TUpdateLinkHrefPayload,
-  link_id: number,
-) => {
-  const payload = await validateFetch<
-    TStandardResponsePayload,
-    TUpdateLinkHrefPayload
-  >(
-    `${UPDATE_LINK_HREF_ENDPOINT}/${link_id}`,
-    "PUT",
+    `${UPDATE_LINK_ENDPOINT}/${link_id}`,
+    "PATCH",
     query,
     TStandardResponsePayloadValidator,
   );
@@ -335,6 +372,26 @@ export const reorderLink = async (query: TReorderPayload) => {
   }
 };
 
+/**
+ * Applies all operations in order or none of them
+ * @returns the ids of the created links by their temp_id
+ */
+export const batchLinks = async (
+  query: TLinkBatchPayload,
+): Promise<TLinkBatchResponseBody | null> => {
+  const payload = await validateFetch<TLinkBatchResponseBody, TLinkBatchPayload>(
+    BATCH_LINKS_ENDPOINT,
+    "POST",
+    query,
+    TLinkBatchResponseBodyValidator,
+  );
+
+  if (payload !== null) {
+    await invalidateAll();
+  }
+  return payload;
+};
+
 export const updateLinkPicture = async (
   image: Blob,
   filetype: String,
@@ -531,9 +588,20 @@ export const checkPasswordResetCode = async (
 
 export const resetPassword = async (
   query: TResetPasswordBody,
+): Promise<TLoginResponsePayload | null> => {
+  return await validateFetch<TLoginResponsePayload>(
+    RESET_PASSWORD_ENDPOINT,
+    "POST",
+    query,
+    TLoginResponsePayloadValidator,
+  );
+};
+
+export const changePassword = async (
+  query: TChangePasswordBody,
 ): Promise<boolean> => {
   const payload = await validateFetch<TStandardResponsePayload>(
-    RESET_PASSWORD_ENDPOINT,
+    CHANGE_PASSWORD_ENDPOINT,
     "POST",
     query,
     TStandardResponsePayloadValidator,
@@ -544,12 +612,27 @@ export const resetPassword = async (
   }
   return false;
 };
+export const changeEmail = async (
+  query: TChangeEmailBody,
+): Promise<boolean> => {
+  const payload = await validateFetch<TStandardResponsePayload>(
+    CHANGE_EMAIL_ENDPOINT,
+    "POST",
+    query,
+    TStandardResponsePayloadValidator,
+  );
 
-export const changePassword = async (
-  query: TChangePasswordBody,
+  if (payload) {
+    return true;
+  }
+  return false;
+};
+
+export const verifyEmail = async (
+  query: TVerifyEmailBody,
 ): Promise<boolean> => {
   const payload = await validateFetch<TStandardResponsePayload>(
-    CHANGE_PASSWORD_ENDPOINT,
+    VERIFY_EMAIL_ENDPOINT,
     "POST",
     query,
     TStandardResponsePayloadValidator,
@@ -560,11 +643,70 @@ export const changePassword = async (
   }
   return false;
 };
-export const changeEmail = async (
-  query: TChangeEmailBody,
+
+export const getEmailVerification = async (
+  fetch?: fetch,
+): Promise<TEmailVerificationStatus | null> => {
+  return await validateFetch<TEmailVerificationStatus>(
+    VERIFY_EMAIL_ENDPOINT,
+    "GET",
+    {},
+    TEmailVerificationStatusValidator,
+    { fetch },
+  );
+};
+
+export const resendEmailVerification = async (): Promise<boolean> => {
+  const payload = await validateFetch<TStandardResponsePayload>(
+    RESEND_EMAIL_VERIFICATION_ENDPOINT,
+    "POST",
+    {},
+    TStandardResponsePayloadValidator,
+  );
+
+  if (payload) {
+    return true;
+  }
+  return false;
+};
+
+export const getTwoFactorStatus = async (
+  fetch?: fetch,
+): Promise<TTwoFactorStatus | null> => {
+  return await validateFetch<TTwoFactorStatus>(
+    TWO_FACTOR_ENDPOINT,
+    "GET",
+    {},
+    TTwoFactorStatusValidator,
+    { fetch },
+  );
+};
+
+export const setupTwoFactor = async (): Promise<TTwoFactorSetup | null> => {
+  return await validateFetch<TTwoFactorSetup>(
+    TWO_FACTOR_SETUP_ENDPOINT,
+    "POST",
+    {},
+    TTwoFactorSetupValidator,
+  );
+};
+
+export const enableTwoFactor = async (
+  query: TTwoFactorCodeBody,
+): Promise<TRecoveryCodes | null> => {
+  return await validateFetch<TRecoveryCodes>(
+    TWO_FACTOR_ENABLE_ENDPOINT,
+    "POST",
+    query,
+    TRecoveryCodesValidator,
+  );
+};
+
+export const disableTwoFactor = async (
+  query: TTwoFactorPasswordBody,
 ): Promise<boolean> => {
   const payload = await validateFetch<TStandardResponsePayload>(
-    CHANGE_EMAIL_ENDPOINT,
+    TWO_FACTOR_DISABLE_ENDPOINT,
     "POST",
     query,
     TStandardResponsePayloadValidator,
@@ -575,6 +717,18 @@ export const changeEmail = async (
   }
   return false;
 };
+
+export const regenerateRecoveryCodes = async (
+  query: TTwoFactorPasswordBody,
+): Promise<TRecoveryCodes | null> => {
+  return await validateFetch<TRecoveryCodes>(
+    RECOVERY_CODES_ENDPOINT,
+    "POST",
+    query,
+    TRecoveryCodesValidator,
+  );
+};
+
 export const changeUsername = async (
   query: TChangeUsernameBody,
 ): Promise<boolean> => {
@@ -606,9 +760,17 @@ export const updatePrivacy = async (
   return false;
 };
 
-export const getUserInsights = async (fetch?: fetch) => {
+export const getUserInsights = async (
+  fetch?: fetch,
+  window?: { from?: Date; to?: Date; granularity?: TInsightGranularity },
+) => {
+  const params = new URLSearchParams();
+  if (window?.from) params.set("from", window.from.toISOString());
+  if (window?.to) params.set("to", window.to.toISOString());
+  if (window?.granularity) params.set("granularity", window.granularity);
+  const query = params.toString();
   return await validateFetch<TUserInsightResponsePayload>(
-    INSIGHT_ENDPOINT,
+    query ? `${INSIGHT_ENDPOINT}?${query}` : INSIGHT_ENDPOINT,
     "GET",
     {},
     UserInsightResponsePayloadValidator,
@@ -16,16 +16,11 @@ export type TCreateLinkPayload = {
   href: string;
 };
 
-export type TUpdateLinkTitlePayload = {
-  title: string;
-};
-
-export type TUpdateLinkBioPayload = {
-  bio: string;
-};
-
-export type TUpdateLinkHrefPayload = {
-  href: string;
+// fields that are left out are not changed
+export type TUpdateLinkPayload = {
+  title?: string;
+  description?: string;
+  href?: string;
 };
 
 export type TReorderPayload = {
@@ -33,6 +28,27 @@ export type TReorderPayload = {
   new_position_id: number | null;
 };
 
+// links created earlier in a batch are referred to by their temp_id
+export type TLinkRef = number | string;
+
+export type TLinkBatchOperation =
+  | ({ op: "create"; temp_id?: string } & TCreateLinkPayload)
+  | {
+      op: "update";
+      link_id: TLinkRef;
+      title?: string;
+      description?: string;
+      href?: string;
+      visible_from?: string | null;
+      visible_until?: string | null;
+    }
+  | { op: "delete"; link_id: TLinkRef }
+  | { op: "move"; link_id: TLinkRef; new_position_id: TLinkRef | null };
+
+export type TLinkBatchPayload = {
+  operations: TLinkBatchOperation[];
+};
+
 export type TCreateFollowRequestPayload = {
   pending_follow_id: number;
 };
@@ -88,6 +104,21 @@ export type TUpdatePrivacyBody = {
   is_private: boolean;
 };
 
+export type TVerifyEmailBody = {
+  token: string;
+};
+
+// a totp code from the authenticator app or a recovery code
+export type TTwoFactorCodeBody = {
+  code: string;
+};
+
+// turning two factor authentication off or making new recovery codes also needs the password
+export type TTwoFactorPasswordBody = {
+  password: string;
+  code: string;
+};
+
 export type TReadNotification = {
   notification_id: number;
 };
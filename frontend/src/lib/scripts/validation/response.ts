@@ -17,18 +17,30 @@ export const TGetUsernamePayloadValidator = Joi.object<TGetUsernamePayload>({
   username: Joi.string().allow(null).allow(""),
 });
 
-export type TUpdateImageResponseBody = { href: string };
+// renditions of an image, sources maps a content type to a srcset
+export type TImageSrcset = {
+  src: string;
+  sources: Record<string, string>;
+};
+
+const TImageSrcsetValidator = Joi.object<TImageSrcset>({
+  src: Joi.string().allow(""),
+  sources: Joi.object().pattern(Joi.string(), Joi.string()),
+});
+
+export type TUpdateImageResponseBody = { href: string; srcset: TImageSrcset };
 
 export const UpdateImageResponseBodyValidator =
   Joi.object<TUpdateImageResponseBody>({
     href: Joi.string().allow(""),
+    srcset: TImageSrcsetValidator,
   });
 
 // profile
 export type TProfileBody = {
   display_name: string;
   bio: string;
-  picture: string;
+  picture: TImageSrcset | null;
   following: number | null;
   followers: number | null;
   is_private: boolean;
@@ -39,7 +51,7 @@ export type TProfileBody = {
 export const TProfileBodyValidator = Joi.object<TProfileBody>({
   display_name: Joi.string().min(0).required(),
   bio: Joi.string().allow(null).min(0),
-  picture: Joi.string().min(0),
+  picture: TImageSrcsetValidator.allow(null),
   following: Joi.number().allow(null),
   followers: Joi.number().allow(null),
   is_private: Joi.boolean(),
@@ -48,27 +60,80 @@ export const TProfileBodyValidator = Joi.object<TProfileBody>({
 }).unknown();
 
 // link
+const LINK_STATUSES = ["scheduled", "visible", "expired"] as const;
+export type TLinkStatus = (typeof LINK_STATUSES)[number];
+
 export type TLink = {
   id: number;
   user_id: number;
-  next_id: number | null;
   title: string | null;
   href: string;
   description: string | null;
-  img_src: string | null;
+  img_srcset: TImageSrcset | null;
+  visible_from: string | null;
+  visible_until: string | null;
+  status: TLinkStatus;
+  position: number;
+  section_id: number | null;
+  health?: TLinkHealth | null;
+};
+
+const LINK_HEALTH_STATUSES = ["healthy", "failing", "broken"] as const;
+export type TLinkHealthStatus = (typeof LINK_HEALTH_STATUSES)[number];
+
+export type TLinkHealth = {
+  status: TLinkHealthStatus;
+  status_code: number | null;
+  latency_ms: number;
+  checked_at: string;
+  consecutive_failures: number;
 };
 
-export const TLinkBodyValidator = Joi.object<{ links: TLink[] }>({
-  links: Joi.array<TLink[]>()
+export type TLinkSection = {
+  id: number;
+  user_id: number;
+  title: string;
+  is_collapsed: boolean;
+  position: number;
+  links: TLink[];
+};
+
+export type TLinkBody = { links: TLink[]; sections: TLinkSection[] };
+
+const TLinkValidator = Joi.object({
+  id: Joi.number(),
+  user_id: Joi.number().required(),
+  href: Joi.string().min(0).required(),
+  title: Joi.string().min(0).allow(null).optional(),
+  description: Joi.string().min(0).allow(null).optional(),
+  img_srcset: TImageSrcsetValidator.allow(null).optional(),
+  visible_from: Joi.string().allow(null).optional(),
+  visible_until: Joi.string().allow(null).optional(),
+  status: Joi.string().valid(...LINK_STATUSES).optional(),
+  position: Joi.number().optional(),
+  section_id: Joi.number().allow(null).optional(),
+  health: Joi.object({
+    status: Joi.string().valid(...LINK_HEALTH_STATUSES).required(),
+    status_code: Joi.number().allow(null),
+    latency_ms: Joi.number(),
+    checked_at: Joi.string(),
+    consecutive_failures: Joi.number(),
+  })
+    .allow(null)
+    .optional(),
+});
+
+export const TLinkBodyValidator = Joi.object<TLinkBody>({
+  links: Joi.array<TLink[]>().items(TLinkValidator).min(0),
+  sections: Joi.array<TLinkSection[]>()
     .items(
       Joi.object({
-        id: Joi.number(),
+        id: Joi.number().required(),
         user_id: Joi.number().required(),
-        next_id: Joi.number().allow(null).optional(),
-        href: Joi.string().min(0).required(),
-        title: Joi.string().min(0).allow(null).optional(),
-        description: Joi.string().min(0).allow(null).optional(),
-        img_src: Joi.string().allow(null).optional(),
+        title: Joi.string().required(),
+        is_collapsed: Joi.boolean().required(),
+        position: Joi.number().optional(),
+        links: Joi.array<TLink[]>().items(TLinkValidator).min(0),
       }),
     )
     .min(0),
@@ -134,22 +199,71 @@ export const TGetPaginatedFollowRequestProfileValidator = Joi.object<
   total_size: Joi.number(),
 });
 
+export type TShareProfileResponseBody = {
+  share_url: string;
+  recorded: boolean;
+};
+
+export const TShareProfileResponseBodyValidator =
+  Joi.object<TShareProfileResponseBody>({
+    share_url: Joi.string().required(),
+    recorded: Joi.boolean(),
+  });
+
+export type TLinkBatchResponseBody = {
+  temp_ids: Record<string, number>;
+};
+
+export const TLinkBatchResponseBodyValidator = Joi.object<TLinkBatchResponseBody>(
+  {
+    temp_ids: Joi.object().pattern(Joi.string(), Joi.number()).required(),
+  },
+);
+
+export const INSIGHT_GRANULARITIES = ["hour", "day", "week", "month"] as const;
+export type TInsightGranularity = (typeof INSIGHT_GRANULARITIES)[number];
+
 export type TUserInsightResponsePayload = {
+  window: { from: Date; to: Date; granularity: TInsightGranularity };
   total_profile_views: number;
+  total_unique_profile_views: number;
+  total_follows: number;
+  total_unfollows: number;
+  total_follow_requests: number;
+  total_shares: number;
   interval_views: [Date, number][];
+  interval_unique_views: [Date, number][];
   interval_follows: [Date, number][];
   interval_unfollows: [Date, number][];
   interval_follow_requests: [Date, number][];
   interval_shares: [Date, number][];
+  total_link_clicks: number;
+  interval_link_clicks: { link_id: number; interval_clicks: [Date, number][] }[];
+  top_referrers: { source: string; view_count: number }[];
+  top_countries: { source: string; view_count: number }[];
+  top_link_sources: { link_id: number; source: string; click_count: number }[];
 };
 
 export const UserInsightResponsePayloadValidator =
   Joi.object<TUserInsightResponsePayload>({
+    window: Joi.object({
+      from: Joi.date(),
+      to: Joi.date(),
+      granularity: Joi.string().valid(...INSIGHT_GRANULARITIES),
+    }),
     total_profile_views: Joi.number(),
+    total_unique_profile_views: Joi.number(),
+    total_follows: Joi.number(),
+    total_unfollows: Joi.number(),
+    total_follow_requests: Joi.number(),
+    total_shares: Joi.number(),
 
     interval_views: Joi.array().items(
       Joi.array().ordered(Joi.date().required(), Joi.number().required()),
     ),
+    interval_unique_views: Joi.array().items(
+      Joi.array().ordered(Joi.date().required(), Joi.number().required()),
+    ),
     interval_follows: Joi.array().items(
       Joi.array().ordered(Joi.date().required(), Joi.number().required()),
     ),
@@ -165,6 +279,33 @@ export const UserInsightResponsePayloadValidator =
     interval_shares: Joi.array().items(
       Joi.array().ordered(Joi.date().required(), Joi.number().required()),
     ),
+
+    total_link_clicks: Joi.number(),
+
+    interval_link_clicks: Joi.array().items(
+      Joi.object({
+        link_id: Joi.number(),
+        interval_clicks: Joi.array().items(
+          Joi.array().ordered(Joi.date().required(), Joi.number().required()),
+        ),
+      }),
+    ),
+
+    top_referrers: Joi.array().items(
+      Joi.object({ source: Joi.string(), view_count: Joi.number() }),
+    ),
+
+    top_countries: Joi.array().items(
+      Joi.object({ source: Joi.string(), view_count: Joi.number() }),
+    ),
+
+    top_link_sources: Joi.array().items(
+      Joi.object({
+        link_id: Joi.number(),
+        source: Joi.string(),
+        click_count: Joi.number(),
+      }),
+    ),
   });
 export type TNotification = {
   id: number;
@@ -179,7 +320,7 @@ export type TNotificationsPayload = {
   notifications: TNotification[];
 };
 
-export const NOTIFICATION_TYPES = [1, 2] as const;
+export const NOTIFICATION_TYPES = [1, 2, 3] as const;
 export const TNotificationsValidator = Joi.object<TNotificationsPayload>({
   notifications: Joi.array<TNotification[]>()
     .items(
@@ -196,3 +337,50 @@ export const TNotificationsValidator = Joi.object<TNotificationsPayload>({
     )
     .min(0),
 });
+
+// pending_email is the address of an email change that was not confirmed yet
+export type TEmailVerificationStatus = {
+  email: string;
+  email_verified: boolean;
+  pending_email: string | null;
+};
+
+export const TEmailVerificationStatusValidator =
+  Joi.object<TEmailVerificationStatus>({
+    email: Joi.string().required(),
+    email_verified: Joi.boolean().required(),
+    pending_email: Joi.string().allow(null),
+  });
+
+// login asks for a two factor code before the session is logged in
+export type TLoginResponsePayload = { two_factor_required: boolean };
+
+export const TLoginResponsePayloadValidator = Joi.object<TLoginResponsePayload>(
+  {
+    two_factor_required: Joi.boolean().required(),
+  },
+);
+
+export type TTwoFactorStatus = {
+  is_enabled: boolean;
+  recovery_codes_left: number;
+};
+
+export const TTwoFactorStatusValidator = Joi.object<TTwoFactorStatus>({
+  is_enabled: Joi.boolean().required(),
+  recovery_codes_left: Joi.number().required().min(0),
+});
+
+export type TTwoFactorSetup = { secret: string; otpauth_uri: string };
+
+export const TTwoFactorSetupValidator = Joi.object<TTwoFactorSetup>({
+  secret: Joi.string().required(),
+  otpauth_uri: Joi.string().required(),
+});
+
+// recovery codes are only shown once
+export type TRecoveryCodes = { recovery_codes: string[] };
+
+export const TRecoveryCodesValidator = Joi.object<TRecoveryCodes>({
+  recovery_codes: Joi.array().items(Joi.string()).required(),
+});
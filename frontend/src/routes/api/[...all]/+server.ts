@@ -8,6 +8,7 @@ export const fallback: RequestHandler = async ({
   params,
   url,
   fetch,
+  getClientAddress,
 }) => {
   const tailURL = params.all + url.search;
 
@@ -15,6 +16,12 @@ export const fallback: RequestHandler = async ({
   request.headers.delete("content-length"); // handled by fetch API
   request.headers.delete("host"); // not needed; we already specify the url in fetch
   request.headers.delete("connection"); // handled by fetch API
+  // append whoever connected to us, like nginx does, so the backend sees the client and not this server
+  const forwardedFor = request.headers.get("x-forwarded-for");
+  request.headers.set(
+    "x-forwarded-for",
+    forwardedFor ? `${forwardedFor}, ${getClientAddress()}` : getClientAddress(),
+  );
   console.log(`fetching to: ${SERVER_IP_ADDR}/${tailURL}`);
 
   return await fetch(`${SERVER_IP_ADDR}/${tailURL}`, {
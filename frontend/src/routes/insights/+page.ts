@@ -1,6 +1,7 @@
 import { error, redirect } from "@sveltejs/kit";
 import type { PageLoad } from "./$types";
 import { getUserInsights } from "$lib/scripts/queries";
+import { INSIGHT_GRANULARITIES } from "$lib/scripts/validation/response";
 /**
  * validates and prepares the corresponding page data
  * @param param0
@@ -11,13 +12,26 @@ export const load: PageLoad = async ({ fetch, parent, url }) => {
   if (!isLoggedIn) return redirect(302, "/auth/login");
 
   const intervalType = url.searchParams.get("interval");
-  const userInsights = (await getUserInsights(fetch)) ?? {
+  const granularityParam = url.searchParams.get("granularity");
+  const granularity = INSIGHT_GRANULARITIES.find((g) => g == granularityParam);
+  const userInsights = (await getUserInsights(fetch, { granularity })) ?? {
+    window: null,
     total_profile_views: 0,
+    total_unique_profile_views: 0,
+    total_follows: 0,
+    total_unfollows: 0,
+    total_follow_requests: 0,
+    total_shares: 0,
     interval_views: [],
+    interval_unique_views: [],
     interval_unfollows: [],
     interval_shares: [],
     interval_follows: [],
     interval_follow_requests: [],
+    total_link_clicks: 0,
+    interval_link_clicks: [],
+    top_referrers: [],
+    top_countries: [],
   };
   return { intervalType, ...userInsights };
 };
@@ -0,0 +1,13 @@
+import type { PageServerLoad } from "./$types";
+
+/**
+ * the page that linked to the profile. the api only sees requests from our own pages,
+ * so the referrer of the page request is passed on to it
+ * @param param0
+ * @returns the referrer, read by the load function in +page.ts
+ */
+export const load: PageServerLoad = async ({ request }) => {
+  return {
+    referrer: request.headers.get("referer"),
+  };
+};
@@ -1,4 +1,8 @@
-import { getProfile, getLinks, getFollowStatus } from "$lib/scripts/queries";
+import {
+  getProfile,
+  getSectionedLinks,
+  getFollowStatus,
+} from "$lib/scripts/queries";
 import { error } from "@sveltejs/kit";
 import type { PageLoad } from "./$types";
 import type { TFollowStatus } from "$lib/scripts/validation/response";
@@ -7,9 +11,14 @@ import type { TFollowStatus } from "$lib/scripts/validation/response";
  * @param param0
  * @returns an object to be pointed to by 'data' variable in +page.svelte
  */
-export const load: PageLoad = async ({ fetch, params, parent }) => {
+export const load: PageLoad = async ({ fetch, params, parent, url, data }) => {
   const { isLoggedIn } = await parent();
-  const profileData = await getProfile(params.username, fetch);
+  const profileData = await getProfile(
+    params.username,
+    fetch,
+    url.searchParams.get("src"),
+    data.referrer,
+  );
   if (!profileData) {
     error(404, {
       message: "Profile not found",
@@ -19,11 +28,12 @@ export const load: PageLoad = async ({ fetch, params, parent }) => {
     ? await getFollowStatus(profileData.id, fetch)
     : undefined;
 
-  let links = await getLinks(params.username, fetch);
+  let { links, sections } = await getSectionedLinks(params.username, fetch);
 
   return {
     ...profileData,
     followStatus,
     links,
+    sections,
   };
 };
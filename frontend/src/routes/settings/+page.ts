@@ -1,7 +1,16 @@
-import { getProfile, getUsername } from "$lib/scripts/queries";
+import {
+  getEmailVerification,
+  getProfile,
+  getTwoFactorStatus,
+  getUsername,
+} from "$lib/scripts/queries";
 import { error } from "@sveltejs/kit";
 import type { PageLoad } from "./$types";
-import { type TProfileBody } from '../../lib/scripts/validation/response';
+import {
+  type TEmailVerificationStatus,
+  type TProfileBody,
+  type TTwoFactorStatus,
+} from '../../lib/scripts/validation/response';
 import { goto } from "$app/navigation";
 /**
  * validates and prepares the corresponding page data
@@ -11,14 +20,22 @@ import { goto } from "$app/navigation";
 export const load: PageLoad = async ({ data, route, fetch, params }) => {
   var username = await getUsername(fetch);
   var profileData: TProfileBody | null;
+  var emailVerification: TEmailVerificationStatus | null;
+  var twoFactor: TTwoFactorStatus | null;
   if (username) {
     profileData = await getProfile(username, fetch);
+    emailVerification = await getEmailVerification(fetch);
+    twoFactor = await getTwoFactorStatus(fetch);
   }
   else {
     goto("/auth/login")
     profileData = null;
+    emailVerification = null;
+    twoFactor = null;
   }
   return {
     profileData,
+    emailVerification,
+    twoFactor,
   };
 };